@@ -0,0 +1,13 @@
+fn main() {
+    // the grpc service is generated from the proto file with a bundled protoc
+    // so nothing needs to be installed
+    #[cfg(feature = "grpc")]
+    {
+        let protoc = protoc_bin_vendored::protoc_bin_path().expect("bundled protoc is available");
+
+        // SAFETY: the build script does not spawn any threads
+        unsafe { std::env::set_var("PROTOC", protoc) };
+
+        tonic_prost_build::compile_protos("proto/knn.proto").expect("failed to compile knn.proto");
+    }
+}
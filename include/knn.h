@@ -0,0 +1,109 @@
+#ifndef KNN_H
+#define KNN_H
+
+/* generated by cbindgen. do not edit */
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+/**
+ * calculates distances with the euclidean algorithm
+ */
+#define KNN_EUCLIDEAN 0
+
+/**
+ * calculates distances with the manhattan algorithm
+ */
+#define KNN_MANHATTAN 1
+
+/**
+ * leaves the columns unmodified
+ */
+#define KNN_SCALE_NONE 0
+
+/**
+ * scales the columns into the range of 0 to 1
+ */
+#define KNN_SCALE_MINMAX 1
+
+/**
+ * scales the columns to have a mean of 0 and standard deviation of 1
+ */
+#define KNN_SCALE_ZSCORE 2
+
+/**
+ * the call completed
+ */
+#define KNN_OK 0
+
+/**
+ * a pointer was null or a value was out of range
+ */
+#define KNN_INVALID_ARGUMENT -1
+
+/**
+ * the classifier could not produce a result
+ */
+#define KNN_FAILED -2
+
+/**
+ * a knn classifier fitted to a set of records
+ */
+typedef struct KnnClassifier KnnClassifier;
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+/**
+ * fits a classifier to the records
+ *
+ * `data` is a row major matrix of `len` records with `dims` values each and
+ * `labels` has the label of each record. returns null if any of the
+ * arguments are invalid.
+ *
+ * # Safety
+ *
+ * `data` must point to `len * dims` values and `labels` must point to `len`
+ * values. neither are used after the call returns
+ */
+struct KnnClassifier *knn_fit(const double *data,
+                              const uint32_t *labels,
+                              size_t len,
+                              size_t dims,
+                              size_t k,
+                              uint32_t metric,
+                              uint32_t scale);
+
+/**
+ * predicts the label of the datapoint and writes it to `label`
+ *
+ * returns [`KNN_OK`] if the label was written
+ *
+ * # Safety
+ *
+ * `classifier` must be returned from [`knn_fit`] and not yet freed.
+ * `datapoint` must point to `dims` values and `label` to a single value
+ */
+int32_t knn_predict(const struct KnnClassifier *classifier,
+                    const double *datapoint,
+                    size_t dims,
+                    uint32_t *label);
+
+/**
+ * releases a classifier returned by [`knn_fit`]. null is ignored
+ *
+ * # Safety
+ *
+ * `classifier` must be returned from [`knn_fit`] and not already freed
+ */
+void knn_free(struct KnnClassifier *classifier);
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif  // __cplusplus
+
+#endif  /* KNN_H */
@@ -0,0 +1,421 @@
+//! the distribution of the labels across the records and their splits
+//!
+//! a label with fewer training records than k can never hold the majority of
+//! the neighbors and a label missing from a split is either never tested or
+//! never predicted. neither stops a run so the distribution is printed with a
+//! warning for each of them before anything is classified.
+//!
+//! the training records can also be balanced with [`Balance`] so that every
+//! label has the same number of records.
+use std::collections::BTreeMap;
+
+use rand::rngs::StdRng;
+use rand::seq::{IndexedRandom, SliceRandom};
+use rand::{Rng, SeedableRng};
+
+use crate::cli::{AlgoType, Balance};
+use crate::csv::{Dataset, KnnRecord};
+use crate::neighbors::nearest;
+use crate::split::partition_fold;
+
+/// the number of closest records of the same label that smote picks from
+pub const SMOTE_NEIGHBORS: usize = 5;
+
+/// the number of records of each label in the training and testing records
+/// of a split
+#[derive(Debug, Clone, PartialEq)]
+pub struct SplitCounts {
+    pub train: Vec<usize>,
+    pub test: Vec<usize>,
+}
+
+impl SplitCounts {
+    /// counts the labels of the records. `labels` is the number of labels in
+    /// the dataset the records are from
+    pub fn new<'a, T, U>(labels: usize, train: T, test: U) -> Self
+    where
+        T: IntoIterator<Item = KnnRecord<'a>>,
+        U: IntoIterator<Item = KnnRecord<'a>>,
+    {
+        Self {
+            train: count_records(labels, train),
+            test: count_records(labels, test),
+        }
+    }
+}
+
+/// counts the labels of every fold from [`fold_dataset`](crate::split::fold_dataset)
+pub fn fold_counts(records: &Dataset, folds: &[Vec<usize>]) -> Vec<SplitCounts> {
+    folds
+        .iter()
+        .map(|fold| {
+            let (train, test) = partition_fold(records.iter(), fold);
+
+            SplitCounts::new(records.label_names().len(), train, test)
+        })
+        .collect()
+}
+
+fn count_records<'a, I>(labels: usize, records: I) -> Vec<usize>
+where
+    I: IntoIterator<Item = KnnRecord<'a>>,
+{
+    let mut counts = vec![0; labels];
+
+    for record in records {
+        counts[record.label as usize] += 1;
+    }
+
+    counts
+}
+
+/// a label that is too small in one of the splits
+#[derive(Debug, Clone, PartialEq)]
+pub enum BalanceWarning {
+    /// the label has fewer training records than the largest k
+    FewerThanK {
+        label: String,
+        split: Option<usize>,
+        count: usize,
+        k: usize,
+    },
+    /// the label has no training records so it is never predicted
+    NoTrain { label: String, split: Option<usize> },
+    /// the label has no test records so it is never tested
+    NoTest { label: String, split: Option<usize> },
+}
+
+impl std::fmt::Display for BalanceWarning {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let (label, split) = match self {
+            Self::FewerThanK { label, split, .. }
+            | Self::NoTrain { label, split }
+            | Self::NoTest { label, split } => (label, split),
+        };
+
+        match self {
+            Self::FewerThanK { count, k, .. } => write!(
+                f,
+                "label {label} has {count} training records which is fewer than k {k}"
+            )?,
+            Self::NoTrain { .. } => write!(
+                f,
+                "label {label} has no training records and is never predicted"
+            )?,
+            Self::NoTest { .. } => write!(f, "label {label} has no test records")?,
+        }
+
+        if let Some(split) = split {
+            write!(f, " in fold {split}")?;
+        }
+
+        Ok(())
+    }
+}
+
+/// finds every label that has no training or test records in a split or
+/// fewer training records than `k`. the fold of the warning is only given
+/// when there is more than one split
+pub fn check_balance(names: &[String], splits: &[SplitCounts], k: usize) -> Vec<BalanceWarning> {
+    let mut warnings = Vec::new();
+
+    for (index, counts) in splits.iter().enumerate() {
+        let split = (splits.len() > 1).then_some(index);
+
+        for (name, (train, test)) in names.iter().zip(counts.train.iter().zip(&counts.test)) {
+            if *train == 0 {
+                warnings.push(BalanceWarning::NoTrain {
+                    label: name.clone(),
+                    split,
+                });
+            } else if *train < k {
+                warnings.push(BalanceWarning::FewerThanK {
+                    label: name.clone(),
+                    split,
+                    count: *train,
+                    k,
+                });
+            }
+
+            if *test == 0 {
+                warnings.push(BalanceWarning::NoTest {
+                    label: name.clone(),
+                    split,
+                });
+            }
+        }
+    }
+
+    warnings
+}
+
+/// prints the number of records of each label and a warning to stderr for
+/// each label that is too small in one of the splits
+pub fn print_balance(records: &Dataset, splits: &[SplitCounts], k: usize) {
+    let names = records.label_names();
+    let mut counts = vec![0usize; names.len()];
+
+    for label in records.labels() {
+        counts[*label as usize] += 1;
+    }
+
+    println!("labels: {}", names.len());
+
+    for (name, count) in names.iter().zip(counts) {
+        println!(
+            "  {name}: {count} {:.2}%",
+            count as f64 / records.len() as f64 * 100.0
+        );
+    }
+
+    for warning in check_balance(names, splits, k) {
+        eprintln!("warning: {warning}");
+    }
+}
+
+/// randomly picks as many positions of each label as the smallest label
+/// has. the positions are returned in order
+///
+/// the positions of each label are shuffled with an rng created from the
+/// seed so the same labels and seed always pick the same positions
+pub fn undersample<I>(labels: I, seed: u64) -> Vec<usize>
+where
+    I: IntoIterator<Item = u32>,
+{
+    // the groups are ordered by label so the rng is consumed in the same
+    // order every time
+    let mut groups: BTreeMap<u32, Vec<usize>> = BTreeMap::new();
+
+    for (position, label) in labels.into_iter().enumerate() {
+        groups.entry(label).or_default().push(position);
+    }
+
+    let smallest = groups.values().map(Vec::len).min().unwrap_or(0);
+    let mut rng = StdRng::seed_from_u64(seed);
+    let mut kept = Vec::with_capacity(smallest * groups.len());
+
+    for (_, mut positions) in groups {
+        positions.shuffle(&mut rng);
+        positions.truncate(smallest);
+
+        kept.extend(positions);
+    }
+
+    kept.sort_unstable();
+    kept
+}
+
+/// creates records for every label with fewer records than the largest label
+/// until they have as many. each record is placed at a random point between a
+/// record of the label and one of its [`SMOTE_NEIGHBORS`] closest records of
+/// the same label
+///
+/// the created records are added to `synthetic`, which must have the same
+/// number of columns as the records
+pub fn smote(records: &[KnnRecord<'_>], algo: AlgoType, seed: u64, synthetic: &mut Dataset) {
+    let dims = synthetic.dims();
+    let mut groups: BTreeMap<u32, Dataset> = BTreeMap::new();
+
+    for record in records {
+        groups
+            .entry(record.label)
+            .or_insert_with(|| Dataset::new(dims))
+            .push_id(record.data, record.label);
+    }
+
+    let largest = groups.values().map(Dataset::len).max().unwrap_or(0);
+    let mut rng = StdRng::seed_from_u64(seed);
+    let mut point = vec![0.0; dims];
+
+    for (label, group) in &groups {
+        // the neighbors of a record are only found once it is picked
+        let mut neighbors: Vec<Option<Vec<usize>>> = vec![None; group.len()];
+
+        for _ in group.len()..largest {
+            let base = rng.random_range(0..group.len());
+            let close = neighbors[base].get_or_insert_with(|| {
+                nearest(
+                    group,
+                    SMOTE_NEIGHBORS,
+                    algo,
+                    group.get(base).data,
+                    Some(base),
+                )
+                .into_iter()
+                .map(|(_, row, _)| row)
+                .collect()
+            });
+            let from = group.get(base).data;
+            // a label with a single record can only repeat it
+            let to = match close.choose(&mut rng) {
+                Some(row) => group.get(*row).data,
+                None => from,
+            };
+            let gap: f64 = rng.random();
+
+            for ((value, from), to) in point.iter_mut().zip(from).zip(to) {
+                *value = from + gap * (to - from);
+            }
+
+            synthetic.push_id(&point, *label);
+        }
+    }
+}
+
+/// adds the records created by [`smote`] for the records of the dataset to
+/// the end of it
+pub fn extend_smote(records: &mut Dataset, algo: AlgoType, seed: u64) {
+    let mut synthetic = Dataset::new(records.dims());
+
+    smote(
+        &records.iter().collect::<Vec<_>>(),
+        algo,
+        seed,
+        &mut synthetic,
+    );
+
+    for record in synthetic.iter() {
+        records.push_id(record.data, record.label);
+    }
+}
+
+/// balances the labels of a list of training records. the records created
+/// by smote are added to `synthetic`, which the returned records borrow
+pub fn balance_records<'a>(
+    records: Vec<KnnRecord<'a>>,
+    balance: Balance,
+    algo: AlgoType,
+    seed: u64,
+    synthetic: &'a mut Dataset,
+) -> Vec<KnnRecord<'a>> {
+    match balance {
+        Balance::None => records,
+        Balance::Undersample => undersample(records.iter().map(|record| record.label), seed)
+            .into_iter()
+            .map(|position| records[position])
+            .collect(),
+        Balance::Smote => {
+            smote(&records, algo, seed, synthetic);
+
+            let synthetic: &'a Dataset = synthetic;
+
+            records.into_iter().chain(synthetic.iter()).collect()
+        }
+    }
+}
+
+/// balances the labels of every record in the dataset
+pub fn balance_dataset(
+    mut records: Dataset,
+    balance: Balance,
+    algo: AlgoType,
+    seed: u64,
+) -> Dataset {
+    match balance {
+        Balance::None => records,
+        Balance::Undersample => {
+            let kept = undersample(records.labels().iter().copied(), seed);
+            let mut balanced = Dataset::with_labels_of(&records, records.dims(), kept.len());
+
+            for position in kept {
+                let record = records.get(position);
+
+                balanced.push_id(record.data, record.label);
+            }
+
+            balanced
+        }
+        Balance::Smote => {
+            extend_smote(&mut records, algo, seed);
+
+            records
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn small_labels() {
+        let names = vec!["a".to_owned(), "b".to_owned(), "c".to_owned()];
+        let splits = vec![
+            SplitCounts {
+                train: vec![10, 2, 0],
+                test: vec![3, 1, 1],
+            },
+            SplitCounts {
+                train: vec![10, 5, 4],
+                test: vec![3, 0, 1],
+            },
+        ];
+
+        let warnings: Vec<String> = check_balance(&names, &splits, 3)
+            .iter()
+            .map(ToString::to_string)
+            .collect();
+
+        assert_eq!(
+            warnings,
+            vec![
+                "label b has 2 training records which is fewer than k 3 in fold 0",
+                "label c has no training records and is never predicted in fold 0",
+                "label b has no test records in fold 1",
+            ]
+        );
+
+        assert!(
+            check_balance(&names, &splits[1..], 3)[0]
+                .to_string()
+                .ends_with("test records")
+        );
+    }
+
+    #[test]
+    fn undersample_reproducible() {
+        let labels = [0, 0, 1, 0, 2, 1, 0, 2, 0];
+        let kept = undersample(labels, 7);
+
+        assert_eq!(kept.len(), 6);
+        assert!(kept.is_sorted());
+
+        for label in 0..3 {
+            assert_eq!(
+                kept.iter()
+                    .filter(|position| labels[**position] == label)
+                    .count(),
+                2
+            );
+        }
+
+        assert_eq!(kept, undersample(labels, 7));
+    }
+
+    #[test]
+    fn smote_between_neighbors() {
+        let mut records = Dataset::new(2);
+
+        for (data, label) in [
+            ([0.0, 0.0], "a"),
+            ([1.0, 0.0], "a"),
+            ([2.0, 0.0], "a"),
+            ([3.0, 0.0], "a"),
+            ([10.0, 10.0], "b"),
+            ([10.0, 12.0], "b"),
+        ] {
+            records.push(&data, label);
+        }
+
+        let balanced = balance_dataset(records, Balance::Smote, AlgoType::Euclidean, 3);
+
+        assert_eq!(balanced.len(), 8);
+
+        // the created records of b are on the line between its two records
+        for record in balanced.iter().skip(6) {
+            assert_eq!(record.label, 1);
+            assert_eq!(record.data[0], 10.0);
+            assert!((10.0..=12.0).contains(&record.data[1]));
+        }
+    }
+}
@@ -0,0 +1,216 @@
+//! measuring the throughput of the distance algorithms and indexes
+//!
+//! the records are either loaded from the csv or generated with uniform
+//! random values. every combination of algorithm and index is built from the
+//! records and then queried with a sample of datapoints. the queries are
+//! classified in parallel so the results depend on the number of threads.
+use std::time::{Duration, Instant};
+
+use anyhow::bail;
+use clap::Args;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use rayon::prelude::*;
+
+use crate::cli::{AlgoType, ColumnList, ColumnType};
+use crate::csv::{Dataset, collect_records, get_columns_excluding};
+use crate::index::{IndexArgs, IndexType, NeighborIndex, recall};
+use crate::source::DataSource;
+
+#[derive(Debug, Args)]
+pub struct BenchArgs {
+    /// the number of neighbors to lookup
+    #[arg(short, default_value = "5")]
+    k: usize,
+
+    /// the list of columns to use as datapoints when a csv is loaded
+    #[arg(short, long = "col", allow_hyphen_values = true)]
+    columns: Vec<ColumnList>,
+
+    /// columns to remove from the list of columns
+    #[arg(long = "exclude-col", allow_hyphen_values = true)]
+    exclude: Vec<ColumnList>,
+
+    /// the column to use as the label when a csv is loaded
+    #[arg(long, allow_negative_numbers = true)]
+    label: Option<ColumnType>,
+
+    /// the number of records to generate when no csv is loaded
+    #[arg(long, default_value = "10000")]
+    records: usize,
+
+    /// the number of values in each generated record
+    #[arg(long, default_value = "8")]
+    dims: usize,
+
+    /// the number of datapoints to query each index with
+    #[arg(long, default_value = "1000")]
+    queries: usize,
+
+    /// a comma delimitered list of algorithms to measure
+    #[arg(long, value_delimiter = ',', default_value = "euclidean,manhattan")]
+    algo: Vec<AlgoType>,
+
+    /// a comma delimitered list of indexes to measure
+    #[arg(
+        long,
+        value_delimiter = ',',
+        default_value = "brute,kdtree,balltree,vptree,covertree,hnsw,lsh"
+    )]
+    index: Vec<IndexType>,
+
+    /// the seed used to generate the records and pick the queries. a random
+    /// seed is used if not specified
+    #[arg(long)]
+    seed: Option<u64>,
+}
+
+pub fn knn_bench<S>(source: Option<S>, arg: BenchArgs) -> anyhow::Result<()>
+where
+    S: DataSource,
+{
+    if arg.k == 0 {
+        bail!("k value cannot be 0");
+    }
+
+    if arg.queries == 0 {
+        bail!("the number of queries must be larger than 0");
+    }
+
+    let seed = arg.seed.unwrap_or_else(rand::random);
+    let mut rng = StdRng::seed_from_u64(seed);
+
+    let start = Instant::now();
+    let records = match source {
+        Some(mut source) => {
+            let label = &ColumnType::label_or_last(arg.label.as_ref());
+
+            let (label, columns) = get_columns_excluding(
+                &mut source,
+                label,
+                &ColumnList::flatten(&arg.columns),
+                &ColumnList::flatten(&arg.exclude),
+            )?;
+
+            collect_records(source, label, &columns)?
+        }
+        None => {
+            if arg.records == 0 || arg.dims == 0 {
+                bail!("the number of generated records and dims must be larger than 0");
+            }
+
+            generate_records(&mut rng, arg.records, arg.dims)
+        }
+    };
+    let load = start.elapsed();
+
+    if records.is_empty() {
+        bail!("no records to benchmark");
+    }
+
+    // the queries are picked from the records so they follow the same
+    // distribution
+    let queries: Vec<&[f64]> = (0..arg.queries)
+        .map(|_| records.get(rng.random_range(0..records.len())).data)
+        .collect();
+
+    println!(
+        "records: {} dims: {} queries: {} k: {} seed: {seed}",
+        records.len(),
+        records.dims(),
+        queries.len(),
+        arg.k
+    );
+    println!("load: {load:.2?}");
+
+    for algo in &arg.algo {
+        for kind in &arg.index {
+            let points = || {
+                records
+                    .iter()
+                    .enumerate()
+                    .map(|(index, record)| (index, record.data))
+            };
+
+            let start = Instant::now();
+            let index = IndexArgs::from(*kind).build(records.dims(), points(), *algo)?;
+            let build = start.elapsed();
+
+            let start = Instant::now();
+            queries.par_iter().for_each(|query| {
+                std::hint::black_box(index.nearest(query, arg.k));
+            });
+            let query = start.elapsed();
+
+            print!(
+                "{algo} {kind} | build: {build:.2?} query: {query:.2?} qps: {:.1}",
+                per_second(queries.len(), query)
+            );
+
+            if !kind.is_exact() {
+                let (found, expected) = recall(
+                    &index,
+                    records.dims(),
+                    points(),
+                    *algo,
+                    queries.iter().copied(),
+                    arg.k,
+                );
+
+                print!(" recall: {:.4}", found as f64 / expected as f64);
+            }
+
+            println!();
+        }
+    }
+
+    Ok(())
+}
+
+/// creates records with uniform random values between 0 and 1
+///
+/// every record has the same label since only the datapoints are measured
+fn generate_records(rng: &mut StdRng, len: usize, dims: usize) -> Dataset {
+    let mut records = Dataset::with_capacity(dims, len);
+    let mut data = vec![0.0; dims];
+
+    for _ in 0..len {
+        for value in data.iter_mut() {
+            *value = rng.random();
+        }
+
+        records.push(&data, "0");
+    }
+
+    records
+}
+
+/// the number of operations completed per second
+fn per_second(count: usize, elapsed: Duration) -> f64 {
+    let secs = elapsed.as_secs_f64();
+
+    if secs == 0.0 {
+        f64::INFINITY
+    } else {
+        count as f64 / secs
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn generated_records() {
+        let mut rng = StdRng::seed_from_u64(1);
+        let records = generate_records(&mut rng, 20, 3);
+
+        assert_eq!(records.len(), 20);
+        assert_eq!(records.dims(), 3);
+        assert!(
+            records
+                .iter()
+                .all(|record| record.data.iter().all(|value| (0.0..1.0).contains(value)))
+        );
+    }
+}
@@ -0,0 +1,285 @@
+//! persisting the progress of a search
+//!
+//! every evaluated configuration is stored along with its results so that an
+//! interrupted search can be resumed. when resuming, the search will run
+//! through the same configurations but any that were previously evaluated are
+//! retrieved from the checkpoint instead of being calculated again.
+use std::collections::HashMap;
+use std::fs::OpenOptions;
+use std::io::{BufReader, BufWriter};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use anyhow::{Context, bail};
+use serde::{Deserialize, Serialize};
+
+use crate::cli::{AlgoType, Balance, EvalAgainst, SplitStrategy, TaskType};
+use crate::metrics::Evaluation;
+use crate::scale::ScaleType;
+
+/// the arguments of a search that affect the results of an evaluation
+///
+/// a checkpoint can only be resumed by a search with matching parameters
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CheckpointParams {
+    pub label: usize,
+    pub columns: Vec<usize>,
+    pub algo: AlgoType,
+    pub task: TaskType,
+    pub test: f64,
+    pub seed: u64,
+    /// checkpoints created before this was configurable always classified
+    /// against every record
+    #[serde(default = "default_eval_against")]
+    pub eval_against: EvalAgainst,
+    /// checkpoints created before this was configurable were not balanced
+    #[serde(default)]
+    pub balance: Balance,
+    #[serde(default)]
+    pub split_strategy: SplitStrategy,
+    /// the csv column the records are ordered by for the temporal split
+    #[serde(default)]
+    pub time: Option<usize>,
+}
+
+fn default_eval_against() -> EvalAgainst {
+    EvalAgainst::All
+}
+
+/// a single evaluated configuration
+#[derive(Debug, Serialize, Deserialize)]
+struct CheckpointEntry {
+    scale: ScaleType,
+    k: usize,
+    /// the sorted csv columns used for the evaluation
+    columns: Vec<usize>,
+    eval: Evaluation,
+}
+
+/// the format of the checkpoint file
+#[derive(Debug, Serialize, Deserialize)]
+struct CheckpointFile {
+    params: CheckpointParams,
+    evaluations: Vec<CheckpointEntry>,
+}
+
+/// the key used to lookup previously evaluated configurations
+type EntryKey = (ScaleType, usize, Vec<usize>);
+
+/// the evaluated configurations of a search
+#[derive(Debug)]
+pub struct Checkpoint {
+    params: CheckpointParams,
+    evaluations: HashMap<EntryKey, Evaluation>,
+    /// where to save the checkpoint. nothing is saved if not specified
+    path: Option<PathBuf>,
+    /// the minimum amount of time between saves
+    interval: Duration,
+    last_save: Instant,
+    /// indicates that there are evaluations that have not been saved
+    dirty: bool,
+}
+
+impl Checkpoint {
+    /// creates an empty checkpoint
+    pub fn new(params: CheckpointParams, path: Option<PathBuf>, interval: Duration) -> Self {
+        Self {
+            params,
+            evaluations: HashMap::new(),
+            path,
+            interval,
+            last_save: Instant::now(),
+            dirty: false,
+        }
+    }
+
+    /// loads a previously saved checkpoint
+    ///
+    /// the parameters should be checked with [`Checkpoint::check_params`]
+    /// before the checkpoint is used
+    pub fn load(resume: &Path, path: Option<PathBuf>, interval: Duration) -> anyhow::Result<Self> {
+        let file = OpenOptions::new()
+            .read(true)
+            .open(resume)
+            .context("failed to open checkpoint file")?;
+
+        let saved: CheckpointFile = serde_json::from_reader(BufReader::new(file))
+            .context("failed to parse checkpoint file")?;
+
+        let mut rtn = Self::new(saved.params, path, interval);
+
+        for entry in saved.evaluations {
+            rtn.evaluations
+                .insert((entry.scale, entry.k, entry.columns), entry.eval);
+        }
+
+        Ok(rtn)
+    }
+
+    /// the parameters of the search that created the checkpoint
+    pub fn params(&self) -> &CheckpointParams {
+        &self.params
+    }
+
+    /// verifies that the checkpoint was created with the provided parameters
+    pub fn check_params(&self, params: &CheckpointParams) -> anyhow::Result<()> {
+        if self.params != *params {
+            bail!(
+                "checkpoint was created with different search arguments.\nexpected: {:#?}\nfound: {:#?}",
+                params,
+                self.params
+            );
+        }
+
+        Ok(())
+    }
+
+    /// the total number of evaluations stored
+    pub fn len(&self) -> usize {
+        self.evaluations.len()
+    }
+
+    /// checks if no evaluations are stored
+    pub fn is_empty(&self) -> bool {
+        self.evaluations.is_empty()
+    }
+
+    /// retrieves a previously evaluated configuration
+    ///
+    /// the columns do not need to be sorted
+    pub fn get(&self, scale: ScaleType, k: usize, columns: &[usize]) -> Option<&Evaluation> {
+        self.evaluations.get(&(scale, k, sorted(columns)))
+    }
+
+    /// stores an evaluated configuration and saves the checkpoint if the save
+    /// interval has elapsed
+    pub fn insert(
+        &mut self,
+        scale: ScaleType,
+        k: usize,
+        columns: &[usize],
+        eval: Evaluation,
+    ) -> anyhow::Result<()> {
+        self.evaluations.insert((scale, k, sorted(columns)), eval);
+        self.dirty = true;
+
+        if self.last_save.elapsed() >= self.interval {
+            self.save()?;
+        }
+
+        Ok(())
+    }
+
+    /// writes the checkpoint to its file if there are unsaved evaluations
+    ///
+    /// the checkpoint is written to a temporary file first and then renamed
+    /// so that an interruption while saving does not corrupt the previous
+    /// checkpoint
+    pub fn save(&mut self) -> anyhow::Result<()> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+
+        if !self.dirty {
+            return Ok(());
+        }
+
+        let mut evaluations = Vec::with_capacity(self.evaluations.len());
+
+        for ((scale, k, columns), eval) in &self.evaluations {
+            evaluations.push(CheckpointEntry {
+                scale: *scale,
+                k: *k,
+                columns: columns.clone(),
+                eval: eval.clone(),
+            });
+        }
+
+        let saved = CheckpointFile {
+            params: self.params.clone(),
+            evaluations,
+        };
+
+        let mut tmp_path = path.clone().into_os_string();
+        tmp_path.push(".tmp");
+
+        let file = OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(&tmp_path)
+            .context("failed to create checkpoint file")?;
+
+        serde_json::to_writer(BufWriter::new(file), &saved)
+            .context("failed to write checkpoint file")?;
+
+        std::fs::rename(&tmp_path, path).context("failed to replace checkpoint file")?;
+
+        self.last_save = Instant::now();
+        self.dirty = false;
+
+        Ok(())
+    }
+}
+
+fn sorted(columns: &[usize]) -> Vec<usize> {
+    let mut rtn = columns.to_vec();
+    rtn.sort_unstable();
+    rtn
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn params() -> CheckpointParams {
+        CheckpointParams {
+            label: 0,
+            columns: vec![1, 2, 3],
+            algo: AlgoType::Euclidean,
+            task: TaskType::Classification,
+            test: 0.25,
+            seed: 1,
+            eval_against: EvalAgainst::Train,
+            balance: Balance::None,
+            split_strategy: SplitStrategy::Random,
+            time: None,
+        }
+    }
+
+    #[test]
+    fn checkpoint_round_trip() {
+        let path = std::env::temp_dir().join(format!("knn_checkpoint_{}.json", std::process::id()));
+        let mut checkpoint = Checkpoint::new(params(), Some(path.clone()), Duration::MAX);
+
+        let eval = Evaluation::Classification {
+            passed: 3,
+            failed: 1,
+            unknown: 0,
+        };
+
+        checkpoint
+            .insert(ScaleType::ZScore, 3, &[3, 1], eval)
+            .unwrap();
+        checkpoint.save().unwrap();
+
+        let loaded = Checkpoint::load(&path, None, Duration::MAX).unwrap();
+
+        std::fs::remove_file(&path).unwrap();
+
+        let mut other = params();
+        other.test = 0.5;
+
+        assert!(loaded.check_params(&params()).is_ok());
+        assert!(loaded.check_params(&other).is_err());
+
+        assert_eq!(loaded.len(), 1);
+        assert_eq!(
+            loaded
+                .get(ScaleType::ZScore, 3, &[1, 3])
+                .map(|eval| eval.score()),
+            Some(0.75)
+        );
+        assert!(loaded.get(ScaleType::None, 3, &[1, 3]).is_none());
+    }
+}
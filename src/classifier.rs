@@ -0,0 +1,329 @@
+//! a fitted knn classifier for using the algorithm from other programs
+//!
+//! [`KnnClassifier::builder`] collects the arguments that the commands accept
+//! on the command line and [`KnnClassifierBuilder::fit`] scales the records
+//! once so that every datapoint afterwards only needs to be scaled before the
+//! neighbors are found.
+//!
+//! ```
+//! use knn::classifier::KnnClassifier;
+//! use knn::cli::AlgoType;
+//! use knn::csv::Dataset;
+//! use knn::error::KnnResult;
+//! use knn::scale::ScaleType;
+//!
+//! # fn main() -> KnnResult<()> {
+//! let mut records = Dataset::new(2);
+//! records.push(&[1.0, 1.0], "a");
+//! records.push(&[1.5, 1.0], "a");
+//! records.push(&[8.0, 9.0], "b");
+//!
+//! let classifier = KnnClassifier::builder()
+//!     .k(1)
+//!     .metric(AlgoType::Euclidean)
+//!     .scale(ScaleType::ZScore)
+//!     .fit(records)?;
+//!
+//! assert_eq!(classifier.predict(&[7.0, 8.0])?, "b");
+//! # Ok(())
+//! # }
+//! ```
+use std::fmt;
+use std::sync::Arc;
+
+use serde::{Deserialize, Serialize};
+
+use crate::classify::{count_labels, largest_group, sort_closest};
+use crate::cli::AlgoType;
+use crate::csv::Dataset;
+use crate::distance::DistanceMetric;
+use crate::error::{KnnError, KnnResult};
+use crate::scale::{ScaleType, Scaler};
+
+/// the arguments used to fit a [`KnnClassifier`]
+#[derive(Clone)]
+pub struct KnnClassifierBuilder {
+    k: usize,
+    metric: Arc<dyn DistanceMetric>,
+    scale: ScaleType,
+}
+
+impl Default for KnnClassifierBuilder {
+    fn default() -> Self {
+        Self {
+            k: 5,
+            metric: Arc::new(AlgoType::Euclidean),
+            scale: ScaleType::None,
+        }
+    }
+}
+
+impl fmt::Debug for KnnClassifierBuilder {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("KnnClassifierBuilder")
+            .field("k", &self.k)
+            .field("scale", &self.scale)
+            .finish_non_exhaustive()
+    }
+}
+
+impl KnnClassifierBuilder {
+    /// the number of neighbors to lookup. defaults to 5
+    pub fn k(mut self, k: usize) -> Self {
+        self.k = k;
+        self
+    }
+
+    /// the distance metric to use. accepts an [`AlgoType`] or any other
+    /// [`DistanceMetric`]. defaults to euclidean
+    pub fn metric<M>(mut self, metric: M) -> Self
+    where
+        M: DistanceMetric + 'static,
+    {
+        self.metric = Arc::new(metric);
+        self
+    }
+
+    /// the scaling applied to the records and datapoints. defaults to none
+    pub fn scale(mut self, scale: ScaleType) -> Self {
+        self.scale = scale;
+        self
+    }
+
+    /// fits the scaling parameters and stores the scaled records
+    pub fn fit(self, records: Dataset) -> KnnResult<KnnClassifier> {
+        if self.k == 0 {
+            return Err(KnnError::ZeroK);
+        }
+
+        if records.is_empty() {
+            return Err(KnnError::NoRecords);
+        }
+
+        let scaler = Scaler::fit(
+            self.scale,
+            records.iter().map(|record| record.data),
+            records.dims(),
+        );
+        let records = scaler.apply_records(&records);
+
+        Ok(KnnClassifier {
+            k: self.k,
+            metric: self.metric,
+            scaler,
+            records,
+        })
+    }
+}
+
+/// a record found near a datapoint
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Neighbor {
+    /// the index of the record in the fitted records
+    pub index: usize,
+    /// the distance between the scaled record and datapoint
+    pub distance: f64,
+    /// the interned id of the label. refer to [`KnnClassifier::labels`]
+    pub label: u32,
+}
+
+/// a knn classifier fitted to a set of records
+#[derive(Clone)]
+pub struct KnnClassifier {
+    k: usize,
+    metric: Arc<dyn DistanceMetric>,
+    scaler: Scaler,
+    records: Dataset,
+}
+
+impl fmt::Debug for KnnClassifier {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("KnnClassifier")
+            .field("k", &self.k)
+            .field("scaler", &self.scaler)
+            .field("records", &self.records)
+            .finish_non_exhaustive()
+    }
+}
+
+impl KnnClassifier {
+    pub fn builder() -> KnnClassifierBuilder {
+        KnnClassifierBuilder::default()
+    }
+
+    /// the labels of the fitted records indexed by their label id
+    pub fn labels(&self) -> &[String] {
+        self.records.label_names()
+    }
+
+    /// adds a labeled record after the classifier was fitted
+    ///
+    /// the record is scaled with the parameters fitted on the original
+    /// records and its index follows the last record
+    pub fn append(&mut self, datapoint: &[f64], label: &str) -> KnnResult<()> {
+        let datapoint = self.scale_datapoint(datapoint)?;
+
+        self.records.push(&datapoint, label);
+
+        Ok(())
+    }
+
+    /// finds the closest `k` records ordered from closest to furthest
+    ///
+    /// records with the same distance are ordered by their index
+    pub fn kneighbors(&self, datapoint: &[f64]) -> KnnResult<Vec<Neighbor>> {
+        let datapoint = self.scale_datapoint(datapoint)?;
+
+        let mut closest: Vec<(f64, usize, u32)> = self
+            .records
+            .iter()
+            .enumerate()
+            .map(|(index, record)| {
+                (
+                    self.metric.rank(&datapoint, record.data),
+                    index,
+                    record.label,
+                )
+            })
+            .collect();
+
+        sort_closest(&mut closest, self.k);
+        closest.truncate(self.k);
+
+        Ok(closest
+            .into_iter()
+            .map(|(_, index, label)| Neighbor {
+                index,
+                distance: self
+                    .metric
+                    .distance(&datapoint, self.records.get(index).data),
+                label,
+            })
+            .collect())
+    }
+
+    /// the label with the most neighbors. ties go to the label that was
+    /// encountered first in the records
+    pub fn predict(&self, datapoint: &[f64]) -> KnnResult<&str> {
+        Ok(self.records.label_name(self.predict_id(datapoint)?))
+    }
+
+    /// the label id with the most neighbors. refer to
+    /// [`KnnClassifier::predict`]
+    pub fn predict_id(&self, datapoint: &[f64]) -> KnnResult<u32> {
+        let groups = self.groups(datapoint)?;
+
+        let Some((label, _)) = largest_group(&groups) else {
+            return Err(KnnError::NoNeighbors);
+        };
+
+        Ok(label)
+    }
+
+    /// predicts each datapoint only when the next label is requested. refer
+    /// to [`KnnClassifier::predict`]
+    pub fn predict_batch<'a, I, D>(
+        &'a self,
+        datapoints: I,
+    ) -> impl Iterator<Item = KnnResult<&'a str>> + 'a
+    where
+        I: IntoIterator<Item = D>,
+        I::IntoIter: 'a,
+        D: AsRef<[f64]>,
+    {
+        datapoints
+            .into_iter()
+            .map(|datapoint| self.predict(datapoint.as_ref()))
+    }
+
+    /// the fraction of neighbors with each label indexed by the label id.
+    /// refer to [`KnnClassifier::labels`]
+    pub fn predict_proba(&self, datapoint: &[f64]) -> KnnResult<Vec<f64>> {
+        let mut groups = self.groups(datapoint)?;
+        groups.resize(self.labels().len(), 0);
+
+        let total: u32 = groups.iter().sum();
+
+        Ok(groups
+            .into_iter()
+            .map(|count| count as f64 / total as f64)
+            .collect())
+    }
+
+    /// counts the labels of the closest `k` records
+    fn groups(&self, datapoint: &[f64]) -> KnnResult<Vec<u32>> {
+        let mut groups = Vec::new();
+
+        count_labels(
+            self.kneighbors(datapoint)?
+                .into_iter()
+                .map(|neighbor| neighbor.label),
+            &mut groups,
+        );
+
+        Ok(groups)
+    }
+
+    /// creates a scaled copy of the datapoint
+    fn scale_datapoint(&self, datapoint: &[f64]) -> KnnResult<Vec<f64>> {
+        if datapoint.len() != self.records.dims() {
+            return Err(KnnError::DimensionMismatch {
+                expected: self.records.dims(),
+                found: datapoint.len(),
+            });
+        }
+
+        let mut rtn = datapoint.to_vec();
+        self.scaler.apply(&mut rtn);
+
+        Ok(rtn)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn fitted_classifier() {
+        let mut records = Dataset::new(2);
+        records.push(&[0.0, 0.0], "a");
+        records.push(&[0.0, 1.0], "a");
+        records.push(&[5.0, 5.0], "b");
+        records.push(&[6.0, 5.0], "b");
+        records.push(&[5.0, 6.0], "b");
+
+        let classifier = KnnClassifier::builder().k(3).fit(records).unwrap();
+
+        assert_eq!(classifier.predict(&[0.0, 0.5]).unwrap(), "a");
+        assert_eq!(classifier.predict(&[5.5, 5.5]).unwrap(), "b");
+
+        let proba = classifier.predict_proba(&[0.0, 0.5]).unwrap();
+        assert_eq!(proba, vec![2.0 / 3.0, 1.0 / 3.0]);
+
+        let neighbors = classifier.kneighbors(&[6.0, 5.0]).unwrap();
+        assert_eq!(
+            neighbors.iter().map(|n| n.index).collect::<Vec<_>>(),
+            vec![3, 2, 4]
+        );
+        assert_eq!(neighbors[0].distance, 0.0);
+        assert_eq!(neighbors[1].distance, 1.0);
+
+        assert!(classifier.predict(&[1.0]).is_err());
+
+        let batch: Vec<&str> = classifier
+            .predict_batch([[0.0, 0.5], [5.5, 5.5]])
+            .collect::<KnnResult<_>>()
+            .unwrap();
+        assert_eq!(batch, ["a", "b"]);
+
+        let mut classifier = classifier;
+        classifier.append(&[0.0, 9.0], "c").unwrap();
+        classifier.append(&[0.0, 10.0], "c").unwrap();
+
+        assert_eq!(classifier.labels(), ["a", "b", "c"]);
+        assert_eq!(classifier.predict(&[0.0, 9.5]).unwrap(), "c");
+        assert!(classifier.append(&[1.0], "c").is_err());
+        assert!(KnnClassifier::builder().k(0).fit(Dataset::new(2)).is_err());
+    }
+}
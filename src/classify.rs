@@ -2,96 +2,528 @@
 //!
 //! this provides the implementation for the KNN algorithm.
 //!
-//! [`classify_datapoint`] is a convienience function that will allocate memory
-//! for the calculated groups and collected records.
+//! [`classify_datapoint_owned`] is a convienience function that will allocate
+//! memory for the calculated groups and collected records.
 //!
-//! [`classify_datapoint_buffered`] performs the actual calculation based on
-//! the records provided to it.
-use std::collections::HashMap;
+//! [`classify_datapoint`] performs the actual calculation based on the records
+//! provided to it.
+//!
+//! labels are interned into numeric ids by the [`Dataset`](crate::csv::Dataset)
+//! so the groups are a flat list of counts indexed by the label id. the
+//! functions also accept any other label type that can be cloned and counted
+//! with a [`HashMap`] so integer or enum labels can be used directly. refer to
+//! [`LabelGroups`]
+//!
+//! [`classify_distances`] accepts distances that were already calculated so
+//! callers can avoid allocating data for every record.
+//!
+//! [`closest_labels`] and [`closest_labels_par`] find the labels of the
+//! closest records in order so the groups of multiple k values can be counted
+//! from a single calculation. the parallel version calculates the distances on
+//! multiple threads for large numbers of records. [`labels_within`] finds
+//! the labels of every record within a radius instead.
+//!
+//! [`closest_labels_batch`] finds the labels for many datapoints at once by
+//! calculating the distances between tiles of datapoints and blocks of
+//! records as matrices.
+use std::cmp::Ordering;
+use std::collections::{BinaryHeap, HashMap};
+use std::hash::{BuildHasher, Hash};
 use std::iter::Iterator;
 
+use ndarray::{Array2, ArrayView2, s};
+use rayon::prelude::*;
+
+use crate::csv::Dataset;
+
+/// the number of datapoints that share a single pass over the records
+const QUERY_TILE: usize = 16;
+
+/// the number of records compared against every datapoint of a tile before
+/// moving to the next block
+const RECORD_TILE: usize = 256;
+
 /// convienience function that will allocate memory for the calculated groups
 /// and collected records.
 ///
 /// refer to [`classify_datapoint`]
-pub fn classify_datapoint_owned<'a, 'b, F, R, D>(
+pub fn classify_datapoint_owned<F, R, D, T>(
     k: usize,
     records: R,
     algo: F,
-    datapoint: &[f64],
-) -> (usize, HashMap<&'a str, u32>)
+    datapoint: &[T],
+) -> (usize, Vec<u32>)
 where
-    D: AsRef<[f64]>,
-    R: Iterator<Item = (D, &'a str)>,
-    F: Fn(&[f64], &[f64]) -> f64,
+    D: AsRef<[T]>,
+    R: Iterator<Item = (D, u32)>,
+    F: Fn(&[T], &[T]) -> f64,
 {
-    let (_, max_size) = records.size_hint();
-
-    let mut groups = HashMap::with_capacity(k);
-    // collect the datapoints with the calculated distance function from
-    // the provided datapoint
-    let mut collected = if let Some(max_size) = max_size {
-        Vec::with_capacity(max_size)
-    } else {
-        Vec::new()
-    };
+    let mut groups = Vec::new();
+    // only the closest k records are kept while calculating distances
+    let mut collected = Vec::with_capacity(k + 1);
 
     let min = classify_datapoint(k, records, algo, datapoint, &mut collected, &mut groups);
 
     (min, groups)
 }
 
+/// a record that was compared against the datapoint
+///
+/// ordered by distance and then by position so that a [`BinaryHeap`] will
+/// keep the furthest record at the top
+#[derive(Debug, Clone, Copy)]
+pub struct Closest<L = u32> {
+    distance: f64,
+    position: usize,
+    label: L,
+}
+
+impl<L> PartialEq for Closest<L> {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl<L> Eq for Closest<L> {}
+
+impl<L> PartialOrd for Closest<L> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<L> Ord for Closest<L> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.distance
+            .total_cmp(&other.distance)
+            .then(self.position.cmp(&other.position))
+    }
+}
+
 /// performs the KNN algorithm on the provided records
 ///
 /// this will calculate a single floating point value based on the result from
-/// the provided algorithm that must accept a pair of [`&[f64]`]'s to compare
-/// against. from that calculation the values will be sorted according to
-/// [`f64::total_cmp`] for comparison and [`slice::sort_by`] for arranging the
-/// values in assending order. once they have been sorted the first `k` values
-/// will be inserted into the `groups` argument.
-pub fn classify_datapoint<'a, F, R, D>(
+/// the provided algorithm that must accept a pair of [`&[f64]`]'s (or
+/// [`&[f32]`]'s) to compare against. only the closest `k` records are kept in
+/// a max heap while calculating so selecting is O(n log k) instead of sorting
+/// every record. records with the same distance are ordered by their position
+/// according to [`f64::total_cmp`]. the closest `k` values will then be
+/// counted in the `groups` argument by their label.
+///
+/// `collected` is only used to reuse the memory of the heap between calls
+pub fn classify_datapoint<F, R, D, T, L, G>(
     k: usize,
     records: R,
     algo: F,
-    datapoint: &[f64],
-    collected: &mut Vec<(f64, &'a str)>,
-    groups: &mut HashMap<&'a str, u32>,
+    datapoint: &[T],
+    collected: &mut Vec<Closest<L>>,
+    groups: &mut G,
 ) -> usize
 where
     // accepting any generic that can return a reference to a slice of f64's
-    D: AsRef<[f64]>,
+    // or f32's
+    D: AsRef<[T]>,
     // accepting any generic that is an iterator that returns a tuple of
     // D and the label associated with it
-    R: Iterator<Item = (D, &'a str)>,
-    F: Fn(&[f64], &[f64]) -> f64,
+    R: Iterator<Item = (D, L)>,
+    F: Fn(&[T], &[T]) -> f64,
+    L: Clone,
+    G: LabelGroups<L> + ?Sized,
 {
-    // note for future improvement. this could be given as a replacement for the
-    // current iterator and just require that the iterator yields a tuple of
-    // an f64 and the label associated with it. could help solve some current
-    // issues with the search code and the predict would require not too much
-    // modification since it only runs this once.
-    for (data, label) in records {
-        collected.push((algo(&datapoint, data.as_ref()), label));
-    }
+    let distances = records.map(|(data, label)| (algo(datapoint, data.as_ref()), label));
 
-    // sort the collected records by the distance function. since floats
-    // dont directly implement the std::cmp::Ord trait we will sort by
-    // f64::total_cmp
-    collected.sort_by(|(a, _), (b, _)| a.total_cmp(b));
+    classify_distances(k, distances, collected, groups)
+}
 
-    let min = std::cmp::min(k, collected.len());
+/// performs the KNN algorithm on distances that have already been calculated
+///
+/// this allows callers to calculate the distances however they need to, such
+/// as from reused buffers, without allocating data for every record. refer to
+/// [`classify_datapoint`]
+pub fn classify_distances<R, L, G>(
+    k: usize,
+    distances: R,
+    collected: &mut Vec<Closest<L>>,
+    groups: &mut G,
+) -> usize
+where
+    R: Iterator<Item = (f64, L)>,
+    L: Clone,
+    G: LabelGroups<L> + ?Sized,
+{
+    select_closest(k, distances, collected);
 
     // collect the label groups and count how many are encountered
-    for index in 0..min {
-        groups
-            .entry(collected[index].1)
-            // increment if the group was previously added
-            .and_modify(|counter| *counter += 1)
-            // insert if not already existing
-            .or_insert(1);
+    count_labels(
+        collected.iter().map(|closest| closest.label.clone()),
+        groups,
+    )
+}
+
+/// finds the labels of the closest `k` records ordered from closest to
+/// furthest
+///
+/// the groups of any smaller k are the counts of a prefix of the labels so a
+/// range of k values only needs to calculate the distances once. refer to
+/// [`classify_datapoint`]
+pub fn closest_labels<F, R, D, T, L>(k: usize, records: R, algo: F, datapoint: &[T]) -> Vec<L>
+where
+    D: AsRef<[T]>,
+    R: Iterator<Item = (D, L)>,
+    F: Fn(&[T], &[T]) -> f64,
+{
+    let mut collected = Vec::with_capacity(k + 1);
+    let distances = records.map(|(data, label)| (algo(datapoint, data.as_ref()), label));
+
+    select_closest(k, distances, &mut collected);
+
+    collected.into_iter().map(|closest| closest.label).collect()
+}
+
+/// finds the labels of every record within `radius` of the datapoint ordered
+/// from closest to furthest
+///
+/// the distances of `algo` are compared against the radius so it must
+/// produce real distances instead of ranking distances
+pub fn labels_within<F, R, D, T, L>(radius: f64, records: R, algo: F, datapoint: &[T]) -> Vec<L>
+where
+    D: AsRef<[T]>,
+    R: Iterator<Item = (D, L)>,
+    F: Fn(&[T], &[T]) -> f64,
+{
+    let mut within: Vec<Closest<L>> = records
+        .enumerate()
+        .filter_map(|(position, (data, label))| {
+            let distance = algo(datapoint, data.as_ref());
+
+            (distance <= radius).then_some(Closest {
+                distance,
+                position,
+                label,
+            })
+        })
+        .collect();
+
+    within.sort_unstable();
+
+    within.into_iter().map(|closest| closest.label).collect()
+}
+
+/// keeps the closest `k` records in a max heap and then stores them in
+/// `collected` sorted from closest to furthest
+fn select_closest<R, L>(k: usize, distances: R, collected: &mut Vec<Closest<L>>)
+where
+    R: Iterator<Item = (f64, L)>,
+{
+    collected.clear();
+
+    let mut heap = BinaryHeap::from(std::mem::take(collected));
+
+    for (position, (distance, label)) in distances.enumerate() {
+        push_closest(
+            k,
+            &mut heap,
+            Closest {
+                distance,
+                position,
+                label,
+            },
+        );
     }
 
-    min
+    *collected = heap.into_sorted_vec();
+}
+
+/// adds the record to the heap if it is one of the closest `k` records seen
+fn push_closest<L>(k: usize, heap: &mut BinaryHeap<Closest<L>>, closest: Closest<L>) {
+    if heap.len() < k {
+        heap.push(closest);
+    } else if let Some(mut furthest) = heap.peek_mut()
+        && closest < *furthest
+    {
+        // the heap is reordered once the furthest record is replaced
+        *furthest = closest;
+    }
+}
+
+/// keeps the closest `k` records seen across any number of calls so records
+/// can be compared in blocks
+///
+/// records with the same distance are ordered by their position the same as
+/// [`closest_labels`]
+#[derive(Debug, Clone)]
+pub struct ClosestRecords {
+    k: usize,
+    heap: BinaryHeap<Closest>,
+}
+
+impl ClosestRecords {
+    pub fn new(k: usize) -> Self {
+        Self {
+            k,
+            heap: BinaryHeap::with_capacity(k + 1),
+        }
+    }
+
+    /// adds the record if it is one of the closest `k` records seen
+    pub fn push(&mut self, distance: f64, position: usize, label: u32) {
+        push_closest(
+            self.k,
+            &mut self.heap,
+            Closest {
+                distance,
+                position,
+                label,
+            },
+        );
+    }
+
+    /// the label ids of the closest records ordered from closest to furthest
+    pub fn into_labels(self) -> Vec<u32> {
+        self.heap
+            .into_sorted_vec()
+            .iter()
+            .map(|closest| closest.label)
+            .collect()
+    }
+}
+
+/// finds the label ids of the closest `k` records for every datapoint
+///
+/// the datapoints are split into tiles that are handled in parallel. each
+/// tile walks the records in blocks and `algo` calculates the distances from
+/// every datapoint of the tile to a block as a single matrix while the block
+/// is still in the cache instead of scanning all of the records once for
+/// each datapoint. the labels are the same as calling [`closest_labels`] for
+/// each datapoint
+pub fn closest_labels_batch<F>(
+    k: usize,
+    records: &Dataset,
+    algo: F,
+    datapoints: &[&[f64]],
+) -> Vec<Vec<u32>>
+where
+    F: Fn(ArrayView2<f64>, ArrayView2<f64>) -> Array2<f64> + Sync,
+{
+    let matrix = records.view();
+
+    datapoints
+        .par_chunks(QUERY_TILE)
+        .flat_map_iter(|tile| {
+            let mut heaps: Vec<ClosestRecords> =
+                tile.iter().map(|_| ClosestRecords::new(k)).collect();
+            let tile = Array2::from_shape_fn((tile.len(), records.dims()), |(row, column)| {
+                tile[row][column]
+            });
+
+            for start in (0..records.len()).step_by(RECORD_TILE) {
+                let end = std::cmp::min(start + RECORD_TILE, records.len());
+                let distances = algo(tile.view(), matrix.slice(s![start..end, ..]));
+
+                for (row, heap) in distances.rows().into_iter().zip(&mut heaps) {
+                    for (position, distance) in (start..end).zip(row) {
+                        heap.push(*distance, position, records.labels()[position]);
+                    }
+                }
+            }
+
+            heaps.into_iter().map(ClosestRecords::into_labels)
+        })
+        .collect()
+}
+
+/// finds the labels of the closest `k` records with the distances
+/// calculated in parallel
+///
+/// each thread only keeps the closest `k` records that it has seen and the
+/// results of the threads are merged together. records with the same distance
+/// are ordered by their position so the same labels are found as
+/// [`closest_labels`]
+pub fn closest_labels_par<F, R, D, T, L>(k: usize, records: R, algo: F, datapoint: &[T]) -> Vec<L>
+where
+    D: AsRef<[T]>,
+    R: IndexedParallelIterator<Item = (D, L)>,
+    F: Fn(&[T], &[T]) -> f64 + Sync,
+    T: Sync,
+    L: Send,
+{
+    let mut closest = records
+        .enumerate()
+        .fold(Vec::new, |mut closest, (index, (data, label))| {
+            closest.push((algo(datapoint, data.as_ref()), index, label));
+
+            // trimming only once twice as many records are collected keeps
+            // the cost of selecting low
+            if closest.len() >= k.max(1) * 2 {
+                keep_closest(&mut closest, k);
+            }
+
+            closest
+        })
+        .reduce(Vec::new, |mut closest, other| {
+            closest.extend(other);
+
+            keep_closest(&mut closest, k);
+
+            closest
+        });
+
+    keep_closest(&mut closest, k);
+
+    closest.sort_by(compare_closest);
+
+    closest.into_iter().map(|(_, _, label)| label).collect()
+}
+
+/// orders records by distance and then by their position
+fn compare_closest<L>(a: &(f64, usize, L), b: &(f64, usize, L)) -> Ordering {
+    a.0.total_cmp(&b.0).then(a.1.cmp(&b.1))
+}
+
+/// removes every record except for the closest `k`
+fn keep_closest<L>(closest: &mut Vec<(f64, usize, L)>, k: usize) {
+    if closest.len() <= k {
+        return;
+    }
+
+    if k == 0 {
+        closest.clear();
+
+        return;
+    }
+
+    closest.select_nth_unstable_by(k - 1, compare_closest);
+    closest.truncate(k);
+}
+
+/// sorts the closest `k` records to the front of the provided list
+///
+/// the records after the first `k` are left in an unspecified order so that
+/// records that can never be neighbors are not sorted. records with the same
+/// distance are ordered by their position
+pub fn sort_closest(closest: &mut [(f64, usize, u32)], k: usize) {
+    if k == 0 {
+        return;
+    }
+
+    if closest.len() > k {
+        closest.select_nth_unstable_by(k - 1, compare_closest);
+    }
+
+    let min = std::cmp::min(k, closest.len());
+
+    closest[..min].sort_unstable_by(compare_closest);
+}
+
+/// counts how many times each label is encountered
+///
+/// the counts are added to the existing groups. returns the number of labels
+/// counted
+pub fn count_labels<I, L, G>(labels: I, groups: &mut G) -> usize
+where
+    I: Iterator<Item = L>,
+    G: LabelGroups<L> + ?Sized,
+{
+    let mut total = 0;
+
+    for label in labels {
+        groups.add(label);
+        total += 1;
+    }
+
+    total
+}
+
+/// the number of closest records found with each label
+///
+/// a [`Vec<u32>`] counts interned label ids by their position in the list so
+/// no hashing is needed. a [`HashMap`] counts any label that can be hashed so
+/// labels do not need to be interned first
+pub trait LabelGroups<L> {
+    /// increases the count of the label by 1
+    fn add(&mut self, label: L);
+}
+
+impl LabelGroups<u32> for Vec<u32> {
+    /// the list is extended as larger label ids are encountered so a cleared
+    /// list can be reused
+    fn add(&mut self, label: u32) {
+        let index = label as usize;
+
+        if index >= self.len() {
+            self.resize(index + 1, 0);
+        }
+
+        self[index] += 1;
+    }
+}
+
+impl<L, S> LabelGroups<L> for HashMap<L, u32, S>
+where
+    L: Eq + Hash,
+    S: BuildHasher,
+{
+    fn add(&mut self, label: L) {
+        *self.entry(label).or_insert(0) += 1;
+    }
+}
+
+/// finds the label id with the largest count from the calculated groups
+///
+/// if multiple groups share the same count then the smallest label id will be
+/// returned
+pub fn largest_group(groups: &[u32]) -> Option<(u32, u32)> {
+    let mut largest = None::<(u32, u32)>;
+
+    for (label, count) in groups.iter().enumerate() {
+        if *count == 0 {
+            continue;
+        }
+
+        largest = match largest {
+            Some((known, largest_count)) if largest_count >= *count => Some((known, largest_count)),
+            _ => Some((label as u32, *count)),
+        };
+    }
+
+    largest
+}
+
+/// finds the most frequent label id from the provided list of label ids
+pub fn majority_label<I>(labels: I) -> Option<u32>
+where
+    I: Iterator<Item = u32>,
+{
+    let mut counts = Vec::new();
+
+    count_labels(labels, &mut counts);
+
+    largest_group(&counts).map(|(label, _)| label)
+}
+
+/// calculates the average of the numeric labels from the calculated groups
+///
+/// used when the labels represent numeric values (regression). `names` are
+/// the labels of each label id. returns [`None`] if there are no groups or a
+/// label fails to parse as a [`f64`]
+pub fn average_groups(groups: &[u32], names: &[String], min: usize) -> Option<f64> {
+    if min == 0 {
+        return None;
+    }
+
+    let mut total = 0.0;
+
+    for (label, count) in groups.iter().enumerate() {
+        if *count == 0 {
+            continue;
+        }
+
+        let value: f64 = names.get(label)?.parse().ok()?;
+
+        total += value * (*count as f64);
+    }
+
+    Some(total / (min as f64))
 }
 
 #[cfg(test)]
@@ -102,8 +534,6 @@ mod test {
     //! datapoints used for classification and testing can be visually
     //! represented in the `test_datapoints_visual.png` at the root of the
     //! repository.
-    use std::collections::HashMap;
-
     use crate::distance;
 
     use super::*;
@@ -111,19 +541,23 @@ mod test {
     const T1: [f64; 2] = [1.5, 1.0];
     const T2: [f64; 2] = [1.5, 1.5];
 
+    // label ids of the records
+    const A: u32 = 0;
+    const B: u32 = 1;
+
     // (x, y) datapoints on a small graph
-    const RECORDS: [([f64; 2], &'static str); 8] = [
-        ([1.0, 1.0], "a"),
-        ([2.0, 2.0], "b"),
-        ([1.5, 2.5], "a"),
-        ([1.0, 3.0], "b"),
-        ([2.0, 1.0], "a"),
-        ([1.0, 2.0], "b"),
-        ([3.0, 1.0], "a"),
-        ([2.5, 1.5], "b"),
+    const RECORDS: [([f64; 2], u32); 8] = [
+        ([1.0, 1.0], A),
+        ([2.0, 2.0], B),
+        ([1.5, 2.5], A),
+        ([1.0, 3.0], B),
+        ([2.0, 1.0], A),
+        ([1.0, 2.0], B),
+        ([3.0, 1.0], A),
+        ([2.5, 1.5], B),
     ];
 
-    fn records_iter() -> impl std::iter::Iterator<Item = (&'static [f64], &'static str)> {
+    fn records_iter() -> impl std::iter::Iterator<Item = (&'static [f64], u32)> {
         RECORDS
             .iter()
             .map(|(data, label)| (data.as_slice(), *label))
@@ -133,7 +567,7 @@ mod test {
     fn classify_datapoint_k2_euclidean_t1() {
         let (_min, groups) = classify_datapoint_owned(2, records_iter(), distance::euclidean, &T1);
 
-        let expected = HashMap::from([("a", 2)]);
+        let expected = vec![2];
 
         assert_eq!(groups, expected);
     }
@@ -142,7 +576,7 @@ mod test {
     fn classify_datapoint_k2_manhattan_t1() {
         let (_min, groups) = classify_datapoint_owned(2, records_iter(), distance::manhattan, &T1);
 
-        let expected = HashMap::from([("a", 2)]);
+        let expected = vec![2];
 
         assert_eq!(groups, expected);
     }
@@ -155,7 +589,7 @@ mod test {
         // depend more on ordering of floating point values when we sort the
         // values which should be a stable sort. refer to f64::total_cmp and
         // slice::sort_by
-        let expected = HashMap::from([("a", 1), ("b", 1)]);
+        let expected = vec![1, 1];
 
         assert_eq!(groups, expected);
     }
@@ -166,7 +600,7 @@ mod test {
 
         // similar to the euclidean, we should expect 4 equidistant datapoints
         // and sort by the specification in slice and f64
-        let expected = HashMap::from([("a", 1), ("b", 1)]);
+        let expected = vec![1, 1];
 
         assert_eq!(groups, expected);
     }
@@ -177,7 +611,7 @@ mod test {
 
         // there will be ambiguity between which b datapoint is selected but
         // it should still just be 1
-        let expected = HashMap::from([("a", 2), ("b", 1)]);
+        let expected = vec![2, 1];
 
         assert_eq!(groups, expected);
     }
@@ -188,7 +622,7 @@ mod test {
 
         // should be similar to the euclidean but still result in the same
         // groups
-        let expected = HashMap::from([("a", 2), ("b", 1)]);
+        let expected = vec![2, 1];
 
         assert_eq!(groups, expected);
     }
@@ -197,7 +631,7 @@ mod test {
     fn classify_datapoint_k3_euclidean_t2() {
         let (_min, groups) = classify_datapoint_owned(3, records_iter(), distance::euclidean, &T2);
 
-        let expected = HashMap::from([("a", 2), ("b", 1)]);
+        let expected = vec![2, 1];
 
         assert_eq!(groups, expected);
     }
@@ -206,8 +640,163 @@ mod test {
     fn classify_datapoint_k3_manhattan_t2() {
         let (_min, groups) = classify_datapoint_owned(3, records_iter(), distance::manhattan, &T2);
 
-        let expected = HashMap::from([("a", 2), ("b", 1)]);
+        let expected = vec![2, 1];
 
         assert_eq!(groups, expected);
     }
+
+    #[test]
+    fn classify_datapoint_enum_labels() {
+        #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+        enum Shape {
+            Circle,
+            Square,
+        }
+
+        let records = records_iter().map(|(data, label)| {
+            let shape = if label == A {
+                Shape::Circle
+            } else {
+                Shape::Square
+            };
+
+            (data, shape)
+        });
+
+        let mut collected = Vec::new();
+        let mut groups = HashMap::new();
+        let min = classify_datapoint(
+            3,
+            records,
+            distance::euclidean,
+            &T2,
+            &mut collected,
+            &mut groups,
+        );
+
+        assert_eq!(min, 3);
+        assert_eq!(groups[&Shape::Circle], 2);
+        assert_eq!(groups[&Shape::Square], 1);
+
+        let labels = closest_labels(2, records_iter(), distance::euclidean, &T1);
+
+        assert_eq!(labels, vec![A, A]);
+    }
+
+    #[test]
+    fn average_groups_numeric_labels() {
+        let names = vec!["1.5".to_owned(), "3".to_owned()];
+
+        assert_eq!(average_groups(&[2, 1], &names, 3), Some(2.0));
+        assert_eq!(average_groups(&[], &names, 0), None);
+    }
+
+    #[test]
+    fn closest_labels_par_matches() {
+        let records: Vec<([f64; 2], u32)> = (0..500)
+            .map(|index| {
+                let data = [(index % 17) as f64, (index % 5) as f64];
+
+                (data, (index % 3) as u32)
+            })
+            .collect();
+
+        for k in [0, 1, 4, 25, 600] {
+            let (min, groups) = classify_datapoint_owned(
+                k,
+                records.iter().map(|(data, label)| (data, *label)),
+                distance::euclidean,
+                &T1,
+            );
+            let labels = closest_labels(
+                k,
+                records.iter().map(|(data, label)| (data, *label)),
+                distance::euclidean,
+                &T1,
+            );
+            let par_labels = closest_labels_par(
+                k,
+                records.par_iter().map(|(data, label)| (data, *label)),
+                distance::euclidean,
+                &T1,
+            );
+            let mut par_groups = Vec::new();
+            let par_min = count_labels(par_labels.iter().copied(), &mut par_groups);
+
+            assert_eq!(labels, par_labels);
+            assert_eq!(min, par_min);
+            assert_eq!(groups, par_groups);
+        }
+    }
+
+    #[test]
+    fn closest_labels_batch_matches() {
+        let mut records = Dataset::new(2);
+
+        for index in 0..700 {
+            records.push(
+                &[(index % 17) as f64, (index % 5) as f64],
+                &(index % 3).to_string(),
+            );
+        }
+
+        let datapoints: Vec<[f64; 2]> = (0..40)
+            .map(|index| [(index % 13) as f64 * 1.5, (index % 7) as f64])
+            .collect();
+        let datapoints: Vec<&[f64]> = datapoints.iter().map(|point| point.as_slice()).collect();
+
+        for k in [0, 1, 9, 800] {
+            let batch =
+                closest_labels_batch(k, &records, distance::squared_euclidean_matrix, &datapoints);
+
+            assert_eq!(batch.len(), datapoints.len());
+
+            for (datapoint, labels) in datapoints.iter().zip(batch) {
+                let expected = closest_labels(
+                    k,
+                    records.iter().map(|record| (record.data, record.label)),
+                    distance::squared_euclidean,
+                    datapoint,
+                );
+
+                assert_eq!(labels, expected);
+            }
+        }
+    }
+
+    #[test]
+    fn largest_group_ties() {
+        assert_eq!(largest_group(&[0, 2, 2, 1]), Some((1, 2)));
+        assert_eq!(largest_group(&[0, 0]), None);
+        assert_eq!(majority_label([2, 0, 2, 0].into_iter()), Some(0));
+    }
+
+    #[test]
+    fn sort_closest_prefix() {
+        let mut closest = vec![
+            (3.0, 0, 0),
+            (1.0, 1, 1),
+            (2.0, 2, 2),
+            (1.0, 3, 3),
+            (0.5, 4, 4),
+        ];
+
+        sort_closest(&mut closest, 3);
+
+        let labels: Vec<u32> = closest.iter().take(3).map(|(_, _, label)| *label).collect();
+
+        assert_eq!(labels, vec![4, 1, 3]);
+    }
+
+    #[test]
+    fn labels_within_radius() {
+        let labels: Vec<u32> = labels_within(0.6, records_iter(), distance::euclidean, &T1);
+
+        // only the records half a unit away are within the radius
+        assert_eq!(labels, vec![A, A]);
+
+        let labels: Vec<u32> = labels_within(0.1, records_iter(), distance::euclidean, &T1);
+
+        assert!(labels.is_empty());
+    }
 }
@@ -1,7 +1,31 @@
+use std::borrow::Cow;
 use std::convert::Infallible;
 use std::str::FromStr;
+use std::time::Duration;
 
+use anyhow::{Context, bail};
 use clap::ValueEnum;
+use ndarray::{Array2, ArrayView2};
+use serde::{Deserialize, Serialize};
+
+use crate::distance;
+
+/// limits the number of threads used for parallel work. every available cpu
+/// is used if not specified
+pub fn init_threads(threads: Option<usize>) -> anyhow::Result<()> {
+    let Some(threads) = threads else {
+        return Ok(());
+    };
+
+    if threads == 0 {
+        bail!("the number of threads must be larger than 0");
+    }
+
+    rayon::ThreadPoolBuilder::new()
+        .num_threads(threads)
+        .build_global()
+        .context("failed to create thread pool")
+}
 
 /// represents the k value to use for calculations
 #[derive(Debug, Clone)]
@@ -33,12 +57,42 @@ impl KValue {
         }
     }
 
-    pub fn get_range(&self, total: usize) -> std::iter::StepBy<std::ops::Range<usize>> {
+    /// retrieves the k values to use with the given number of records
+    ///
+    /// if `odd_only` is true then even k values are skipped to avoid ties
+    /// between two labels
+    pub fn get_range(&self, total: usize, odd_only: bool) -> impl Iterator<Item = usize> + use<> {
         // figure out if the minimum value is either the k or the number of records
         // collected
         let len = std::cmp::min(total, self.0.1);
 
-        ((self.0.0)..(len)).step_by(self.0.2)
+        ((self.0.0)..(len))
+            .step_by(self.0.2)
+            .filter(move |k| !odd_only || k % 2 == 1)
+    }
+
+    /// fails if `odd_only` would skip every k value of the range, such as
+    /// `4` or `2-10,2`
+    pub fn check_odd_only(&self, odd_only: bool) -> anyhow::Result<()> {
+        if odd_only && self.get_range(usize::MAX, true).next().is_none() {
+            bail!("k {self} has no odd values to use with --odd-only");
+        }
+
+        Ok(())
+    }
+}
+
+impl std::fmt::Display for KValue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let (low, high, step) = self.0;
+
+        if high == low + 1 {
+            write!(f, "{low}")
+        } else if step == 1 {
+            write!(f, "{low}-{}", high - 1)
+        } else {
+            write!(f, "{low}-{},{step}", high - 1)
+        }
     }
 }
 
@@ -74,13 +128,267 @@ impl FromStr for KValue {
     }
 }
 
+/// represents the k value for predictions which can also be automatically
+/// selected
+#[derive(Debug, Clone)]
+pub enum KSelection {
+    /// the k value is chosen by cross validating over a range of k values
+    Auto,
+
+    /// the k values were specified
+    Value(KValue),
+}
+
+impl Default for KSelection {
+    /// a single k value of 3
+    fn default() -> Self {
+        Self::Value(KValue((3, 4, 1)))
+    }
+}
+
+impl FromStr for KSelection {
+    type Err = &'static str;
+
+    fn from_str(given: &str) -> Result<Self, Self::Err> {
+        if given == "auto" {
+            Ok(Self::Auto)
+        } else {
+            KValue::from_str(given).map(Self::Value)
+        }
+    }
+}
+
+/// the floating point precision used to store datapoints and calculate
+/// distances
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Precision {
+    /// 64 bit floating point values
+    F64,
+
+    /// 32 bit floating point values. uses half the memory of f64 and is
+    /// faster to calculate
+    F32,
+
+    /// product quantized codes. uses 8 to 16 times less memory than f64 but
+    /// the distances are approximate
+    Pq,
+}
+
+/// the device used to calculate brute force distances
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Device {
+    /// calculates distances on the cpu
+    Cpu,
+
+    /// calculates distances with a compute shader. requires the gpu feature
+    /// and falls back to the cpu if no gpu is available
+    Gpu,
+}
+
+/// how a name shared by more than one header is resolved
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum OnDuplicate {
+    /// fails when a name shared by more than one header is used
+    #[default]
+    Error,
+
+    /// uses the first column with the name
+    First,
+
+    /// uses the last column with the name
+    Last,
+
+    /// renames every column after the first with a suffix of its count,
+    /// such as `x_2`, so each can be selected
+    Suffix,
+}
+
+/// how the names of columns are compared against the headers
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum HeaderMatch {
+    /// the name must be the same as the header
+    #[default]
+    Exact,
+
+    /// whitespace around the name and its case are ignored
+    Loose,
+}
+
+impl HeaderMatch {
+    /// the form of a name or header that is compared. a byte order mark at
+    /// the start is always removed
+    pub fn normalize<'a>(&self, name: &'a str) -> Cow<'a, str> {
+        let name = name.strip_prefix('\u{feff}').unwrap_or(name);
+
+        match self {
+            Self::Exact => Cow::Borrowed(name),
+            Self::Loose => Cow::Owned(name.trim().to_lowercase()),
+        }
+    }
+}
+
+/// what to predict when no records are within the radius of a datapoint
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum RadiusFallback {
+    /// uses the closest record even though it is outside of the radius
+    Nearest,
+
+    /// uses every record so the most common label, or the average for
+    /// regression, is predicted
+    Majority,
+
+    /// reports that the datapoint has no neighbors
+    None,
+}
+
+/// how the results of a command are printed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum OutputFormat {
+    /// aligned columns for reading in a terminal
+    Table,
+
+    /// a versioned json object on each line
+    Json,
+}
+
+/// how the labels of the training records are balanced before classifying
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Balance {
+    /// the training records are used as they are
+    #[default]
+    None,
+
+    /// randomly drops records of the larger labels until every label has
+    /// as many records as the smallest one
+    Undersample,
+
+    /// creates records for the smaller labels between each of their records
+    /// and its closest records of the same label until every label has as
+    /// many records as the largest one
+    Smote,
+}
+
 /// represents the algorithm to use when calculating distances
-#[derive(Debug, Clone, ValueEnum)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
 pub enum AlgoType {
     Euclidean,
     Manhattan,
 }
 
+impl AlgoType {
+    /// retrieves the distance function for the algorithm
+    pub fn as_fn(&self) -> fn(&[f64], &[f64]) -> f64 {
+        match self {
+            Self::Euclidean => distance::euclidean,
+            Self::Manhattan => distance::manhattan,
+        }
+    }
+
+    /// calculates the distance between 2 datapoints
+    pub fn distance(&self, a: &[f64], b: &[f64]) -> f64 {
+        (self.as_fn())(a, b)
+    }
+
+    /// retrieves a function that produces distances with the same ordering
+    /// as [`AlgoType::as_fn`]
+    ///
+    /// the euclidean square root is skipped so this should only be used when
+    /// ranking neighbors and never when the distances are shown or compared
+    /// against real distances
+    pub fn as_rank_fn(&self) -> fn(&[f64], &[f64]) -> f64 {
+        match self {
+            Self::Euclidean => distance::squared_euclidean,
+            Self::Manhattan => distance::manhattan,
+        }
+    }
+
+    /// the same as [`AlgoType::as_rank_fn`] but calculates the distance from
+    /// every row of the first matrix to every row of the second
+    pub fn as_rank_matrix_fn(&self) -> fn(ArrayView2<f64>, ArrayView2<f64>) -> Array2<f64> {
+        match self {
+            Self::Euclidean => distance::squared_euclidean_matrix,
+            Self::Manhattan => distance::manhattan_matrix,
+        }
+    }
+
+    /// the same as [`AlgoType::as_rank_fn`] but for f32 datapoints
+    pub fn as_rank_f32_fn(&self) -> fn(&[f32], &[f32]) -> f64 {
+        match self {
+            Self::Euclidean => distance::squared_euclidean_f32,
+            Self::Manhattan => distance::manhattan_f32,
+        }
+    }
+
+    /// retrieves the function that calculates the contribution of a single
+    /// column to the distance
+    ///
+    /// summing the contributions of each column will produce distances with
+    /// the same ordering as [`AlgoType::as_fn`]
+    pub fn as_term_fn(&self) -> fn(f64, f64) -> f64 {
+        match self {
+            Self::Euclidean => distance::squared_difference,
+            Self::Manhattan => distance::absolute_difference,
+        }
+    }
+}
+
+impl std::fmt::Display for AlgoType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Euclidean => f.write_str("euclidean"),
+            Self::Manhattan => f.write_str("manhattan"),
+        }
+    }
+}
+
+/// represents how the labels of a dataset are treated
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TaskType {
+    /// labels are discrete classes and predictions are made by majority vote
+    Classification,
+
+    /// labels are numeric and predictions are the average of the neighbors
+    Regression,
+}
+
+impl std::fmt::Display for TaskType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Classification => f.write_str("classification"),
+            Self::Regression => f.write_str("regression"),
+        }
+    }
+}
+
+/// represents the records that test records are classified against when
+/// searching
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EvalAgainst {
+    /// only the training records
+    Train,
+
+    /// every record including the test records. a test record will be one
+    /// of its own neighbors
+    All,
+}
+
+/// how the records are divided into training and test records
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SplitStrategy {
+    /// shuffles the records of each label before splitting them
+    #[default]
+    Random,
+
+    /// tests the latest records so every test record comes after every
+    /// training record in time
+    Temporal,
+}
+
 /// represents the column type specified in the command line arguments
 #[derive(Debug, Clone)]
 pub enum ColumnType {
@@ -89,20 +397,243 @@ pub enum ColumnType {
 
     /// a defined zero based index number in the csv
     Index(usize),
+
+    /// an index counted back from the end of the columns. given as a
+    /// negative number so 1 is the last column
+    FromEnd(usize),
+
+    /// an inclusive range of zero based indexes in the csv
+    Range(usize, usize),
+
+    /// a regular expression matching the names of the columns. given with a
+    /// `re:` prefix
+    Pattern(String),
+
+    /// every column except the label
+    All,
+}
+
+impl ColumnType {
+    /// the specified label or the last column if none was specified. a
+    /// notice is printed when the last column is assumed
+    pub fn label_or_last(label: Option<&ColumnType>) -> ColumnType {
+        match label {
+            Some(label) => label.clone(),
+            None => {
+                eprintln!("no label column specified. using the last column as the label");
+
+                ColumnType::FromEnd(1)
+            }
+        }
+    }
+
+    /// parses both sides of a range like `2-7`
+    fn parse_range(given: &str) -> Option<(usize, usize)> {
+        let (start, end) = given.split_once('-')?;
+
+        Some((usize::from_str(start).ok()?, usize::from_str(end).ok()?))
+    }
 }
 
 impl FromStr for ColumnType {
     type Err = Infallible;
 
     fn from_str(given: &str) -> Result<Self, Self::Err> {
-        if let Ok(index) = usize::from_str(given) {
+        if given == "all" {
+            Ok(Self::All)
+        } else if let Some(pattern) = given.strip_prefix("re:") {
+            Ok(Self::Pattern(pattern.into()))
+        } else if let Ok(index) = usize::from_str(given) {
             Ok(Self::Index(index))
+        } else if let Some(Ok(offset)) = given.strip_prefix('-').map(usize::from_str) {
+            Ok(Self::FromEnd(offset))
+        } else if let Some((start, end)) = Self::parse_range(given) {
+            Ok(Self::Range(start, end))
         } else {
             Ok(Self::Name(given.into()))
         }
     }
 }
 
+/// a comma separated list of columns given to a single argument
+///
+/// a `re:` column takes the rest of the argument since a regular expression
+/// can contain commas. columns after one need their own argument
+#[derive(Debug, Clone)]
+pub struct ColumnList(pub Vec<ColumnType>);
+
+impl ColumnList {
+    /// the columns of every argument in order
+    pub fn flatten(lists: &[ColumnList]) -> Vec<ColumnType> {
+        lists
+            .iter()
+            .flat_map(|list| list.0.iter().cloned())
+            .collect()
+    }
+}
+
+impl FromStr for ColumnList {
+    type Err = Infallible;
+
+    fn from_str(given: &str) -> Result<Self, Self::Err> {
+        let mut rtn = Vec::new();
+        let mut rest = given;
+
+        loop {
+            if rest.starts_with("re:") {
+                rtn.push(ColumnType::from_str(rest)?);
+
+                break;
+            }
+
+            match rest.split_once(',') {
+                Some((column, next)) => {
+                    rtn.push(ColumnType::from_str(column)?);
+                    rest = next;
+                }
+                None => {
+                    rtn.push(ColumnType::from_str(rest)?);
+
+                    break;
+                }
+            }
+        }
+
+        Ok(Self(rtn))
+    }
+}
+
+/// represents an amount of time specified as a number with an optional unit
+///
+/// the available units are `ms`, `s`, `m`, and `h`. seconds are used if no
+/// unit is specified
+#[derive(Debug, Clone, Copy)]
+pub struct TimeLimit(Duration);
+
+impl TimeLimit {
+    pub fn into_inner(self) -> Duration {
+        self.0
+    }
+}
+
+impl FromStr for TimeLimit {
+    type Err = &'static str;
+
+    fn from_str(given: &str) -> Result<Self, Self::Err> {
+        // the order matters since "ms" also ends with "s"
+        let (value, multiplier) = if let Some(value) = given.strip_suffix("ms") {
+            (value, 0.001)
+        } else if let Some(value) = given.strip_suffix('s') {
+            (value, 1.0)
+        } else if let Some(value) = given.strip_suffix('m') {
+            (value, 60.0)
+        } else if let Some(value) = given.strip_suffix('h') {
+            (value, 3600.0)
+        } else {
+            (given, 1.0)
+        };
+
+        let Ok(value) = f64::from_str(value) else {
+            return Err("failed to parse time limit");
+        };
+
+        if !value.is_finite() || value <= 0.0 {
+            return Err("time limit must be larger than 0");
+        }
+
+        Ok(Self(Duration::from_secs_f64(value * multiplier)))
+    }
+}
+
+/// represents an amount of memory specified as a number with an optional unit
+///
+/// the available units are `K`, `M`, `G`, and `T` with an optional `B` or `iB`
+/// suffix. each unit is a power of 1024 and bytes are used if no unit is
+/// specified
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MemorySize(u64);
+
+impl MemorySize {
+    pub fn bytes(&self) -> u64 {
+        self.0
+    }
+}
+
+impl FromStr for MemorySize {
+    type Err = &'static str;
+
+    fn from_str(given: &str) -> Result<Self, Self::Err> {
+        let upper = given.trim().to_ascii_uppercase();
+        let value = upper
+            .strip_suffix("IB")
+            .or_else(|| upper.strip_suffix('B'))
+            .unwrap_or(&upper);
+
+        let (value, shift) = if let Some(value) = value.strip_suffix('K') {
+            (value, 10)
+        } else if let Some(value) = value.strip_suffix('M') {
+            (value, 20)
+        } else if let Some(value) = value.strip_suffix('G') {
+            (value, 30)
+        } else if let Some(value) = value.strip_suffix('T') {
+            (value, 40)
+        } else {
+            (value, 0)
+        };
+
+        let Ok(value) = f64::from_str(value.trim()) else {
+            return Err("failed to parse memory size");
+        };
+
+        if !value.is_finite() || value <= 0.0 {
+            return Err("memory size must be larger than 0");
+        }
+
+        Ok(Self((value * (1u64 << shift) as f64) as u64))
+    }
+}
+
+/// the number of outer and inner folds for nested cross validation
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NestedCv {
+    pub outer: usize,
+    pub inner: usize,
+}
+
+impl FromStr for NestedCv {
+    type Err = &'static str;
+
+    fn from_str(given: &str) -> Result<Self, Self::Err> {
+        let mut outer = None;
+        let mut inner = None;
+
+        for part in given.split(',') {
+            let Some((key, value)) = part.split_once('=') else {
+                return Err("expected outer=N,inner=N for nested cross validation");
+            };
+
+            let Ok(value) = usize::from_str(value.trim()) else {
+                return Err("failed to parse number of folds");
+            };
+
+            if value < 2 {
+                return Err("the number of folds must be at least 2");
+            }
+
+            match key.trim() {
+                "outer" => outer = Some(value),
+                "inner" => inner = Some(value),
+                _ => return Err("unknown nested cross validation key. expected outer or inner"),
+            }
+        }
+
+        match (outer, inner) {
+            (Some(outer), Some(inner)) => Ok(Self { outer, inner }),
+            _ => Err("both outer and inner folds must be specified"),
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct Datapoint(Vec<f64>);
 
@@ -126,3 +657,104 @@ impl FromStr for Datapoint {
         Ok(Self(rtn))
     }
 }
+
+/// a datapoint given in the arguments or `-` to read the datapoints from
+/// stdin
+#[derive(Debug, Clone)]
+pub enum DatapointArg {
+    Value(Datapoint),
+    /// values bound to columns by name, such as `sepal_length=5.1`
+    Named(Vec<(String, f64)>),
+    Stdin,
+}
+
+impl FromStr for DatapointArg {
+    type Err = &'static str;
+
+    fn from_str(given: &str) -> Result<Self, Self::Err> {
+        if given == "-" {
+            Ok(Self::Stdin)
+        } else if given.contains('=') {
+            let mut rtn = Vec::new();
+
+            for pair in given.split(',') {
+                let Some((name, value)) = pair.split_once('=') else {
+                    return Err("named datapoints must give every value as name=value");
+                };
+                let value = f64::from_str(value.trim()).map_err(|_| "failed to parse datapoint")?;
+
+                rtn.push((name.trim().to_owned(), value));
+            }
+
+            Ok(Self::Named(rtn))
+        } else {
+            Datapoint::from_str(given).map(Self::Value)
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn nested_cv() {
+        assert_eq!(
+            NestedCv::from_str("outer=5,inner=3"),
+            Ok(NestedCv { outer: 5, inner: 3 })
+        );
+        assert!(NestedCv::from_str("outer=5").is_err());
+        assert!(NestedCv::from_str("outer=1,inner=3").is_err());
+    }
+
+    #[test]
+    fn k_value_range() {
+        let k = KValue::from_str("2-8").unwrap();
+
+        assert_eq!(
+            k.get_range(100, false).collect::<Vec<_>>(),
+            vec![2, 3, 4, 5, 6, 7, 8]
+        );
+        assert_eq!(k.get_range(5, false).collect::<Vec<_>>(), vec![2, 3, 4]);
+        assert_eq!(k.get_range(100, true).collect::<Vec<_>>(), vec![3, 5, 7]);
+
+        // an even start with an even step never reaches an odd value
+        let even = KValue::from_str("2-10,2").unwrap();
+
+        assert_eq!(even.get_range(100, true).count(), 0);
+        assert!(even.check_odd_only(true).is_err());
+        assert!(even.check_odd_only(false).is_ok());
+        assert!(KValue::from_str("4").unwrap().check_odd_only(true).is_err());
+        assert!(
+            KValue::from_str("3-9,2")
+                .unwrap()
+                .check_odd_only(true)
+                .is_ok()
+        );
+        assert_eq!(k.to_string(), "2-8");
+        assert_eq!(KValue::from_str("2-8,3").unwrap().to_string(), "2-8,3");
+        assert_eq!(KValue::from_str("7").unwrap().to_string(), "7");
+    }
+
+    #[test]
+    fn column_list_pattern() {
+        let list = ColumnList::from_str("0,bill,re:^bill_[a-z]{1,6}_mm$").unwrap();
+
+        assert!(matches!(list.0[0], ColumnType::Index(0)));
+        assert!(matches!(&list.0[1], ColumnType::Name(name) if name == "bill"));
+        assert!(
+            matches!(&list.0[2], ColumnType::Pattern(pattern) if pattern == "^bill_[a-z]{1,6}_mm$")
+        );
+        assert_eq!(list.0.len(), 3);
+    }
+
+    #[test]
+    fn memory_size() {
+        assert_eq!(MemorySize::from_str("4G").unwrap().bytes(), 4 << 30);
+        assert_eq!(MemorySize::from_str("512mb").unwrap().bytes(), 512 << 20);
+        assert_eq!(MemorySize::from_str("1.5KiB").unwrap().bytes(), 1536);
+        assert_eq!(MemorySize::from_str("100").unwrap().bytes(), 100);
+        assert!(MemorySize::from_str("0G").is_err());
+        assert!(MemorySize::from_str("lots").is_err());
+    }
+}
@@ -0,0 +1,194 @@
+use anyhow::bail;
+use clap::Args;
+
+use crate::classify::{classify_distances, largest_group};
+use crate::cli::{AlgoType, ColumnList, ColumnType};
+use crate::csv::{KnnRecord, collect_records, get_columns_excluding};
+use crate::distance::DistanceMetric;
+use crate::metrics::{Evaluation, mcnemar};
+use crate::source::DataSource;
+use crate::split::fold_dataset;
+
+#[derive(Debug, Args)]
+pub struct CompareArgs {
+    /// the number of neighbors to lookup for the first configuration
+    #[arg(long = "a-k", default_value = "3")]
+    a_k: usize,
+
+    /// the algorithm to use for the first configuration
+    #[arg(long = "a-algo", default_value = "euclidean")]
+    a_algo: AlgoType,
+
+    /// the list of columns to use for the first configuration
+    #[arg(long = "a-col", allow_hyphen_values = true)]
+    a_columns: Vec<ColumnList>,
+
+    /// the number of neighbors to lookup for the second configuration
+    #[arg(long = "b-k", default_value = "3")]
+    b_k: usize,
+
+    /// the algorithm to use for the second configuration
+    #[arg(long = "b-algo", default_value = "euclidean")]
+    b_algo: AlgoType,
+
+    /// the list of columns to use for the second configuration. defaults to
+    /// the columns of the first configuration
+    #[arg(long = "b-col", allow_hyphen_values = true)]
+    b_columns: Vec<ColumnList>,
+
+    /// columns to remove from the columns of both configurations
+    #[arg(long = "exclude-col", allow_hyphen_values = true)]
+    exclude: Vec<ColumnList>,
+
+    /// the number of folds to evaluate the configurations over
+    #[arg(long, default_value = "5")]
+    folds: usize,
+
+    /// the column to use as the label
+    #[arg(long, allow_negative_numbers = true)]
+    label: Option<ColumnType>,
+}
+
+/// a single set of parameters to evaluate
+struct Config {
+    k: usize,
+    algo: AlgoType,
+    /// the indexes in the collected record data to use
+    data: Vec<usize>,
+}
+
+pub fn knn_compare<S>(mut source: S, arg: CompareArgs) -> anyhow::Result<()>
+where
+    S: DataSource,
+{
+    if arg.a_k == 0 || arg.b_k == 0 {
+        bail!("k value cannot be 0");
+    }
+
+    if arg.folds < 2 {
+        bail!("the number of folds must be at least 2");
+    }
+
+    let a_columns = ColumnList::flatten(&arg.a_columns);
+    let b_columns = if arg.b_columns.is_empty() {
+        a_columns.clone()
+    } else {
+        ColumnList::flatten(&arg.b_columns)
+    };
+    let exclude = ColumnList::flatten(&arg.exclude);
+
+    // both configurations are retrieved at once so that the records only
+    // have to be parsed a single time. the first configuration will be at
+    // the start of the record data followed by the second configuration
+    let label_column = ColumnType::label_or_last(arg.label.as_ref());
+    let (label, mut columns) =
+        get_columns_excluding(&mut source, &label_column, &a_columns, &exclude)?;
+    let a_len = columns.len();
+    let (_, b_cols) = get_columns_excluding(&mut source, &label_column, &b_columns, &exclude)?;
+
+    columns.extend(b_cols);
+
+    let records = collect_records(source, label, &columns)?;
+
+    let configs = [
+        Config {
+            k: arg.a_k,
+            algo: arg.a_algo,
+            data: (0..a_len).collect(),
+        },
+        Config {
+            k: arg.b_k,
+            algo: arg.b_algo,
+            data: (a_len..columns.len()).collect(),
+        },
+    ];
+
+    let folds = fold_dataset(records.iter(), arg.folds);
+
+    let mut collected = Vec::new();
+    let mut groups = Vec::new();
+    let mut a_buf = Vec::new();
+    let mut b_buf = Vec::new();
+
+    // (a correct, b correct) counts for each combination
+    let mut both = 0;
+    let mut a_only = 0;
+    let mut b_only = 0;
+    let mut neither = 0;
+
+    for fold in &folds {
+        let mut in_fold = vec![false; records.len()];
+
+        for index in fold {
+            in_fold[*index] = true;
+        }
+
+        for test_index in fold {
+            let test_record = records.get(*test_index);
+            let mut correct = [false; 2];
+
+            for (config, is_correct) in configs.iter().zip(correct.iter_mut()) {
+                collected.clear();
+                groups.clear();
+
+                collect_data(test_record, &config.data, &mut a_buf);
+
+                // only the records outside of the current fold are used for
+                // training. the columns of each record are collected into the
+                // same buffer so nothing is allocated per record
+                let distances = records
+                    .iter()
+                    .enumerate()
+                    .filter(|(index, _)| !in_fold[*index])
+                    .map(|(_, train_record)| {
+                        collect_data(train_record, &config.data, &mut b_buf);
+
+                        (config.algo.rank(&a_buf, &b_buf), train_record.label)
+                    });
+
+                classify_distances(config.k, distances, &mut collected, &mut groups);
+
+                if let Some((label, _)) = largest_group(&groups) {
+                    *is_correct = label == test_record.label;
+                }
+            }
+
+            match correct {
+                [true, true] => both += 1,
+                [true, false] => a_only += 1,
+                [false, true] => b_only += 1,
+                [false, false] => neither += 1,
+            }
+        }
+    }
+
+    let a_eval = Evaluation::Classification {
+        passed: both + a_only,
+        failed: b_only + neither,
+        unknown: 0,
+    };
+    let b_eval = Evaluation::Classification {
+        passed: both + b_only,
+        failed: a_only + neither,
+        unknown: 0,
+    };
+
+    println!("records: {} folds: {}", records.len(), folds.len());
+    println!("a: k {} {:?} | {a_eval}", arg.a_k, arg.a_algo);
+    println!("b: k {} {:?} | {b_eval}", arg.b_k, arg.b_algo);
+    println!("both: {both} a only: {a_only} b only: {b_only} neither: {neither}");
+
+    let (chi2, p) = mcnemar(a_only, b_only);
+
+    println!("mcnemar chi2: {chi2:.4} p-value: {p:.4}");
+
+    Ok(())
+}
+
+fn collect_data(record: KnnRecord, data: &[usize], buf: &mut Vec<f64>) {
+    buf.clear();
+
+    for index in data {
+        buf.push(record.data[*index]);
+    }
+}
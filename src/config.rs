@@ -0,0 +1,205 @@
+//! default arguments loaded from a toml file
+//!
+//! the config lists the arguments that are otherwise typed for every
+//! command. a value is only used when the command accepts the argument and
+//! it was not given on the command line or through the environment.
+//!
+//! ```toml
+//! file = "penguins.csv"
+//! columns = ["bill_length_mm", "bill_depth_mm", 5]
+//! label = "species"
+//! algo = "manhattan"
+//! scale = "zscore"
+//! ```
+//!
+//! a relative `file` is relative to the directory of the config
+use std::path::{Path, PathBuf};
+
+use anyhow::Context;
+use clap::parser::ValueSource;
+use clap::{ArgMatches, Command};
+use serde::Deserialize;
+
+use crate::cli::AlgoType;
+use crate::scale::ScaleType;
+
+/// the config that is loaded from the working directory if one is not
+/// specified
+pub const DEFAULT_CONFIG: &str = "knn.toml";
+
+/// the arguments that replace the csv. the file of the config is not used
+/// when any of them are given
+const REPLACES_CSV: [&str; 2] = ["model", "index_file"];
+
+/// the format of the config file
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    pub file: Option<PathBuf>,
+    #[serde(default)]
+    pub columns: Vec<ConfigColumn>,
+    pub label: Option<ConfigColumn>,
+    pub algo: Option<AlgoType>,
+    pub scale: Option<ScaleType>,
+}
+
+/// a column can either be a header name or an index
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+pub enum ConfigColumn {
+    Index(i64),
+    Name(String),
+}
+
+impl std::fmt::Display for ConfigColumn {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Index(index) => write!(f, "{index}"),
+            Self::Name(name) => f.write_str(name),
+        }
+    }
+}
+
+impl Config {
+    /// loads a config from the specified toml file
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        let contents = std::fs::read_to_string(path)
+            .with_context(|| format!("failed to read config file. path: {}", path.display()))?;
+        let mut config = Self::parse(&contents)?;
+
+        if let (Some(file), Some(dir)) = (&config.file, path.parent()) {
+            config.file = Some(dir.join(file));
+        }
+
+        Ok(config)
+    }
+
+    /// parses a config from the contents of a toml file
+    pub fn parse(contents: &str) -> anyhow::Result<Self> {
+        toml::from_str(contents).context("failed to parse config file")
+    }
+
+    /// the command line arguments for the values of the config that were
+    /// not already given
+    ///
+    /// `command` must be built so that the global arguments are available to
+    /// the subcommands. the arguments are for the deepest subcommand of
+    /// `matches` so they can be appended to the end of the command line
+    pub fn args(&self, command: &Command, matches: &ArgMatches) -> Vec<String> {
+        let (mut command, mut matches) = (command, matches);
+
+        while let Some((name, sub_matches)) = matches.subcommand() {
+            let Some(sub_command) = command.find_subcommand(name) else {
+                break;
+            };
+
+            command = sub_command;
+            matches = sub_matches;
+        }
+
+        let given = |id: &str| {
+            command.get_arguments().any(|arg| arg.get_id() == id)
+                && matches!(
+                    matches.value_source(id),
+                    Some(ValueSource::CommandLine | ValueSource::EnvVariable)
+                )
+        };
+
+        let mut values: Vec<(&str, Vec<String>)> = Vec::new();
+
+        if let Some(file) = &self.file
+            && !REPLACES_CSV.iter().any(|id| given(id))
+        {
+            values.push(("file", vec![file.display().to_string()]));
+        }
+
+        values.push((
+            "columns",
+            self.columns.iter().map(ToString::to_string).collect(),
+        ));
+        values.push((
+            "label",
+            self.label.iter().map(ToString::to_string).collect(),
+        ));
+        values.push(("algo", self.algo.iter().map(ToString::to_string).collect()));
+        values.push((
+            "scale",
+            self.scale.iter().map(ToString::to_string).collect(),
+        ));
+
+        let mut args = Vec::new();
+
+        for (id, given_values) in values {
+            let Some(arg) = command.get_arguments().find(|arg| arg.get_id() == id) else {
+                continue;
+            };
+            let Some(long) = arg.get_long() else {
+                continue;
+            };
+
+            // a value the command line conflicts with would fail to parse.
+            // conflicts are only listed on the argument that declared them
+            // so both directions are checked
+            let conflicts = command.get_arguments().any(|other| {
+                given(other.get_id().as_str())
+                    && (command.get_arg_conflicts_with(arg).contains(&other)
+                        || command.get_arg_conflicts_with(other).contains(&arg))
+            });
+
+            if given(id) || conflicts {
+                continue;
+            }
+
+            for value in given_values {
+                args.push(format!("--{long}={value}"));
+            }
+        }
+
+        args
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use clap::{Arg, ArgAction};
+
+    use super::*;
+
+    #[test]
+    fn config_args() {
+        let config = Config::parse(
+            "file = \"data.csv\"\ncolumns = [\"a\", -1]\nlabel = 2\nalgo = \"manhattan\"\n",
+        )
+        .unwrap();
+        let mut command = Command::new("knn")
+            .arg(Arg::new("file").long("file").global(true))
+            .subcommand(
+                Command::new("predict")
+                    .arg(
+                        Arg::new("columns")
+                            .long("col")
+                            .action(ArgAction::Append)
+                            .allow_hyphen_values(true),
+                    )
+                    .arg(Arg::new("label").long("label"))
+                    .arg(Arg::new("model").long("model").conflicts_with("label")),
+            );
+
+        command.build();
+
+        let matches = command
+            .clone()
+            .get_matches_from(["knn", "predict", "--label", "x"]);
+
+        assert_eq!(
+            config.args(&command, &matches),
+            vec!["--file=data.csv", "--col=a", "--col=-1"]
+        );
+
+        let matches = command
+            .clone()
+            .get_matches_from(["knn", "predict", "--model", "m", "--col", "b"]);
+
+        assert!(config.args(&command, &matches).is_empty());
+    }
+}
@@ -1,164 +1,798 @@
+use std::borrow::Cow;
 use std::collections::HashMap;
-use std::str::FromStr;
 
-use anyhow::{Context, bail};
-pub use csv::{Reader, ReaderBuilder, StringRecord};
+pub use csv::{Reader, ReaderBuilder, StringRecord, Writer};
+use ndarray::{Array2, ArrayView1, ArrayView2};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
 
-use crate::cli::ColumnType;
+use crate::cli::{ColumnType, HeaderMatch, OnDuplicate};
+use crate::error::{KnnError, KnnResult};
+use crate::memory;
+use crate::source::{DataSource, LabelFilter};
 
-/// represents the data collected from the csv for the knn
-#[derive(Debug)]
-pub struct KnnRecord {
+/// the number of csv rows loaded between checks of the memory budget
+const MEMORY_CHECK_ROWS: usize = 4096;
+
+/// a view of a single record in a [`Dataset`]
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct KnnRecord<'a> {
+    pub data: &'a [f64],
+    /// the interned id of the label. refer to [`Dataset::label_name`]
+    pub label: u32,
+}
+
+impl KnnRecord<'_> {
+    /// copies the record so it can outlive the [`Dataset`]
+    pub fn to_owned_record(&self) -> OwnedKnnRecord {
+        OwnedKnnRecord {
+            data: self.data.to_vec(),
+            label: self.label,
+        }
+    }
+}
+
+/// an owned copy of a [`KnnRecord`]
+///
+/// serializes the same as a [`KnnRecord`] so a record that was written out
+/// can be read back without a [`Dataset`] to borrow from
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct OwnedKnnRecord {
     pub data: Vec<f64>,
-    pub label: String,
+    /// the interned id of the label. refer to [`Dataset::label_name`]
+    pub label: u32,
 }
 
-/// attempts to retrieve the desired data columns and label from the csv file
-pub fn get_columns_and_label<R>(
-    reader: &mut csv::Reader<R>,
+impl OwnedKnnRecord {
+    /// borrows the record as a [`KnnRecord`]
+    pub fn as_record(&self) -> KnnRecord<'_> {
+        KnnRecord {
+            data: &self.data,
+            label: self.label,
+        }
+    }
+}
+
+/// represents the data collected from the csv for the knn
+///
+/// the data of every record is stored in a single row major [`Array2`] with
+/// the labels stored in a parallel list so that distance calculations walk
+/// through contiguous memory and whole blocks of records can be handed to
+/// matrix operations. labels are interned into numeric ids in the order that
+/// they are first encountered
+#[derive(Debug, Clone, Default)]
+pub struct Dataset {
+    dims: usize,
+    data: Array2<f64>,
+    labels: Vec<u32>,
+    names: Vec<String>,
+    ids: HashMap<String, u32>,
+}
+
+impl Dataset {
+    /// creates an empty dataset with the specified number of columns
+    pub fn new(dims: usize) -> Self {
+        Self::with_capacity(dims, 0)
+    }
+
+    /// creates an empty dataset with room for the specified number of records
+    pub fn with_capacity(dims: usize, capacity: usize) -> Self {
+        Self {
+            dims,
+            data: empty_matrix(dims, capacity),
+            labels: Vec::with_capacity(capacity),
+            names: Vec::new(),
+            ids: HashMap::new(),
+        }
+    }
+
+    /// creates an empty dataset that uses the same label ids as another
+    ///
+    /// records from both datasets can then be compared by their label id
+    pub fn with_labels_of(other: &Self, dims: usize, capacity: usize) -> Self {
+        Self {
+            dims,
+            data: empty_matrix(dims, capacity),
+            labels: Vec::with_capacity(capacity),
+            names: other.names.clone(),
+            ids: other.ids.clone(),
+        }
+    }
+
+    /// retrieves the id of the label, interning it if it has not been seen
+    pub fn intern(&mut self, label: &str) -> u32 {
+        if let Some(id) = self.ids.get(label) {
+            return *id;
+        }
+
+        let id = self.names.len() as u32;
+
+        self.names.push(label.to_owned());
+        self.ids.insert(label.to_owned(), id);
+
+        id
+    }
+
+    /// adds a record to the end of the dataset
+    ///
+    /// panics if the data does not have the same number of columns as the
+    /// dataset
+    pub fn push(&mut self, data: &[f64], label: &str) {
+        let id = self.intern(label);
+
+        self.push_id(data, id);
+    }
+
+    /// adds a record with an already interned label id to the end of the
+    /// dataset
+    pub fn push_id(&mut self, data: &[f64], label: u32) {
+        assert_eq!(
+            data.len(),
+            self.dims,
+            "record has the wrong number of columns"
+        );
+
+        self.data
+            .push_row(ArrayView1::from(data))
+            .expect("record has the wrong number of columns");
+        self.labels.push(label);
+    }
+
+    /// the number of columns of each record
+    pub fn dims(&self) -> usize {
+        self.dims
+    }
+
+    /// the number of records
+    pub fn len(&self) -> usize {
+        self.labels.len()
+    }
+
+    /// checks if there are no records
+    pub fn is_empty(&self) -> bool {
+        self.labels.is_empty()
+    }
+
+    /// retrieves the record at the specified index
+    pub fn get(&self, index: usize) -> KnnRecord<'_> {
+        KnnRecord {
+            data: &self.matrix()[index * self.dims..(index + 1) * self.dims],
+            label: self.labels[index],
+        }
+    }
+
+    /// iterates over every record in order
+    pub fn iter(
+        &self,
+    ) -> impl ExactSizeIterator<Item = KnnRecord<'_>> + DoubleEndedIterator + Clone + '_ {
+        (0..self.len()).map(|index| self.get(index))
+    }
+
+    /// removes every record but keeps the label ids that were interned
+    pub fn clear(&mut self) {
+        self.data = empty_matrix(self.dims, self.labels.len());
+        self.labels.clear();
+    }
+
+    /// the data of every record stored one after another
+    pub fn matrix(&self) -> &[f64] {
+        self.data
+            .as_slice()
+            .expect("records are stored in row major order")
+    }
+
+    /// mutable access to the data of every record
+    pub fn matrix_mut(&mut self) -> &mut [f64] {
+        self.data
+            .as_slice_mut()
+            .expect("records are stored in row major order")
+    }
+
+    /// the records as a matrix with a row for each record
+    pub fn view(&self) -> ArrayView2<'_, f64> {
+        self.data.view()
+    }
+
+    /// the label id of every record
+    pub fn labels(&self) -> &[u32] {
+        &self.labels
+    }
+
+    /// the label of the specified label id
+    pub fn label_name(&self, label: u32) -> &str {
+        &self.names[label as usize]
+    }
+
+    /// the labels of every label id
+    pub fn label_names(&self) -> &[String] {
+        &self.names
+    }
+
+    /// consumes the dataset returning the label id of every record and the
+    /// labels of every label id
+    pub fn into_labels(self) -> (Vec<u32>, Vec<String>) {
+        (self.labels, self.names)
+    }
+}
+
+/// retrieves the source for commands that need the csv file
+pub fn require_csv<S>(source: Option<S>) -> KnnResult<S> {
+    source.ok_or(KnnError::NoSource)
+}
+
+/// attempts to retrieve the desired data columns and label from the source
+///
+/// every column except the label is used if no columns are specified
+pub fn get_columns_and_label<S>(
+    source: &mut S,
+    label: &ColumnType,
+    retrieve: &[ColumnType],
+) -> KnnResult<(usize, Vec<usize>)>
+where
+    S: DataSource + ?Sized,
+{
+    get_columns_excluding(source, label, retrieve, &[])
+}
+
+/// retrieves the desired data columns and label from the source without the
+/// excluded columns
+pub fn get_columns_excluding<S>(
+    source: &mut S,
     label: &ColumnType,
     retrieve: &[ColumnType],
-) -> anyhow::Result<(usize, Vec<usize>)>
+    exclude: &[ColumnType],
+) -> KnnResult<(usize, Vec<usize>)>
 where
-    R: std::io::Read,
+    S: DataSource + ?Sized,
 {
-    let mut columns = Vec::with_capacity(retrieve.len());
+    let known_headers = source.headers()?;
+    let header_match = source.header_match();
+    let lookup = Lookup {
+        headers: known_headers.as_ref().map(|known| {
+            let mut headers: HashMap<Cow<str>, Vec<usize>> = HashMap::new();
 
-    let found = if reader.has_headers() {
-        let known_headers = reader.headers().context("failed to retrieve csv headers")?;
+            for (index, name) in known.iter().enumerate() {
+                headers
+                    .entry(header_match.normalize(name))
+                    .or_default()
+                    .push(index);
+            }
 
-        let headers = known_headers
-            .iter()
-            .enumerate()
-            .map(|(index, name)| (name, index))
-            .collect::<HashMap<&str, usize>>();
+            headers
+        }),
+        known_headers: known_headers.as_deref(),
+        width: source.width()?,
+        on_duplicate: source.on_duplicate(),
+        header_match,
+    };
+
+    let found = match label {
+        ColumnType::All | ColumnType::Range(..) | ColumnType::Pattern(_) => {
+            return Err(KnnError::MultipleLabels);
+        }
+        ColumnType::Name(name) => lookup.name(name, true)?,
+        ColumnType::Index(index) => lookup.index(*index, true)?,
+        ColumnType::FromEnd(offset) => lookup.back_index(*offset, true)?,
+    };
+
+    let all = [ColumnType::All];
+    let retrieve = if retrieve.is_empty() { &all } else { retrieve };
+    let mut columns = lookup.resolve(retrieve, found)?;
+    let excluded = lookup.resolve(exclude, found)?;
+
+    columns.retain(|col| !excluded.contains(col));
+
+    if columns.is_empty() {
+        return Err(KnnError::NoColumns);
+    }
+
+    Ok((found, columns))
+}
+
+/// resolves columns against the headers of a source
+struct Lookup<'a> {
+    /// the index of every header with each normalized name
+    headers: Option<HashMap<Cow<'a, str>, Vec<usize>>>,
+    known_headers: Option<&'a [String]>,
+    width: Option<usize>,
+    on_duplicate: OnDuplicate,
+    header_match: HeaderMatch,
+}
+
+impl Lookup<'_> {
+    /// the indexes of the columns. `found` is the label which is skipped
+    /// when every column is requested
+    fn resolve(&self, retrieve: &[ColumnType], found: usize) -> KnnResult<Vec<usize>> {
+        let mut columns = Vec::with_capacity(retrieve.len());
 
         for to_get in retrieve {
             match to_get {
-                ColumnType::Name(name) => {
-                    let Some(index) = headers.get(name.as_str()) else {
-                        bail!(
-                            "unknown column header specified. column: {name}\navail: {:#?}",
-                            headers
-                        );
+                ColumnType::All => {
+                    let Some(width) = self.width else {
+                        return Err(KnnError::UnknownWidth);
                     };
 
-                    columns.push(*index);
+                    columns.extend((0..width).filter(|col| *col != found));
                 }
-                ColumnType::Index(index) => {
-                    if *index >= headers.len() {
-                        bail!("index is out of range for known headers. column index: {index}");
+                ColumnType::Name(name) => columns.push(self.name(name, false)?),
+                ColumnType::Index(index) => columns.push(self.index(*index, false)?),
+                ColumnType::FromEnd(offset) => columns.push(self.back_index(*offset, false)?),
+                ColumnType::Range(start, end) => {
+                    if start > end {
+                        return Err(KnnError::ReversedRange {
+                            start: *start,
+                            end: *end,
+                        });
                     }
 
-                    columns.push(*index);
+                    self.index(*end, false)?;
+
+                    columns.extend(*start..=*end);
                 }
+                ColumnType::Pattern(pattern) => columns.extend(self.pattern(pattern, found)?),
             }
         }
 
-        match label {
-            ColumnType::Name(name) => {
-                let Some(index) = headers.get(name.as_str()) else {
-                    bail!(
-                        "unknown label column header specified. column: {name}\navail: {:#?}",
-                        headers
-                    );
-                };
+        Ok(columns)
+    }
 
-                *index
-            }
-            ColumnType::Index(index) => {
-                if *index >= headers.len() {
-                    bail!("label index is out of range for known headers. column index: {index}");
-                }
+    /// the columns with names matching the pattern in the order of the
+    /// headers. the label is skipped
+    fn pattern(&self, pattern: &str, found: usize) -> KnnResult<Vec<usize>> {
+        let Some(known_headers) = self.known_headers else {
+            return Err(KnnError::NoHeaders {
+                name: format!("re:{pattern}"),
+                label: false,
+            });
+        };
 
-                *index
-            }
+        let regex = Regex::new(pattern).map_err(|source| KnnError::Pattern {
+            pattern: pattern.to_owned(),
+            source,
+        })?;
+
+        let matched = known_headers
+            .iter()
+            .enumerate()
+            .filter(|(index, name)| *index != found && regex.is_match(name))
+            .map(|(index, _)| index)
+            .collect::<Vec<_>>();
+
+        if matched.is_empty() {
+            return Err(KnnError::NoMatches {
+                pattern: pattern.to_owned(),
+            });
         }
-    } else {
-        for to_get in retrieve {
-            match to_get {
-                ColumnType::Name(name) => {
-                    bail!(
-                        "no headers were specified in the csv but given a named column. column: {name}"
-                    )
-                }
-                ColumnType::Index(index) => columns.push(*index),
-            }
+
+        Ok(matched)
+    }
+
+    fn name(&self, name: &str, label: bool) -> KnnResult<usize> {
+        let (Some(headers), Some(known_headers)) = (&self.headers, self.known_headers) else {
+            return Err(KnnError::NoHeaders {
+                name: name.to_owned(),
+                label,
+            });
+        };
+
+        let Some(found) = headers.get(&self.header_match.normalize(name)) else {
+            return Err(KnnError::ColumnNotFound {
+                name: name.to_owned(),
+                available: known_headers.to_vec(),
+                label,
+            });
+        };
+
+        match (found.as_slice(), self.on_duplicate) {
+            ([index], _) => Ok(*index),
+            (_, OnDuplicate::Error) => Err(KnnError::DuplicateHeader {
+                name: name.to_owned(),
+                columns: found.clone(),
+                label,
+            }),
+            // sources rename the headers for suffix so any duplicates left
+            // are treated the same as first
+            (found, OnDuplicate::First | OnDuplicate::Suffix) => Ok(found[0]),
+            (found, OnDuplicate::Last) => Ok(found[found.len() - 1]),
         }
+    }
 
-        match label {
-            ColumnType::Name(name) => {
-                bail!(
-                    "no headers were specified in the csv but given a named label column. column: {name}"
-                );
+    /// indexes are only checked if the source has headers
+    fn index(&self, index: usize, label: bool) -> KnnResult<usize> {
+        match self.known_headers {
+            Some(known_headers) if index >= known_headers.len() => {
+                Err(KnnError::ColumnOutOfRange { index, label })
             }
-            ColumnType::Index(index) => *index,
+            _ => Ok(index),
         }
-    };
+    }
 
-    Ok((found, columns))
+    /// the index of a column counted back from the end where 1 is the last
+    /// column
+    fn back_index(&self, offset: usize, label: bool) -> KnnResult<usize> {
+        let Some(width) = self.width else {
+            return Err(KnnError::UnknownWidth);
+        };
+
+        if offset == 0 || offset > width {
+            return Err(KnnError::FromEndOutOfRange {
+                offset,
+                width,
+                label,
+            });
+        }
+
+        Ok(width - offset)
+    }
 }
 
-/// maps at csv record into a [`KnnRecord`] with the expected columns and label
-pub fn map_record(
-    label: usize,
-    columns: &[usize],
-    index: usize,
-    record: StringRecord,
-) -> anyhow::Result<KnnRecord> {
-    let mut rtn = Vec::with_capacity(columns.len());
+/// retrieves the names of the specified columns
+///
+/// the header name is used if the source has headers otherwise the index of
+/// the column is used
+pub fn column_names<S>(source: &mut S, columns: &[usize]) -> KnnResult<HashMap<usize, String>>
+where
+    S: DataSource + ?Sized,
+{
+    let mut rtn = HashMap::with_capacity(columns.len());
 
-    for col in columns {
-        if let Some(value) = record.get(*col) {
-            let Ok(v) = f64::from_str(&value) else {
-                bail!(
-                    "failed to parse column data. row: {} column index: {}",
-                    index + 1,
-                    col + 1
-                );
-            };
+    if let Some(headers) = source.headers()? {
+        for col in columns {
+            let name = headers
+                .get(*col)
+                .map(|name| name.to_owned())
+                .unwrap_or_else(|| col.to_string());
 
-            rtn.push(v);
-        } else {
-            bail!("column data not found. column index: {}", col + 1);
+            rtn.insert(*col, name);
+        }
+    } else {
+        for col in columns {
+            rtn.insert(*col, col.to_string());
         }
     }
 
-    let Some(found) = record.get(label) else {
-        bail!("failed to find label. label index: {index}");
-    };
+    Ok(rtn)
+}
+
+/// loads every row of the source into a [`Dataset`]
+pub fn collect_records<S>(mut source: S, label: usize, columns: &[usize]) -> KnnResult<Dataset>
+where
+    S: DataSource,
+{
+    // collect all the records since we are offering the ability to run k over
+    // a range vs a single iteration
+    let mut rtn = Dataset::new(columns.len());
+    let mut index = 0;
+
+    while let Some(row) = source.next_row(label, columns) {
+        // the records can double in size when they grow so the memory budget
+        // is checked against their current size every so often
+        if index % MEMORY_CHECK_ROWS == 0 {
+            memory::reserve(
+                rtn.len() * (rtn.dims() + 1) * std::mem::size_of::<f64>(),
+                "loading the csv records",
+            )?;
+        }
+
+        let row = row?;
 
-    Ok(KnnRecord {
-        data: rtn,
-        label: found.to_owned(),
-    })
+        rtn.push(row.features, row.label);
+        index += 1;
+    }
+
+    check_label_filter(&source.label_filter(), rtn.is_empty())?;
+
+    Ok(rtn)
+}
+
+/// warns about every label of the filter that is not in the source once it
+/// has been read. fails if only included labels are kept and none of the
+/// rows had them
+pub fn check_label_filter(filter: &LabelFilter, empty: bool) -> KnnResult<()> {
+    for label in filter.unseen() {
+        eprintln!("warning: no rows have the label {label}");
+    }
+
+    if empty && filter.is_including() {
+        return Err(KnnError::NoIncludedLabels);
+    }
+
+    Ok(())
+}
+
+/// creates a matrix with no rows that has room for `capacity` rows
+fn empty_matrix(dims: usize, capacity: usize) -> Array2<f64> {
+    let mut rtn = Array2::zeros((0, dims));
+
+    rtn.reserve_rows(capacity)
+        .expect("too many records to reserve");
+
+    rtn
 }
 
-pub fn collect_records<R>(
-    mut reader: Reader<R>,
+/// reads the records of the source in blocks of at most `block` records
+///
+/// only a single block is kept in memory. the block is passed to `each` along
+/// with the position of its first record in the source and is cleared once
+/// `each` returns. label ids are kept between blocks and the names of every
+/// label id are returned once the source has been read
+pub fn stream_records<S, F>(
+    mut source: S,
     label: usize,
     columns: &[usize],
-) -> anyhow::Result<Vec<KnnRecord>>
+    block: usize,
+    mut each: F,
+) -> KnnResult<Vec<String>>
 where
-    R: std::io::Read,
+    S: DataSource,
+    F: FnMut(&Dataset, usize) -> KnnResult<()>,
 {
-    // map the csv records iterator into a list of knn records to use later
-    let iter = reader
-        .records()
-        .enumerate()
-        .map(|(index, maybe)| match maybe {
-            Ok(record) => map_record(label, &columns, index, record),
-            Err(err) => Err(anyhow::Error::new(err)
-                .context(format!("failed to parse csv record. row: {index}"))),
-        });
+    let mut rtn = Dataset::with_capacity(columns.len(), block);
+    let mut start = 0;
 
-    // collect all the records since we are offering the ability to run k over
-    // a range vs a single iteration
-    let mut rtn = Vec::new();
+    while let Some(row) = source.next_row(label, columns) {
+        let row = row?;
+
+        rtn.push(row.features, row.label);
 
-    for maybe in iter {
-        rtn.push(maybe?);
+        if rtn.len() == block {
+            each(&rtn, start)?;
+
+            start += rtn.len();
+            rtn.clear();
+        }
     }
 
-    Ok(rtn)
+    if !rtn.is_empty() {
+        each(&rtn, start)?;
+    }
+
+    check_label_filter(&source.label_filter(), start + rtn.len() == 0)?;
+
+    let (_, names) = rtn.into_labels();
+
+    Ok(names)
+}
+
+/// verifies that every label can be parsed as a numeric value
+pub fn check_numeric_labels(records: &Dataset) -> KnnResult<()> {
+    for (index, label) in records.labels().iter().enumerate() {
+        let label = records.label_name(*label);
+
+        if label.parse::<f64>().is_err() {
+            return Err(KnnError::NonNumericLabel {
+                row: index,
+                label: label.to_owned(),
+            });
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::source::{CsvSource, MemorySource};
+
+    #[test]
+    fn dataset_views() {
+        let mut dataset = Dataset::with_capacity(2, 2);
+
+        dataset.push(&[1.0, 2.0], "a");
+        dataset.push(&[3.0, 4.0], "b");
+        dataset.push(&[5.0, 6.0], "a");
+
+        assert_eq!(dataset.len(), 3);
+        assert_eq!(dataset.get(1).data, &[3.0, 4.0]);
+        assert_eq!(dataset.label_name(dataset.get(1).label), "b");
+        assert_eq!(
+            dataset
+                .iter()
+                .map(|record| record.label)
+                .collect::<Vec<_>>(),
+            vec![0, 1, 0]
+        );
+    }
+
+    #[test]
+    fn streamed_blocks() {
+        let contents = "x,label\n1,a\n2,b\n3,a\n4,c\n5,b\n";
+        let source = CsvSource::new(ReaderBuilder::new().from_reader(contents.as_bytes()));
+        let mut blocks = Vec::new();
+
+        let names = stream_records(source, 1, &[0], 2, |block, start| {
+            blocks.push((start, block.matrix().to_vec(), block.labels().to_vec()));
+
+            Ok(())
+        })
+        .unwrap();
+
+        assert_eq!(
+            blocks,
+            vec![
+                (0, vec![1.0, 2.0], vec![0, 1]),
+                (2, vec![3.0, 4.0], vec![0, 2]),
+                (4, vec![5.0], vec![1]),
+            ]
+        );
+        assert_eq!(names, vec!["a", "b", "c"]);
+    }
+
+    #[test]
+    fn all_columns() {
+        let contents = "x,class,y\n1,a,2\n";
+        let mut source = CsvSource::new(ReaderBuilder::new().from_reader(contents.as_bytes()));
+        let label = ColumnType::Name("class".to_owned());
+
+        let (found, columns) = get_columns_and_label(&mut source, &label, &[]).unwrap();
+
+        assert_eq!((found, columns), (1, vec![0, 2]));
+
+        let (_, columns) = get_columns_and_label(&mut source, &label, &[ColumnType::All]).unwrap();
+
+        assert_eq!(columns, vec![0, 2]);
+
+        let excluded = [ColumnType::Name("x".to_owned())];
+        let (_, columns) = get_columns_excluding(&mut source, &label, &[], &excluded).unwrap();
+
+        assert_eq!(columns, vec![2]);
+
+        // the first row is still read when it is used for the width
+        let contents = "1,2,a\n3,4,b\n";
+        let mut source = CsvSource::new(
+            ReaderBuilder::new()
+                .has_headers(false)
+                .from_reader(contents.as_bytes()),
+        );
+
+        let (label, columns) =
+            get_columns_and_label(&mut source, &ColumnType::Index(2), &[]).unwrap();
+        let records = collect_records(source, label, &columns).unwrap();
+
+        assert_eq!(columns, vec![0, 1]);
+        assert_eq!(records.len(), 2);
+
+        let mut only_label = MemorySource::new(Some(vec!["class".to_owned()]), vec![]);
+
+        assert!(matches!(
+            get_columns_and_label(&mut only_label, &ColumnType::Index(0), &[]),
+            Err(KnnError::NoColumns)
+        ));
+    }
+
+    #[test]
+    fn column_ranges() {
+        let headers = ["a", "b", "c", "d", "class"];
+        let mut source = MemorySource::new(
+            Some(headers.iter().map(|name| name.to_string()).collect()),
+            vec![],
+        );
+        let label = ColumnType::Name("class".to_owned());
+        let retrieve = "0,2-3"
+            .split(',')
+            .map(|given| given.parse::<ColumnType>().unwrap())
+            .collect::<Vec<_>>();
+
+        let (_, columns) = get_columns_and_label(&mut source, &label, &retrieve).unwrap();
+
+        assert_eq!(columns, vec![0, 2, 3]);
+
+        assert!(matches!(
+            get_columns_and_label(&mut source, &label, &[ColumnType::Range(3, 5)]),
+            Err(KnnError::ColumnOutOfRange { index: 5, .. })
+        ));
+        assert!(matches!(
+            get_columns_and_label(&mut source, &label, &[ColumnType::Range(3, 1)]),
+            Err(KnnError::ReversedRange { start: 3, end: 1 })
+        ));
+    }
+
+    #[test]
+    fn column_patterns() {
+        let headers = ["sensor_1", "sensor_22", "sensor_x", "other", "sensor_9"];
+        let mut source = MemorySource::new(
+            Some(headers.iter().map(|name| name.to_string()).collect()),
+            vec![],
+        );
+        let label = ColumnType::Index(4);
+        let pattern = r"re:^sensor_\d+$".parse::<ColumnType>().unwrap();
+
+        let (_, columns) = get_columns_and_label(&mut source, &label, &[pattern]).unwrap();
+
+        assert_eq!(columns, vec![0, 1]);
+
+        assert!(matches!(
+            get_columns_and_label(&mut source, &label, &[ColumnType::Pattern("^x".to_owned())]),
+            Err(KnnError::NoMatches { .. })
+        ));
+        assert!(matches!(
+            get_columns_and_label(&mut source, &label, &[ColumnType::Pattern("(".to_owned())]),
+            Err(KnnError::Pattern { .. })
+        ));
+    }
+
+    #[test]
+    fn negative_indexes() {
+        let mut source = MemorySource::new(None, vec![vec!["1".to_owned(); 4]]);
+
+        let (label, columns) = get_columns_and_label(
+            &mut source,
+            &"-1".parse().unwrap(),
+            &[ColumnType::FromEnd(4)],
+        )
+        .unwrap();
+
+        assert_eq!((label, columns), (3, vec![0]));
+
+        assert!(matches!(
+            get_columns_and_label(&mut source, &ColumnType::FromEnd(5), &[]),
+            Err(KnnError::FromEndOutOfRange { offset: 5, .. })
+        ));
+    }
+
+    #[test]
+    fn duplicate_headers() {
+        let contents = "x,y,x,x_2,class\n1,2,3,4,a\n";
+        let source = |on_duplicate| {
+            CsvSource::new(ReaderBuilder::new().from_reader(contents.as_bytes()))
+                .with_on_duplicate(on_duplicate)
+        };
+        let label = ColumnType::Name("class".to_owned());
+        let columns = [ColumnType::Name("x".to_owned())];
+        let resolve = |on_duplicate| {
+            get_columns_and_label(&mut source(on_duplicate), &label, &columns)
+                .map(|(_, columns)| columns)
+        };
+
+        assert!(matches!(
+            resolve(OnDuplicate::Error),
+            Err(KnnError::DuplicateHeader { ref columns, .. }) if columns == &[0, 2]
+        ));
+        assert_eq!(resolve(OnDuplicate::First).unwrap(), vec![0]);
+        assert_eq!(resolve(OnDuplicate::Last).unwrap(), vec![2]);
+
+        let mut suffixed = source(OnDuplicate::Suffix);
+
+        assert_eq!(
+            suffixed.headers().unwrap().unwrap(),
+            vec!["x", "y", "x_3", "x_2", "class"]
+        );
+        assert_eq!(
+            get_columns_and_label(&mut suffixed, &label, &[ColumnType::Name("x_3".to_owned())])
+                .unwrap()
+                .1,
+            vec![2]
+        );
+    }
+
+    #[test]
+    fn loose_header_match() {
+        let contents = "\u{feff}Sepal Length , Class\n1,a\n";
+        let source = |header_match| {
+            CsvSource::new(ReaderBuilder::new().from_reader(contents.as_bytes()))
+                .with_header_match(header_match)
+        };
+        let label = ColumnType::Name("class".to_owned());
+        let columns = [ColumnType::Name("sepal length".to_owned())];
+
+        assert!(matches!(
+            get_columns_and_label(&mut source(HeaderMatch::Exact), &label, &columns),
+            Err(KnnError::ColumnNotFound { .. })
+        ));
+        assert_eq!(
+            get_columns_and_label(&mut source(HeaderMatch::Loose), &label, &columns).unwrap(),
+            (1, vec![0])
+        );
+
+        // the byte order mark is removed even with an exact match
+        assert!(
+            get_columns_and_label(
+                &mut source(HeaderMatch::Exact),
+                &ColumnType::Name(" Class".to_owned()),
+                &[ColumnType::Name("Sepal Length ".to_owned())],
+            )
+            .is_ok()
+        );
+    }
 }
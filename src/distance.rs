@@ -1,13 +1,110 @@
+use ndarray::{Array2, ArrayView2, Zip};
+
+use crate::cli::AlgoType;
+
+/// calculates the distance between 2 datapoints
+///
+/// implemented by [`AlgoType`] for the algorithms available on the command
+/// line and by any `Fn(&[f64], &[f64]) -> f64`. other metrics, including ones
+/// with fitted state, can implement it to be used anywhere a metric is
+/// accepted.
+///
+/// ```
+/// use knn::classifier::KnnClassifier;
+/// use knn::csv::Dataset;
+/// use knn::distance::DistanceMetric;
+///
+/// /// euclidean distance with every column divided by its fitted range
+/// struct Ranged {
+///     ranges: Vec<f64>,
+/// }
+///
+/// impl DistanceMetric for Ranged {
+///     fn distance(&self, a: &[f64], b: &[f64]) -> f64 {
+///         self.rank(a, b).sqrt()
+///     }
+///
+///     fn rank(&self, a: &[f64], b: &[f64]) -> f64 {
+///         a.iter()
+///             .zip(b)
+///             .zip(&self.ranges)
+///             .map(|((a, b), range)| ((a - b) / range).powi(2))
+///             .sum()
+///     }
+/// }
+///
+/// # fn main() -> anyhow::Result<()> {
+/// let mut records = Dataset::new(2);
+/// records.push(&[0.0, 0.0], "a");
+/// records.push(&[10.0, 1.0], "b");
+///
+/// let classifier = KnnClassifier::builder()
+///     .k(1)
+///     .metric(Ranged { ranges: vec![10.0, 1.0] })
+///     .fit(records)?;
+///
+/// assert_eq!(classifier.predict(&[4.0, 0.8])?, "b");
+/// # Ok(())
+/// # }
+/// ```
+pub trait DistanceMetric: Send + Sync {
+    /// the distance between the datapoints
+    fn distance(&self, a: &[f64], b: &[f64]) -> f64;
+
+    /// a value with the same ordering as [`DistanceMetric::distance`] that
+    /// is only used to rank neighbors. defaults to the distance
+    fn rank(&self, a: &[f64], b: &[f64]) -> f64 {
+        self.distance(a, b)
+    }
+
+    /// the contribution of a single column when the rank is the sum of the
+    /// contributions of every column. lets the search cache the
+    /// contributions of each column. defaults to [`None`]
+    fn term(&self) -> Option<fn(f64, f64) -> f64> {
+        None
+    }
+}
+
+impl DistanceMetric for AlgoType {
+    fn distance(&self, a: &[f64], b: &[f64]) -> f64 {
+        (self.as_fn())(a, b)
+    }
+
+    fn rank(&self, a: &[f64], b: &[f64]) -> f64 {
+        (self.as_rank_fn())(a, b)
+    }
+
+    fn term(&self) -> Option<fn(f64, f64) -> f64> {
+        Some(self.as_term_fn())
+    }
+}
+
+impl<F> DistanceMetric for F
+where
+    F: Fn(&[f64], &[f64]) -> f64 + Send + Sync,
+{
+    fn distance(&self, a: &[f64], b: &[f64]) -> f64 {
+        self(a, b)
+    }
+}
+
 /// calculates the euclidean distance between 2 sets of datapoints
 pub fn euclidean(a_data: &[f64], b_data: &[f64]) -> f64 {
+    squared_euclidean(a_data, b_data).sqrt()
+}
+
+/// calculates the squared euclidean distance between 2 sets of datapoints
+///
+/// the square root does not change the order of distances so this can be
+/// used in place of [`euclidean`] when only the closest datapoints are needed
+pub fn squared_euclidean(a_data: &[f64], b_data: &[f64]) -> f64 {
     // we will expect the total datapoints from a and b to be the same and just
     // zip them together for the iterator chain
     a_data
         .iter()
         .zip(b_data)
-        .map(|(a, b)| (a - b).powf(2.0))
+        .map(|(a, b)| (a - b) * (a - b))
         .sum::<f64>()
-        .sqrt()
 }
 
 /// calculates the manhattan distance between 2 sets of datapoints
@@ -20,6 +117,84 @@ pub fn manhattan(a_data: &[f64], b_data: &[f64]) -> f64 {
         .sum::<f64>()
 }
 
+/// calculates the squared euclidean distance between 2 sets of f32 datapoints
+///
+/// the distance is calculated in f32 and only converted at the end
+pub fn squared_euclidean_f32(a_data: &[f32], b_data: &[f32]) -> f64 {
+    a_data
+        .iter()
+        .zip(b_data)
+        .map(|(a, b)| (a - b) * (a - b))
+        .sum::<f32>() as f64
+}
+
+/// calculates the manhattan distance between 2 sets of f32 datapoints
+pub fn manhattan_f32(a_data: &[f32], b_data: &[f32]) -> f64 {
+    a_data
+        .iter()
+        .zip(b_data)
+        .map(|(a, b)| (a - b).abs())
+        .sum::<f32>() as f64
+}
+
+/// calculates the squared euclidean distance from every row of `a` to every
+/// row of `b`
+///
+/// every pair uses [`squared_euclidean`] instead of expanding the distance
+/// into a matrix product since the rounding of the expansion will break
+/// ties between records differently than the single datapoint functions
+pub fn squared_euclidean_matrix(a: ArrayView2<f64>, b: ArrayView2<f64>) -> Array2<f64> {
+    pairwise(a, b, squared_euclidean)
+}
+
+/// calculates the manhattan distance from every row of `a` to every row of
+/// `b`
+pub fn manhattan_matrix(a: ArrayView2<f64>, b: ArrayView2<f64>) -> Array2<f64> {
+    pairwise(a, b, manhattan)
+}
+
+/// calculates the distance between every pair of rows with the given
+/// function
+fn pairwise<F>(a: ArrayView2<f64>, b: ArrayView2<f64>, algo: F) -> Array2<f64>
+where
+    F: Fn(&[f64], &[f64]) -> f64,
+{
+    let mut rtn = Array2::zeros((a.nrows(), b.nrows()));
+
+    Zip::from(rtn.rows_mut())
+        .and(a.rows())
+        .for_each(|mut distances, a_row| {
+            let a_row = a_row.to_vec();
+
+            Zip::from(&mut distances)
+                .and(b.rows())
+                .for_each(|distance, b_row| {
+                    *distance = match b_row.as_slice() {
+                        Some(b_row) => algo(&a_row, b_row),
+                        None => algo(&a_row, &b_row.to_vec()),
+                    };
+                });
+        });
+
+    rtn
+}
+
+/// converts a datapoint to f32
+pub fn narrow(data: &[f64]) -> Vec<f32> {
+    data.iter().map(|value| *value as f32).collect()
+}
+
+/// the contribution of a single column to the euclidean distance before the
+/// square root is taken
+pub fn squared_difference(a: f64, b: f64) -> f64 {
+    (a - b).powf(2.0)
+}
+
+/// the contribution of a single column to the manhattan distance
+pub fn absolute_difference(a: f64, b: f64) -> f64 {
+    (a - b).abs()
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -36,6 +211,7 @@ mod test {
             ((a_data[0] - b_data[0]).powf(2.0) + (a_data[0] - b_data[0]).powf(2.0)).sqrt()
         );
         assert_eq!(calc, 8.0f64.sqrt());
+        assert_eq!(squared_euclidean(&a_data, &b_data), 8.0);
     }
 
     #[test]
@@ -51,4 +227,37 @@ mod test {
         );
         assert_eq!(calc, 4.0);
     }
+
+    #[test]
+    fn check_f32() {
+        let a_data = [3.0, 4.0];
+        let b_data = [0.0, 0.0];
+
+        assert_eq!(squared_euclidean_f32(&a_data, &b_data), 25.0);
+        assert_eq!(manhattan_f32(&a_data, &b_data), 7.0);
+    }
+
+    #[test]
+    fn check_matrix() {
+        let a = ndarray::array![[3.0, 4.0], [1.0, 1.0]];
+        let b = ndarray::array![[0.0, 0.0], [1.0, 1.0], [4.0, -2.0]];
+
+        let squared = squared_euclidean_matrix(a.view(), b.view());
+        let manhattan_rows = manhattan_matrix(a.view(), b.view());
+
+        for (a_index, a_row) in a.rows().into_iter().enumerate() {
+            for (b_index, b_row) in b.rows().into_iter().enumerate() {
+                let (a_row, b_row) = (a_row.to_vec(), b_row.to_vec());
+
+                assert_eq!(
+                    squared[[a_index, b_index]],
+                    squared_euclidean(&a_row, &b_row)
+                );
+                assert_eq!(
+                    manhattan_rows[[a_index, b_index]],
+                    manhattan(&a_row, &b_row)
+                );
+            }
+        }
+    }
 }
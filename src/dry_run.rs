@@ -0,0 +1,137 @@
+//! the resolved configuration of a command without running it
+//!
+//! a dry run resolves the columns and label, loads the records, and fits the
+//! scaling the same way the command would. the result is printed instead of
+//! classifying anything so a column found by the wrong name or a k range
+//! larger than the records is caught before a long search.
+use std::collections::HashMap;
+
+use crate::cli::KValue;
+use crate::csv::{Dataset, KnnRecord};
+use crate::scale::{ScaleType, Scaler};
+
+/// the configuration of a command once it is resolved against the source
+#[derive(Debug)]
+pub struct DryRun<'a> {
+    pub label: usize,
+    pub columns: &'a [usize],
+    /// the name of the label and each column
+    pub names: &'a HashMap<usize, String>,
+    pub records: &'a Dataset,
+    /// the records the scaling is fitted on and that limit the k values
+    pub fitted: &'a [KnnRecord<'a>],
+    pub scales: &'a [ScaleType],
+    /// empty when k is selected automatically
+    pub k_values: &'a [KValue],
+    pub odd_only: bool,
+}
+
+impl DryRun<'_> {
+    fn name(&self, col: usize) -> &str {
+        self.names.get(&col).map(String::as_str).unwrap_or_default()
+    }
+}
+
+impl std::fmt::Display for DryRun<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "label: {} {}", self.label, self.name(self.label))?;
+        writeln!(f, "columns:")?;
+
+        for col in self.columns {
+            writeln!(f, "  {col} {}", self.name(*col))?;
+        }
+
+        let mut counts = vec![0usize; self.records.label_names().len()];
+
+        for label in self.records.labels() {
+            counts[*label as usize] += 1;
+        }
+
+        writeln!(
+            f,
+            "records: {} labels: {}",
+            self.records.len(),
+            counts.len()
+        )?;
+
+        for (name, count) in self.records.label_names().iter().zip(counts) {
+            writeln!(f, "  {name}: {count}")?;
+        }
+
+        for scale in self.scales {
+            let scaler = Scaler::fit(
+                *scale,
+                self.fitted.iter().map(|record| record.data),
+                self.columns.len(),
+            );
+
+            writeln!(f, "scale: {scale}")?;
+
+            if *scale == ScaleType::None {
+                continue;
+            }
+
+            for (col, (offset, factor)) in self.columns.iter().zip(scaler.params()) {
+                writeln!(
+                    f,
+                    "  {} offset: {offset:.4} factor: {factor:.4}",
+                    self.name(*col)
+                )?;
+            }
+        }
+
+        if self.k_values.is_empty() {
+            writeln!(f, "k: auto")?;
+        }
+
+        for k in self.k_values {
+            let ks: Vec<String> = k
+                .get_range(self.fitted.len(), self.odd_only)
+                .map(|k| k.to_string())
+                .collect();
+
+            if ks.is_empty() {
+                writeln!(f, "k: {k} | none available")?;
+            } else {
+                writeln!(f, "k: {k} | {}", ks.join(" "))?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::str::FromStr;
+
+    use super::*;
+
+    #[test]
+    fn resolved_configuration() {
+        let mut records = Dataset::new(1);
+
+        records.push(&[1.0], "a");
+        records.push(&[3.0], "b");
+        records.push(&[5.0], "a");
+
+        let fitted: Vec<KnnRecord> = records.iter().collect();
+        let names = HashMap::from([(0, "size".to_owned()), (1, "class".to_owned())]);
+        let dry_run = DryRun {
+            label: 1,
+            columns: &[0],
+            names: &names,
+            records: &records,
+            fitted: &fitted,
+            scales: &[ScaleType::MinMax],
+            k_values: &[KValue::from_str("1-5").unwrap()],
+            odd_only: false,
+        };
+
+        assert_eq!(
+            dry_run.to_string(),
+            "label: 1 class\ncolumns:\n  0 size\nrecords: 3 labels: 2\n  a: 2\n  b: 1\n\
+             scale: minmax\n  size offset: 1.0000 factor: 4.0000\nk: 1-5 | 1 2\n"
+        );
+    }
+}
@@ -0,0 +1,475 @@
+//! the errors returned by the core of the library
+//!
+//! loading records from a [`DataSource`](crate::source::DataSource), fitting
+//! a [`KnnClassifier`](crate::classifier::KnnClassifier), and building an
+//! [`Index`](crate::index::Index) fail with a [`KnnError`] so callers can
+//! match on the cause. the commands convert them into [`anyhow::Error`] with
+//! `?` like any other error.
+//!
+//! errors of a single row carry a [`RowContext`] when the source knows the
+//! text of the row. it is shown below the error with the value that failed
+//! marked and a hint for fixing it.
+use std::error::Error;
+use std::fmt;
+use std::ops::Range;
+
+use crate::memory;
+use crate::source::is_missing;
+
+/// the result of the core functions
+pub type KnnResult<T> = Result<T, KnnError>;
+
+#[derive(Debug)]
+pub enum KnnError {
+    /// a command requires the csv file but none was specified
+    NoSource,
+    /// the headers of the csv could not be read
+    Headers(csv::Error),
+    /// a row of the csv could not be read
+    Csv { row: usize, source: csv::Error },
+    /// a row of a source other than a csv could not be read
+    Source(Box<dyn Error + Send + Sync>),
+    /// a named column is not one of the headers
+    ColumnNotFound {
+        name: String,
+        /// the headers of the source
+        available: Vec<String>,
+        /// the column was requested as the label
+        label: bool,
+    },
+    /// a named column is shared by more than one header
+    DuplicateHeader {
+        name: String,
+        /// the indexes of every header with the name
+        columns: Vec<usize>,
+        label: bool,
+    },
+    /// a column index is past the last header
+    ColumnOutOfRange { index: usize, label: bool },
+    /// a negative column index is before the first column
+    FromEndOutOfRange {
+        offset: usize,
+        width: usize,
+        label: bool,
+    },
+    /// a named column was requested from a source without headers
+    NoHeaders { name: String, label: bool },
+    /// no columns were left to pull numeric data from
+    NoColumns,
+    /// every column was requested but the source does not know how many
+    /// columns it has
+    UnknownWidth,
+    /// the label was given as more than a single column
+    MultipleLabels,
+    /// the start of a column range is after the end
+    ReversedRange { start: usize, end: usize },
+    /// a column pattern is not a valid regular expression
+    Pattern {
+        pattern: String,
+        source: regex::Error,
+    },
+    /// a column pattern did not match any of the headers
+    NoMatches { pattern: String },
+    /// a row does not have a value for the column
+    MissingValue {
+        row: usize,
+        column: usize,
+        context: Option<Box<RowContext>>,
+    },
+    /// the value of the column could not be parsed as a number
+    Parse {
+        row: usize,
+        column: usize,
+        context: Option<Box<RowContext>>,
+    },
+    /// a row does not have a value for the label column
+    MissingLabel {
+        row: usize,
+        context: Option<Box<RowContext>>,
+    },
+    /// regression was requested but the label of the row is not a number
+    NonNumericLabel { row: usize, label: String },
+    /// a datapoint has a different number of values than the records
+    DimensionMismatch { expected: usize, found: usize },
+    /// k was 0
+    ZeroK,
+    /// there were no records to fit
+    NoRecords,
+    /// only rows with the included labels were read and none of the rows
+    /// had them
+    NoIncludedLabels,
+    /// no neighbors were found to predict from
+    NoNeighbors,
+    /// an allocation would exceed the memory budget
+    MemoryBudget {
+        /// describes the allocation
+        what: String,
+        needed: u64,
+        used: u64,
+        budget: u64,
+    },
+}
+
+impl fmt::Display for KnnError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NoSource => f.write_str("no csv file specified. use --file to specify one"),
+            Self::Headers(_) => f.write_str("failed to retrieve csv headers"),
+            Self::Csv { row, .. } => write!(f, "failed to parse csv record. row: {row}"),
+            Self::Source(_) => f.write_str("failed to read the source"),
+            Self::ColumnNotFound {
+                name,
+                available,
+                label,
+            } => {
+                let kind = if *label { "label column" } else { "column" };
+
+                write!(
+                    f,
+                    "unknown {kind} header specified. column: {name}\navail: {available:#?}"
+                )
+            }
+            Self::DuplicateHeader {
+                name,
+                columns,
+                label,
+            } => {
+                let kind = if *label { "label column" } else { "column" };
+                let columns = columns
+                    .iter()
+                    .map(ToString::to_string)
+                    .collect::<Vec<_>>()
+                    .join(",");
+
+                write!(
+                    f,
+                    "more than one header has the name of the {kind}. column: {name} indexes: \
+                     {columns}. select it by index or use --on-duplicate"
+                )
+            }
+            Self::ColumnOutOfRange { index, label } => {
+                let kind = if *label { "label index" } else { "index" };
+
+                write!(
+                    f,
+                    "{kind} is out of range for known headers. column index: {index}"
+                )
+            }
+            Self::FromEndOutOfRange {
+                offset,
+                width,
+                label,
+            } => {
+                let kind = if *label { "label index" } else { "index" };
+
+                write!(
+                    f,
+                    "{kind} is out of range for the {width} columns. column index: -{offset}"
+                )
+            }
+            Self::NoHeaders { name, label } => {
+                let kind = if *label { "label column" } else { "column" };
+
+                write!(
+                    f,
+                    "no headers were specified in the csv but given a named {kind}. column: {name}"
+                )
+            }
+            Self::NoColumns => f.write_str("no columns specified to pull numeric data from"),
+            Self::UnknownWidth => f.write_str(
+                "the number of columns in the source is not known. specify the columns by index",
+            ),
+            Self::MultipleLabels => f.write_str("the label must be a single column"),
+            Self::ReversedRange { start, end } => write!(
+                f,
+                "the start of a column range cannot be greater than the end. range: {start}-{end}"
+            ),
+            Self::Pattern { pattern, .. } => {
+                write!(f, "invalid column pattern. pattern: {pattern}")
+            }
+            Self::NoMatches { pattern } => {
+                write!(
+                    f,
+                    "the column pattern did not match any headers. pattern: {pattern}"
+                )
+            }
+            Self::MissingValue {
+                row,
+                column,
+                context,
+            } => {
+                write!(
+                    f,
+                    "column data not found. row: {} column index: {}",
+                    row + 1,
+                    column + 1
+                )?;
+
+                write_context(f, context.as_deref())
+            }
+            Self::Parse {
+                row,
+                column,
+                context,
+            } => {
+                write!(
+                    f,
+                    "failed to parse column data. row: {} column index: {}",
+                    row + 1,
+                    column + 1
+                )?;
+
+                write_context(f, context.as_deref())
+            }
+            Self::MissingLabel { row, context } => {
+                write!(f, "failed to find label. label index: {row}")?;
+
+                write_context(f, context.as_deref())
+            }
+            Self::NonNumericLabel { row, label } => write!(
+                f,
+                "regression requires numeric labels. row: {} label: {label}",
+                row + 1
+            ),
+            Self::DimensionMismatch { expected, found } => write!(
+                f,
+                "the datapoint has {found} values but the records have {expected} columns"
+            ),
+            Self::ZeroK => f.write_str("k value cannot be 0"),
+            Self::NoRecords => f.write_str("no records were provided to fit"),
+            Self::NoIncludedLabels => f.write_str("no records have the included labels"),
+            Self::NoNeighbors => f.write_str("no neighbors were found for the datapoint"),
+            Self::MemoryBudget {
+                what,
+                needed,
+                used,
+                budget,
+            } => write!(
+                f,
+                "{what} would exceed the memory budget. needed: {} in use: {} budget: {}",
+                memory::format_bytes(*needed),
+                memory::format_bytes(*used),
+                memory::format_bytes(*budget),
+            ),
+        }
+    }
+}
+
+impl KnnError {
+    /// adds the text of the row to an error of a single row so the value
+    /// that failed can be shown. other errors are returned unchanged
+    ///
+    /// `column` is the column that failed for errors that do not know it,
+    /// such as the label column. `line` is the line of the file the row
+    /// starts on if it is known
+    pub fn with_row<'a, I>(
+        mut self,
+        values: I,
+        headers: Option<&[String]>,
+        label: usize,
+        line: Option<u64>,
+    ) -> Self
+    where
+        I: IntoIterator<Item = &'a str>,
+    {
+        let values: Vec<&str> = values.into_iter().collect();
+
+        let (column, slot) = match &mut self {
+            Self::MissingValue {
+                column, context, ..
+            }
+            | Self::Parse {
+                column, context, ..
+            } => (*column, context),
+            Self::MissingLabel { context, .. } => (label, context),
+            _ => return self,
+        };
+
+        let header = headers.and_then(|headers| headers.get(column)).cloned();
+        let name = header
+            .clone()
+            .unwrap_or_else(|| format!("column index {}", column + 1));
+        let hint = match values.get(column) {
+            Some(value) if is_missing(value) => format!(
+                "{name} is missing a value. fill it in, remove the row, or leave the column out"
+            ),
+            Some(value) => format!(
+                "\"{value}\" in {name} is not a number. fix the value or leave the column out"
+            ),
+            None => format!(
+                "the row has {} values but {name} is value {}. check the delimiter and quotes of \
+                 the row",
+                values.len(),
+                column + 1
+            ),
+        };
+
+        *slot = Some(Box::new(RowContext::new(
+            &values, column, header, line, hint,
+        )));
+
+        self
+    }
+}
+
+/// the text of a row that failed to load along with where the value that
+/// failed is in it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RowContext {
+    /// the line of the file the row starts on
+    pub line: Option<u64>,
+    /// the values of the row joined by commas. quotes are not kept
+    pub text: String,
+    /// the byte range of the value in `text`. empty at the end of the text
+    /// when the row does not have the value
+    pub span: Range<usize>,
+    /// the header of the column
+    pub header: Option<String>,
+    /// a suggestion for fixing the row
+    pub hint: String,
+}
+
+impl RowContext {
+    /// creates the context of `column` in a row with the given values
+    pub fn new(
+        values: &[&str],
+        column: usize,
+        header: Option<String>,
+        line: Option<u64>,
+        hint: String,
+    ) -> Self {
+        let text = values.join(",");
+        let start = values
+            .iter()
+            .take(column)
+            .map(|value| value.len() + 1)
+            .sum::<usize>()
+            .min(text.len());
+        let end = values
+            .get(column)
+            .map_or(start, |value| start + value.len());
+
+        Self {
+            line,
+            text,
+            span: start..end,
+            header,
+            hint,
+        }
+    }
+}
+
+impl fmt::Display for RowContext {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let number = self.line.map(|line| line.to_string()).unwrap_or_default();
+        let pad = " ".repeat(number.len() + 2);
+        // the marker is placed by characters so values before it that are
+        // not ascii do not shift it
+        let offset = self.text[..self.span.start].chars().count();
+        let width = self.text[self.span.clone()].chars().count().max(1);
+
+        if let Some(line) = self.line {
+            writeln!(f, "{}--> line {line}", &pad[1..])?;
+        }
+
+        writeln!(f, "{pad}|")?;
+        writeln!(f, " {number} | {}", self.text)?;
+        write!(f, "{pad}| {}{}", " ".repeat(offset), "^".repeat(width))?;
+
+        if let Some(header) = &self.header {
+            write!(f, " {header}")?;
+        }
+
+        write!(f, "\n{pad}= hint: {}", self.hint)
+    }
+}
+
+/// writes the context of a row below the error if there is one
+fn write_context(f: &mut fmt::Formatter<'_>, context: Option<&RowContext>) -> fmt::Result {
+    match context {
+        Some(context) => write!(f, "\n{context}"),
+        None => Ok(()),
+    }
+}
+
+impl Error for KnnError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::Headers(err) | Self::Csv { source: err, .. } => Some(err),
+            Self::Source(err) => Some(err.as_ref()),
+            Self::Pattern { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::cli::ColumnType;
+    use crate::csv::get_columns_and_label;
+    use crate::source::MemorySource;
+
+    #[test]
+    fn match_on_cause() {
+        let mut source = MemorySource::new(Some(vec!["x".to_owned(), "class".to_owned()]), vec![]);
+        let result = get_columns_and_label(
+            &mut source,
+            &ColumnType::Name("class".to_owned()),
+            &[ColumnType::Name("y".to_owned())],
+        );
+
+        match result {
+            Err(KnnError::ColumnNotFound { name, label, .. }) => {
+                assert_eq!(name, "y");
+                assert!(!label);
+            }
+            other => panic!("unexpected result {other:?}"),
+        }
+
+        let err = anyhow::Error::from(KnnError::Parse {
+            row: 2,
+            column: 0,
+            context: None,
+        });
+
+        assert_eq!(
+            err.to_string(),
+            "failed to parse column data. row: 3 column index: 1"
+        );
+    }
+
+    #[test]
+    fn row_context() {
+        let headers = ["x".to_owned(), "y".to_owned(), "class".to_owned()];
+        let err = KnnError::Parse {
+            row: 2,
+            column: 1,
+            context: None,
+        }
+        .with_row(["1.5", "abc", "a"], Some(&headers), 2, Some(4));
+
+        assert_eq!(
+            err.to_string(),
+            "failed to parse column data. row: 3 column index: 2\n  --> line 4\n   |\n \
+             4 | 1.5,abc,a\n   |     ^^^ y\n   = hint: \"abc\" in y is not a number. fix the \
+             value or leave the column out"
+        );
+
+        let err = KnnError::MissingLabel {
+            row: 0,
+            context: None,
+        }
+        .with_row(["1.5", "2"], None, 2, None);
+
+        let KnnError::MissingLabel {
+            context: Some(context),
+            ..
+        } = err
+        else {
+            panic!("the context was not added");
+        };
+
+        assert_eq!(context.span, 5..5);
+    }
+}
@@ -0,0 +1,872 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, bail};
+use clap::Args;
+use clap::builder::RangedU64ValueParser;
+use rand::SeedableRng;
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rayon::prelude::*;
+
+use crate::balance::{extend_smote, fold_counts, print_balance, undersample};
+use crate::classify::{
+    average_groups, classify_datapoint, classify_datapoint_owned, closest_labels_batch,
+    count_labels, largest_group, majority_label,
+};
+use crate::cli::{AlgoType, Balance, ColumnList, ColumnType, Precision, TaskType};
+use crate::csv::{
+    Dataset, KnnRecord, Writer, check_numeric_labels, collect_records, column_names,
+    get_columns_excluding, require_csv,
+};
+use crate::distance::{DistanceMetric, narrow};
+use crate::index::{IndexArgs, IndexType, NeighborIndex, recall};
+use crate::memory;
+use crate::metrics::Evaluation;
+use crate::pq::{ProductQuantizer, QuantizedRecords};
+use crate::progress::Progress;
+use crate::scale::{ScaleType, Scaler};
+use crate::source::DataSource;
+use crate::split::{fold_dataset, partition_fold};
+use crate::store::StoredIndex;
+
+#[derive(Debug, Args)]
+pub struct EvaluateArgs {
+    /// the number of neighbors to lookup
+    #[arg(short, default_value = "3")]
+    k: usize,
+
+    /// specifies the algorithm to use when calculating distances
+    #[arg(long, default_value = "euclidean")]
+    algo: AlgoType,
+
+    /// the list of columns to use as datapoints
+    #[arg(short, long = "col", allow_hyphen_values = true)]
+    columns: Vec<ColumnList>,
+
+    /// columns to remove from the list of columns
+    #[arg(long = "exclude-col", allow_hyphen_values = true)]
+    exclude: Vec<ColumnList>,
+
+    /// the column to use as the label
+    #[arg(long, allow_negative_numbers = true)]
+    label: Option<ColumnType>,
+
+    /// the type of task to evaluate the labels with
+    #[arg(long, default_value = "classification")]
+    task: TaskType,
+
+    /// the scaling method to apply to the columns
+    #[arg(long, default_value = "none")]
+    scale: ScaleType,
+
+    /// the number of folds to evaluate over
+    #[arg(long, default_value = "5")]
+    folds: usize,
+
+    /// balances the labels of the training records of each fold. undersample
+    /// randomly drops records of the larger labels using the seed
+    #[arg(long, default_value = "none", conflicts_with = "index_file")]
+    balance: Balance,
+
+    /// the seed used to balance the training records and shuffle the columns
+    /// for the importance report. a random seed is used if not specified
+    #[arg(long)]
+    seed: Option<u64>,
+
+    #[command(flatten)]
+    index: IndexArgs,
+
+    /// writes a calibration curve of the predictions to the specified csv
+    /// file. only available for classification
+    #[arg(long)]
+    calibration: Option<PathBuf>,
+
+    /// the number of confidence bins to use for the calibration curve
+    #[arg(long, default_value = "10")]
+    bins: usize,
+
+    /// reports how much the score drops when the values of each column are
+    /// shuffled between the test records
+    #[arg(long)]
+    importance: bool,
+
+    /// evaluates the records of an index file created by `index build`
+    /// instead of the csv. every record is classified against the others
+    /// using the algorithm, scaling, and index the file was built with
+    #[arg(long, conflicts_with_all = ["columns", "label", "importance", "precision"])]
+    index_file: Option<PathBuf>,
+
+    /// the precision used to store the records and calculate distances. f32
+    /// and pq are only available with the brute index
+    #[arg(long, default_value = "f64")]
+    precision: Precision,
+
+    /// the number of subspaces each record is split into with pq precision.
+    /// defaults to one subspace for every 2 columns
+    #[arg(long, value_parser = RangedU64ValueParser::<usize>::new().range(1..))]
+    pq_subspaces: Option<usize>,
+}
+
+/// tracks the predictions that fall into a single confidence range
+#[derive(Debug, Default, Clone)]
+struct CalibrationBin {
+    count: usize,
+    correct: usize,
+    confidence: f64,
+}
+
+pub fn knn_evaluate<S>(
+    source: Option<S>,
+    mut arg: EvaluateArgs,
+    progress: &dyn Progress,
+) -> anyhow::Result<()>
+where
+    S: DataSource,
+{
+    if arg.k == 0 {
+        bail!("k value cannot be 0");
+    }
+
+    if arg.bins == 0 {
+        bail!("the number of calibration bins must be larger than 0");
+    }
+
+    if arg.calibration.is_some() && arg.task != TaskType::Classification {
+        bail!("calibration is only available for classification");
+    }
+
+    if arg.balance != Balance::None && arg.task != TaskType::Classification {
+        bail!("balancing is only available for classification");
+    }
+
+    if arg.balance == Balance::Smote
+        && (arg.precision != Precision::F64 || arg.index.kind != IndexType::Brute)
+    {
+        bail!("smote is only available with the brute index and f64 precision");
+    }
+
+    if let Some(path) = arg.index_file.take() {
+        if source.is_some() {
+            bail!("a csv file cannot be used with an index file");
+        }
+
+        return evaluate_stored(&path, &arg);
+    }
+
+    let mut source = require_csv(source)?;
+
+    if arg.folds < 2 {
+        bail!("the number of folds must be at least 2");
+    }
+
+    if arg.precision != Precision::F64 && arg.index.kind != IndexType::Brute {
+        bail!("f32 and pq precision are only available with the brute index");
+    }
+
+    let label = &ColumnType::label_or_last(arg.label.as_ref());
+
+    let (label, columns) = get_columns_excluding(
+        &mut source,
+        label,
+        &ColumnList::flatten(&arg.columns),
+        &ColumnList::flatten(&arg.exclude),
+    )?;
+    let names = column_names(&mut source, &columns)?;
+    let records = collect_records(source, label, &columns)?;
+
+    if arg.task == TaskType::Regression {
+        check_numeric_labels(&records)?;
+    }
+
+    let folds = fold_dataset(records.iter(), arg.folds);
+
+    if arg.task == TaskType::Classification {
+        print_balance(&records, &fold_counts(&records, &folds), arg.k);
+    }
+
+    let seed = arg.seed.unwrap_or_else(rand::random);
+
+    if arg.balance != Balance::None {
+        println!("balance seed: {seed}");
+    }
+
+    let mut tally = Tally::new(arg.bins);
+
+    for (fold_index, fold) in folds.iter().enumerate() {
+        let mut in_train = vec![true; records.len()];
+
+        for index in fold {
+            in_train[*index] = false;
+        }
+
+        // only the training records are balanced so every record is still
+        // tested once
+        match arg.balance {
+            // smote adds records once the training records are scaled
+            Balance::None | Balance::Smote => {}
+            Balance::Undersample => {
+                let positions: Vec<usize> = (0..records.len())
+                    .filter(|index| in_train[*index])
+                    .collect();
+                let kept = undersample(
+                    positions.iter().map(|index| records.get(*index).label),
+                    seed,
+                );
+
+                in_train.fill(false);
+
+                for position in kept {
+                    in_train[positions[position]] = true;
+                }
+            }
+        }
+
+        let train = || {
+            records
+                .iter()
+                .enumerate()
+                .filter(|(index, _)| in_train[*index])
+        };
+
+        let baseline = majority_label(train().map(|(_, record)| record.label));
+
+        // the scaling parameters are fitted on only the training records of
+        // the current fold
+        let scaler = Scaler::fit(
+            arg.scale,
+            train().map(|(_, record)| record.data),
+            columns.len(),
+        );
+
+        // the groups are found in parallel and then tallied in order
+        let found: Vec<(usize, Vec<u32>)> = match arg.precision {
+            Precision::F64 if arg.index.kind == IndexType::Brute => {
+                let scaled = scaler.apply_records(&records);
+                let train_len = in_train.iter().filter(|kept| **kept).count();
+
+                memory::reserve(
+                    train_len * (columns.len() + 1) * std::mem::size_of::<f64>(),
+                    "copying the training records",
+                )?;
+
+                // the training records are copied next to each other so the
+                // test records can be compared against them in tiles
+                let mut against = Dataset::with_labels_of(&scaled, columns.len(), train_len);
+
+                for (index, record) in train() {
+                    against.push_id(scaled.get(index).data, record.label);
+                }
+
+                if arg.balance == Balance::Smote {
+                    extend_smote(&mut against, arg.algo, seed);
+                }
+
+                let queries: Vec<&[f64]> =
+                    fold.iter().map(|index| scaled.get(*index).data).collect();
+
+                closest_labels_batch(arg.k, &against, arg.algo.as_rank_matrix_fn(), &queries)
+                    .into_iter()
+                    .map(|labels| {
+                        let mut groups = Vec::new();
+                        let min = count_labels(labels.into_iter(), &mut groups);
+
+                        (min, groups)
+                    })
+                    .collect()
+            }
+            Precision::F64 => {
+                let scaled = scaler.apply_records(&records);
+                let index = arg.index.build(
+                    columns.len(),
+                    train().map(|(index, _)| (index, scaled.get(index).data)),
+                    arg.algo,
+                )?;
+
+                // approximate indexes are compared to brute force on a
+                // sample of the test records
+                if !index.is_exact() {
+                    let (found, expected) = recall(
+                        &index,
+                        columns.len(),
+                        train().map(|(index, _)| (index, scaled.get(index).data)),
+                        arg.algo,
+                        fold.iter().map(|index| scaled.get(*index).data),
+                        arg.k,
+                    );
+
+                    tally.recall_found += found;
+                    tally.recall_expected += expected;
+                }
+
+                fold.par_iter()
+                    .map(|test_index| {
+                        let mut groups = Vec::new();
+                        let neighbors = index.nearest(scaled.get(*test_index).data, arg.k);
+                        let min = count_labels(
+                            neighbors.iter().map(|(_, index)| records.get(*index).label),
+                            &mut groups,
+                        );
+
+                        (min, groups)
+                    })
+                    .collect()
+            }
+            Precision::Pq => {
+                let scaled = scaler.apply_records(&records);
+                // the centroids are only trained on the training records
+                let quantizer = ProductQuantizer::fit(
+                    train().map(|(index, _)| scaled.get(index).data),
+                    columns.len(),
+                    arg.pq_subspaces.unwrap_or(columns.len().div_ceil(2)),
+                );
+                let quantized = QuantizedRecords::encode(
+                    quantizer,
+                    train().map(|(index, record)| (scaled.get(index).data, record.label)),
+                );
+
+                fold.par_iter()
+                    .map(|test_index| {
+                        let mut groups = Vec::new();
+                        let labels =
+                            quantized.closest_labels(arg.k, arg.algo, scaled.get(*test_index).data);
+                        let min = count_labels(labels.into_iter(), &mut groups);
+
+                        (min, groups)
+                    })
+                    .collect()
+            }
+            Precision::F32 => {
+                // only the f32 copies of the scaled records are kept
+                let scaled = records
+                    .iter()
+                    .map(|record| {
+                        let mut data = record.data.to_vec();
+
+                        scaler.apply(&mut data);
+
+                        narrow(&data)
+                    })
+                    .collect::<Vec<Vec<f32>>>();
+                let algo = arg.algo.as_rank_f32_fn();
+
+                fold.par_iter()
+                    .map(|test_index| {
+                        let train_iter =
+                            train().map(|(index, record)| (&scaled[index], record.label));
+
+                        classify_datapoint_owned(arg.k, train_iter, algo, &scaled[*test_index])
+                    })
+                    .collect()
+            }
+        };
+
+        for (test_index, (min, groups)) in fold.iter().zip(found) {
+            tally.add(
+                arg.task,
+                records.get(*test_index).label,
+                baseline,
+                &groups,
+                records.label_names(),
+                min,
+            )?;
+        }
+
+        progress.on_fold_finished(fold_index, folds.len());
+    }
+
+    println!("records: {} folds: {}", records.len(), folds.len());
+
+    print_tally(&arg, &tally, arg.algo, records.len())?;
+
+    if arg.importance {
+        let drops = permutation_importance(&arg, &records, &folds, seed)?;
+
+        let mut ranked: Vec<(usize, f64)> = columns.iter().copied().zip(drops).collect();
+        ranked.sort_by(|(_, a), (_, b)| b.total_cmp(a));
+
+        println!("importance seed: {seed}");
+
+        for (col, drop) in ranked {
+            match arg.task {
+                TaskType::Classification => {
+                    println!("  {} drop % {:.2}", names[&col], drop * 100.0);
+                }
+                TaskType::Regression => println!("  {} drop {drop:.4}", names[&col]),
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// evaluates the records of an index file by classifying every record
+/// against all of the other records
+///
+/// the index was built with every record so leaving one out is used instead
+/// of folds to avoid building the index again
+fn evaluate_stored(path: &Path, arg: &EvaluateArgs) -> anyhow::Result<()> {
+    let stored = StoredIndex::load(path)?;
+    let mut groups = Vec::new();
+    let mut tally = Tally::new(arg.bins);
+    // the baseline is not recalculated without each record since a single
+    // record will rarely change the majority
+    let baseline = majority_label(stored.labels.iter().copied());
+
+    if !stored.index.is_exact() {
+        let (found, expected) = recall(
+            &stored.index,
+            stored.index.dims(),
+            stored.index.datapoints(),
+            stored.algo,
+            stored.index.datapoints().map(|(_, datapoint)| datapoint),
+            arg.k + 1,
+        );
+
+        tally.recall_found += found;
+        tally.recall_expected += expected;
+    }
+
+    // the record will find itself so an extra neighbor is retrieved
+    let datapoints: Vec<(usize, &[f64])> = stored.index.datapoints().collect();
+    let found: Vec<Vec<(f64, usize)>> = datapoints
+        .par_iter()
+        .map(|(_, datapoint)| stored.index.nearest(datapoint, arg.k + 1))
+        .collect();
+
+    for ((id, _), neighbors) in datapoints.into_iter().zip(found) {
+        groups.clear();
+
+        let min = count_labels(
+            neighbors
+                .iter()
+                .filter(|(_, index)| *index != id)
+                .take(arg.k)
+                .map(|(_, index)| stored.labels[*index]),
+            &mut groups,
+        );
+
+        tally.add(
+            arg.task,
+            stored.labels[id],
+            baseline,
+            &groups,
+            &stored.label_names,
+            min,
+        )?;
+    }
+
+    println!("records: {} leave one out", stored.labels.len());
+
+    print_tally(arg, &tally, stored.algo, stored.labels.len())
+}
+
+/// the predictions made while evaluating
+struct Tally {
+    passed: usize,
+    failed: usize,
+    baseline_passed: usize,
+    unknown: usize,
+    actual: Vec<f64>,
+    predicted: Vec<f64>,
+    bins: Vec<CalibrationBin>,
+    /// the neighbors found by an approximate index and the number expected
+    recall_found: usize,
+    recall_expected: usize,
+}
+
+impl Tally {
+    fn new(bins: usize) -> Self {
+        Self {
+            passed: 0,
+            failed: 0,
+            baseline_passed: 0,
+            unknown: 0,
+            actual: Vec::new(),
+            predicted: Vec::new(),
+            bins: vec![CalibrationBin::default(); bins],
+            recall_found: 0,
+            recall_expected: 0,
+        }
+    }
+
+    /// records the prediction made for a single test record from the groups
+    /// of its neighbors
+    ///
+    /// `names` are the labels of each label id
+    fn add(
+        &mut self,
+        task: TaskType,
+        label: u32,
+        baseline: Option<u32>,
+        groups: &[u32],
+        names: &[String],
+        min: usize,
+    ) -> anyhow::Result<()> {
+        match task {
+            TaskType::Classification => {
+                if baseline == Some(label) {
+                    self.baseline_passed += 1;
+                }
+
+                let Some((predicted, count)) = largest_group(groups) else {
+                    self.unknown += 1;
+
+                    return Ok(());
+                };
+
+                let is_correct = predicted == label;
+
+                if is_correct {
+                    self.passed += 1;
+                } else {
+                    self.failed += 1;
+                }
+
+                // the vote fraction of the predicted label is used as the
+                // confidence of the prediction
+                let bins = self.bins.len();
+                let confidence = (count as f64) / (min as f64);
+                let bin_index = std::cmp::min((confidence * bins as f64) as usize, bins - 1);
+                let bin = &mut self.bins[bin_index];
+
+                bin.count += 1;
+                bin.confidence += confidence;
+
+                if is_correct {
+                    bin.correct += 1;
+                }
+            }
+            TaskType::Regression => {
+                if let Some(value) = average_groups(groups, names, min) {
+                    self.actual.push(names[label as usize].parse::<f64>()?);
+                    self.predicted.push(value);
+                } else {
+                    self.unknown += 1;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn evaluation(&self, task: TaskType) -> Evaluation {
+        match task {
+            TaskType::Classification => Evaluation::Classification {
+                passed: self.passed,
+                failed: self.failed,
+                unknown: self.unknown,
+            },
+            TaskType::Regression => {
+                Evaluation::regression(&self.actual, &self.predicted, self.unknown)
+            }
+        }
+    }
+}
+
+/// outputs the evaluation and writes the calibration curve if requested
+fn print_tally(
+    arg: &EvaluateArgs,
+    tally: &Tally,
+    algo: AlgoType,
+    records: usize,
+) -> anyhow::Result<()> {
+    let eval = tally.evaluation(arg.task);
+
+    println!("k {} {:?} | {eval}", arg.k, algo);
+
+    if tally.recall_expected > 0 {
+        println!(
+            "recall: {:.4}",
+            tally.recall_found as f64 / tally.recall_expected as f64
+        );
+    }
+
+    if arg.task == TaskType::Classification && records > 0 {
+        let baseline_p = (tally.baseline_passed as f64) / (records as f64);
+
+        println!(
+            "baseline % {:.2} lift {:+.2}",
+            baseline_p * 100.0,
+            (eval.score() - baseline_p) * 100.0
+        );
+    }
+
+    if let Some(path) = &arg.calibration {
+        write_calibration(path, &tally.bins).context("failed to write calibration csv")?;
+    }
+
+    Ok(())
+}
+
+/// calculates the mean drop in score of each column when its values are
+/// shuffled between the test records of a fold
+///
+/// the drops are returned in the same order as the columns of the records
+fn permutation_importance(
+    arg: &EvaluateArgs,
+    records: &Dataset,
+    folds: &[Vec<usize>],
+    seed: u64,
+) -> anyhow::Result<Vec<f64>> {
+    let algo = &arg.algo;
+    let columns = records.dims();
+    let mut rng = StdRng::seed_from_u64(seed);
+    let mut drops = vec![0.0; columns];
+    let mut counted = 0;
+
+    for fold in folds {
+        if fold.is_empty() {
+            continue;
+        }
+
+        let (train, _) = partition_fold(records.iter(), fold);
+        let scaler = Scaler::fit(arg.scale, train.iter().map(|record| record.data), columns);
+        let scaled = scaler.apply_records(records);
+        let (train, test) = partition_fold(scaled.iter(), fold);
+
+        let names = records.label_names();
+        let base = evaluate_split(arg.k, algo, arg.task, &train, &test, names)?.score();
+
+        for (column, drop) in drops.iter_mut().enumerate() {
+            let mut values: Vec<f64> = test.iter().map(|record| record.data[column]).collect();
+
+            values.shuffle(&mut rng);
+
+            let mut permuted = Dataset::with_labels_of(&scaled, columns, test.len());
+
+            for (record, value) in test.iter().zip(values) {
+                let mut data = record.data.to_vec();
+                data[column] = value;
+
+                permuted.push_id(&data, record.label);
+            }
+
+            let permuted: Vec<KnnRecord> = permuted.iter().collect();
+
+            *drop +=
+                base - evaluate_split(arg.k, algo, arg.task, &train, &permuted, names)?.score();
+        }
+
+        counted += 1;
+    }
+
+    if counted > 0 {
+        for drop in &mut drops {
+            *drop /= counted as f64;
+        }
+    }
+
+    Ok(drops)
+}
+
+/// evaluates the records with cross validation over the specified number of
+/// folds
+///
+/// returns the mean score of the folds. refer to [`Evaluation::score`]
+pub fn cross_validate(
+    k: usize,
+    algo: &dyn DistanceMetric,
+    task: TaskType,
+    records: &Dataset,
+    folds: usize,
+    progress: &dyn Progress,
+) -> anyhow::Result<f64> {
+    let folds = fold_dataset(records.iter(), folds);
+    let mut total = 0.0;
+    let mut counted = 0;
+
+    for (fold_index, fold) in folds.iter().enumerate() {
+        if fold.is_empty() {
+            continue;
+        }
+
+        let (train, test) = partition_fold(records.iter(), fold);
+
+        total += evaluate_split(k, algo, task, &train, &test, records.label_names())?.score();
+        counted += 1;
+
+        progress.on_fold_finished(fold_index, folds.len());
+    }
+
+    if counted == 0 {
+        Ok(0.0)
+    } else {
+        Ok(total / counted as f64)
+    }
+}
+
+/// evaluates the test records against the train records with the specified
+/// parameters
+///
+/// `names` are the labels of each label id of the records
+pub fn evaluate_split(
+    k: usize,
+    algo: &dyn DistanceMetric,
+    task: TaskType,
+    train: &[KnnRecord],
+    test: &[KnnRecord],
+    names: &[String],
+) -> anyhow::Result<Evaluation> {
+    let mut collected = Vec::with_capacity(k + 1);
+    let mut groups = Vec::new();
+
+    let mut passed = 0;
+    let mut failed = 0;
+    let mut actual = Vec::new();
+    let mut predicted = Vec::new();
+    let mut unknown = 0;
+
+    for test_record in test {
+        collected.clear();
+        groups.clear();
+
+        let iter = train.iter().map(|record| (record.data, record.label));
+
+        let min = classify_datapoint(
+            k,
+            iter,
+            |a, b| algo.rank(a, b),
+            test_record.data,
+            &mut collected,
+            &mut groups,
+        );
+
+        match task {
+            TaskType::Classification => match largest_group(&groups) {
+                Some((label, _)) if label == test_record.label => passed += 1,
+                Some(_) => failed += 1,
+                None => unknown += 1,
+            },
+            TaskType::Regression => {
+                if let Some(value) = average_groups(&groups, names, min) {
+                    actual.push(names[test_record.label as usize].parse::<f64>()?);
+                    predicted.push(value);
+                } else {
+                    unknown += 1;
+                }
+            }
+        }
+    }
+
+    Ok(match task {
+        TaskType::Classification => Evaluation::Classification {
+            passed,
+            failed,
+            unknown,
+        },
+        TaskType::Regression => Evaluation::regression(&actual, &predicted, unknown),
+    })
+}
+
+/// writes the calibration bins to the specified csv file
+///
+/// empty bins are still written with a count of 0 so that the ranges are
+/// consistent between runs
+fn write_calibration(path: &Path, bins: &[CalibrationBin]) -> anyhow::Result<()> {
+    let mut writer = Writer::from_path(path)?;
+    let width = 1.0 / bins.len() as f64;
+
+    writer.write_record([
+        "bin_start",
+        "bin_end",
+        "count",
+        "mean_confidence",
+        "accuracy",
+    ])?;
+
+    for (index, bin) in bins.iter().enumerate() {
+        let (confidence, accuracy) = if bin.count == 0 {
+            (String::new(), String::new())
+        } else {
+            (
+                format!("{:.4}", bin.confidence / bin.count as f64),
+                format!("{:.4}", bin.correct as f64 / bin.count as f64),
+            )
+        };
+
+        writer.write_record([
+            format!("{:.4}", width * index as f64),
+            format!("{:.4}", width * (index + 1) as f64),
+            bin.count.to_string(),
+            confidence,
+            accuracy,
+        ])?;
+    }
+
+    writer.flush()?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use clap::Parser;
+
+    #[derive(Parser)]
+    struct Cli {
+        #[command(flatten)]
+        evaluate: EvaluateArgs,
+    }
+
+    #[test]
+    fn calibration_bins() {
+        let names = ["a".to_owned(), "b".to_owned()];
+        let mut tally = Tally::new(4);
+
+        for (label, groups) in [(0, [4, 0]), (0, [3, 1]), (1, [3, 1]), (0, [2, 2])] {
+            tally
+                .add(TaskType::Classification, label, None, &groups, &names, 4)
+                .unwrap();
+        }
+
+        // a confidence of 1.0 is placed in the last bin instead of past it
+        assert_eq!(tally.bins[3].count, 3);
+        assert_eq!(tally.bins[3].correct, 2);
+        assert_eq!(tally.bins[2].count, 1);
+        assert_eq!(tally.bins[2].correct, 1);
+
+        let path = std::env::temp_dir().join(format!("knn_calibration_{}.csv", std::process::id()));
+
+        write_calibration(&path, &tally.bins).unwrap();
+
+        let written = std::fs::read_to_string(&path).unwrap();
+
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(
+            written.lines().collect::<Vec<&str>>(),
+            vec![
+                "bin_start,bin_end,count,mean_confidence,accuracy",
+                "0.0000,0.2500,0,,",
+                "0.2500,0.5000,0,,",
+                "0.5000,0.7500,1,0.5000,1.0000",
+                "0.7500,1.0000,3,0.8333,0.6667",
+            ]
+        );
+    }
+
+    #[test]
+    fn importance_finds_signal() {
+        let mut records = Dataset::new(2);
+
+        // the first column decides the label while the second is noise
+        for index in 0..40 {
+            let signal = index as f64;
+            let noise = (index * 7 % 13) as f64;
+
+            records.push(&[signal, noise], if index < 20 { "a" } else { "b" });
+        }
+
+        let cli = Cli::parse_from(["knn", "-k", "3"]);
+        let folds = fold_dataset(records.iter(), 4);
+        let drops = permutation_importance(&cli.evaluate, &records, &folds, 4).unwrap();
+
+        assert_eq!(drops.len(), 2);
+        assert!(drops[0] > drops[1]);
+        assert_eq!(
+            drops,
+            permutation_importance(&cli.evaluate, &records, &folds, 4).unwrap()
+        );
+    }
+}
@@ -0,0 +1,189 @@
+//! a c compatible api for embedding the classifier in other languages
+//!
+//! the library is also built as a shared and static library so these can be
+//! linked from c. the header for these functions is generated into
+//! `include/knn.h` with cbindgen.
+//!
+//! labels are passed as numbers since they only need to be told apart. a
+//! classifier returned by [`knn_fit`] must be released with [`knn_free`].
+use std::ptr;
+use std::slice;
+
+use crate::classifier::KnnClassifier;
+use crate::cli::AlgoType;
+use crate::csv::Dataset;
+use crate::scale::ScaleType;
+
+/// calculates distances with the euclidean algorithm
+pub const KNN_EUCLIDEAN: u32 = 0;
+/// calculates distances with the manhattan algorithm
+pub const KNN_MANHATTAN: u32 = 1;
+
+/// leaves the columns unmodified
+pub const KNN_SCALE_NONE: u32 = 0;
+/// scales the columns into the range of 0 to 1
+pub const KNN_SCALE_MINMAX: u32 = 1;
+/// scales the columns to have a mean of 0 and standard deviation of 1
+pub const KNN_SCALE_ZSCORE: u32 = 2;
+
+/// the call completed
+pub const KNN_OK: i32 = 0;
+/// a pointer was null or a value was out of range
+pub const KNN_INVALID_ARGUMENT: i32 = -1;
+/// the classifier could not produce a result
+pub const KNN_FAILED: i32 = -2;
+
+/// fits a classifier to the records
+///
+/// `data` is a row major matrix of `len` records with `dims` values each and
+/// `labels` has the label of each record. returns null if any of the
+/// arguments are invalid.
+///
+/// # Safety
+///
+/// `data` must point to `len * dims` values and `labels` must point to `len`
+/// values. neither are used after the call returns
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn knn_fit(
+    data: *const f64,
+    labels: *const u32,
+    len: usize,
+    dims: usize,
+    k: usize,
+    metric: u32,
+    scale: u32,
+) -> *mut KnnClassifier {
+    if data.is_null() || labels.is_null() || len == 0 || dims == 0 {
+        return ptr::null_mut();
+    }
+
+    let Some(size) = len.checked_mul(dims) else {
+        return ptr::null_mut();
+    };
+
+    let algo = match metric {
+        KNN_EUCLIDEAN => AlgoType::Euclidean,
+        KNN_MANHATTAN => AlgoType::Manhattan,
+        _ => return ptr::null_mut(),
+    };
+    let scale = match scale {
+        KNN_SCALE_NONE => ScaleType::None,
+        KNN_SCALE_MINMAX => ScaleType::MinMax,
+        KNN_SCALE_ZSCORE => ScaleType::ZScore,
+        _ => return ptr::null_mut(),
+    };
+
+    // SAFETY: the caller guarantees the sizes of both lists
+    let (data, labels) = unsafe {
+        (
+            slice::from_raw_parts(data, size),
+            slice::from_raw_parts(labels, len),
+        )
+    };
+
+    let mut records = Dataset::with_capacity(dims, len);
+
+    for (data, label) in data.chunks_exact(dims).zip(labels) {
+        records.push(data, &label.to_string());
+    }
+
+    match KnnClassifier::builder()
+        .k(k)
+        .metric(algo)
+        .scale(scale)
+        .fit(records)
+    {
+        Ok(classifier) => Box::into_raw(Box::new(classifier)),
+        Err(_) => ptr::null_mut(),
+    }
+}
+
+/// predicts the label of the datapoint and writes it to `label`
+///
+/// returns [`KNN_OK`] if the label was written
+///
+/// # Safety
+///
+/// `classifier` must be returned from [`knn_fit`] and not yet freed.
+/// `datapoint` must point to `dims` values and `label` to a single value
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn knn_predict(
+    classifier: *const KnnClassifier,
+    datapoint: *const f64,
+    dims: usize,
+    label: *mut u32,
+) -> i32 {
+    if classifier.is_null() || datapoint.is_null() || label.is_null() {
+        return KNN_INVALID_ARGUMENT;
+    }
+
+    // SAFETY: the caller guarantees the classifier is live and the size of
+    // the datapoint
+    let (classifier, datapoint) = unsafe { (&*classifier, slice::from_raw_parts(datapoint, dims)) };
+
+    let Ok(predicted) = classifier.predict(datapoint) else {
+        return KNN_FAILED;
+    };
+
+    // every label was created from a number in knn_fit
+    let Ok(predicted) = predicted.parse() else {
+        return KNN_FAILED;
+    };
+
+    // SAFETY: the caller guarantees label points to a single value
+    unsafe { label.write(predicted) };
+
+    KNN_OK
+}
+
+/// releases a classifier returned by [`knn_fit`]. null is ignored
+///
+/// # Safety
+///
+/// `classifier` must be returned from [`knn_fit`] and not already freed
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn knn_free(classifier: *mut KnnClassifier) {
+    if !classifier.is_null() {
+        // SAFETY: the caller guarantees the classifier came from knn_fit
+        drop(unsafe { Box::from_raw(classifier) });
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn fit_and_predict() {
+        let data = [0.0, 0.0, 0.0, 1.0, 5.0, 5.0, 6.0, 5.0];
+        let labels = [7, 7, 9, 9];
+
+        unsafe {
+            let classifier = knn_fit(
+                data.as_ptr(),
+                labels.as_ptr(),
+                4,
+                2,
+                1,
+                KNN_EUCLIDEAN,
+                KNN_SCALE_NONE,
+            );
+            assert!(!classifier.is_null());
+
+            let mut label = 0;
+            assert_eq!(
+                knn_predict(classifier, [5.5, 5.0].as_ptr(), 2, &mut label),
+                KNN_OK
+            );
+            assert_eq!(label, 9);
+            assert_eq!(
+                knn_predict(classifier, [5.5].as_ptr(), 1, &mut label),
+                KNN_FAILED
+            );
+
+            knn_free(classifier);
+
+            assert!(knn_fit(data.as_ptr(), labels.as_ptr(), 4, 2, 1, 5, KNN_SCALE_NONE).is_null());
+        }
+    }
+}
@@ -0,0 +1,243 @@
+//! calculating distances on the gpu
+//!
+//! the records are uploaded to the gpu and a compute shader calculates the
+//! distance from the datapoint to every record. only the distances are read
+//! back so the closest records are still selected on the cpu. distances use
+//! f32 and the same ordering as [`AlgoType::as_rank_fn`].
+//!
+//! only available when built with the `gpu` feature. any failure to find a
+//! gpu is returned as an error so the caller can fall back to the cpu.
+use crate::cli::AlgoType;
+
+/// calculates the ranking distance from the datapoint to every record
+///
+/// `records` is a row major matrix with `dims` values for each record
+#[cfg(feature = "gpu")]
+pub fn rank_distances(
+    records: &[f32],
+    dims: usize,
+    algo: AlgoType,
+    datapoint: &[f32],
+) -> anyhow::Result<Vec<f32>> {
+    pollster::block_on(imp::rank_distances(records, dims, algo, datapoint))
+}
+
+/// always fails since the gpu feature was not enabled
+#[cfg(not(feature = "gpu"))]
+pub fn rank_distances(
+    _records: &[f32],
+    _dims: usize,
+    _algo: AlgoType,
+    _datapoint: &[f32],
+) -> anyhow::Result<Vec<f32>> {
+    anyhow::bail!("knn was built without the gpu feature")
+}
+
+#[cfg(feature = "gpu")]
+mod imp {
+    use anyhow::{Context, bail};
+    use wgpu::util::DeviceExt;
+
+    use crate::cli::AlgoType;
+
+    /// the number of records each workgroup calculates
+    const WORKGROUP_SIZE: u32 = 64;
+
+    /// the maximum number of workgroups in a single dimension of a dispatch
+    const MAX_WORKGROUPS: u32 = 65535;
+
+    const SHADER: &str = r#"
+struct Params {
+    dims: u32,
+    len: u32,
+    metric: u32,
+    stride: u32,
+}
+
+@group(0) @binding(0) var<uniform> params: Params;
+@group(0) @binding(1) var<storage, read> records: array<f32>;
+@group(0) @binding(2) var<storage, read> datapoint: array<f32>;
+@group(0) @binding(3) var<storage, read_write> distances: array<f32>;
+
+@compute @workgroup_size(64)
+fn main(@builtin(global_invocation_id) id: vec3<u32>) {
+    let index = id.y * params.stride + id.x;
+
+    if (index >= params.len) {
+        return;
+    }
+
+    let start = index * params.dims;
+    var total = 0.0;
+
+    for (var dim = 0u; dim < params.dims; dim = dim + 1u) {
+        let diff = records[start + dim] - datapoint[dim];
+
+        if (params.metric == 0u) {
+            total = total + diff * diff;
+        } else {
+            total = total + abs(diff);
+        }
+    }
+
+    distances[index] = total;
+}
+"#;
+
+    pub async fn rank_distances(
+        records: &[f32],
+        dims: usize,
+        algo: AlgoType,
+        datapoint: &[f32],
+    ) -> anyhow::Result<Vec<f32>> {
+        let len = records.len().checked_div(dims).unwrap_or(0);
+
+        if len == 0 {
+            return Ok(Vec::new());
+        }
+
+        let instance = wgpu::Instance::default();
+        let adapter = instance
+            .request_adapter(&wgpu::RequestAdapterOptions::default())
+            .await
+            .context("no gpu adapter available")?;
+        let limits = adapter.limits();
+
+        if std::mem::size_of_val(records) as u64 > limits.max_storage_buffer_binding_size as u64 {
+            bail!("the records are too large for a single gpu buffer");
+        }
+
+        let (device, queue) = adapter
+            .request_device(&wgpu::DeviceDescriptor {
+                label: Some("knn"),
+                required_limits: limits,
+                ..Default::default()
+            })
+            .await
+            .context("failed to create gpu device")?;
+
+        // each row of the dispatch covers as many records as a single
+        // dimension allows
+        let groups = (len as u32).div_ceil(WORKGROUP_SIZE);
+        let groups_x = groups.min(MAX_WORKGROUPS);
+        let groups_y = groups.div_ceil(groups_x);
+
+        let metric = match algo {
+            AlgoType::Euclidean => 0u32,
+            AlgoType::Manhattan => 1u32,
+        };
+        let params = [dims as u32, len as u32, metric, groups_x * WORKGROUP_SIZE];
+
+        let params = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("params"),
+            contents: &to_bytes(params.iter().map(|value| value.to_ne_bytes())),
+            usage: wgpu::BufferUsages::UNIFORM,
+        });
+        let records = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("records"),
+            contents: &to_bytes(records.iter().map(|value| value.to_ne_bytes())),
+            usage: wgpu::BufferUsages::STORAGE,
+        });
+        let datapoint = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("datapoint"),
+            contents: &to_bytes(datapoint.iter().map(|value| value.to_ne_bytes())),
+            usage: wgpu::BufferUsages::STORAGE,
+        });
+
+        let size = (len * std::mem::size_of::<f32>()) as u64;
+        let distances = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("distances"),
+            size,
+            usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_SRC,
+            mapped_at_creation: false,
+        });
+        let staging = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("staging"),
+            size,
+            usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let module = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("distances"),
+            source: wgpu::ShaderSource::Wgsl(SHADER.into()),
+        });
+        let pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+            label: Some("distances"),
+            layout: None,
+            module: &module,
+            entry_point: Some("main"),
+            compilation_options: Default::default(),
+            cache: None,
+        });
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("distances"),
+            layout: &pipeline.get_bind_group_layout(0),
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: params.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: records.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: datapoint.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 3,
+                    resource: distances.as_entire_binding(),
+                },
+            ],
+        });
+
+        let mut encoder = device.create_command_encoder(&Default::default());
+
+        {
+            let mut pass = encoder.begin_compute_pass(&Default::default());
+
+            pass.set_pipeline(&pipeline);
+            pass.set_bind_group(0, &bind_group, &[]);
+            pass.dispatch_workgroups(groups_x, groups_y, 1);
+        }
+
+        encoder.copy_buffer_to_buffer(&distances, 0, &staging, 0, size);
+        queue.submit([encoder.finish()]);
+
+        let (sender, receiver) = std::sync::mpsc::channel();
+        let slice = staging.slice(..);
+
+        slice.map_async(wgpu::MapMode::Read, move |result| {
+            let _ = sender.send(result);
+        });
+
+        device
+            .poll(wgpu::PollType::wait_indefinitely())
+            .context("failed to wait for the gpu")?;
+
+        receiver
+            .recv()
+            .context("gpu did not return the distances")?
+            .context("failed to read the distances from the gpu")?;
+
+        let view = slice
+            .get_mapped_range()
+            .context("failed to read the distances from the gpu")?;
+        let rtn = view
+            .chunks_exact(std::mem::size_of::<f32>())
+            .map(|bytes| f32::from_ne_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+            .collect();
+
+        Ok(rtn)
+    }
+
+    /// flattens the bytes of each value into a single list
+    fn to_bytes<I>(values: I) -> Vec<u8>
+    where
+        I: Iterator<Item = [u8; 4]>,
+    {
+        values.flatten().collect()
+    }
+}
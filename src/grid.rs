@@ -0,0 +1,127 @@
+//! experiment grids for searching
+//!
+//! a grid is a toml file that lists the k ranges, algorithms, scaling methods,
+//! and pools of columns to search over. every combination of algorithm and
+//! column pool is searched with each of the k ranges and scaling methods.
+//!
+//! ```toml
+//! k = ["3-10", "15"]
+//! algo = ["euclidean", "manhattan"]
+//! scale = ["none", "zscore"]
+//! columns = [
+//!     ["bill_length_mm", "bill_depth_mm"],
+//!     ["flipper_length_mm", 6],
+//! ]
+//! ```
+//!
+//! any field that is not specified will use the command line argument
+use std::path::Path;
+use std::str::FromStr;
+
+use anyhow::{Context, anyhow};
+use serde::Deserialize;
+
+use crate::cli::{AlgoType, ColumnType, KValue};
+use crate::scale::ScaleType;
+
+/// the format of the grid file
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct GridFile {
+    #[serde(default)]
+    k: Vec<String>,
+    #[serde(default)]
+    algo: Vec<AlgoType>,
+    #[serde(default)]
+    scale: Vec<ScaleType>,
+    #[serde(default)]
+    columns: Vec<Vec<GridColumn>>,
+}
+
+/// a column can either be a header name or an index
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum GridColumn {
+    Index(usize),
+    Name(String),
+}
+
+/// the configurations to search over
+#[derive(Debug)]
+pub struct Grid {
+    pub k: Vec<KValue>,
+    pub algo: Vec<AlgoType>,
+    pub scale: Vec<ScaleType>,
+    pub columns: Vec<Vec<ColumnType>>,
+}
+
+impl Grid {
+    /// loads a grid from the specified toml file
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        let contents = std::fs::read_to_string(path).context("failed to read grid file")?;
+
+        Self::parse(&contents)
+    }
+
+    /// parses a grid from the contents of a toml file
+    pub fn parse(contents: &str) -> anyhow::Result<Self> {
+        let file: GridFile = toml::from_str(contents).context("failed to parse grid file")?;
+
+        let mut k = Vec::with_capacity(file.k.len());
+
+        for given in &file.k {
+            k.push(
+                KValue::from_str(given)
+                    .map_err(|err| anyhow!("invalid k value in grid file. k: {given} {err}"))?,
+            );
+        }
+
+        let columns = file
+            .columns
+            .into_iter()
+            .map(|pool| {
+                pool.into_iter()
+                    .map(|column| match column {
+                        GridColumn::Index(index) => ColumnType::Index(index),
+                        GridColumn::Name(name) => ColumnType::Name(name),
+                    })
+                    .collect()
+            })
+            .collect();
+
+        Ok(Self {
+            k,
+            algo: file.algo,
+            scale: file.scale,
+            columns,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parse_grid() {
+        let grid = Grid::parse(
+            r#"
+            k = ["3-10", "15"]
+            algo = ["manhattan"]
+            columns = [["a", "b"], ["c", 4]]
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(grid.k.len(), 2);
+        assert_eq!(grid.algo, vec![AlgoType::Manhattan]);
+        assert!(grid.scale.is_empty());
+        assert_eq!(grid.columns.len(), 2);
+        assert!(matches!(grid.columns[1][1], ColumnType::Index(4)));
+    }
+
+    #[test]
+    fn parse_grid_unknown_field() {
+        assert!(Grid::parse("metric = [\"euclidean\"]").is_err());
+    }
+}
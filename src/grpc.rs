@@ -0,0 +1,171 @@
+//! serving predictions over grpc
+//!
+//! the service is defined in `proto/knn.proto` and answers from the same
+//! model as the http server so records added over http are used here as
+//! well. only available when built with the `grpc` feature.
+use std::sync::{Arc, RwLock};
+
+use crate::model::Model;
+
+/// serves the model on the address until the server fails
+#[cfg(feature = "grpc")]
+pub fn serve(model: Arc<RwLock<Model>>, addr: &str) -> anyhow::Result<()> {
+    imp::serve(model, addr)
+}
+
+/// always fails since the grpc feature was not enabled
+#[cfg(not(feature = "grpc"))]
+pub fn serve(_model: Arc<RwLock<Model>>, _addr: &str) -> anyhow::Result<()> {
+    anyhow::bail!("knn was built without the grpc feature")
+}
+
+#[cfg(feature = "grpc")]
+mod imp {
+    use std::sync::{Arc, PoisonError, RwLock};
+
+    use anyhow::Context;
+    use tonic::{Request, Response, Status};
+
+    use crate::model::Model;
+    use crate::results::LabelPrediction;
+
+    mod proto {
+        tonic::include_proto!("knn");
+    }
+
+    use proto::knn_server::{Knn, KnnServer};
+    use proto::{BatchPredictReply, BatchPredictRequest, PredictReply, PredictRequest};
+
+    struct KnnService {
+        model: Arc<RwLock<Model>>,
+    }
+
+    impl KnnService {
+        fn predict_one(&self, datapoint: &[f64]) -> Result<PredictReply, Status> {
+            let prediction = self
+                .model
+                .read()
+                .unwrap_or_else(PoisonError::into_inner)
+                .predict(datapoint)
+                .and_then(LabelPrediction::try_from)
+                .map_err(|err| Status::invalid_argument(format!("{err:#}")))?;
+
+            Ok(PredictReply {
+                label: prediction.label,
+                probabilities: prediction.probabilities.into_iter().collect(),
+            })
+        }
+    }
+
+    #[tonic::async_trait]
+    impl Knn for KnnService {
+        async fn predict(
+            &self,
+            request: Request<PredictRequest>,
+        ) -> Result<Response<PredictReply>, Status> {
+            self.predict_one(&request.into_inner().datapoint)
+                .map(Response::new)
+        }
+
+        async fn batch_predict(
+            &self,
+            request: Request<BatchPredictRequest>,
+        ) -> Result<Response<BatchPredictReply>, Status> {
+            let predictions = request
+                .into_inner()
+                .datapoints
+                .iter()
+                .map(|request| self.predict_one(&request.datapoint))
+                .collect::<Result<_, _>>()?;
+
+            Ok(Response::new(BatchPredictReply { predictions }))
+        }
+    }
+
+    pub fn serve(model: Arc<RwLock<Model>>, addr: &str) -> anyhow::Result<()> {
+        let addr = addr.parse().context("invalid grpc address")?;
+        let runtime = tokio::runtime::Builder::new_multi_thread()
+            .enable_all()
+            .build()
+            .context("failed to start the grpc runtime")?;
+
+        runtime
+            .block_on(
+                tonic::transport::Server::builder()
+                    .add_service(KnnServer::new(KnnService { model }))
+                    .serve(addr),
+            )
+            .context("grpc server failed")
+    }
+
+    #[cfg(test)]
+    mod test {
+        use super::*;
+        use crate::cli::{AlgoType, TaskType};
+        use crate::csv::Dataset;
+        use crate::index::IndexType;
+        use crate::model::MODEL_VERSION;
+        use crate::scale::{ScaleType, Scaler};
+        use crate::store::{ScaledRecords, StoredIndex};
+
+        #[test]
+        fn batch_predict() {
+            let mut records = Dataset::new(1);
+            records.push(&[0.0], "a");
+            records.push(&[10.0], "b");
+
+            let loaded = ScaledRecords {
+                columns: vec!["x".to_owned()],
+                label: "class".to_owned(),
+                scale: ScaleType::None,
+                scaler: Scaler::fit(ScaleType::None, records.iter().map(|r| r.data), 1),
+                records,
+            };
+            let service = KnnService {
+                model: Arc::new(RwLock::new(Model {
+                    version: MODEL_VERSION,
+                    k: 1,
+                    task: TaskType::Classification,
+                    stored: StoredIndex::build(
+                        loaded,
+                        AlgoType::Euclidean,
+                        &IndexType::Brute.into(),
+                    )
+                    .unwrap(),
+                })),
+            };
+            let request = BatchPredictRequest {
+                datapoints: vec![
+                    PredictRequest {
+                        datapoint: vec![1.0],
+                    },
+                    PredictRequest {
+                        datapoint: vec![9.0],
+                    },
+                ],
+            };
+
+            let runtime = tokio::runtime::Builder::new_current_thread()
+                .build()
+                .unwrap();
+            let reply = runtime
+                .block_on(service.batch_predict(Request::new(request)))
+                .unwrap()
+                .into_inner();
+
+            assert_eq!(reply.predictions[0].label, "a");
+            assert_eq!(reply.predictions[1].label, "b");
+            assert_eq!(reply.predictions[1].probabilities["b"], 1.0);
+
+            let wrong = PredictRequest {
+                datapoint: vec![1.0, 2.0],
+            };
+
+            assert!(
+                runtime
+                    .block_on(service.predict(Request::new(wrong)))
+                    .is_err()
+            );
+        }
+    }
+}
@@ -0,0 +1,655 @@
+//! spatial indexes for finding the nearest neighbors of a datapoint
+//!
+//! the brute force approach calculates the distance to every record and then
+//! sorts all of them. an index will organize the records ahead of time so that
+//! most of the records can be skipped when looking for the nearest neighbors.
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+
+use clap::builder::RangedU64ValueParser;
+use clap::{Args, ValueEnum};
+use serde::{Deserialize, Serialize};
+
+use crate::cli::AlgoType;
+use crate::error::KnnResult;
+use crate::memory;
+
+mod balltree;
+mod brute;
+mod covertree;
+mod hnsw;
+mod kdtree;
+mod lsh;
+mod vptree;
+
+pub use balltree::BallTree;
+pub use brute::BruteForce;
+pub use covertree::CoverTree;
+pub use hnsw::Hnsw;
+pub use kdtree::KdTree;
+pub use lsh::Lsh;
+pub use vptree::VpTree;
+
+/// the default number of candidates tracked when searching a hnsw graph
+const DEFAULT_HNSW_EF: usize = 50;
+
+/// the default number of links each datapoint keeps in a hnsw graph
+const DEFAULT_HNSW_M: usize = 16;
+
+/// the default number of hash tables used by lsh
+const DEFAULT_TABLES: usize = 8;
+
+/// the default number of bits in each lsh hash
+const DEFAULT_HASH_BITS: usize = 12;
+
+/// the maximum number of queries used to estimate the recall of an
+/// approximate index
+pub const RECALL_SAMPLE: usize = 100;
+
+/// represents the method used to find the nearest neighbors
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum IndexType {
+    /// calculates the distance to every record
+    Brute,
+
+    /// a kd-tree that splits the records on a single column at each level.
+    /// works best when there are only a few columns
+    #[value(name = "kdtree")]
+    KdTree,
+
+    /// a ball tree that groups nearby records into spheres. works better
+    /// than a kd-tree when there are more columns
+    #[value(name = "balltree")]
+    BallTree,
+
+    /// a vantage point tree that splits the records by their distance to a
+    /// single record. only relies on the triangle inequality so it works
+    /// with any metric
+    #[value(name = "vptree")]
+    VpTree,
+
+    /// a cover tree that nests the records into levels of shrinking radius.
+    /// works well with many columns when the records only vary along a few
+    /// directions
+    #[value(name = "covertree")]
+    CoverTree,
+
+    /// an approximate graph of nearby records for very large datasets. can
+    /// miss some of the nearest neighbors
+    Hnsw,
+
+    /// approximate locality sensitive hashing that only compares records
+    /// which hash to the same bucket. lighter than hnsw but can miss more of
+    /// the nearest neighbors. the buckets group records by direction like
+    /// cosine similarity but cosine is not supported as an algorithm, so the
+    /// candidates are ranked by euclidean or manhattan distance
+    Lsh,
+}
+
+impl std::fmt::Display for IndexType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Brute => f.write_str("brute"),
+            Self::KdTree => f.write_str("kdtree"),
+            Self::BallTree => f.write_str("balltree"),
+            Self::VpTree => f.write_str("vptree"),
+            Self::CoverTree => f.write_str("covertree"),
+            Self::Hnsw => f.write_str("hnsw"),
+            Self::Lsh => f.write_str("lsh"),
+        }
+    }
+}
+
+impl IndexType {
+    /// checks if the index always finds the true nearest neighbors
+    pub fn is_exact(&self) -> bool {
+        !matches!(self, Self::Hnsw | Self::Lsh)
+    }
+}
+
+/// the method used to find the nearest neighbors and its options
+#[derive(Debug, Clone, Copy, Args)]
+pub struct IndexArgs {
+    /// the method used to find the nearest neighbors
+    #[arg(long = "index", default_value = "brute")]
+    pub kind: IndexType,
+
+    /// the number of candidates to track when searching a hnsw index. larger
+    /// values are slower but find more of the nearest neighbors
+    #[arg(
+        long,
+        default_value_t = DEFAULT_HNSW_EF,
+        value_parser = RangedU64ValueParser::<usize>::new().range(1..)
+    )]
+    pub hnsw_ef: usize,
+
+    /// the number of links each record keeps in a hnsw index
+    #[arg(
+        long,
+        default_value_t = DEFAULT_HNSW_M,
+        value_parser = RangedU64ValueParser::<usize>::new().range(2..)
+    )]
+    pub hnsw_m: usize,
+
+    /// the number of hash tables used by lsh. more tables find more of the
+    /// nearest neighbors
+    #[arg(
+        long,
+        default_value_t = DEFAULT_TABLES,
+        value_parser = RangedU64ValueParser::<usize>::new().range(1..)
+    )]
+    pub tables: usize,
+
+    /// the number of bits in each lsh hash. more bits create smaller buckets
+    /// which are faster to search but miss more of the nearest neighbors
+    #[arg(
+        long,
+        default_value_t = DEFAULT_HASH_BITS,
+        value_parser = RangedU64ValueParser::<usize>::new().range(1..=64)
+    )]
+    pub hash_bits: usize,
+}
+
+impl IndexArgs {
+    /// builds the index from a list of (id, datapoint) pairs
+    ///
+    /// every datapoint must have `dims` values. fails if copying the
+    /// datapoints would exceed the memory budget
+    pub fn build<I, D>(&self, dims: usize, points: I, algo: AlgoType) -> KnnResult<Index>
+    where
+        I: Iterator<Item = (usize, D)>,
+        D: AsRef<[f64]>,
+    {
+        let (len, _) = points.size_hint();
+
+        memory::reserve(
+            len * (dims + 1) * std::mem::size_of::<f64>(),
+            "building the index",
+        )?;
+
+        let points = Points::collect(dims, points);
+
+        Ok(match self.kind {
+            IndexType::Brute => Index::Brute(BruteForce::new(points, algo)),
+            IndexType::KdTree => Index::KdTree(KdTree::new(points, algo)),
+            IndexType::BallTree => Index::BallTree(BallTree::new(points, algo)),
+            IndexType::VpTree => Index::VpTree(VpTree::new(points, algo)),
+            IndexType::CoverTree => Index::CoverTree(CoverTree::new(points, algo)),
+            IndexType::Hnsw => Index::Hnsw(Hnsw::new(points, algo, self.hnsw_m, self.hnsw_ef)),
+            IndexType::Lsh => Index::Lsh(Lsh::new(points, algo, self.tables, self.hash_bits)),
+        })
+    }
+}
+
+impl Default for IndexArgs {
+    fn default() -> Self {
+        Self {
+            kind: IndexType::Brute,
+            hnsw_ef: DEFAULT_HNSW_EF,
+            hnsw_m: DEFAULT_HNSW_M,
+            tables: DEFAULT_TABLES,
+            hash_bits: DEFAULT_HASH_BITS,
+        }
+    }
+}
+
+impl From<IndexType> for IndexArgs {
+    fn from(kind: IndexType) -> Self {
+        Self {
+            kind,
+            ..Self::default()
+        }
+    }
+}
+
+/// a built index of any type
+///
+/// can be saved and loaded so that the index does not need to be built again
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Index {
+    Brute(BruteForce),
+    KdTree(KdTree),
+    BallTree(BallTree),
+    VpTree(VpTree),
+    CoverTree(CoverTree),
+    Hnsw(Hnsw),
+    Lsh(Lsh),
+}
+
+impl Index {
+    /// the datapoints of the index
+    fn points(&self) -> &Points {
+        match self {
+            Self::Brute(index) => &index.points,
+            Self::KdTree(index) => &index.points,
+            Self::BallTree(index) => &index.points,
+            Self::VpTree(index) => &index.points,
+            Self::CoverTree(index) => &index.points,
+            Self::Hnsw(index) => &index.points,
+            Self::Lsh(index) => &index.points,
+        }
+    }
+
+    fn points_mut(&mut self) -> &mut Points {
+        match self {
+            Self::Brute(index) => &mut index.points,
+            Self::KdTree(index) => &mut index.points,
+            Self::BallTree(index) => &mut index.points,
+            Self::VpTree(index) => &mut index.points,
+            Self::CoverTree(index) => &mut index.points,
+            Self::Hnsw(index) => &mut index.points,
+            Self::Lsh(index) => &mut index.points,
+        }
+    }
+
+    /// the number of values in each datapoint
+    pub fn dims(&self) -> usize {
+        self.points().dims
+    }
+
+    /// the (id, datapoint) pairs in the index in the order they were added
+    pub fn datapoints(&self) -> impl Iterator<Item = (usize, &[f64])> {
+        let points = self.points();
+
+        (0..points.len()).map(|index| (points.ids[index], points.get(index)))
+    }
+
+    /// checks if the index always finds the true nearest neighbors
+    pub fn is_exact(&self) -> bool {
+        !matches!(self, Self::Hnsw(_) | Self::Lsh(_))
+    }
+
+    /// adds more (id, datapoint) pairs to a built index
+    ///
+    /// every datapoint must have [`Index::dims`] values. brute force, hnsw,
+    /// and lsh add the datapoints in place while the trees are rebuilt so
+    /// that they stay balanced. fails if copying the datapoints would exceed
+    /// the memory budget
+    pub fn extend<I, D>(&mut self, points: I) -> KnnResult<()>
+    where
+        I: Iterator<Item = (usize, D)>,
+        D: AsRef<[f64]>,
+    {
+        let (len, _) = points.size_hint();
+        let dims = self.dims();
+
+        memory::reserve(
+            len * (dims + 1) * std::mem::size_of::<f64>(),
+            "extending the index",
+        )?;
+
+        let start = self.points().len();
+
+        self.points_mut().extend(points);
+
+        match self {
+            Self::Brute(_) => {}
+            Self::KdTree(index) => index.rebuild(),
+            Self::BallTree(index) => index.rebuild(),
+            Self::VpTree(index) => index.rebuild(),
+            Self::CoverTree(index) => index.rebuild(),
+            Self::Hnsw(index) => index.extend(),
+            Self::Lsh(index) => index.extend(start),
+        }
+
+        Ok(())
+    }
+}
+
+impl NeighborIndex for Index {
+    fn nearest(&self, datapoint: &[f64], k: usize) -> Vec<(f64, usize)> {
+        match self {
+            Self::Brute(index) => index.nearest(datapoint, k),
+            Self::KdTree(index) => index.nearest(datapoint, k),
+            Self::BallTree(index) => index.nearest(datapoint, k),
+            Self::VpTree(index) => index.nearest(datapoint, k),
+            Self::CoverTree(index) => index.nearest(datapoint, k),
+            Self::Hnsw(index) => index.nearest(datapoint, k),
+            Self::Lsh(index) => index.nearest(datapoint, k),
+        }
+    }
+}
+
+/// estimates how many of the true nearest neighbors an index finds by
+/// comparing it to brute force for a sample of the queries
+///
+/// `points` must be the same datapoints the index was built from. a neighbor
+/// counts as found if it is no further than the true `k`th neighbor so that
+/// ties are not counted as misses.
+///
+/// returns the number of neighbors found and the number expected
+pub fn recall<'a, I, D, Q>(
+    index: &dyn NeighborIndex,
+    dims: usize,
+    points: I,
+    algo: AlgoType,
+    queries: Q,
+    k: usize,
+) -> (usize, usize)
+where
+    I: Iterator<Item = (usize, D)>,
+    D: AsRef<[f64]>,
+    Q: Iterator<Item = &'a [f64]>,
+{
+    let exact = BruteForce::new(Points::collect(dims, points), algo);
+    let mut found = 0;
+    let mut expected = 0;
+
+    for query in queries.take(RECALL_SAMPLE) {
+        let truth = exact.nearest(query, k);
+        let Some((furthest, _)) = truth.last() else {
+            continue;
+        };
+
+        found += index
+            .nearest(query, k)
+            .iter()
+            .filter(|(distance, _)| distance <= furthest)
+            .count()
+            .min(truth.len());
+        expected += truth.len();
+    }
+
+    (found, expected)
+}
+
+/// finds the nearest neighbors of a datapoint
+pub trait NeighborIndex: Send + Sync {
+    /// finds the `k` closest datapoints to the provided datapoint
+    ///
+    /// returns the distance and id of each neighbor sorted from closest to
+    /// furthest. neighbors with the same distance are ordered by their id
+    fn nearest(&self, datapoint: &[f64], k: usize) -> Vec<(f64, usize)>;
+}
+
+/// the datapoints of an index stored one after another along with the id of
+/// each datapoint
+#[derive(Default, Serialize, Deserialize)]
+struct Points {
+    dims: usize,
+    data: Vec<f64>,
+    ids: Vec<usize>,
+}
+
+impl Points {
+    fn collect<I, D>(dims: usize, points: I) -> Self
+    where
+        I: Iterator<Item = (usize, D)>,
+        D: AsRef<[f64]>,
+    {
+        let mut rtn = Self {
+            dims,
+            data: Vec::new(),
+            ids: Vec::new(),
+        };
+
+        rtn.extend(points);
+        rtn
+    }
+
+    /// adds the datapoints to the end
+    fn extend<I, D>(&mut self, points: I)
+    where
+        I: Iterator<Item = (usize, D)>,
+        D: AsRef<[f64]>,
+    {
+        for (id, point) in points {
+            self.data.extend_from_slice(point.as_ref());
+            self.ids.push(id);
+        }
+    }
+
+    fn len(&self) -> usize {
+        self.ids.len()
+    }
+
+    fn get(&self, index: usize) -> &[f64] {
+        &self.data[index * self.dims..(index + 1) * self.dims]
+    }
+}
+
+/// a neighbor found while searching an index
+///
+/// ordered by distance so that a [`BinaryHeap`] will keep the furthest
+/// neighbor at the top
+#[derive(Debug, Clone, Copy)]
+struct Neighbor {
+    distance: f64,
+    id: usize,
+}
+
+impl PartialEq for Neighbor {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Neighbor {}
+
+impl PartialOrd for Neighbor {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Neighbor {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.distance
+            .total_cmp(&other.distance)
+            .then(self.id.cmp(&other.id))
+    }
+}
+
+/// keeps the closest `k` neighbors found
+struct NeighborHeap {
+    k: usize,
+    heap: BinaryHeap<Neighbor>,
+}
+
+impl NeighborHeap {
+    fn new(k: usize) -> Self {
+        Self {
+            k,
+            heap: BinaryHeap::with_capacity(k + 1),
+        }
+    }
+
+    /// adds the neighbor if it is closer than the furthest neighbor found
+    ///
+    /// neighbors with the same distance are ordered by their id so the same
+    /// neighbors are found regardless of the order they are visited in
+    fn push(&mut self, distance: f64, id: usize) {
+        let neighbor = Neighbor { distance, id };
+
+        if self.heap.len() < self.k {
+            self.heap.push(neighbor);
+        } else if let Some(furthest) = self.heap.peek()
+            && neighbor < *furthest
+        {
+            self.heap.pop();
+            self.heap.push(neighbor);
+        }
+    }
+
+    /// checks if a region that is at least `bound` away could contain a
+    /// closer neighbor
+    fn could_contain(&self, bound: f64) -> bool {
+        match self.heap.peek() {
+            Some(furthest) if self.heap.len() >= self.k => bound <= furthest.distance,
+            _ => self.k > 0,
+        }
+    }
+
+    /// the neighbors sorted from closest to furthest
+    fn into_sorted(self) -> Vec<(f64, usize)> {
+        self.heap
+            .into_sorted_vec()
+            .into_iter()
+            .map(|neighbor| (neighbor.distance, neighbor.id))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn points() -> Vec<Vec<f64>> {
+        let mut rtn = Vec::new();
+
+        for x in 0..10 {
+            for y in 0..10 {
+                rtn.push(vec![
+                    x as f64 * 1.3,
+                    (y * 7 % 10) as f64,
+                    (x * y % 4) as f64,
+                ]);
+            }
+        }
+
+        rtn
+    }
+
+    /// the neighbors found by sorting every distance
+    fn sorted(
+        points: &[Vec<f64>],
+        datapoint: &[f64],
+        k: usize,
+        algo: AlgoType,
+    ) -> Vec<(f64, usize)> {
+        let mut rtn: Vec<(f64, usize)> = points
+            .iter()
+            .enumerate()
+            .map(|(id, point)| (algo.distance(datapoint, point), id))
+            .collect();
+
+        rtn.sort_by(|(a, _), (b, _)| a.total_cmp(b));
+        rtn.truncate(k);
+        rtn
+    }
+
+    #[test]
+    fn indexes_match_sorted() {
+        let points = points();
+
+        for index_type in IndexType::value_variants() {
+            if !index_type.is_exact() {
+                continue;
+            }
+
+            for algo in [AlgoType::Euclidean, AlgoType::Manhattan] {
+                let index = IndexArgs::from(*index_type)
+                    .build(3, points.iter().enumerate(), algo)
+                    .unwrap();
+
+                for datapoint in [[0.0, 0.0, 0.0], [4.4, 3.2, 1.0], [20.0, -1.0, 2.5]] {
+                    for k in [1, 5, 17] {
+                        assert_eq!(
+                            index.nearest(&datapoint, k),
+                            sorted(&points, &datapoint, k, algo),
+                            "{index_type:?} k {k}"
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn more_neighbors_than_records() {
+        for index_type in IndexType::value_variants() {
+            let index = IndexArgs::from(*index_type)
+                .build(1, [(4, [1.0]), (9, [2.0])].into_iter(), AlgoType::Euclidean)
+                .unwrap();
+
+            assert_eq!(index.nearest(&[0.0], 5), vec![(1.0, 4), (2.0, 9)]);
+            assert!(index.nearest(&[0.0], 0).is_empty());
+        }
+    }
+
+    #[test]
+    fn approximate_recall() {
+        let points = points();
+
+        for index_type in IndexType::value_variants() {
+            if index_type.is_exact() {
+                continue;
+            }
+
+            let index = IndexArgs::from(*index_type)
+                .build(3, points.iter().enumerate(), AlgoType::Euclidean)
+                .unwrap();
+            let (found, expected) = recall(
+                &index,
+                3,
+                points.iter().enumerate(),
+                AlgoType::Euclidean,
+                points.iter().map(|point| point.as_slice()),
+                5,
+            );
+
+            assert_eq!(expected, 500);
+            assert!(
+                found as f64 / expected as f64 > 0.5,
+                "{index_type:?} found {found}"
+            );
+        }
+    }
+
+    #[test]
+    fn extended_indexes_match() {
+        let points = points();
+        let (first, rest) = points.split_at(60);
+
+        for index_type in IndexType::value_variants() {
+            let args = IndexArgs::from(*index_type);
+            let built = args
+                .build(3, points.iter().enumerate(), AlgoType::Euclidean)
+                .unwrap();
+            let mut extended = args
+                .build(3, first.iter().enumerate(), AlgoType::Euclidean)
+                .unwrap();
+
+            extended
+                .extend(rest.iter().enumerate().map(|(id, point)| (id + 60, point)))
+                .unwrap();
+
+            assert_eq!(extended.datapoints().count(), points.len());
+
+            // lsh keeps the hyperplanes of the first build so only the
+            // found neighbors that exist can be checked
+            if *index_type == IndexType::Lsh {
+                assert_eq!(extended.nearest(&[20.0, -1.0, 2.5], 100).len(), 100);
+                continue;
+            }
+
+            for datapoint in [[0.0, 0.0, 0.0], [4.4, 3.2, 1.0], [20.0, -1.0, 2.5]] {
+                assert_eq!(
+                    extended.nearest(&datapoint, 7),
+                    built.nearest(&datapoint, 7),
+                    "{index_type:?}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn saved_indexes_match() {
+        let points = points();
+
+        for index_type in IndexType::value_variants() {
+            let index = IndexArgs::from(*index_type)
+                .build(3, points.iter().enumerate(), AlgoType::Manhattan)
+                .unwrap();
+            let saved = serde_json::to_string(&index).unwrap();
+            let loaded: Index = serde_json::from_str(&saved).unwrap();
+
+            assert_eq!(
+                loaded.nearest(&[4.4, 3.2, 1.0], 7),
+                index.nearest(&[4.4, 3.2, 1.0], 7),
+                "{index_type:?}"
+            );
+        }
+    }
+}
@@ -0,0 +1,180 @@
+use serde::{Deserialize, Serialize};
+
+use super::{NeighborHeap, NeighborIndex, Points};
+use crate::cli::AlgoType;
+
+/// the maximum number of datapoints in a leaf of the tree
+const LEAF_SIZE: usize = 16;
+
+/// a sphere that contains a range of the datapoints
+#[derive(Serialize, Deserialize)]
+struct Ball {
+    center: Vec<f64>,
+    radius: f64,
+    /// the range of `order` contained in the ball
+    start: usize,
+    end: usize,
+    /// the indexes of the child balls. leaves do not have children
+    children: Option<(usize, usize)>,
+}
+
+/// a ball tree over a list of datapoints
+///
+/// each ball is split in half along the column with the largest spread until
+/// there are only a few datapoints remaining. a ball can be skipped when the
+/// distance to its center minus its radius is further than the neighbors
+/// already found, which holds for any distance that satisfies the triangle
+/// inequality
+#[derive(Serialize, Deserialize)]
+pub struct BallTree {
+    pub(super) points: Points,
+    /// the indexes of the datapoints arranged so that every ball is a
+    /// continuous range
+    order: Vec<usize>,
+    balls: Vec<Ball>,
+    algo: AlgoType,
+}
+
+impl BallTree {
+    pub(super) fn new(points: Points, algo: AlgoType) -> Self {
+        let mut rtn = Self {
+            order: (0..points.len()).collect(),
+            points,
+            balls: Vec::new(),
+            algo,
+        };
+
+        if rtn.points.len() > 0 {
+            rtn.arrange(0, rtn.order.len());
+        }
+
+        rtn
+    }
+
+    /// builds the tree again so that it includes datapoints added to the
+    /// end of the points
+    pub(super) fn rebuild(&mut self) {
+        *self = Self::new(std::mem::take(&mut self.points), self.algo);
+    }
+
+    /// creates the ball for the range and its children
+    ///
+    /// returns the index of the ball
+    fn arrange(&mut self, start: usize, end: usize) -> usize {
+        let dims = self.points.dims;
+        let count = (end - start) as f64;
+        let mut center = vec![0.0; dims];
+
+        for index in &self.order[start..end] {
+            for (total, value) in center.iter_mut().zip(self.points.get(*index)) {
+                *total += value / count;
+            }
+        }
+
+        let radius = self.order[start..end]
+            .iter()
+            .map(|index| self.algo.distance(&center, self.points.get(*index)))
+            .fold(0.0, f64::max);
+
+        let ball_index = self.balls.len();
+
+        self.balls.push(Ball {
+            center,
+            radius,
+            start,
+            end,
+            children: None,
+        });
+
+        if end - start <= LEAF_SIZE || dims == 0 {
+            return ball_index;
+        }
+
+        // split on the column with the largest spread
+        let mut axis = 0;
+        let mut spread = f64::NEG_INFINITY;
+
+        for column in 0..dims {
+            let mut low = f64::INFINITY;
+            let mut high = f64::NEG_INFINITY;
+
+            for index in &self.order[start..end] {
+                let value = self.points.get(*index)[column];
+
+                low = low.min(value);
+                high = high.max(value);
+            }
+
+            if high - low > spread {
+                spread = high - low;
+                axis = column;
+            }
+        }
+
+        let mid = start + (end - start) / 2;
+        let data = &self.points.data;
+
+        self.order[start..end].select_nth_unstable_by(mid - start, |a, b| {
+            data[a * dims + axis].total_cmp(&data[b * dims + axis])
+        });
+
+        let left = self.arrange(start, mid);
+        let right = self.arrange(mid, end);
+
+        self.balls[ball_index].children = Some((left, right));
+
+        ball_index
+    }
+
+    fn search(
+        &self,
+        ball_index: usize,
+        center_distance: f64,
+        datapoint: &[f64],
+        heap: &mut NeighborHeap,
+    ) {
+        let ball = &self.balls[ball_index];
+
+        if !heap.could_contain(center_distance - ball.radius) {
+            return;
+        }
+
+        let Some((left, right)) = ball.children else {
+            for index in &self.order[ball.start..ball.end] {
+                heap.push(
+                    self.algo.distance(datapoint, self.points.get(*index)),
+                    self.points.ids[*index],
+                );
+            }
+
+            return;
+        };
+
+        let left_distance = self.algo.distance(datapoint, &self.balls[left].center);
+        let right_distance = self.algo.distance(datapoint, &self.balls[right].center);
+
+        // search the closer ball first so that the neighbors found can be
+        // used to skip the other ball
+        if left_distance <= right_distance {
+            self.search(left, left_distance, datapoint, heap);
+            self.search(right, right_distance, datapoint, heap);
+        } else {
+            self.search(right, right_distance, datapoint, heap);
+            self.search(left, left_distance, datapoint, heap);
+        }
+    }
+}
+
+impl NeighborIndex for BallTree {
+    fn nearest(&self, datapoint: &[f64], k: usize) -> Vec<(f64, usize)> {
+        let mut heap = NeighborHeap::new(k);
+
+        if k > 0 && !self.balls.is_empty() {
+            let distance = self.algo.distance(datapoint, &self.balls[0].center);
+
+            self.search(0, distance, datapoint, &mut heap);
+        }
+
+        heap.into_sorted()
+    }
+}
@@ -0,0 +1,35 @@
+use serde::{Deserialize, Serialize};
+
+use super::{NeighborHeap, NeighborIndex, Points};
+use crate::cli::AlgoType;
+
+/// calculates the distance to every datapoint
+///
+/// the same as not using an index but allows the brute force approach to be
+/// used anywhere an index is accepted
+#[derive(Serialize, Deserialize)]
+pub struct BruteForce {
+    pub(super) points: Points,
+    algo: AlgoType,
+}
+
+impl BruteForce {
+    pub(super) fn new(points: Points, algo: AlgoType) -> Self {
+        Self { points, algo }
+    }
+}
+
+impl NeighborIndex for BruteForce {
+    fn nearest(&self, datapoint: &[f64], k: usize) -> Vec<(f64, usize)> {
+        let mut heap = NeighborHeap::new(k);
+
+        for index in 0..self.points.len() {
+            heap.push(
+                self.algo.distance(datapoint, self.points.get(index)),
+                self.points.ids[index],
+            );
+        }
+
+        heap.into_sorted()
+    }
+}
@@ -0,0 +1,172 @@
+use serde::{Deserialize, Serialize};
+
+use super::{NeighborHeap, NeighborIndex, Points};
+use crate::cli::AlgoType;
+
+/// a datapoint in the tree along with the datapoints it covers
+#[derive(Serialize, Deserialize)]
+struct Node {
+    /// the index of the datapoint in `points`
+    index: usize,
+    /// the furthest distance to any datapoint below this node
+    max_distance: f64,
+    /// the indexes of the child nodes
+    children: Vec<usize>,
+}
+
+/// a cover tree over a list of datapoints
+///
+/// every node covers the datapoints within a radius that halves at each
+/// level. a node keeps covering the datapoints closest to it while the
+/// datapoints that are further away are grouped under new children. the
+/// number of children at each level depends on the intrinsic dimension of
+/// the data instead of the number of columns so it works well when the
+/// records lie close to a low dimensional surface.
+///
+/// a node can be skipped when the distance to it minus the furthest distance
+/// below it is further than the neighbors already found, which holds for any
+/// distance that satisfies the triangle inequality
+#[derive(Serialize, Deserialize)]
+pub struct CoverTree {
+    pub(super) points: Points,
+    nodes: Vec<Node>,
+    algo: AlgoType,
+}
+
+impl CoverTree {
+    pub(super) fn new(points: Points, algo: AlgoType) -> Self {
+        let mut rtn = Self {
+            points,
+            nodes: Vec::new(),
+            algo,
+        };
+
+        if rtn.points.len() > 0 {
+            let root = rtn.points.get(0);
+            let covered = (1..rtn.points.len())
+                .map(|index| (rtn.algo.distance(root, rtn.points.get(index)), index))
+                .collect();
+
+            rtn.arrange(0, covered);
+        }
+
+        rtn
+    }
+
+    /// builds the tree again so that it includes datapoints added to the
+    /// end of the points
+    pub(super) fn rebuild(&mut self) {
+        *self = Self::new(std::mem::take(&mut self.points), self.algo);
+    }
+
+    /// creates the node for the datapoint and the nodes for the datapoints
+    /// it covers. `covered` is a list of (distance, index) pairs
+    ///
+    /// returns the index of the node
+    fn arrange(&mut self, index: usize, mut covered: Vec<(f64, usize)>) -> usize {
+        let node_index = self.nodes.len();
+        let max_distance = covered
+            .iter()
+            .map(|(distance, _)| *distance)
+            .fold(0.0, f64::max);
+
+        self.nodes.push(Node {
+            index,
+            max_distance,
+            children: Vec::new(),
+        });
+
+        let mut children = Vec::new();
+
+        while !covered.is_empty() {
+            let furthest = covered
+                .iter()
+                .map(|(distance, _)| *distance)
+                .fold(0.0, f64::max);
+
+            // duplicate datapoints cannot be separated any further
+            if furthest <= 0.0 || !furthest.is_finite() {
+                for (_, dup) in covered.drain(..) {
+                    children.push(self.arrange(dup, Vec::new()));
+                }
+
+                break;
+            }
+
+            // the radius of the next level down. everything further than
+            // the radius is grouped under a new child
+            let radius = 2f64.powi(furthest.log2().ceil() as i32 - 1);
+            let (near, mut far): (Vec<_>, Vec<_>) = covered
+                .into_iter()
+                .partition(|(distance, _)| *distance <= radius);
+
+            while !far.is_empty() {
+                let (_, child) = far.swap_remove(0);
+                let child_point = self.points.get(child);
+                let mut child_covered = Vec::new();
+                let mut remaining = Vec::with_capacity(far.len());
+
+                for (distance, other) in far {
+                    let between = self.algo.distance(child_point, self.points.get(other));
+
+                    if between <= radius {
+                        child_covered.push((between, other));
+                    } else {
+                        remaining.push((distance, other));
+                    }
+                }
+
+                far = remaining;
+
+                children.push(self.arrange(child, child_covered));
+            }
+
+            covered = near;
+        }
+
+        self.nodes[node_index].children = children;
+
+        node_index
+    }
+
+    fn search(&self, node_index: usize, distance: f64, datapoint: &[f64], heap: &mut NeighborHeap) {
+        let node = &self.nodes[node_index];
+
+        heap.push(distance, self.points.ids[node.index]);
+
+        let mut children: Vec<(f64, usize)> = node
+            .children
+            .iter()
+            .map(|child| {
+                let point = self.points.get(self.nodes[*child].index);
+
+                (self.algo.distance(datapoint, point), *child)
+            })
+            .collect();
+
+        // visit the closest children first so more of the others are skipped
+        children.sort_by(|(a, _), (b, _)| a.total_cmp(b));
+
+        for (child_distance, child) in children {
+            if heap.could_contain(child_distance - self.nodes[child].max_distance) {
+                self.search(child, child_distance, datapoint, heap);
+            }
+        }
+    }
+}
+
+impl NeighborIndex for CoverTree {
+    fn nearest(&self, datapoint: &[f64], k: usize) -> Vec<(f64, usize)> {
+        let mut heap = NeighborHeap::new(k);
+
+        if k > 0 && !self.nodes.is_empty() {
+            let distance = self
+                .algo
+                .distance(datapoint, self.points.get(self.nodes[0].index));
+
+            self.search(0, distance, datapoint, &mut heap);
+        }
+
+        heap.into_sorted()
+    }
+}
@@ -0,0 +1,237 @@
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashSet};
+
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+
+use super::{Neighbor, NeighborHeap, NeighborIndex, Points};
+use crate::cli::AlgoType;
+
+/// the seed used to pick the level of each datapoint so that the same graph
+/// is built every time
+const LEVEL_SEED: u64 = 0;
+
+/// a hierarchical navigable small world graph over a list of datapoints
+///
+/// every datapoint is linked to a few of its closest datapoints on a random
+/// number of levels. the upper levels only contain a handful of datapoints
+/// and are used to quickly move towards the datapoint being searched for
+/// before the bottom level is searched more thoroughly.
+///
+/// the search is approximate and can miss some of the nearest neighbors.
+/// `ef` controls how many candidates are tracked while searching and
+/// `connections` controls how many links each datapoint keeps
+#[derive(Serialize, Deserialize)]
+pub struct Hnsw {
+    pub(super) points: Points,
+    /// the links of each datapoint for every level that it is on. level 0
+    /// comes first
+    links: Vec<Vec<Vec<usize>>>,
+    /// the datapoint on the highest level that every search starts from
+    entry: Option<usize>,
+    connections: usize,
+    ef: usize,
+    algo: AlgoType,
+}
+
+impl Hnsw {
+    pub(super) fn new(points: Points, algo: AlgoType, connections: usize, ef: usize) -> Self {
+        let mut rtn = Self {
+            links: Vec::with_capacity(points.len()),
+            points,
+            entry: None,
+            connections,
+            ef,
+            algo,
+        };
+
+        for (index, level) in levels(connections).take(rtn.points.len()).enumerate() {
+            rtn.insert(index, level);
+        }
+
+        rtn
+    }
+
+    /// links datapoints that were added to the end of the points
+    ///
+    /// the levels continue from where the build left off so the graph is the
+    /// same as one built with every datapoint at once
+    pub(super) fn extend(&mut self) {
+        let start = self.links.len();
+
+        for (index, level) in levels(self.connections)
+            .enumerate()
+            .skip(start)
+            .take(self.points.len() - start)
+        {
+            self.insert(index, level);
+        }
+    }
+
+    /// the maximum number of links a datapoint can keep on a level
+    fn max_links(&self, level: usize) -> usize {
+        if level == 0 {
+            self.connections * 2
+        } else {
+            self.connections
+        }
+    }
+
+    fn distance(&self, datapoint: &[f64], index: usize) -> Neighbor {
+        Neighbor {
+            distance: self.algo.distance(datapoint, self.points.get(index)),
+            id: index,
+        }
+    }
+
+    /// adds the datapoint to the graph on the specified level and every
+    /// level below it
+    fn insert(&mut self, index: usize, level: usize) {
+        self.links.push(vec![Vec::new(); level + 1]);
+
+        let Some(entry) = self.entry else {
+            self.entry = Some(index);
+
+            return;
+        };
+
+        let point = self.points.get(index).to_vec();
+        let top = self.links[entry].len() - 1;
+        let mut nearest = vec![self.distance(&point, entry)];
+
+        for layer in (level + 1..=top).rev() {
+            nearest = self.search_layer(&point, nearest, 1, layer);
+        }
+
+        for layer in (0..=level.min(top)).rev() {
+            nearest = self.search_layer(&point, nearest, self.ef.max(self.connections), layer);
+
+            let max = self.max_links(layer);
+            let selected: Vec<usize> = nearest
+                .iter()
+                .take(self.connections)
+                .map(|neighbor| neighbor.id)
+                .collect();
+
+            for linked in &selected {
+                self.links[*linked][layer].push(index);
+
+                if self.links[*linked][layer].len() > max {
+                    self.prune(*linked, layer, max);
+                }
+            }
+
+            self.links[index][layer] = selected;
+        }
+
+        if level > top {
+            self.entry = Some(index);
+        }
+    }
+
+    /// keeps only the closest links of a datapoint on a level
+    fn prune(&mut self, index: usize, layer: usize, max: usize) {
+        let point = self.points.get(index);
+        let mut linked: Vec<Neighbor> = self.links[index][layer]
+            .iter()
+            .map(|other| self.distance(point, *other))
+            .collect();
+
+        linked.sort();
+        linked.truncate(max);
+
+        self.links[index][layer] = linked.into_iter().map(|neighbor| neighbor.id).collect();
+    }
+
+    /// finds the `ef` closest datapoints on a level by following the links
+    /// from the entry datapoints
+    ///
+    /// returns the datapoints sorted from closest to furthest
+    fn search_layer(
+        &self,
+        datapoint: &[f64],
+        entries: Vec<Neighbor>,
+        ef: usize,
+        layer: usize,
+    ) -> Vec<Neighbor> {
+        let mut visited: HashSet<usize> = entries.iter().map(|neighbor| neighbor.id).collect();
+        let mut candidates: BinaryHeap<Reverse<Neighbor>> =
+            entries.iter().copied().map(Reverse).collect();
+        let mut found: BinaryHeap<Neighbor> = entries.into_iter().collect();
+
+        while found.len() > ef {
+            found.pop();
+        }
+
+        while let Some(Reverse(closest)) = candidates.pop() {
+            if let Some(furthest) = found.peek()
+                && found.len() >= ef
+                && closest > *furthest
+            {
+                break;
+            }
+
+            for linked in &self.links[closest.id][layer] {
+                if !visited.insert(*linked) {
+                    continue;
+                }
+
+                let neighbor = self.distance(datapoint, *linked);
+                let closer = match found.peek() {
+                    Some(furthest) if found.len() >= ef => neighbor < *furthest,
+                    _ => true,
+                };
+
+                if closer {
+                    candidates.push(Reverse(neighbor));
+                    found.push(neighbor);
+
+                    if found.len() > ef {
+                        found.pop();
+                    }
+                }
+            }
+        }
+
+        found.into_sorted_vec()
+    }
+}
+
+/// the level of each datapoint in the order they are inserted
+fn levels(connections: usize) -> impl Iterator<Item = usize> {
+    // each level has roughly 1 / connections of the datapoints from the
+    // level below it
+    let multiplier = 1.0 / (connections as f64).ln();
+    let mut rng = StdRng::seed_from_u64(LEVEL_SEED);
+
+    std::iter::repeat_with(move || {
+        (-(1.0 - rng.random::<f64>()).ln() * multiplier).floor() as usize
+    })
+}
+
+impl NeighborIndex for Hnsw {
+    fn nearest(&self, datapoint: &[f64], k: usize) -> Vec<(f64, usize)> {
+        let mut heap = NeighborHeap::new(k);
+
+        let Some(entry) = self.entry else {
+            return heap.into_sorted();
+        };
+
+        if k == 0 {
+            return heap.into_sorted();
+        }
+
+        let mut nearest = vec![self.distance(datapoint, entry)];
+
+        for layer in (1..self.links[entry].len()).rev() {
+            nearest = self.search_layer(datapoint, nearest, 1, layer);
+        }
+
+        for neighbor in self.search_layer(datapoint, nearest, self.ef.max(k), 0) {
+            heap.push(neighbor.distance, self.points.ids[neighbor.id]);
+        }
+
+        heap.into_sorted()
+    }
+}
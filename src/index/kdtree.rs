@@ -0,0 +1,115 @@
+use serde::{Deserialize, Serialize};
+
+use super::{NeighborHeap, NeighborIndex, Points};
+use crate::cli::AlgoType;
+
+/// a kd-tree over a list of datapoints
+///
+/// the tree is stored implicitly in `order`. for a range of the list the
+/// middle entry is the node and the entries before and after it are the left
+/// and right subtrees. each level of the tree splits on the next column.
+///
+/// only the distance along the splitting column is used to skip subtrees
+/// which is a lower bound for both the euclidean and manhattan distances
+#[derive(Serialize, Deserialize)]
+pub struct KdTree {
+    pub(super) points: Points,
+    /// the indexes of the datapoints arranged as the tree
+    order: Vec<usize>,
+    algo: AlgoType,
+}
+
+impl KdTree {
+    pub(super) fn new(points: Points, algo: AlgoType) -> Self {
+        let mut rtn = Self {
+            order: (0..points.len()).collect(),
+            points,
+            algo,
+        };
+
+        if rtn.points.dims > 0 {
+            rtn.arrange(0, rtn.order.len(), 0);
+        }
+
+        rtn
+    }
+
+    /// builds the tree again so that it includes datapoints added to the
+    /// end of the points
+    pub(super) fn rebuild(&mut self) {
+        *self = Self::new(std::mem::take(&mut self.points), self.algo);
+    }
+
+    /// arranges the range so that the middle entry is the median of the
+    /// splitting column for the depth
+    fn arrange(&mut self, low: usize, high: usize, depth: usize) {
+        if high - low <= 1 {
+            return;
+        }
+
+        let dims = self.points.dims;
+        let axis = depth % dims;
+        let mid = low + (high - low) / 2;
+        let data = &self.points.data;
+
+        self.order[low..high].select_nth_unstable_by(mid - low, |a, b| {
+            data[a * dims + axis].total_cmp(&data[b * dims + axis])
+        });
+
+        self.arrange(low, mid, depth + 1);
+        self.arrange(mid + 1, high, depth + 1);
+    }
+
+    fn search(
+        &self,
+        low: usize,
+        high: usize,
+        depth: usize,
+        datapoint: &[f64],
+        heap: &mut NeighborHeap,
+    ) {
+        if low >= high {
+            return;
+        }
+
+        let mid = low + (high - low) / 2;
+        let index = self.order[mid];
+        let point = self.points.get(index);
+
+        heap.push(self.algo.distance(datapoint, point), self.points.ids[index]);
+
+        let axis = depth % self.points.dims;
+        let diff = datapoint[axis] - point[axis];
+
+        // search the side that the datapoint is on first so that the
+        // neighbors found can be used to skip the other side
+        let (near, far) = if diff < 0.0 {
+            ((low, mid), (mid + 1, high))
+        } else {
+            ((mid + 1, high), (low, mid))
+        };
+
+        self.search(near.0, near.1, depth + 1, datapoint, heap);
+
+        if heap.could_contain(diff.abs()) {
+            self.search(far.0, far.1, depth + 1, datapoint, heap);
+        }
+    }
+}
+
+impl NeighborIndex for KdTree {
+    fn nearest(&self, datapoint: &[f64], k: usize) -> Vec<(f64, usize)> {
+        let mut heap = NeighborHeap::new(k);
+
+        if k > 0 && self.points.dims > 0 {
+            self.search(0, self.order.len(), 0, datapoint, &mut heap);
+        } else if k > 0 {
+            // without any columns every datapoint is the same distance away
+            for index in 0..self.points.len() {
+                heap.push(self.algo.distance(datapoint, &[]), self.points.ids[index]);
+            }
+        }
+
+        heap.into_sorted()
+    }
+}
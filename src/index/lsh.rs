@@ -0,0 +1,176 @@
+use std::collections::{HashMap, HashSet};
+
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+
+use super::{NeighborHeap, NeighborIndex, Points};
+use crate::cli::AlgoType;
+
+/// the seed used to pick the hyperplanes so that the same tables are built
+/// every time
+const PLANE_SEED: u64 = 0;
+
+/// a single hash table and the hyperplanes used to hash into it
+#[derive(Serialize, Deserialize)]
+struct Table {
+    /// `bits` hyperplanes stored one after another
+    planes: Vec<f64>,
+    buckets: HashMap<u64, Vec<usize>>,
+}
+
+/// locality sensitive hashing with random hyperplanes
+///
+/// every table hashes a datapoint to one bit per hyperplane depending on
+/// which side of the hyperplane it falls on. the hyperplanes pass through the
+/// mean of the datapoints so datapoints pointing in a similar direction from
+/// the mean will usually share a bucket. only the datapoints that share a
+/// bucket with the datapoint being searched for in at least one table are
+/// compared.
+///
+/// if the buckets do not contain enough datapoints then the buckets that
+/// differ by a single bit are checked followed by every datapoint
+#[derive(Serialize, Deserialize)]
+pub struct Lsh {
+    pub(super) points: Points,
+    mean: Vec<f64>,
+    tables: Vec<Table>,
+    bits: usize,
+    algo: AlgoType,
+}
+
+impl Lsh {
+    pub(super) fn new(points: Points, algo: AlgoType, tables: usize, bits: usize) -> Self {
+        let dims = points.dims;
+        let mut mean = vec![0.0; dims];
+
+        for index in 0..points.len() {
+            for (total, value) in mean.iter_mut().zip(points.get(index)) {
+                *total += value / points.len() as f64;
+            }
+        }
+
+        let mut rng = StdRng::seed_from_u64(PLANE_SEED);
+        let mut rtn = Self {
+            tables: (0..tables)
+                .map(|_| Table {
+                    planes: (0..bits * dims).map(|_| gaussian(&mut rng)).collect(),
+                    buckets: HashMap::new(),
+                })
+                .collect(),
+            points,
+            mean,
+            bits,
+            algo,
+        };
+
+        rtn.extend(0);
+
+        rtn
+    }
+
+    /// hashes the datapoints starting at `start` into every table
+    ///
+    /// the hyperplanes are not moved so datapoints added after the build are
+    /// hashed around the mean of the original datapoints
+    pub(super) fn extend(&mut self, start: usize) {
+        for index in start..self.points.len() {
+            for table_index in 0..self.tables.len() {
+                let hash = self.hash(table_index, self.points.get(index));
+
+                self.tables[table_index]
+                    .buckets
+                    .entry(hash)
+                    .or_default()
+                    .push(index);
+            }
+        }
+    }
+
+    /// the bucket of the datapoint in the specified table
+    fn hash(&self, table_index: usize, datapoint: &[f64]) -> u64 {
+        let dims = self.points.dims;
+        let mut rtn = 0;
+
+        for (bit, plane) in self.tables[table_index]
+            .planes
+            .chunks(dims.max(1))
+            .take(self.bits)
+            .enumerate()
+        {
+            let side: f64 = plane
+                .iter()
+                .zip(datapoint.iter().zip(&self.mean))
+                .map(|(normal, (value, mean))| normal * (value - mean))
+                .sum();
+
+            if side >= 0.0 {
+                rtn |= 1 << bit;
+            }
+        }
+
+        rtn
+    }
+}
+
+impl NeighborIndex for Lsh {
+    fn nearest(&self, datapoint: &[f64], k: usize) -> Vec<(f64, usize)> {
+        let mut heap = NeighborHeap::new(k);
+        let mut seen = HashSet::new();
+
+        if k == 0 {
+            return heap.into_sorted();
+        }
+
+        let hashes: Vec<u64> = (0..self.tables.len())
+            .map(|table_index| self.hash(table_index, datapoint))
+            .collect();
+        let mut check = |bucket: Option<&Vec<usize>>, heap: &mut NeighborHeap| {
+            for index in bucket.into_iter().flatten() {
+                if seen.insert(*index) {
+                    heap.push(
+                        self.algo.distance(datapoint, self.points.get(*index)),
+                        self.points.ids[*index],
+                    );
+                }
+            }
+
+            seen.len()
+        };
+        let mut found = 0;
+
+        for (table, hash) in self.tables.iter().zip(&hashes) {
+            found = check(table.buckets.get(hash), &mut heap);
+        }
+
+        if found < k {
+            for (table, hash) in self.tables.iter().zip(&hashes) {
+                for bit in 0..self.bits {
+                    found = check(table.buckets.get(&(hash ^ (1 << bit))), &mut heap);
+                }
+            }
+        }
+
+        if found < k {
+            for index in 0..self.points.len() {
+                if seen.insert(index) {
+                    heap.push(
+                        self.algo.distance(datapoint, self.points.get(index)),
+                        self.points.ids[index],
+                    );
+                }
+            }
+        }
+
+        heap.into_sorted()
+    }
+}
+
+/// a random value from the standard normal distribution using the box-muller
+/// transform
+fn gaussian(rng: &mut StdRng) -> f64 {
+    let u = 1.0 - rng.random::<f64>();
+    let v = rng.random::<f64>();
+
+    (-2.0 * u.ln()).sqrt() * (2.0 * std::f64::consts::PI * v).cos()
+}
@@ -0,0 +1,116 @@
+use serde::{Deserialize, Serialize};
+
+use super::{NeighborHeap, NeighborIndex, Points};
+use crate::cli::AlgoType;
+
+/// a vantage point tree over a list of datapoints
+///
+/// the tree is stored implicitly in `order`. for a range of the list the
+/// first entry is the vantage point and the remaining entries are split by
+/// the median distance to the vantage point. entries closer than the median
+/// come first followed by the entries further away.
+///
+/// subtrees are skipped by using only the triangle inequality so any metric
+/// can be used
+#[derive(Serialize, Deserialize)]
+pub struct VpTree {
+    pub(super) points: Points,
+    /// the indexes of the datapoints arranged as the tree
+    order: Vec<usize>,
+    /// the median distance of each vantage point. indexed the same as
+    /// `order`
+    thresholds: Vec<f64>,
+    algo: AlgoType,
+}
+
+impl VpTree {
+    pub(super) fn new(points: Points, algo: AlgoType) -> Self {
+        let mut rtn = Self {
+            order: (0..points.len()).collect(),
+            thresholds: vec![0.0; points.len()],
+            points,
+            algo,
+        };
+
+        rtn.arrange(0, rtn.order.len());
+
+        rtn
+    }
+
+    /// builds the tree again so that it includes datapoints added to the
+    /// end of the points
+    pub(super) fn rebuild(&mut self) {
+        *self = Self::new(std::mem::take(&mut self.points), self.algo);
+    }
+
+    /// arranges the range so that the first entry is the vantage point
+    /// followed by the inside and then outside entries
+    fn arrange(&mut self, low: usize, high: usize) {
+        if high - low <= 1 {
+            return;
+        }
+
+        let vantage = self.points.get(self.order[low]);
+        let mut distances: Vec<(f64, usize)> = self.order[low + 1..high]
+            .iter()
+            .map(|index| (self.algo.distance(vantage, self.points.get(*index)), *index))
+            .collect();
+
+        let mid = distances.len() / 2;
+
+        distances.select_nth_unstable_by(mid, |(a, _), (b, _)| a.total_cmp(b));
+
+        self.thresholds[low] = distances[mid].0;
+
+        for (slot, (_, index)) in self.order[low + 1..high].iter_mut().zip(distances) {
+            *slot = index;
+        }
+
+        let split = low + 1 + mid;
+
+        self.arrange(low + 1, split);
+        self.arrange(split, high);
+    }
+
+    fn search(&self, low: usize, high: usize, datapoint: &[f64], heap: &mut NeighborHeap) {
+        if low >= high {
+            return;
+        }
+
+        let index = self.order[low];
+        let distance = self.algo.distance(datapoint, self.points.get(index));
+
+        heap.push(distance, self.points.ids[index]);
+
+        let threshold = self.thresholds[low];
+        let split = low + 1 + (high - low - 1) / 2;
+
+        // the inside entries are at least `distance - threshold` away and the
+        // outside entries are at least `threshold - distance` away
+        if distance < threshold {
+            self.search(low + 1, split, datapoint, heap);
+
+            if heap.could_contain(threshold - distance) {
+                self.search(split, high, datapoint, heap);
+            }
+        } else {
+            self.search(split, high, datapoint, heap);
+
+            if heap.could_contain(distance - threshold) {
+                self.search(low + 1, split, datapoint, heap);
+            }
+        }
+    }
+}
+
+impl NeighborIndex for VpTree {
+    fn nearest(&self, datapoint: &[f64], k: usize) -> Vec<(f64, usize)> {
+        let mut heap = NeighborHeap::new(k);
+
+        if k > 0 {
+            self.search(0, self.order.len(), datapoint, &mut heap);
+        }
+
+        heap.into_sorted()
+    }
+}
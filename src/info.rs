@@ -0,0 +1,297 @@
+//! statistics of a dataset for checking it before predicting or searching
+//!
+//! every value is read as text so columns that would fail to parse are still
+//! described. a value is missing if it is empty or one of
+//! [`MISSING`](crate::source::MISSING) and a column is numeric only if every
+//! value that is not missing is a number.
+use std::collections::{HashMap, HashSet};
+use std::str::FromStr;
+
+use clap::Args;
+
+use crate::cli::ColumnType;
+use crate::csv::{check_label_filter, get_columns_and_label};
+use crate::error::KnnResult;
+use crate::source::{DataSource, is_missing};
+
+#[derive(Debug, Args)]
+pub struct InfoArgs {
+    /// the column to report the label distribution of
+    #[arg(long, allow_negative_numbers = true)]
+    label: Option<ColumnType>,
+}
+
+/// the inferred type of a column
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColumnKind {
+    /// every value is a whole number
+    Integer,
+    /// every value is a number
+    Float,
+    /// some values are not numbers
+    Text,
+    /// every value is missing
+    Empty,
+}
+
+impl std::fmt::Display for ColumnKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Integer => f.pad("integer"),
+            Self::Float => f.pad("float"),
+            Self::Text => f.pad("text"),
+            Self::Empty => f.pad("empty"),
+        }
+    }
+}
+
+/// the statistics of a single column
+#[derive(Debug, Clone, Default)]
+pub struct ColumnInfo {
+    pub name: String,
+    pub missing: usize,
+    /// the number of values that are numbers
+    pub numbers: usize,
+    /// the number of values that are not numbers or missing
+    pub text: usize,
+    /// every number is a whole number
+    integers: bool,
+    pub min: f64,
+    pub max: f64,
+    pub mean: f64,
+    /// the sum of squared differences from the mean
+    m2: f64,
+    /// the distinct values that are not numbers
+    unique: HashSet<String>,
+}
+
+impl ColumnInfo {
+    fn new(name: String) -> Self {
+        Self {
+            name,
+            integers: true,
+            min: f64::INFINITY,
+            max: f64::NEG_INFINITY,
+            ..Self::default()
+        }
+    }
+
+    /// adds a value to the statistics. the mean and variance are updated
+    /// with welford's method so a single pass is enough
+    fn push(&mut self, value: &str) {
+        let value = value.trim();
+
+        if is_missing(value) {
+            self.missing += 1;
+
+            return;
+        }
+
+        let Ok(number) = f64::from_str(value) else {
+            self.text += 1;
+
+            if !self.unique.contains(value) {
+                self.unique.insert(value.to_owned());
+            }
+
+            return;
+        };
+
+        self.numbers += 1;
+        self.integers &= number.fract() == 0.0;
+        self.min = self.min.min(number);
+        self.max = self.max.max(number);
+
+        let delta = number - self.mean;
+
+        self.mean += delta / self.numbers as f64;
+        self.m2 += delta * (number - self.mean);
+    }
+
+    pub fn kind(&self) -> ColumnKind {
+        if self.text > 0 {
+            ColumnKind::Text
+        } else if self.numbers == 0 {
+            ColumnKind::Empty
+        } else if self.integers {
+            ColumnKind::Integer
+        } else {
+            ColumnKind::Float
+        }
+    }
+
+    /// the sample standard deviation of the numbers
+    pub fn std(&self) -> f64 {
+        if self.numbers < 2 {
+            0.0
+        } else {
+            (self.m2 / (self.numbers - 1) as f64).sqrt()
+        }
+    }
+
+    /// the number of distinct values that are not numbers
+    pub fn unique(&self) -> usize {
+        self.unique.len()
+    }
+}
+
+/// the statistics of a dataset
+#[derive(Debug, Clone)]
+pub struct DatasetInfo {
+    pub rows: usize,
+    pub columns: Vec<ColumnInfo>,
+    /// the name of the label column
+    pub label: String,
+    /// the number of rows with each label ordered from the most common
+    pub labels: Vec<(String, usize)>,
+}
+
+/// reads every row of the source and calculates the statistics of each
+/// column and the distribution of the label
+pub fn describe<S>(mut source: S, label: &ColumnType) -> KnnResult<DatasetInfo>
+where
+    S: DataSource,
+{
+    let (label, _) = get_columns_and_label(&mut source, label, &[])?;
+    let headers = source.headers()?;
+    let name = |index: usize| match &headers {
+        Some(headers) => headers[index].clone(),
+        None => index.to_string(),
+    };
+
+    let mut columns: Vec<ColumnInfo> = match source.width()? {
+        Some(width) => (0..width)
+            .map(|index| ColumnInfo::new(name(index)))
+            .collect(),
+        None => Vec::new(),
+    };
+    let mut filter = source.label_filter();
+    let mut counts: HashMap<String, usize> = HashMap::new();
+    let mut rows = 0;
+
+    while let Some(row) = source.next_text() {
+        let row = row?;
+
+        if row.get(label).is_some_and(|value| !filter.keeps(value)) {
+            continue;
+        }
+
+        // sources without a known width describe as many columns as the
+        // widest row
+        while columns.len() < row.len() {
+            columns.push(ColumnInfo::new(columns.len().to_string()));
+        }
+
+        for (index, info) in columns.iter_mut().enumerate() {
+            info.push(row.get(index).map(String::as_str).unwrap_or_default());
+        }
+
+        if let Some(value) = row.get(label) {
+            *counts.entry(value.clone()).or_insert(0) += 1;
+        }
+
+        rows += 1;
+    }
+
+    check_label_filter(&filter, rows == 0)?;
+
+    let mut labels: Vec<(String, usize)> = counts.into_iter().collect();
+    labels.sort_by(|(a_name, a), (b_name, b)| b.cmp(a).then_with(|| a_name.cmp(b_name)));
+
+    Ok(DatasetInfo {
+        rows,
+        label: columns
+            .get(label)
+            .map(|info| info.name.clone())
+            .unwrap_or_else(|| label.to_string()),
+        columns,
+        labels,
+    })
+}
+
+pub fn knn_info<S>(source: S, arg: InfoArgs) -> anyhow::Result<()>
+where
+    S: DataSource,
+{
+    let label = ColumnType::label_or_last(arg.label.as_ref());
+    let info = describe(source, &label)?;
+    let width = info
+        .columns
+        .iter()
+        .map(|column| column.name.len())
+        .max()
+        .unwrap_or(0);
+
+    println!("rows: {} columns: {}", info.rows, info.columns.len());
+
+    for column in &info.columns {
+        let kind = column.kind();
+        let mut line = format!(
+            "  {:<width$} {:<7} | missing: {}",
+            column.name, kind, column.missing
+        );
+
+        match kind {
+            ColumnKind::Integer | ColumnKind::Float => line.push_str(&format!(
+                " min: {} max: {} mean: {:.4} std: {:.4}",
+                column.min,
+                column.max,
+                column.mean,
+                column.std()
+            )),
+            ColumnKind::Text => line.push_str(&format!(
+                " unique: {} numbers: {}",
+                column.unique(),
+                column.numbers
+            )),
+            ColumnKind::Empty => {}
+        }
+
+        println!("{line}");
+    }
+
+    println!("label: {} classes: {}", info.label, info.labels.len());
+
+    for (name, count) in &info.labels {
+        println!(
+            "  {name}: {count} {:.2}%",
+            *count as f64 / info.rows.max(1) as f64 * 100.0
+        );
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::source::MemorySource;
+
+    #[test]
+    fn describe_columns() {
+        let rows = [
+            ["1", "2.5", "a"],
+            ["3", "NA", "b"],
+            ["5", "", "a"],
+            ["x", "4.5", "a"],
+        ];
+        let source = MemorySource::new(
+            Some(vec!["id".to_owned(), "size".to_owned(), "class".to_owned()]),
+            rows.iter()
+                .map(|row| row.iter().map(|value| value.to_string()).collect())
+                .collect(),
+        );
+
+        let info = describe(source, &ColumnType::FromEnd(1)).unwrap();
+
+        assert_eq!(info.rows, 4);
+        assert_eq!(info.columns[0].kind(), ColumnKind::Text);
+        assert_eq!(info.columns[0].numbers, 3);
+        assert_eq!(info.columns[1].kind(), ColumnKind::Float);
+        assert_eq!(info.columns[1].missing, 2);
+        assert_eq!(info.columns[1].mean, 3.5);
+        assert!((info.columns[1].std() - 2f64.sqrt()).abs() < 1e-12);
+        assert_eq!(info.label, "class");
+        assert_eq!(info.labels, vec![("a".to_owned(), 3), ("b".to_owned(), 1)]);
+    }
+}
@@ -0,0 +1,109 @@
+use anyhow::bail;
+use clap::Args;
+
+use crate::cli::{AlgoType, ColumnList, ColumnType, TaskType};
+use crate::csv::{check_numeric_labels, collect_records, get_columns_excluding};
+use crate::evaluate::evaluate_split;
+use crate::source::DataSource;
+use crate::split::{split_dataset, subsample_dataset};
+
+#[derive(Debug, Args)]
+pub struct LearningCurveArgs {
+    /// the number of neighbors to lookup
+    #[arg(short, default_value = "3")]
+    k: usize,
+
+    /// specifies the algorithm to use when calculating distances
+    #[arg(long, default_value = "euclidean")]
+    algo: AlgoType,
+
+    /// the list of columns to use as datapoints
+    #[arg(short, long = "col", allow_hyphen_values = true)]
+    columns: Vec<ColumnList>,
+
+    /// columns to remove from the list of columns
+    #[arg(long = "exclude-col", allow_hyphen_values = true)]
+    exclude: Vec<ColumnList>,
+
+    /// the column to use as the label
+    #[arg(long, allow_negative_numbers = true)]
+    label: Option<ColumnType>,
+
+    /// the type of task to evaluate the labels with
+    #[arg(long, default_value = "classification")]
+    task: TaskType,
+
+    /// the percent of data to test against
+    #[arg(long, default_value = "0.25")]
+    test: f64,
+
+    /// the seed used to shuffle records when splitting the dataset. a random
+    /// seed is used if not specified
+    #[arg(long)]
+    seed: Option<u64>,
+
+    /// a comma delimitered list of training set fractions to evaluate
+    #[arg(long, value_delimiter = ',', default_value = "0.1,0.25,0.5,0.75,1")]
+    sizes: Vec<f64>,
+}
+
+pub fn knn_learning_curve<S>(mut source: S, arg: LearningCurveArgs) -> anyhow::Result<()>
+where
+    S: DataSource,
+{
+    if arg.k == 0 {
+        bail!("k value cannot be 0");
+    }
+
+    for size in &arg.sizes {
+        if *size <= 0.0 || *size > 1.0 {
+            bail!("training set fractions must be larger than 0 and at most 1. size: {size}");
+        }
+    }
+
+    let label = ColumnType::label_or_last(arg.label.as_ref());
+    let (label, columns) = get_columns_excluding(
+        &mut source,
+        &label,
+        &ColumnList::flatten(&arg.columns),
+        &ColumnList::flatten(&arg.exclude),
+    )?;
+    let records = collect_records(source, label, &columns)?;
+
+    if arg.task == TaskType::Regression {
+        check_numeric_labels(&records)?;
+    }
+
+    // the test set is kept the same for every size so that the scores are
+    // comparable
+    let seed = arg.seed.unwrap_or_else(rand::random);
+    let (train, test) = split_dataset(
+        &records,
+        arg.test,
+        arg.task == TaskType::Classification,
+        seed,
+    );
+
+    println!(
+        "train size: {} test size: {} seed: {seed}",
+        train.len(),
+        test.len()
+    );
+
+    for size in &arg.sizes {
+        let sample = subsample_dataset(&train, *size);
+
+        let eval = evaluate_split(
+            arg.k,
+            &arg.algo,
+            arg.task,
+            &sample,
+            &test,
+            records.label_names(),
+        )?;
+
+        println!("{:>6.2}% {:>8} | {eval}", size * 100.0, sample.len());
+    }
+
+    Ok(())
+}
@@ -0,0 +1,96 @@
+//! a k nearest neighbors (knn) library for classifying and estimating
+//! datapoints from the records of a csv
+//!
+//! the `knn` binary is a thin command line wrapper around this crate so every
+//! command is also available to other rust programs.
+//!
+//! - [`csv`] loads the records of a [`source::DataSource`] into a
+//!   [`csv::Dataset`]
+//! - [`source`] reads the rows of a csv or any other source
+//! - [`distance`] provides the distance functions between datapoints
+//! - [`classify`] finds the closest records and counts their labels
+//! - [`classifier`] wraps the algorithm in a fitted [`classifier::KnnClassifier`]
+//! - [`scale`] fits and applies feature scaling to the records
+//! - [`index`] provides spatial indexes for finding the nearest neighbors
+//! - [`predict`], [`evaluate`] and [`search`] are the commands of the binary
+//!   that can also be called with their arguments directly
+//! - [`results`] provides the serializable results of the commands
+//! - [`schema`] versions and upgrades the files and results written as json
+//! - [`progress`] reports the progress of search and evaluate
+//! - [`error`] provides the errors of the core modules
+//! - [`ffi`] exposes the classifier through a c compatible api
+//! - `linfa` implements the linfa traits for the classifier with the `linfa`
+//!   feature
+//! - `wasm` exposes the classifier to javascript when built for wasm32
+//!
+//! ```no_run
+//! use knn::classify::{closest_labels, count_labels};
+//! use knn::cli::AlgoType;
+//! use knn::csv::{ReaderBuilder, collect_records, get_columns_and_label};
+//! use knn::cli::ColumnType;
+//! use knn::source::CsvSource;
+//!
+//! # fn main() -> anyhow::Result<()> {
+//! let mut source = CsvSource::new(ReaderBuilder::new().from_path("penguins.csv")?);
+//! let columns = [
+//!     "bill_length_mm".parse::<ColumnType>()?,
+//!     "bill_depth_mm".parse::<ColumnType>()?,
+//! ];
+//! let label = "species".parse::<ColumnType>()?;
+//!
+//! let (label, columns) = get_columns_and_label(&mut source, &label, &columns)?;
+//! let records = collect_records(source, label, &columns)?;
+//!
+//! let labels = closest_labels(
+//!     5,
+//!     records.iter().map(|record| (record.data, record.label)),
+//!     AlgoType::Euclidean.as_rank_fn(),
+//!     &[45.0, 17.0],
+//! );
+//! let mut groups = Vec::new();
+//! count_labels(labels.into_iter(), &mut groups);
+//! # Ok(())
+//! # }
+//! ```
+pub mod balance;
+pub mod bench;
+pub mod checkpoint;
+pub mod classifier;
+pub mod classify;
+pub mod cli;
+pub mod compare;
+pub mod config;
+pub mod csv;
+pub mod distance;
+pub mod dry_run;
+pub mod error;
+pub mod evaluate;
+pub mod ffi;
+pub mod gpu;
+pub mod grid;
+pub mod grpc;
+pub mod index;
+pub mod info;
+pub mod learning;
+#[cfg(feature = "linfa")]
+pub mod linfa;
+pub mod memory;
+pub mod metrics;
+pub mod model;
+pub mod neighbors;
+pub mod pq;
+pub mod predict;
+pub mod progress;
+pub mod repl;
+pub mod results;
+pub mod scale;
+pub mod schema;
+pub mod search;
+pub mod serve;
+pub mod source;
+pub mod split;
+pub mod store;
+pub mod validate;
+#[cfg(target_arch = "wasm32")]
+pub mod wasm;
+pub mod watch;
@@ -0,0 +1,143 @@
+//! fitting and predicting through the traits of linfa
+//!
+//! [`KnnClassifierBuilder`] implements [`Fit`] for a linfa dataset and the
+//! fitted [`LinfaKnn`] implements [`PredictInplace`], which provides linfa's
+//! `Predict`, so the classifier can be used in a linfa pipeline or compared
+//! against the other linfa algorithms. the targets can be any linfa label.
+//! the builder has its own `fit` so the linfa one is called through the
+//! trait.
+//!
+//! only available when built with the `linfa` feature.
+//!
+//! ```
+//! use linfa::prelude::*;
+//! use linfa_ndarray::array;
+//! use knn::classifier::KnnClassifier;
+//!
+//! let train = Dataset::new(
+//!     array![[1.0, 1.0], [1.5, 1.0], [8.0, 9.0], [9.0, 8.0]],
+//!     array![true, true, false, false],
+//! );
+//! let model = Fit::fit(&KnnClassifier::builder().k(1), &train).unwrap();
+//! let predicted = model.predict(&array![[7.0, 8.0], [1.0, 2.0]]);
+//!
+//! assert_eq!(predicted, array![false, true]);
+//! ```
+use std::collections::HashMap;
+
+use ::linfa::dataset::{AsSingleTargets, DatasetBase, Label};
+use ::linfa::traits::{Fit, PredictInplace};
+use linfa_ndarray::{Array1, ArrayBase, Data, Ix2};
+
+use crate::classifier::{KnnClassifier, KnnClassifierBuilder};
+use crate::csv::Dataset;
+use crate::error::KnnError;
+
+/// a classifier fitted to a linfa dataset
+#[derive(Debug, Clone)]
+pub struct LinfaKnn<L> {
+    classifier: KnnClassifier,
+    /// the target of each label id of the classifier
+    targets: Vec<L>,
+}
+
+impl<L> LinfaKnn<L> {
+    /// the fitted classifier. its labels are the positions of the targets
+    /// in [`LinfaKnn::targets`]
+    pub fn classifier(&self) -> &KnnClassifier {
+        &self.classifier
+    }
+
+    /// the targets of the dataset in the order they were first seen
+    pub fn targets(&self) -> &[L] {
+        &self.targets
+    }
+}
+
+impl<D, T, L> Fit<ArrayBase<D, Ix2>, T, ::linfa::Error> for KnnClassifierBuilder
+where
+    D: Data<Elem = f64>,
+    T: AsSingleTargets<Elem = L>,
+    L: Label,
+{
+    type Object = LinfaKnn<L>;
+
+    fn fit(
+        &self,
+        dataset: &DatasetBase<ArrayBase<D, Ix2>, T>,
+    ) -> Result<LinfaKnn<L>, ::linfa::Error> {
+        let records = dataset.records();
+        let targets = dataset.as_single_targets();
+
+        if records.nrows() != targets.len() {
+            return Err(::linfa::Error::MismatchedShapes(
+                records.nrows(),
+                targets.len(),
+            ));
+        }
+
+        let mut ids = HashMap::new();
+        let mut fitted = Dataset::new(records.ncols());
+        let mut known = Vec::new();
+        let mut data = Vec::with_capacity(records.ncols());
+
+        for (row, target) in records.rows().into_iter().zip(targets.iter()) {
+            // every target is interned once in the order they are seen so
+            // the label id of a target is also its position in `known`
+            let id = *ids.entry(target.clone()).or_insert_with(|| {
+                known.push(target.clone());
+
+                fitted.intern(&(known.len() - 1).to_string())
+            });
+
+            data.clear();
+            data.extend(row.iter().copied());
+
+            fitted.push_id(&data, id);
+        }
+
+        let classifier = self.clone().fit(fitted).map_err(|err| match err {
+            KnnError::NoRecords => ::linfa::Error::NotEnoughSamples,
+            err => ::linfa::Error::Parameters(err.to_string()),
+        })?;
+
+        Ok(LinfaKnn {
+            classifier,
+            targets: known,
+        })
+    }
+}
+
+impl<D, L> PredictInplace<ArrayBase<D, Ix2>, Array1<L>> for LinfaKnn<L>
+where
+    D: Data<Elem = f64>,
+    L: Label,
+{
+    /// panics if the datapoints do not have the same number of columns as
+    /// the fitted records
+    fn predict_inplace(&self, x: &ArrayBase<D, Ix2>, y: &mut Array1<L>) {
+        assert_eq!(
+            x.nrows(),
+            y.len(),
+            "the number of datapoints and targets must match"
+        );
+
+        let mut data = Vec::with_capacity(x.ncols());
+
+        for (row, target) in x.rows().into_iter().zip(y.iter_mut()) {
+            data.clear();
+            data.extend(row.iter().copied());
+
+            let id = self
+                .classifier
+                .predict_id(&data)
+                .expect("datapoint has the wrong number of columns");
+
+            *target = self.targets[id as usize].clone();
+        }
+    }
+
+    fn default_target(&self, x: &ArrayBase<D, Ix2>) -> Array1<L> {
+        Array1::default(x.nrows())
+    }
+}
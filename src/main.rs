@@ -1,23 +1,196 @@
-use std::fs::OpenOptions;
+use std::ffi::OsString;
+use std::fs::{File, OpenOptions};
 use std::io::{BufReader, ErrorKind};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 use anyhow::{Error, bail};
-use clap::{Parser, Subcommand};
+use clap::{Args, Command, CommandFactory, FromArgMatches, Parser, Subcommand};
+use clap_complete::Shell;
 
-mod classify;
-mod cli;
-mod csv;
-mod distance;
-mod predict;
-mod search;
-
-use csv::ReaderBuilder;
+use knn::cli::{self, HeaderMatch, MemorySize, OnDuplicate};
+use knn::config::{Config, DEFAULT_CONFIG};
+use knn::csv::{ReaderBuilder, require_csv};
+use knn::progress::{NoProgress, Progress, ProgressBar};
+use knn::source::{CsvSource, LabelFilter};
+use knn::{
+    bench, compare, evaluate, info, learning, memory, model, neighbors, predict, repl, search,
+    serve, store, validate, watch,
+};
 
 fn main() -> anyhow::Result<()> {
-    let args = CliArgs::parse();
+    let args = parse_args()?;
+
+    cli::init_threads(args.threads)?;
+    memory::init_budget(args.max_memory);
+
+    let progress: Box<dyn Progress> = if args.progress {
+        Box::new(ProgressBar::new())
+    } else {
+        Box::new(NoProgress)
+    };
+
+    let result = if args.watch {
+        watch_cmd(&args, progress.as_ref())
+    } else {
+        // predict and evaluate can load an index file instead of the csv
+        match open_source(&args) {
+            Ok(source) => run_cmd(args.cmd, source, progress.as_ref()),
+            Err(err) => Err(err),
+        }
+    };
+
+    // the peak is reported even when the command fails since running out of
+    // budget is when it is the most useful
+    if args.report_memory {
+        memory::print_report();
+    }
+
+    result
+}
+
+/// parses the command line and adds the values of the config file that were
+/// not given on the command line or through the environment
+fn parse_args() -> anyhow::Result<CliArgs> {
+    let mut command = with_env(CliArgs::command());
+    let matches = command.get_matches_mut();
+    let args = CliArgs::from_arg_matches(&matches)?;
+
+    let config = match &args.config {
+        Some(path) => Config::load(path)?,
+        None if args.no_config => return Ok(args),
+        None => {
+            let path = Path::new(DEFAULT_CONFIG);
+
+            if !path.try_exists().unwrap_or(false) {
+                return Ok(args);
+            }
+
+            Config::load(path)?
+        }
+    };
+
+    command.build();
+
+    let extra = config.args(&command, &matches);
+
+    if extra.is_empty() {
+        return Ok(args);
+    }
+
+    // the values are parsed again so they are checked the same as the rest
+    // of the command line
+    let matches =
+        command.get_matches_from(std::env::args_os().chain(extra.into_iter().map(OsString::from)));
+
+    Ok(CliArgs::from_arg_matches(&matches)?)
+}
+
+/// reads every argument of the command and its subcommands from a `KNN_`
+/// environment variable named after its long flag, such as `KNN_LABEL` for
+/// `--label`, or its short flag when it has no long flag, such as `KNN_K` for
+/// `-k`. values on the command line replace the environment
+fn with_env(command: Command) -> Command {
+    command
+        .mut_args(|arg| {
+            if arg.get_env().is_some() {
+                return arg;
+            }
+
+            let flag = match (arg.get_long(), arg.get_short()) {
+                (Some(long), _) => long.to_owned(),
+                (None, Some(short)) => short.to_string(),
+                (None, None) => return arg,
+            };
+
+            if flag == "help" || flag == "version" {
+                return arg;
+            }
+
+            let name = format!("KNN_{}", flag.replace('-', "_").to_ascii_uppercase());
+
+            arg.env(name)
+        })
+        .mut_subcommands(with_env)
+}
+
+fn run_cmd(
+    cmd: KnnCmd,
+    source: Option<CsvSource<BufReader<File>>>,
+    progress: &dyn Progress,
+) -> anyhow::Result<()> {
+    match cmd {
+        KnnCmd::Predict(arg) => predict::knn_predict(source, arg),
+        KnnCmd::Search(arg) => search::knn_search(require_csv(source)?, *arg, progress),
+        KnnCmd::Compare(arg) => compare::knn_compare(require_csv(source)?, arg),
+        KnnCmd::Evaluate(arg) => evaluate::knn_evaluate(source, arg, progress),
+        KnnCmd::LearningCurve(arg) => learning::knn_learning_curve(require_csv(source)?, arg),
+        KnnCmd::Index(cmd) => store::knn_index(require_csv(source)?, cmd),
+        KnnCmd::Fit(arg) => model::knn_fit(require_csv(source)?, arg),
+        KnnCmd::Serve(arg) => serve::knn_serve(source, arg),
+        KnnCmd::Bench(arg) => bench::knn_bench(source, arg),
+        KnnCmd::Repl(arg) => repl::knn_repl(require_csv(source)?, arg),
+        KnnCmd::Info(arg) => info::knn_info(require_csv(source)?, arg),
+        KnnCmd::Validate(arg) => validate::knn_validate(require_csv(source)?, arg),
+        KnnCmd::Neighbors(arg) => neighbors::knn_neighbors(require_csv(source)?, arg),
+        KnnCmd::Completions(arg) => {
+            print_completions(arg.shell);
+
+            Ok(())
+        }
+    }
+}
+
+/// runs predict or search again every time the csv or any of the other
+/// files they read change
+fn watch_cmd(args: &CliArgs, progress: &dyn Progress) -> anyhow::Result<()> {
+    let mut paths: Vec<PathBuf> = args.file.iter().cloned().collect();
+
+    match &args.cmd {
+        KnnCmd::Predict(arg) => paths.extend(arg.input_files().cloned()),
+        KnnCmd::Search(arg) => paths.extend(arg.input_files().cloned()),
+        _ => bail!("only predict and search can be watched"),
+    }
+
+    // the csv is opened again for every run so the changes are read
+    watch::watch(&paths, || {
+        let source = open_source(args)?;
+
+        match &args.cmd {
+            KnnCmd::Predict(arg) => predict::knn_predict(source, arg.clone()),
+            KnnCmd::Search(arg) => {
+                search::knn_search(require_csv(source)?, (**arg).clone(), progress)
+            }
+            _ => unreachable!("only predict and search are watched"),
+        }
+    })
+}
 
-    let result = OpenOptions::new().read(true).open(&args.file);
+/// writes the completion script for the shell to stdout. enum values and
+/// the flags of every subcommand are completed
+fn print_completions(shell: Shell) {
+    let mut command = with_env(CliArgs::command());
+    let name = command.get_name().to_owned();
+
+    clap_complete::generate(shell, &mut command, name, &mut std::io::stdout());
+}
+
+fn open_source(args: &CliArgs) -> anyhow::Result<Option<CsvSource<BufReader<File>>>> {
+    match &args.file {
+        Some(path) => Ok(Some(
+            open_csv(path, args.no_header)?
+                .with_on_duplicate(args.on_duplicate)
+                .with_header_match(args.header_match)
+                .with_label_filter(LabelFilter::new(
+                    args.include_label.clone(),
+                    args.exclude_label.clone(),
+                )),
+        )),
+        None => Ok(None),
+    }
+}
+
+fn open_csv(path: &Path, no_header: bool) -> anyhow::Result<CsvSource<BufReader<File>>> {
+    let result = OpenOptions::new().read(true).open(path);
 
     let file = match result {
         Ok(f) => f,
@@ -27,14 +200,11 @@ fn main() -> anyhow::Result<()> {
         },
     };
 
-    let reader = ReaderBuilder::new()
-        .has_headers(!args.no_header)
-        .from_reader(BufReader::new(file));
-
-    match args.cmd {
-        KnnCmd::Predict(arg) => predict::knn_predict(reader, arg),
-        KnnCmd::Search(arg) => search::knn_search(reader, arg),
-    }
+    Ok(CsvSource::new(
+        ReaderBuilder::new()
+            .has_headers(!no_header)
+            .from_reader(BufReader::new(file)),
+    ))
 }
 
 /// a simple k nearest neighbors (knn) calculator that loads a csv file
@@ -42,12 +212,71 @@ fn main() -> anyhow::Result<()> {
 #[derive(Debug, Parser)]
 struct CliArgs {
     /// indicates that the csv contains no header row
-    #[arg(long)]
+    #[arg(long, global = true)]
     no_header: bool,
 
+    /// how a column name shared by more than one header is resolved
+    #[arg(long, global = true, default_value = "error")]
+    on_duplicate: OnDuplicate,
+
+    /// how the names of columns are compared against the headers. loose
+    /// ignores whitespace around the names and their case
+    #[arg(long, global = true, default_value = "exact")]
+    header_match: HeaderMatch,
+
+    /// only loads the rows with the label. can be specified multiple times
+    #[arg(long, global = true)]
+    include_label: Vec<String>,
+
+    /// skips the rows with the label. can be specified multiple times
+    #[arg(long, global = true)]
+    exclude_label: Vec<String>,
+
     /// path to the csv file to load
-    #[arg(short, long)]
-    file: PathBuf,
+    #[arg(short, long, global = true)]
+    file: Option<PathBuf>,
+
+    /// the number of threads used for all parallel work. defaults to the
+    /// number of available cpus
+    #[arg(long, global = true)]
+    threads: Option<usize>,
+
+    /// the maximum amount of memory to use, such as 4G or 512M. loading and
+    /// indexing will fail instead of going over the limit
+    #[arg(long, global = true)]
+    max_memory: Option<MemorySize>,
+
+    /// prints the peak memory used by the process once the command finishes
+    #[arg(long, global = true)]
+    report_memory: bool,
+
+    /// the seed used by everything that is random, such as splitting the
+    /// records, the genetic and random search strategies, the importance
+    /// report, and bench records. a random seed is used and printed if not
+    /// specified. the value is shared with the `--seed` of the command so it
+    /// can be given before or after it. hnsw and lsh indexes are always built
+    /// with their own fixed seed and are not affected
+    #[arg(long, global = true)]
+    seed: Option<u64>,
+
+    /// draws the progress of search and evaluate on stderr
+    #[arg(long, global = true)]
+    progress: bool,
+
+    /// a toml file with default values for the file, columns, label,
+    /// algorithm, and scaling. `knn.toml` is used if it exists in the
+    /// working directory
+    #[arg(long, global = true)]
+    config: Option<PathBuf>,
+
+    /// ignores `knn.toml` in the working directory
+    #[arg(long, global = true, conflicts_with = "config")]
+    no_config: bool,
+
+    /// runs predict or search again every time the csv, datapoint file,
+    /// index file, model, or grid changes on disk
+    #[arg(long, global = true)]
+    watch: bool,
 
     #[command(subcommand)]
     cmd: KnnCmd,
@@ -58,5 +287,42 @@ pub enum KnnCmd {
     /// attempts to predict a specific datapoint with the specified dataset
     Predict(predict::PredictArgs),
     /// searches for an optimal set of arguments to predict values with
-    Search(search::SearchArgs),
+    Search(Box<search::SearchArgs>),
+    /// compares two sets of arguments over the same folds of the dataset
+    Compare(compare::CompareArgs),
+    /// evaluates a single set of arguments over folds of the dataset
+    Evaluate(evaluate::EvaluateArgs),
+    /// evaluates a single set of arguments at increasing training set sizes
+    LearningCurve(learning::LearningCurveArgs),
+    /// manages index files that skip parsing and indexing the csv
+    #[command(subcommand)]
+    Index(store::IndexCmd),
+    /// fits the csv into a model file that predict can load without any
+    /// other arguments
+    Fit(model::FitArgs),
+    /// serves predictions over http from a csv or model file
+    Serve(serve::ServeArgs),
+    /// measures the speed of each algorithm and index on a csv or generated
+    /// records
+    Bench(bench::BenchArgs),
+    /// loads the csv once and predicts datapoints typed at a prompt
+    Repl(repl::ReplArgs),
+    /// reports the type and statistics of every column and the distribution
+    /// of the label
+    #[command(alias = "describe")]
+    Info(info::InfoArgs),
+    /// checks every row of the csv and reports each one that cannot be
+    /// loaded with the columns and label
+    Validate(validate::ValidateArgs),
+    /// lists the closest rows of the csv to a datapoint or another row with
+    /// their distances and labels
+    Neighbors(neighbors::NeighborsArgs),
+    /// prints a shell completion script
+    Completions(CompletionsArgs),
+}
+
+#[derive(Debug, Args)]
+pub struct CompletionsArgs {
+    /// the shell to print the completion script for
+    shell: Shell,
 }
@@ -0,0 +1,116 @@
+//! limiting and reporting the memory used by the process
+//!
+//! the budget is compared against the resident memory of the process before
+//! large allocations so that loading or indexing fails with an error instead
+//! of the process being killed by the system. the resident memory is only
+//! available on linux, other platforms will only compare the size of the
+//! allocation against the budget.
+use std::sync::OnceLock;
+
+use crate::cli::MemorySize;
+use crate::error::{KnnError, KnnResult};
+
+/// the maximum number of bytes the process is allowed to use
+static BUDGET: OnceLock<u64> = OnceLock::new();
+
+/// sets the memory budget for the process. nothing is limited if not
+/// specified
+pub fn init_budget(max: Option<MemorySize>) {
+    if let Some(max) = max {
+        let _ = BUDGET.set(max.bytes());
+    }
+}
+
+/// checks that allocating the given number of bytes will stay within the
+/// memory budget
+///
+/// `what` describes the allocation for the error message
+pub fn reserve(bytes: usize, what: &str) -> KnnResult<()> {
+    let Some(budget) = BUDGET.get().copied() else {
+        return Ok(());
+    };
+
+    let used = resident().unwrap_or(0);
+    let needed = used.saturating_add(bytes as u64);
+
+    if needed > budget {
+        return Err(KnnError::MemoryBudget {
+            what: what.to_owned(),
+            needed: bytes as u64,
+            used,
+            budget,
+        });
+    }
+
+    Ok(())
+}
+
+/// the current resident memory of the process in bytes
+pub fn resident() -> Option<u64> {
+    read_status("VmRSS:")
+}
+
+/// the largest resident memory of the process in bytes
+pub fn peak_resident() -> Option<u64> {
+    read_status("VmHWM:")
+}
+
+/// prints the peak resident memory of the process
+pub fn print_report() {
+    match peak_resident() {
+        Some(peak) => println!("peak memory: {}", format_bytes(peak)),
+        None => println!("peak memory: unavailable on this platform"),
+    }
+}
+
+/// retrieves a kB field from the status of the process
+fn read_status(field: &str) -> Option<u64> {
+    let status = std::fs::read_to_string("/proc/self/status").ok()?;
+
+    for line in status.lines() {
+        if let Some(value) = line.strip_prefix(field) {
+            let kb = value
+                .trim()
+                .strip_suffix("kB")?
+                .trim()
+                .parse::<u64>()
+                .ok()?;
+
+            return Some(kb * 1024);
+        }
+    }
+
+    None
+}
+
+/// formats a number of bytes with the largest unit that keeps the value
+/// above 1
+pub(crate) fn format_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 5] = ["B", "KiB", "MiB", "GiB", "TiB"];
+
+    let mut value = bytes as f64;
+    let mut unit = 0;
+
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+
+    if unit == 0 {
+        format!("{bytes} B")
+    } else {
+        format!("{value:.1} {}", UNITS[unit])
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn bytes_formatting() {
+        assert_eq!(format_bytes(512), "512 B");
+        assert_eq!(format_bytes(1536), "1.5 KiB");
+        assert_eq!(format_bytes(4 << 30), "4.0 GiB");
+    }
+}
@@ -0,0 +1,229 @@
+//! evaluation metrics for the results of the knn algorithm
+//!
+//! classification tasks are scored by the percentage of test records that
+//! were assigned the correct label. regression tasks are scored with the root
+//! mean squared error (RMSE), mean absolute error (MAE), and the coefficient
+//! of determination (R²).
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+/// the calculated results of evaluating a set of test records
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Evaluation {
+    Classification {
+        passed: usize,
+        failed: usize,
+        unknown: usize,
+    },
+    Regression {
+        rmse: f64,
+        mae: f64,
+        r2: f64,
+        unknown: usize,
+    },
+}
+
+impl Evaluation {
+    /// creates a regression evaluation from the list of actual and predicted
+    /// values
+    pub fn regression(actual: &[f64], predicted: &[f64], unknown: usize) -> Self {
+        Self::Regression {
+            rmse: rmse(actual, predicted),
+            mae: mae(actual, predicted),
+            r2: r2(actual, predicted),
+            unknown,
+        }
+    }
+
+    /// a single value used for ranking evaluations where larger is better
+    ///
+    /// classification will use the percentage of correct records and
+    /// regression will use the negated RMSE
+    pub fn score(&self) -> f64 {
+        match self {
+            Self::Classification {
+                passed,
+                failed,
+                unknown,
+            } => {
+                let total = passed + failed + unknown;
+
+                if total == 0 {
+                    0.0
+                } else {
+                    (*passed as f64) / (total as f64)
+                }
+            }
+            Self::Regression { rmse, .. } => -rmse,
+        }
+    }
+}
+
+impl fmt::Display for Evaluation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Classification {
+                passed,
+                failed,
+                unknown,
+            } => write!(
+                f,
+                "passed: {passed} {:.2} failed: {failed} unknown: {unknown}",
+                self.score()
+            ),
+            Self::Regression {
+                rmse,
+                mae,
+                r2,
+                unknown,
+            } => write!(
+                f,
+                "rmse: {rmse:.4} mae: {mae:.4} r2: {r2:.4} unknown: {unknown}"
+            ),
+        }
+    }
+}
+
+/// calculates the root mean squared error between actual and predicted values
+pub fn rmse(actual: &[f64], predicted: &[f64]) -> f64 {
+    if actual.is_empty() {
+        return 0.0;
+    }
+
+    let sum = actual
+        .iter()
+        .zip(predicted)
+        .map(|(a, p)| (a - p).powf(2.0))
+        .sum::<f64>();
+
+    (sum / actual.len() as f64).sqrt()
+}
+
+/// calculates the mean absolute error between actual and predicted values
+pub fn mae(actual: &[f64], predicted: &[f64]) -> f64 {
+    if actual.is_empty() {
+        return 0.0;
+    }
+
+    let sum = actual
+        .iter()
+        .zip(predicted)
+        .map(|(a, p)| (a - p).abs())
+        .sum::<f64>();
+
+    sum / actual.len() as f64
+}
+
+/// calculates the coefficient of determination between actual and predicted
+/// values
+///
+/// if the actual values have no variance then the result will be 0 when the
+/// predictions are not exact
+pub fn r2(actual: &[f64], predicted: &[f64]) -> f64 {
+    if actual.is_empty() {
+        return 0.0;
+    }
+
+    let mean = actual.iter().sum::<f64>() / actual.len() as f64;
+
+    let ss_res = actual
+        .iter()
+        .zip(predicted)
+        .map(|(a, p)| (a - p).powf(2.0))
+        .sum::<f64>();
+    let ss_tot = actual.iter().map(|a| (a - mean).powf(2.0)).sum::<f64>();
+
+    if ss_tot == 0.0 {
+        if ss_res == 0.0 { 1.0 } else { 0.0 }
+    } else {
+        1.0 - (ss_res / ss_tot)
+    }
+}
+
+/// performs McNemar's test on the disagreements between two classifiers
+///
+/// `a_only` is the number of records only the first classifier predicted
+/// correctly and `b_only` is the number of records only the second classifier
+/// predicted correctly. returns the chi-squared statistic (with continuity
+/// correction) and the p-value for 1 degree of freedom.
+pub fn mcnemar(a_only: usize, b_only: usize) -> (f64, f64) {
+    let total = (a_only + b_only) as f64;
+
+    if total == 0.0 {
+        return (0.0, 1.0);
+    }
+
+    let diff = ((a_only as f64) - (b_only as f64)).abs() - 1.0;
+    let chi2 = diff.max(0.0).powf(2.0) / total;
+
+    // the survival function of a chi-squared distribution with 1 degree of
+    // freedom is erfc(sqrt(x / 2))
+    (chi2, erfc((chi2 / 2.0).sqrt()))
+}
+
+/// approximates the complementary error function
+///
+/// uses the Abramowitz and Stegun formula 7.1.26 which has a maximum error of
+/// 1.5e-7 and is only valid for non-negative values
+fn erfc(x: f64) -> f64 {
+    let t = 1.0 / (1.0 + 0.3275911 * x);
+    let poly = t
+        * (0.254829592
+            + t * (-0.284496736 + t * (1.421413741 + t * (-1.453152027 + t * 1.061405429))));
+
+    poly * (-x * x).exp()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const ACTUAL: [f64; 4] = [3.0, -0.5, 2.0, 7.0];
+    const PREDICTED: [f64; 4] = [2.5, 0.0, 2.0, 8.0];
+
+    #[test]
+    fn check_rmse() {
+        let calc = rmse(&ACTUAL, &PREDICTED);
+
+        assert_eq!(calc, (1.5f64 / 4.0).sqrt());
+    }
+
+    #[test]
+    fn check_mae() {
+        let calc = mae(&ACTUAL, &PREDICTED);
+
+        assert_eq!(calc, 0.5);
+    }
+
+    #[test]
+    fn check_r2() {
+        let calc = r2(&ACTUAL, &PREDICTED);
+
+        // ss_res = 1.5 and ss_tot = 29.1875
+        assert!((calc - (1.0 - 1.5 / 29.1875)).abs() < 1e-12);
+    }
+
+    #[test]
+    fn check_classification_score() {
+        let eval = Evaluation::Classification {
+            passed: 3,
+            failed: 1,
+            unknown: 0,
+        };
+
+        assert_eq!(eval.score(), 0.75);
+    }
+
+    #[test]
+    fn check_mcnemar() {
+        let (chi2, p) = mcnemar(10, 2);
+
+        // (|10 - 2| - 1)^2 / 12
+        assert_eq!(chi2, 49.0 / 12.0);
+        assert!((p - 0.0433).abs() < 1e-3);
+
+        assert_eq!(mcnemar(0, 0), (0.0, 1.0));
+    }
+}
@@ -0,0 +1,321 @@
+//! saving everything needed to make predictions
+//!
+//! a model file is an index file along with the k value and task so that
+//! predictions only need the datapoint. the k value can be selected by cross
+//! validating the records when the model is fitted.
+use std::path::{Path, PathBuf};
+
+use anyhow::bail;
+use clap::Args;
+use serde::{Deserialize, Serialize};
+
+use crate::classify::count_labels;
+use crate::cli::{AlgoType, ColumnList, ColumnType, KSelection, TaskType};
+use crate::csv::check_numeric_labels;
+use crate::index::{IndexArgs, NeighborIndex};
+use crate::predict::auto_k;
+use crate::results::Prediction;
+use crate::scale::ScaleType;
+use crate::schema::Schema;
+use crate::source::DataSource;
+use crate::store::{INDEX_SCHEMA, StoredIndex, load_json, load_scaled, save_json};
+
+/// the version of the model file layout
+pub const MODEL_VERSION: u32 = 2;
+
+/// the versions of the model file that can be loaded. newer files are
+/// rejected instead of being misread
+pub const MODEL_SCHEMA: Schema = Schema {
+    what: "model file",
+    current: MODEL_VERSION,
+    oldest: 1,
+    // version 2 added the version of the stored index
+    migrations: &[|value| INDEX_SCHEMA.upgrade(&mut value["stored"])],
+};
+
+#[derive(Debug, Args)]
+pub struct FitArgs {
+    #[command(flatten)]
+    model: ModelArgs,
+
+    /// the file to save the model to
+    #[arg(long)]
+    out: PathBuf,
+}
+
+/// the arguments used to fit a model from the csv
+#[derive(Debug, Args)]
+pub struct ModelArgs {
+    /// the number of neighbors to lookup. specifying `auto` will cross
+    /// validate a range of k values with the records and store the best
+    #[arg(short, long, default_value = "3")]
+    k: KSelection,
+
+    /// skips even k values when selecting k automatically
+    #[arg(long)]
+    odd_only: bool,
+
+    /// specifies the algorithm to use when calculating distances
+    #[arg(long, default_value = "euclidean")]
+    algo: AlgoType,
+
+    /// the list of columns to use as datapoints
+    #[arg(short, long = "col", allow_hyphen_values = true)]
+    columns: Vec<ColumnList>,
+
+    /// columns to remove from the list of columns
+    #[arg(long = "exclude-col", allow_hyphen_values = true)]
+    exclude: Vec<ColumnList>,
+
+    /// the column to use as the label
+    #[arg(long, allow_negative_numbers = true)]
+    label: Option<ColumnType>,
+
+    /// the scaling method to apply to the columns
+    #[arg(long, default_value = "none")]
+    scale: ScaleType,
+
+    /// the type of task to perform with the labels
+    #[arg(long, default_value = "classification")]
+    task: TaskType,
+
+    #[command(flatten)]
+    index: IndexArgs,
+}
+
+/// the format of the model file
+#[derive(Serialize, Deserialize)]
+pub struct Model {
+    pub version: u32,
+    pub k: usize,
+    pub task: TaskType,
+    /// the columns, scaling, algorithm, and indexed records
+    pub stored: StoredIndex,
+}
+
+impl Model {
+    /// loads a previously saved model file. older versions are upgraded
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        load_json(path, &MODEL_SCHEMA)
+    }
+
+    /// writes the model to the specified file
+    pub fn save(&self, path: &Path) -> anyhow::Result<()> {
+        save_json(path, MODEL_SCHEMA.what, self)
+    }
+
+    /// adds the labeled datapoints to the model. refer to
+    /// [`StoredIndex::append`]
+    pub fn append<'a, I>(&mut self, records: I) -> anyhow::Result<()>
+    where
+        I: IntoIterator<Item = (&'a [f64], &'a str)>,
+    {
+        let records: Vec<_> = records.into_iter().collect();
+
+        if self.task == TaskType::Regression
+            && let Some((_, label)) = records
+                .iter()
+                .find(|(_, label)| label.parse::<f64>().is_err())
+        {
+            bail!("regression requires numeric labels. label: {label}");
+        }
+
+        self.stored.append(records)
+    }
+
+    /// finds the neighbors of the datapoint and counts their labels
+    pub fn predict(&self, datapoint: &[f64]) -> anyhow::Result<Prediction> {
+        if datapoint.len() != self.stored.columns.len() {
+            bail!(
+                "number of datapoints does not match number of columns in the model. columns: {}",
+                self.stored.columns.join(" ")
+            );
+        }
+
+        let mut scaled = datapoint.to_vec();
+
+        self.stored.scaler.apply(&mut scaled);
+
+        let mut groups = Vec::new();
+        let min = count_labels(
+            self.stored
+                .index
+                .nearest(&scaled, self.k)
+                .iter()
+                .map(|(_, index)| self.stored.labels[*index]),
+            &mut groups,
+        );
+
+        Prediction::from_groups(
+            self.k,
+            datapoint,
+            self.task,
+            min,
+            &groups,
+            &self.stored.label_names,
+        )
+    }
+
+    /// predicts each datapoint only when the next prediction is requested
+    /// so the predictions can be used or stopped before every datapoint has
+    /// been predicted
+    pub fn predict_batch<'a, I, D>(
+        &'a self,
+        datapoints: I,
+    ) -> impl Iterator<Item = anyhow::Result<Prediction>> + 'a
+    where
+        I: IntoIterator<Item = D>,
+        I::IntoIter: 'a,
+        D: AsRef<[f64]>,
+    {
+        datapoints
+            .into_iter()
+            .map(|datapoint| self.predict(datapoint.as_ref()))
+    }
+}
+
+pub fn knn_fit<S>(source: S, arg: FitArgs) -> anyhow::Result<()>
+where
+    S: DataSource,
+{
+    let model = fit_model(source, &arg.model)?;
+
+    model.save(&arg.out)?;
+
+    println!(
+        "records: {} columns: {} k: {} index: {}",
+        model.stored.labels.len(),
+        model.stored.columns.join(" "),
+        model.k,
+        arg.model.index.kind
+    );
+
+    Ok(())
+}
+
+/// parses, scales, and indexes the csv and selects the k value
+pub fn fit_model<S>(source: S, arg: &ModelArgs) -> anyhow::Result<Model>
+where
+    S: DataSource,
+{
+    let label = &ColumnType::label_or_last(arg.label.as_ref());
+
+    let loaded = load_scaled(
+        source,
+        &ColumnList::flatten(&arg.columns),
+        &ColumnList::flatten(&arg.exclude),
+        label,
+        arg.scale,
+    )?;
+
+    if arg.task == TaskType::Regression {
+        check_numeric_labels(&loaded.records)?;
+    }
+
+    let k = match &arg.k {
+        KSelection::Value(k) => {
+            let mut ks = k.get_range(loaded.records.len(), arg.odd_only);
+
+            match (ks.next(), ks.next()) {
+                (Some(k), None) => k,
+                (None, _) => bail!("no k values available for the number of records"),
+                (Some(_), Some(_)) => bail!("a model can only store a single k value"),
+            }
+        }
+        KSelection::Auto => {
+            let k = auto_k(&loaded.records, &arg.algo, arg.task, arg.odd_only)?;
+
+            println!("auto k: {k}");
+
+            k
+        }
+    };
+
+    Ok(Model {
+        version: MODEL_VERSION,
+        k,
+        task: arg.task,
+        stored: StoredIndex::build(loaded, arg.algo, &arg.index)?,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::csv::Dataset;
+    use crate::index::IndexType;
+    use crate::scale::Scaler;
+
+    #[test]
+    fn model_round_trip() {
+        let mut records = Dataset::new(2);
+        records.push(&[1.0, 2.0], "a");
+        records.push(&[3.0, 4.0], "b");
+
+        let loaded = crate::store::ScaledRecords {
+            columns: vec!["x".to_owned(), "y".to_owned()],
+            label: "class".to_owned(),
+            scale: ScaleType::None,
+            scaler: Scaler::fit(ScaleType::None, records.iter().map(|r| r.data), 2),
+            records,
+        };
+        let model = Model {
+            version: MODEL_VERSION,
+            k: 1,
+            task: TaskType::Classification,
+            stored: StoredIndex::build(loaded, AlgoType::Euclidean, &IndexType::KdTree.into())
+                .unwrap(),
+        };
+
+        let path = std::env::temp_dir().join(format!("knn_model_{}.knn", std::process::id()));
+        model.save(&path).unwrap();
+
+        let saved = Model::load(&path).unwrap();
+
+        assert_eq!(saved.k, 1);
+        assert_eq!(saved.stored.columns, ["x", "y"]);
+        assert_eq!(saved.stored.index.nearest(&[2.9, 4.0], 1)[0].1, 1);
+        assert_eq!(
+            saved.predict(&[2.9, 4.0]).unwrap().outcome,
+            crate::results::Outcome::Classification {
+                groups: vec![crate::results::LabelGroup {
+                    label: "b".to_owned(),
+                    count: 1,
+                    fraction: 1.0,
+                }],
+            }
+        );
+
+        let mut saved = saved;
+        saved
+            .append([([9.0, 9.0].as_slice(), "c"), ([1.0, 2.0].as_slice(), "a")])
+            .unwrap();
+
+        assert_eq!(saved.stored.labels, [0, 1, 2, 0]);
+        assert_eq!(saved.stored.label_names, ["a", "b", "c"]);
+        assert_eq!(saved.predict(&[8.0, 8.0]).unwrap().label().unwrap(), "c");
+        assert!(saved.append([([1.0].as_slice(), "d")]).is_err());
+        assert_eq!(saved.stored.labels.len(), 4);
+
+        // version 1 files did not have the version of the stored index
+        let mut legacy = serde_json::to_value(&model).unwrap();
+        legacy["version"] = 1.into();
+        legacy["stored"].as_object_mut().unwrap().remove("version");
+        std::fs::write(&path, legacy.to_string()).unwrap();
+
+        let upgraded = Model::load(&path).unwrap();
+
+        assert_eq!(upgraded.version, MODEL_VERSION);
+        assert_eq!(upgraded.stored.version, crate::store::INDEX_VERSION);
+
+        let mut future = model;
+        future.version = MODEL_VERSION + 1;
+        future.save(&path).unwrap();
+
+        let result = Model::load(&path);
+
+        std::fs::remove_file(&path).unwrap();
+
+        assert!(result.is_err());
+    }
+}
@@ -0,0 +1,241 @@
+//! listing the closest records to a datapoint
+//!
+//! unlike predict the labels of the neighbors are not counted. every
+//! neighbor is listed with its row in the csv, its distance, and its label so
+//! the records behind a prediction can be inspected directly.
+use std::path::PathBuf;
+
+use anyhow::bail;
+use clap::Args;
+use clap::builder::RangedU64ValueParser;
+
+use crate::classify::sort_closest;
+use crate::cli::{AlgoType, ColumnList, ColumnType, DatapointArg, OutputFormat};
+use crate::csv::{Dataset, collect_records, get_columns_excluding};
+use crate::predict::{load_datapoints, scaled_queries};
+use crate::results::{Neighbor, NeighborList, Versioned};
+use crate::scale::{ScaleType, Scaler};
+use crate::source::DataSource;
+
+#[derive(Debug, Args)]
+pub struct NeighborsArgs {
+    /// the number of neighbors to list
+    #[arg(
+        short,
+        default_value = "5",
+        value_parser = RangedU64ValueParser::<usize>::new().range(1..)
+    )]
+    k: usize,
+
+    /// specifies the algorithm to use when calculating distances
+    #[arg(long, default_value = "euclidean")]
+    algo: AlgoType,
+
+    /// the list of columns to calculate distances with
+    #[arg(short, long = "col", allow_hyphen_values = true)]
+    columns: Vec<ColumnList>,
+
+    /// columns to remove from the list of columns
+    #[arg(long = "exclude-col", allow_hyphen_values = true)]
+    exclude: Vec<ColumnList>,
+
+    /// the column to use as the label
+    #[arg(long, allow_negative_numbers = true)]
+    label: Option<ColumnType>,
+
+    /// a datapoint to list the neighbors of. accepts the same values as
+    /// predict and can be specified multiple times
+    #[arg(long, required_unless_present_any = ["datapoint_file", "row"])]
+    datapoint: Vec<DatapointArg>,
+
+    /// reads the datapoints from a csv the same as predict
+    #[arg(long, conflicts_with = "datapoint")]
+    datapoint_file: Option<PathBuf>,
+
+    /// a comma delimitered list of zero based rows of the csv to list the
+    /// neighbors of. the row itself is not listed
+    #[arg(
+        long,
+        value_delimiter = ',',
+        conflicts_with_all = ["datapoint", "datapoint_file"]
+    )]
+    row: Vec<usize>,
+
+    /// the scaling method to apply to the columns before calculating
+    /// distances
+    #[arg(long, default_value = "none")]
+    scale: ScaleType,
+
+    /// how the neighbors are printed
+    #[arg(long, default_value = "table")]
+    format: OutputFormat,
+}
+
+/// finds the closest `k` records to the datapoint as the distance, row, and
+/// label id of each ordered from closest to furthest
+///
+/// the record at `skip` is left out so that a row is not its own neighbor
+pub fn nearest(
+    records: &Dataset,
+    k: usize,
+    algo: AlgoType,
+    datapoint: &[f64],
+    skip: Option<usize>,
+) -> Vec<(f64, usize, u32)> {
+    let distance = algo.as_fn();
+    let mut closest: Vec<(f64, usize, u32)> = records
+        .iter()
+        .enumerate()
+        .filter(|(index, _)| Some(*index) != skip)
+        .map(|(index, record)| (distance(record.data, datapoint), index, record.label))
+        .collect();
+
+    sort_closest(&mut closest, k);
+    closest.truncate(k);
+
+    closest
+}
+
+pub fn knn_neighbors<S>(mut source: S, arg: NeighborsArgs) -> anyhow::Result<()>
+where
+    S: DataSource,
+{
+    let label = &ColumnType::label_or_last(arg.label.as_ref());
+    let (label, columns) = get_columns_excluding(
+        &mut source,
+        label,
+        &ColumnList::flatten(&arg.columns),
+        &ColumnList::flatten(&arg.exclude),
+    )?;
+    let names = source.headers()?.map(|headers| {
+        columns
+            .iter()
+            .map(|col| headers[*col].clone())
+            .collect::<Vec<_>>()
+    });
+    let datapoints = if arg.row.is_empty() {
+        Some(load_datapoints(
+            &arg.datapoint,
+            arg.datapoint_file.as_deref(),
+            names.as_deref(),
+        )?)
+    } else {
+        None
+    };
+    let mismatch = "number of datapoints does not match number of columns";
+
+    if let Some(datapoints) = &datapoints {
+        datapoints.check(columns.len(), mismatch)?;
+    }
+
+    let records = collect_records(source, label, &columns)?;
+    let scaler = Scaler::fit(
+        arg.scale,
+        records.iter().map(|record| record.data),
+        columns.len(),
+    );
+    let scaled = scaler.apply_records(&records);
+
+    let list = |datapoint: Vec<f64>, query: &[f64], row: Option<usize>| NeighborList {
+        neighbors: nearest(&scaled, arg.k, arg.algo, query, row)
+            .into_iter()
+            .map(|(distance, row, label)| Neighbor {
+                row,
+                distance,
+                label: records.label_name(label).to_owned(),
+            })
+            .collect(),
+        datapoint,
+        row,
+    };
+
+    match datapoints {
+        Some(datapoints) => {
+            for query in scaled_queries(datapoints.read(columns.len(), mismatch), &scaler) {
+                let (datapoint, query) = query?;
+
+                print_neighbors(&list(datapoint, &query, None), arg.format);
+            }
+        }
+        None => {
+            for row in &arg.row {
+                if *row >= records.len() {
+                    bail!("row is out of range. row: {row} records: {}", records.len());
+                }
+
+                let datapoint = records.get(*row).data.to_vec();
+
+                print_neighbors(
+                    &list(datapoint, scaled.get(*row).data, Some(*row)),
+                    arg.format,
+                );
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn print_neighbors(list: &NeighborList, format: OutputFormat) {
+    if format == OutputFormat::Json {
+        println!(
+            "{}",
+            serde_json::to_string(&Versioned::new(list)).expect("neighbors serialize to json")
+        );
+
+        return;
+    }
+
+    print!("datapoint:");
+
+    for value in &list.datapoint {
+        print!(" {value}");
+    }
+
+    match list.row {
+        Some(row) => println!(" | row: {row}"),
+        None => println!(),
+    }
+
+    let width = list
+        .neighbors
+        .iter()
+        .map(|neighbor| neighbor.row.to_string().len())
+        .max()
+        .unwrap_or(0)
+        .max(3);
+
+    println!("  {:>width$} {:>10} label", "row", "distance");
+
+    for neighbor in &list.neighbors {
+        println!(
+            "  {:>width$} {:>10.4} {}",
+            neighbor.row, neighbor.distance, neighbor.label
+        );
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn nearest_rows() {
+        let mut records = Dataset::new(1);
+
+        for (value, label) in [(0.0, "a"), (4.0, "b"), (1.0, "a"), (2.0, "b")] {
+            records.push(&[value], label);
+        }
+
+        let found = nearest(&records, 2, AlgoType::Euclidean, &[1.0], Some(2));
+
+        assert_eq!(found, vec![(1.0, 0, 0), (1.0, 3, 1)]);
+
+        let found = nearest(&records, 10, AlgoType::Manhattan, &[4.5], None);
+
+        assert_eq!(
+            found.iter().map(|(_, row, _)| *row).collect::<Vec<_>>(),
+            vec![1, 3, 2, 0]
+        );
+    }
+}
@@ -0,0 +1,307 @@
+//! product quantization for compressing the records
+//!
+//! the columns are split into subspaces and the values of each subspace are
+//! clustered into at most 256 centroids. a record is then stored as the id of
+//! the closest centroid in every subspace which only needs a single byte per
+//! subspace instead of 8 bytes per column.
+//!
+//! distances are asymmetric. the datapoint being searched for is never
+//! compressed and the distance from each of its subspaces to every centroid is
+//! calculated once. the distance to a record is then the sum of the table
+//! entries for its codes which has the same ordering as
+//! [`AlgoType::as_rank_fn`] when the centroids match the records exactly.
+use rand::SeedableRng;
+use rand::rngs::StdRng;
+use rand::seq::index::sample;
+
+use crate::classify::ClosestRecords;
+use crate::cli::AlgoType;
+
+/// the maximum number of centroids in each subspace so that a code fits in a
+/// single byte
+const MAX_CENTROIDS: usize = 256;
+
+/// the number of k-means iterations used to train the centroids
+const ITERATIONS: usize = 10;
+
+/// the maximum number of records used to train the centroids
+const TRAIN_SAMPLE: usize = 10_000;
+
+/// the seed used to pick the training records and initial centroids so the
+/// same codes are created every time
+const TRAIN_SEED: u64 = 0;
+
+/// the trained centroids of every subspace
+#[derive(Debug, Clone)]
+pub struct ProductQuantizer {
+    /// the first column of each subspace followed by the end of the last
+    bounds: Vec<usize>,
+    centroids: usize,
+    /// the centroids of each subspace stored one after another. every
+    /// subspace has `centroids` centroids with as many values as its columns
+    codebooks: Vec<Vec<f64>>,
+}
+
+impl ProductQuantizer {
+    /// trains the centroids with k-means on a sample of the records
+    ///
+    /// the columns are split into `subspaces` groups of nearly equal size.
+    /// `subspaces` is limited to the number of columns
+    pub fn fit<'a, I>(records: I, dims: usize, subspaces: usize) -> Self
+    where
+        I: Iterator<Item = &'a [f64]>,
+    {
+        let records: Vec<&[f64]> = records.collect();
+        let subspaces = subspaces.clamp(1, dims.max(1));
+        let bounds: Vec<usize> = (0..=subspaces)
+            .map(|subspace| subspace * dims / subspaces)
+            .collect();
+
+        let mut rng = StdRng::seed_from_u64(TRAIN_SEED);
+        let training: Vec<&[f64]> = if records.len() > TRAIN_SAMPLE {
+            sample(&mut rng, records.len(), TRAIN_SAMPLE)
+                .into_iter()
+                .map(|index| records[index])
+                .collect()
+        } else {
+            records
+        };
+        let centroids = std::cmp::min(MAX_CENTROIDS, training.len()).max(1);
+
+        let codebooks = bounds
+            .windows(2)
+            .map(|range| {
+                let values: Vec<&[f64]> = training
+                    .iter()
+                    .map(|record| &record[range[0]..range[1]])
+                    .collect();
+
+                kmeans(&values, range[1] - range[0], centroids, &mut rng)
+            })
+            .collect();
+
+        Self {
+            bounds,
+            centroids,
+            codebooks,
+        }
+    }
+
+    /// the number of codes used for each record
+    pub fn subspaces(&self) -> usize {
+        self.codebooks.len()
+    }
+
+    /// appends the codes of the closest centroid in each subspace
+    pub fn encode(&self, data: &[f64], codes: &mut Vec<u8>) {
+        for (range, codebook) in self.bounds.windows(2).zip(&self.codebooks) {
+            let values = &data[range[0]..range[1]];
+
+            codes.push(closest_centroid(codebook, values) as u8);
+        }
+    }
+
+    /// calculates the distance from every subspace of the datapoint to each
+    /// of the centroids
+    pub fn table(&self, datapoint: &[f64], algo: AlgoType) -> DistanceTable {
+        let term = algo.as_term_fn();
+        let mut distances = Vec::with_capacity(self.subspaces() * self.centroids);
+
+        for (range, codebook) in self.bounds.windows(2).zip(&self.codebooks) {
+            let values = &datapoint[range[0]..range[1]];
+
+            for centroid in codebook.chunks_exact(values.len().max(1)) {
+                distances.push(
+                    values
+                        .iter()
+                        .zip(centroid)
+                        .map(|(a, b)| term(*a, *b))
+                        .sum::<f64>(),
+                );
+            }
+        }
+
+        DistanceTable {
+            centroids: self.centroids,
+            distances,
+        }
+    }
+}
+
+/// the distances from a datapoint to every centroid of each subspace
+pub struct DistanceTable {
+    centroids: usize,
+    distances: Vec<f64>,
+}
+
+impl DistanceTable {
+    /// the distance from the datapoint to the record with the given codes
+    pub fn distance(&self, codes: &[u8]) -> f64 {
+        codes
+            .iter()
+            .enumerate()
+            .map(|(subspace, code)| self.distances[subspace * self.centroids + *code as usize])
+            .sum()
+    }
+}
+
+/// the records stored as product quantized codes
+pub struct QuantizedRecords {
+    pub quantizer: ProductQuantizer,
+    /// the codes of every record stored one after another
+    pub codes: Vec<u8>,
+    pub labels: Vec<u32>,
+}
+
+impl QuantizedRecords {
+    /// encodes every record with the quantizer
+    pub fn encode<'a, I>(quantizer: ProductQuantizer, records: I) -> Self
+    where
+        I: Iterator<Item = (&'a [f64], u32)>,
+    {
+        let mut codes = Vec::new();
+        let mut labels = Vec::new();
+
+        for (data, label) in records {
+            quantizer.encode(data, &mut codes);
+            labels.push(label);
+        }
+
+        Self {
+            quantizer,
+            codes,
+            labels,
+        }
+    }
+
+    /// the (codes, label) pairs of every record
+    pub fn iter(&self) -> impl Iterator<Item = (&[u8], u32)> {
+        self.codes
+            .chunks_exact(self.quantizer.subspaces())
+            .zip(self.labels.iter().copied())
+    }
+
+    /// finds the label ids of the closest `k` records to the datapoint
+    /// ordered from closest to furthest
+    pub fn closest_labels(&self, k: usize, algo: AlgoType, datapoint: &[f64]) -> Vec<u32> {
+        let table = self.quantizer.table(datapoint, algo);
+        let mut closest = ClosestRecords::new(k);
+
+        for (position, (codes, label)) in self.iter().enumerate() {
+            closest.push(table.distance(codes), position, label);
+        }
+
+        closest.into_labels()
+    }
+}
+
+/// clusters the values into `centroids` groups and returns the centroids
+/// stored one after another
+///
+/// the initial centroids are picked from the values. a centroid that loses
+/// all of its values keeps its previous position
+fn kmeans(values: &[&[f64]], dims: usize, centroids: usize, rng: &mut StdRng) -> Vec<f64> {
+    let mut rtn = vec![0.0; centroids * dims];
+
+    if values.is_empty() || dims == 0 {
+        return rtn;
+    }
+
+    for (centroid, index) in rtn
+        .chunks_exact_mut(dims)
+        .zip(sample(rng, values.len(), centroids))
+    {
+        centroid.copy_from_slice(values[index]);
+    }
+
+    let mut assigned = vec![0; values.len()];
+
+    for _ in 0..ITERATIONS {
+        for (value, assigned) in values.iter().zip(assigned.iter_mut()) {
+            *assigned = closest_centroid(&rtn, value);
+        }
+
+        let mut totals = vec![0.0; centroids * dims];
+        let mut counts = vec![0usize; centroids];
+
+        for (value, assigned) in values.iter().zip(&assigned) {
+            counts[*assigned] += 1;
+
+            for (total, value) in totals[*assigned * dims..].iter_mut().zip(*value) {
+                *total += value;
+            }
+        }
+
+        for ((centroid, total), count) in rtn
+            .chunks_exact_mut(dims)
+            .zip(totals.chunks_exact(dims))
+            .zip(counts)
+        {
+            if count > 0 {
+                for (value, total) in centroid.iter_mut().zip(total) {
+                    *value = total / count as f64;
+                }
+            }
+        }
+    }
+
+    rtn
+}
+
+/// finds the index of the centroid closest to the values by squared
+/// euclidean distance
+fn closest_centroid(codebook: &[f64], values: &[f64]) -> usize {
+    let mut rtn = 0;
+    let mut best = f64::INFINITY;
+
+    for (index, centroid) in codebook.chunks_exact(values.len().max(1)).enumerate() {
+        let distance: f64 = values
+            .iter()
+            .zip(centroid)
+            .map(|(a, b)| (a - b) * (a - b))
+            .sum();
+
+        if distance < best {
+            best = distance;
+            rtn = index;
+        }
+    }
+
+    rtn
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn exact_with_few_records() {
+        let records: Vec<[f64; 4]> = vec![
+            [0.0, 1.0, 2.0, 3.0],
+            [4.0, 5.0, 6.0, 7.0],
+            [1.0, 1.0, 1.0, 1.0],
+            [9.0, 0.0, 3.0, 2.0],
+        ];
+        let quantizer = ProductQuantizer::fit(records.iter().map(|record| record.as_slice()), 4, 2);
+        let quantized = QuantizedRecords::encode(
+            quantizer,
+            records.iter().map(|record| (record.as_slice(), 0)),
+        );
+
+        assert_eq!(quantized.codes.len(), records.len() * 2);
+
+        // every value is its own centroid so the distances are exact
+        let datapoint = [2.0, 3.0, 0.5, 1.5];
+
+        for algo in [AlgoType::Euclidean, AlgoType::Manhattan] {
+            let table = quantized.quantizer.table(&datapoint, algo);
+
+            for ((codes, _), record) in quantized.iter().zip(&records) {
+                assert_eq!(
+                    table.distance(codes),
+                    (algo.as_rank_fn())(&datapoint, record),
+                );
+            }
+        }
+    }
+}
@@ -1,82 +1,1193 @@
-use anyhow::bail;
+use std::fs::File;
+use std::io::{BufReader, Read};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use anyhow::{Context, bail};
 use clap::Args;
+use clap::builder::RangedU64ValueParser;
+use rayon::prelude::*;
 
-use crate::classify::classify_datapoint_owned;
-use crate::cli::{AlgoType, ColumnType, Datapoint, KValue};
-use crate::csv::{Reader, collect_records, get_columns_and_label};
-use crate::distance;
+use crate::balance::{balance_dataset, extend_smote};
+use crate::classify::{
+    ClosestRecords, closest_labels, closest_labels_par, count_labels, labels_within, sort_closest,
+};
+use crate::cli::{
+    AlgoType, Balance, ColumnList, ColumnType, Datapoint, DatapointArg, Device, KSelection,
+    Precision, RadiusFallback, TaskType,
+};
+use crate::csv::{
+    Dataset, KnnRecord, ReaderBuilder, check_numeric_labels, collect_records, column_names,
+    get_columns_excluding, require_csv, stream_records,
+};
+use crate::distance::{DistanceMetric, narrow};
+use crate::dry_run::DryRun;
+use crate::evaluate::cross_validate;
+use crate::gpu;
+use crate::index::{IndexArgs, IndexType, NeighborIndex, recall};
+use crate::model::Model;
+use crate::pq::{ProductQuantizer, QuantizedRecords};
+use crate::progress::NoProgress;
+use crate::results::Prediction;
+use crate::scale::{ScaleType, Scaler};
+use crate::source::DataSource;
+use crate::store::StoredIndex;
 
-#[derive(Debug, Args)]
+#[derive(Debug, Clone, Args)]
 pub struct PredictArgs {
-    /// the number of neighbors to lookup
-    #[arg(short, default_value = "3")]
-    k: KValue,
+    /// the number of neighbors to lookup. specifying `auto` will cross
+    /// validate a range of k values with the loaded records and use the best.
+    /// defaults to 3 unless a radius is given
+    #[arg(short, long)]
+    k: Option<KSelection>,
+
+    /// uses every record within the distance of the datapoint as its
+    /// neighbors. the distance is measured after scaling. when a k value is
+    /// also given at most k of the closest records within the radius are
+    /// used. only available with the brute index and f64 precision
+    #[arg(
+        long,
+        conflicts_with_all = ["index_file", "model", "stream", "precision", "device"]
+    )]
+    radius: Option<f64>,
+
+    /// what to predict when no records are within the radius
+    #[arg(long, default_value = "nearest", requires = "radius")]
+    radius_fallback: RadiusFallback,
+
+    /// skips even k values to avoid ties between two labels
+    #[arg(long)]
+    odd_only: bool,
 
     /// specifies the algorithm to use when calculating distances
     #[arg(long, default_value = "euclidean")]
     algo: AlgoType,
 
     /// the list of columns to use as datapoints
-    #[arg(short, long = "col")]
-    columns: Vec<ColumnType>,
+    #[arg(short, long = "col", allow_hyphen_values = true)]
+    columns: Vec<ColumnList>,
+
+    /// columns to remove from the list of columns
+    #[arg(long = "exclude-col", allow_hyphen_values = true)]
+    exclude: Vec<ColumnList>,
 
     /// the column to use as the label
-    #[arg(long)]
-    label: ColumnType,
+    #[arg(long, allow_negative_numbers = true)]
+    label: Option<ColumnType>,
+
+    /// a comma delimitered list of numbers to estimate its group for. can be
+    /// specified multiple times to predict several datapoints with the same
+    /// loaded records. `-` reads a datapoint from each line of stdin and
+    /// predicts it once it is read. values can also be bound to columns by
+    /// name with `name=value`. named values given across several arguments
+    /// are combined into one datapoint until a column is given again
+    #[arg(long, required_unless_present = "datapoint_file")]
+    datapoint: Vec<DatapointArg>,
+
+    /// reads the datapoints from a csv. if the csv has a header row then
+    /// its columns are matched to the columns of the records by name,
+    /// otherwise they are expected in the same order
+    #[arg(long, conflicts_with = "datapoint")]
+    datapoint_file: Option<PathBuf>,
+
+    /// the scaling method to apply to the columns
+    #[arg(long, default_value = "none")]
+    scale: ScaleType,
+
+    /// the type of task to perform with the labels
+    #[arg(long, default_value = "classification")]
+    task: TaskType,
+
+    #[command(flatten)]
+    index: IndexArgs,
+
+    /// loads the records from an index file created by `index build`
+    /// instead of the csv. the columns, algorithm, scaling, and index are
+    /// the ones the file was built with
+    #[arg(long, conflicts_with_all = ["columns", "label", "precision"])]
+    index_file: Option<PathBuf>,
+
+    /// loads a model file created by `fit` instead of the csv. the k value
+    /// and task are also the ones the model was fitted with
+    #[arg(
+        long,
+        conflicts_with_all = ["columns", "label", "precision", "index_file", "k", "task"]
+    )]
+    model: Option<PathBuf>,
+
+    /// the precision used to store the records and calculate distances. f32
+    /// and pq are only available with the brute index
+    #[arg(long, default_value = "f64")]
+    precision: Precision,
+
+    /// the number of subspaces each record is split into with pq precision.
+    /// defaults to one subspace for every 2 columns
+    #[arg(long, value_parser = RangedU64ValueParser::<usize>::new().range(1..))]
+    pq_subspaces: Option<usize>,
+
+    /// the device used to calculate distances. the gpu always uses f32 and
+    /// is only available with the brute index
+    #[arg(long, default_value = "cpu", conflicts_with_all = ["index_file", "model"])]
+    device: Device,
+
+    /// reads the csv in blocks instead of loading every record so that
+    /// files larger than memory can be used. only available with the brute
+    /// index, no scaling, and a given k value
+    #[arg(long, conflicts_with_all = ["index_file", "model", "precision", "device"])]
+    stream: bool,
 
-    /// a comma delimitered list of numbers to estimate its group for
+    /// prints the resolved columns, label, scaling parameters, k values, and
+    /// record counts without predicting anything
+    #[arg(long, conflicts_with_all = ["index_file", "model", "stream"])]
+    dry_run: bool,
+
+    /// balances the labels of the records before predicting. undersample
+    /// randomly drops records of the larger labels using the seed
+    #[arg(
+        long,
+        default_value = "none",
+        conflicts_with_all = ["index_file", "model", "stream"]
+    )]
+    balance: Balance,
+
+    /// the seed used to balance the records. a random seed is used if not
+    /// specified
     #[arg(long)]
-    datapoint: Datapoint,
+    seed: Option<u64>,
+}
+
+impl PredictArgs {
+    /// the given k value or the default
+    fn k(&self) -> KSelection {
+        self.k.clone().unwrap_or_default()
+    }
+
+    /// the files other than the csv that the prediction reads
+    pub fn input_files(&self) -> impl Iterator<Item = &PathBuf> {
+        [&self.datapoint_file, &self.index_file, &self.model]
+            .into_iter()
+            .flatten()
+    }
+}
+
+/// the number of records read from the csv at a time when streaming
+const STREAM_BLOCK: usize = 4096;
+
+/// balances the records before the scaling is fitted. smote is left until
+/// the records are scaled so the records it creates are placed by the same
+/// distances that predict with and do not change the scaling
+fn balance_unscaled(records: Dataset, arg: &PredictArgs, seed: u64) -> Dataset {
+    match arg.balance {
+        Balance::Smote => records,
+        balance => balance_dataset(records, balance, arg.algo, seed),
+    }
 }
 
-pub fn knn_predict<R>(mut reader: Reader<R>, arg: PredictArgs) -> anyhow::Result<()>
+pub fn knn_predict<S>(source: Option<S>, mut arg: PredictArgs) -> anyhow::Result<()>
 where
-    R: std::io::Read,
+    S: DataSource,
 {
-    if arg.columns.is_empty() {
-        bail!("no columns specified to pull numeric data from");
+    if let KSelection::Value(k) = arg.k() {
+        k.check_odd_only(arg.odd_only)?;
     }
 
-    // store a reference to the distance algorithm
-    let algo = match arg.algo {
-        AlgoType::Euclidean => distance::euclidean,
-        AlgoType::Manhattan => distance::manhattan,
-    };
+    if let Some(path) = arg.index_file.take() {
+        if source.is_some() {
+            bail!("a csv file cannot be used with an index file");
+        }
 
-    // retrieve the label and datapoint columns from the csv reader
-    let (label, columns) = get_columns_and_label(&mut reader, &arg.label, &arg.columns)?;
-    // parse the provided datapoint to estimate. will expect a similar amount of
-    // numbers as the provided number of columns
-    let datapoint = arg.datapoint.into_inner();
+        return predict_stored(&path, arg);
+    }
 
-    if datapoint.len() != columns.len() {
-        bail!("number of datapoints does not match number of columns");
+    if let Some(path) = arg.model.take() {
+        if source.is_some() {
+            bail!("a csv file cannot be used with a model file");
+        }
+
+        let model = Model::load(&path)?;
+        let datapoints = load_datapoints(
+            &arg.datapoint,
+            arg.datapoint_file.as_deref(),
+            Some(&model.stored.columns),
+        )?;
+
+        return predict_indexed(&model.stored, datapoints, &[model.k], model.task);
     }
 
-    let records = collect_records(reader, label, &columns)?;
+    let mut source = require_csv(source)?;
+
+    let label = &ColumnType::label_or_last(arg.label.as_ref());
 
-    // k will be the min of the specified high value or the total number of
-    // records
-    for k in arg.k.get_range(records.len()) {
-        let iter = records
+    if arg.precision != Precision::F64 && arg.index.kind != IndexType::Brute {
+        bail!("f32 and pq precision are only available with the brute index");
+    }
+
+    if arg.device == Device::Gpu && arg.index.kind != IndexType::Brute {
+        bail!("the gpu is only available with the brute index");
+    }
+
+    // store a reference to the distance algorithm. only the order of the
+    // neighbors is needed so the ranking distance is used
+    let algo = arg.algo.as_rank_fn();
+
+    // retrieve the label and datapoint columns from the source
+    let (label, columns) = get_columns_excluding(
+        &mut source,
+        label,
+        &ColumnList::flatten(&arg.columns),
+        &ColumnList::flatten(&arg.exclude),
+    )?;
+    // parse the provided datapoints to estimate. will expect a similar amount
+    // of numbers as the provided number of columns
+    let names = source.headers()?.map(|headers| {
+        columns
             .iter()
-            .map(|record| (&record.data, record.label.as_str()));
+            .map(|col| headers[*col].clone())
+            .collect::<Vec<_>>()
+    });
+    let datapoints = load_datapoints(
+        &arg.datapoint,
+        arg.datapoint_file.as_deref(),
+        names.as_deref(),
+    )?;
+    let mismatch = "number of datapoints does not match number of columns";
 
-        let (min, groups) = classify_datapoint_owned(k, iter, algo, &datapoint);
+    datapoints.check(columns.len(), mismatch)?;
 
-        print!("k value: {k} |");
+    if let Some(radius) = arg.radius {
+        if !radius.is_finite() || radius < 0.0 {
+            bail!("radius must be a positive number or 0");
+        }
 
-        for v in &datapoint {
-            print!(" {v}");
+        if arg.index.kind != IndexType::Brute {
+            bail!("a radius is only available with the brute index");
         }
+    }
+
+    if arg.balance != Balance::None && arg.task != TaskType::Classification {
+        bail!("balancing is only available for classification");
+    }
 
-        println!();
+    let seed = arg.seed.unwrap_or_else(rand::random);
 
-        for (key, count) in groups {
-            // print the calculated percentage for each group found
-            println!("  {key}: {count} {:.2}", (count as f64) / (min as f64));
+    if arg.dry_run {
+        let names = column_names(&mut source, &[columns.as_slice(), &[label]].concat())?;
+        let records = balance_unscaled(collect_records(source, label, &columns)?, &arg, seed);
+        let fitted: Vec<KnnRecord> = records.iter().collect();
+        // only the number of records created by smote is shown so they can
+        // be created before scaling
+        let mut balanced = records.clone();
+
+        if arg.balance == Balance::Smote {
+            extend_smote(&mut balanced, arg.algo, seed);
         }
+
+        let k = arg.k();
+        let k_values = match &k {
+            KSelection::Value(k) => std::slice::from_ref(k),
+            KSelection::Auto => &[],
+        };
+
+        println!("datapoints: {}", datapoints.known().len());
+        print!(
+            "{}",
+            DryRun {
+                label,
+                columns: &columns,
+                names: &names,
+                records: &balanced,
+                fitted: &fitted,
+                scales: &[arg.scale],
+                k_values,
+                odd_only: arg.odd_only,
+            }
+        );
+
+        return Ok(());
+    }
+
+    if arg.stream {
+        let datapoints = datapoints
+            .read(columns.len(), mismatch)
+            .collect::<anyhow::Result<Vec<_>>>()?;
+
+        return predict_streamed(source, label, &columns, &arg, &datapoints);
+    }
+
+    if arg.balance != Balance::None {
+        println!("balance seed: {seed}");
+    }
+
+    let records = balance_unscaled(collect_records(source, label, &columns)?, &arg, seed);
+
+    // the scaling parameters are fitted on all the records and then applied
+    // to the records and the datapoints
+    let scaler = Scaler::fit(
+        arg.scale,
+        records.iter().map(|record| record.data),
+        columns.len(),
+    );
+    let mut records = scaler.apply_records(&records);
+
+    if arg.balance == Balance::Smote {
+        extend_smote(&mut records, arg.algo, seed);
+    }
+
+    let ks: Vec<usize> = match arg.k() {
+        // k will be the min of the specified high value or the total number
+        // of records
+        KSelection::Value(k) => k.get_range(records.len(), arg.odd_only).collect(),
+        KSelection::Auto => {
+            if arg.task == TaskType::Regression {
+                check_numeric_labels(&records)?;
+            }
+
+            let k = auto_k(&records, &arg.algo, arg.task, arg.odd_only)?;
+
+            println!("auto k: {k}");
+
+            vec![k]
+        }
+    };
+
+    if let Some(radius) = arg.radius {
+        // a k value is only a limit when it was given
+        let limit = arg.k.as_ref().map(|_| ks.as_slice());
+
+        for query in scaled_queries(datapoints.read(columns.len(), mismatch), &scaler) {
+            let (datapoint, scaled) = query?;
+
+            predict_radius(&records, radius, limit, &arg, &datapoint, &scaled)?;
+        }
+
+        return Ok(());
+    }
+
+    // the neighbors of the largest k are found once and the groups of every
+    // smaller k are counted from the closest of them
+    let max_k = ks.last().copied().unwrap_or(0);
+
+    // brute force does not need an index to be built
+    let index = match arg.index.kind {
+        IndexType::Brute => None,
+        _ => Some(
+            arg.index.build(
+                columns.len(),
+                records
+                    .iter()
+                    .enumerate()
+                    .map(|(index, record)| (index, record.data)),
+                arg.algo,
+            )?,
+        ),
+    };
+
+    // approximate indexes are checked against brute force so the results
+    // can be trusted. only the datapoints known ahead of time are checked
+    if let Some(index) = &index
+        && !arg.index.kind.is_exact()
+    {
+        let known = scale_datapoints(&scaler, datapoints.known());
+        let (found, expected) = recall(
+            index,
+            columns.len(),
+            records
+                .iter()
+                .enumerate()
+                .map(|(index, record)| (index, record.data)),
+            arg.algo,
+            known.iter().map(Vec::as_slice),
+            max_k,
+        );
+
+        if expected > 0 {
+            println!("recall: {:.4}", found as f64 / expected as f64);
+        }
+    }
+
+    // the original datapoints are kept for output
+    let mut queries = scaled_queries(datapoints.read(columns.len(), mismatch), &scaler);
+
+    if arg.device == Device::Gpu {
+        for query in queries.by_ref() {
+            let (datapoint, scaled) = query?;
+
+            match closest_records_gpu(max_k, &records, arg.algo, &scaled) {
+                Ok(labels) => {
+                    print_predictions(&ks, &datapoint, arg.task, &labels, records.label_names())?;
+                }
+                Err(err) => {
+                    println!("gpu unavailable, using the cpu: {err:#}");
+
+                    // the datapoint that failed is predicted with the rest on
+                    // the cpu
+                    queries = Box::new(std::iter::once(Ok((datapoint, scaled))).chain(queries));
+
+                    break;
+                }
+            }
+        }
+    }
+
+    if arg.precision == Precision::Pq {
+        let subspaces = arg.pq_subspaces.unwrap_or(columns.len().div_ceil(2));
+        let quantizer = ProductQuantizer::fit(
+            records.iter().map(|record| record.data),
+            columns.len(),
+            subspaces,
+        );
+        // the f64 records are dropped once they are encoded
+        let quantized = QuantizedRecords::encode(
+            quantizer,
+            records.iter().map(|record| (record.data, record.label)),
+        );
+        let (_, names) = records.into_labels();
+
+        for query in queries {
+            let (datapoint, scaled) = query?;
+            let labels = quantized.closest_labels(max_k, arg.algo, &scaled);
+
+            print_predictions(&ks, &datapoint, arg.task, &labels, &names)?;
+        }
+
+        return Ok(());
+    }
+
+    if arg.precision == Precision::F32 {
+        // the f64 records are dropped so that only the f32 copies are kept
+        let data: Vec<Vec<f32>> = records.iter().map(|record| narrow(record.data)).collect();
+        let (labels, names) = records.into_labels();
+        let records: Vec<(Vec<f32>, u32)> = data.into_iter().zip(labels).collect();
+
+        for query in queries {
+            let (datapoint, scaled) = query?;
+            let labels = closest_records(
+                max_k,
+                records.len(),
+                |index| (records[index].0.as_slice(), records[index].1),
+                arg.algo.as_rank_f32_fn(),
+                &narrow(&scaled),
+            );
+
+            print_predictions(&ks, &datapoint, arg.task, &labels, &names)?;
+        }
+
+        return Ok(());
+    }
+
+    for query in queries {
+        let (datapoint, scaled) = query?;
+        let labels: Vec<u32> = if let Some(index) = &index {
+            index
+                .nearest(&scaled, max_k)
+                .iter()
+                .map(|(_, index)| records.get(*index).label)
+                .collect()
+        } else {
+            closest_records(
+                max_k,
+                records.len(),
+                |index| {
+                    let record = records.get(index);
+
+                    (record.data, record.label)
+                },
+                algo,
+                &scaled,
+            )
+        };
+
+        print_predictions(&ks, &datapoint, arg.task, &labels, records.label_names())?;
     }
 
     Ok(())
 }
+
+/// the datapoints to predict
+pub(crate) enum Datapoints {
+    /// given as arguments or read from the datapoint file
+    Given(Vec<Vec<f64>>),
+    /// read from stdin a line at a time
+    Stdin,
+}
+
+/// the datapoints to predict as they are read
+pub(crate) type DatapointIter<'a, T> = Box<dyn Iterator<Item = anyhow::Result<T>> + 'a>;
+
+impl Datapoints {
+    /// the datapoints that are known before predicting
+    pub(crate) fn known(&self) -> &[Vec<f64>] {
+        match self {
+            Self::Given(datapoints) => datapoints,
+            Self::Stdin => &[],
+        }
+    }
+
+    /// checks that the known datapoints have `dims` values so that the
+    /// records are not loaded for nothing
+    pub(crate) fn check(&self, dims: usize, mismatch: &str) -> anyhow::Result<()> {
+        if self.known().iter().any(|datapoint| datapoint.len() != dims) {
+            bail!("{mismatch}");
+        }
+
+        Ok(())
+    }
+
+    /// iterates the datapoints. a line of stdin is only read once the next
+    /// datapoint is needed so datapoints can be predicted as they arrive.
+    /// blank lines are skipped
+    pub(crate) fn read<'a>(self, dims: usize, mismatch: &'a str) -> DatapointIter<'a, Vec<f64>> {
+        match self {
+            Self::Given(datapoints) => Box::new(datapoints.into_iter().map(Ok)),
+            Self::Stdin => Box::new(
+                std::io::stdin()
+                    .lines()
+                    .enumerate()
+                    .filter(|(_, line)| !matches!(line, Ok(line) if line.trim().is_empty()))
+                    .map(move |(index, line)| {
+                        let line = line.context("failed to read datapoint from stdin")?;
+                        let Ok(datapoint) = Datapoint::from_str(line.trim()) else {
+                            bail!("failed to parse datapoint from stdin. line: {}", index + 1);
+                        };
+                        let datapoint = datapoint.into_inner();
+
+                        if datapoint.len() != dims {
+                            bail!("{mismatch}. line: {}", index + 1);
+                        }
+
+                        Ok(datapoint)
+                    }),
+            ),
+        }
+    }
+}
+
+/// the datapoints given as arguments, read from the datapoint file, or read
+/// from stdin
+///
+/// `names` are the names of the columns of the records if they are known
+pub(crate) fn load_datapoints(
+    datapoint: &[DatapointArg],
+    datapoint_file: Option<&Path>,
+    names: Option<&[String]>,
+) -> anyhow::Result<Datapoints> {
+    if let Some(path) = datapoint_file {
+        let file = File::open(path).context("failed to open datapoint file")?;
+
+        return Ok(Datapoints::Given(read_datapoints(
+            BufReader::new(file),
+            names,
+        )?));
+    }
+
+    let mut given = Vec::with_capacity(datapoint.len());
+    // the named values that have not been completed into a datapoint yet
+    let mut pending: Option<Vec<Option<f64>>> = None;
+
+    for arg in datapoint {
+        match arg {
+            DatapointArg::Value(datapoint) => {
+                if let Some(bound) = pending.take() {
+                    given.push(complete_named(bound, names)?);
+                }
+
+                given.push(datapoint.clone().into_inner());
+            }
+            DatapointArg::Named(values) => {
+                let Some(names) = names else {
+                    bail!("named datapoints require the columns to have names");
+                };
+                let bound = bind_named(values, names)?;
+
+                pending = match pending.take() {
+                    Some(mut current)
+                        if current
+                            .iter()
+                            .zip(&bound)
+                            .all(|(a, b)| a.is_none() || b.is_none()) =>
+                    {
+                        for (value, given) in current.iter_mut().zip(bound) {
+                            *value = value.or(given);
+                        }
+
+                        Some(current)
+                    }
+                    Some(current) => {
+                        // a column that is given again starts the next
+                        // datapoint
+                        given.push(complete_named(current, Some(names))?);
+
+                        Some(bound)
+                    }
+                    None => Some(bound),
+                };
+            }
+            DatapointArg::Stdin if datapoint.len() == 1 => return Ok(Datapoints::Stdin),
+            DatapointArg::Stdin => bail!("datapoints from stdin cannot be mixed with others"),
+        }
+    }
+
+    if let Some(bound) = pending {
+        given.push(complete_named(bound, names)?);
+    }
+
+    Ok(Datapoints::Given(given))
+}
+
+/// places each named value at the position of its column in `names`
+fn bind_named(values: &[(String, f64)], names: &[String]) -> anyhow::Result<Vec<Option<f64>>> {
+    let mut bound = vec![None; names.len()];
+
+    for (name, value) in values {
+        let Some(col) = names.iter().position(|known| known == name) else {
+            bail!(
+                "datapoint column not found. name: {name} columns: {}",
+                names.join(",")
+            );
+        };
+
+        if bound[col].replace(*value).is_some() {
+            bail!("datapoint column given more than once. name: {name}");
+        }
+    }
+
+    Ok(bound)
+}
+
+/// the values of a named datapoint once every column has been given
+fn complete_named(bound: Vec<Option<f64>>, names: Option<&[String]>) -> anyhow::Result<Vec<f64>> {
+    let names = names.unwrap_or_default();
+    let missing: Vec<&str> = bound
+        .iter()
+        .zip(names)
+        .filter(|(value, _)| value.is_none())
+        .map(|(_, name)| name.as_str())
+        .collect();
+
+    if !missing.is_empty() {
+        bail!("datapoint is missing columns. names: {}", missing.join(","));
+    }
+
+    Ok(bound.into_iter().flatten().collect())
+}
+
+/// reads the datapoints of a csv
+///
+/// the first row is treated as a header row if any of its values are not a
+/// number. the header is matched to `names` when every name is found in it
+/// otherwise the columns are used in order
+fn read_datapoints<R>(reader: R, names: Option<&[String]>) -> anyhow::Result<Vec<Vec<f64>>>
+where
+    R: Read,
+{
+    let mut reader = ReaderBuilder::new().has_headers(false).from_reader(reader);
+    let mut datapoints = Vec::new();
+    // the columns of the csv in the order of the names
+    let mut order = None;
+
+    for (index, result) in reader.records().enumerate() {
+        let record =
+            result.with_context(|| format!("failed to read datapoint file. row: {}", index + 1))?;
+
+        // only the first row can be a header row
+        if index == 0 && record.iter().any(|value| f64::from_str(value).is_err()) {
+            order = names.and_then(|names| {
+                names
+                    .iter()
+                    .map(|name| record.iter().position(|header| header == name))
+                    .collect::<Option<Vec<_>>>()
+            });
+
+            continue;
+        }
+
+        let parse = |col: usize| {
+            let Some(Ok(value)) = record.get(col).map(f64::from_str) else {
+                bail!(
+                    "failed to parse datapoint file. row: {} column index: {}",
+                    index + 1,
+                    col + 1
+                );
+            };
+
+            Ok(value)
+        };
+
+        let datapoint = match &order {
+            Some(order) => order.iter().map(|col| parse(*col)).collect(),
+            None => (0..record.len())
+                .map(parse)
+                .collect::<anyhow::Result<Vec<_>>>(),
+        }?;
+
+        datapoints.push(datapoint);
+    }
+
+    if datapoints.is_empty() {
+        bail!("no datapoints found in the datapoint file");
+    }
+
+    Ok(datapoints)
+}
+
+/// scales a copy of each datapoint
+fn scale_datapoints(scaler: &Scaler, datapoints: &[Vec<f64>]) -> Vec<Vec<f64>> {
+    datapoints
+        .iter()
+        .map(|datapoint| {
+            let mut scaled = datapoint.clone();
+
+            scaler.apply(&mut scaled);
+
+            scaled
+        })
+        .collect()
+}
+
+/// pairs each datapoint with a scaled copy
+pub(crate) fn scaled_queries<'a>(
+    datapoints: DatapointIter<'a, Vec<f64>>,
+    scaler: &'a Scaler,
+) -> DatapointIter<'a, (Vec<f64>, Vec<f64>)> {
+    Box::new(datapoints.map(|datapoint| {
+        let datapoint = datapoint?;
+        let mut scaled = datapoint.clone();
+
+        scaler.apply(&mut scaled);
+
+        Ok((datapoint, scaled))
+    }))
+}
+
+/// predicts the datapoints by reading the csv in blocks and keeping only the
+/// closest records found so far for each datapoint
+fn predict_streamed<S>(
+    source: S,
+    label: usize,
+    columns: &[usize],
+    arg: &PredictArgs,
+    datapoints: &[Vec<f64>],
+) -> anyhow::Result<()>
+where
+    S: DataSource,
+{
+    if arg.index.kind != IndexType::Brute {
+        bail!("streaming is only available with the brute index");
+    }
+
+    // fitting the scaling parameters would require reading the csv twice
+    if arg.scale != ScaleType::None {
+        bail!("scaling is not available when streaming");
+    }
+
+    let KSelection::Value(k) = &arg.k() else {
+        bail!("auto k is not available when streaming");
+    };
+
+    // the number of records is not known ahead of time so the k values are
+    // only limited by the number of records once the csv has been read
+    let max_k = k.get_range(usize::MAX, arg.odd_only).last().unwrap_or(0);
+    let algo = arg.algo.as_rank_fn();
+    let mut closest: Vec<ClosestRecords> = datapoints
+        .iter()
+        .map(|_| ClosestRecords::new(max_k))
+        .collect();
+    let mut total = 0;
+
+    let names = stream_records(source, label, columns, STREAM_BLOCK, |block, start| {
+        for (datapoint, closest) in datapoints.iter().zip(&mut closest) {
+            for (offset, record) in block.iter().enumerate() {
+                closest.push(algo(datapoint, record.data), start + offset, record.label);
+            }
+        }
+
+        total += block.len();
+
+        Ok(())
+    })?;
+
+    let ks: Vec<usize> = k.get_range(total, arg.odd_only).collect();
+
+    for (datapoint, closest) in datapoints.iter().zip(closest) {
+        print_predictions(&ks, datapoint, arg.task, &closest.into_labels(), &names)?;
+    }
+
+    Ok(())
+}
+
+/// predicts the datapoint with the records and index of an index file
+fn predict_stored(path: &Path, arg: PredictArgs) -> anyhow::Result<()> {
+    let stored = StoredIndex::load(path)?;
+
+    let ks: Vec<usize> = match arg.k() {
+        KSelection::Value(k) => k.get_range(stored.labels.len(), arg.odd_only).collect(),
+        KSelection::Auto => bail!("auto k is not available when using an index file"),
+    };
+
+    let datapoints = load_datapoints(
+        &arg.datapoint,
+        arg.datapoint_file.as_deref(),
+        Some(&stored.columns),
+    )?;
+
+    predict_indexed(&stored, datapoints, &ks, arg.task)
+}
+
+/// predicts the datapoints with the records of an index or model file
+fn predict_indexed(
+    stored: &StoredIndex,
+    datapoints: Datapoints,
+    ks: &[usize],
+    task: TaskType,
+) -> anyhow::Result<()> {
+    let mismatch = format!(
+        "number of datapoints does not match number of columns in the index file. columns: {}",
+        stored.columns.join(" ")
+    );
+
+    datapoints.check(stored.columns.len(), &mismatch)?;
+
+    let max_k = ks.last().copied().unwrap_or(0);
+
+    if !stored.index.is_exact() {
+        let known = scale_datapoints(&stored.scaler, datapoints.known());
+        let (found, expected) = recall(
+            &stored.index,
+            stored.index.dims(),
+            stored.index.datapoints(),
+            stored.algo,
+            known.iter().map(Vec::as_slice),
+            max_k,
+        );
+
+        if expected > 0 {
+            println!("recall: {:.4}", found as f64 / expected as f64);
+        }
+    }
+
+    let queries = scaled_queries(
+        datapoints.read(stored.columns.len(), &mismatch),
+        &stored.scaler,
+    );
+
+    for query in queries {
+        let (datapoint, scaled) = query?;
+        let labels: Vec<u32> = stored
+            .index
+            .nearest(&scaled, max_k)
+            .iter()
+            .map(|(_, index)| stored.labels[*index])
+            .collect();
+
+        print_predictions(ks, &datapoint, task, &labels, &stored.label_names)?;
+    }
+
+    Ok(())
+}
+
+/// finds the label ids of the closest `k` records to the datapoint ordered
+/// from closest to furthest
+///
+/// the distances are calculated in parallel unless only a single thread is
+/// available
+pub(crate) fn closest_records<'a, T, G>(
+    k: usize,
+    len: usize,
+    get: G,
+    algo: fn(&[T], &[T]) -> f64,
+    datapoint: &[T],
+) -> Vec<u32>
+where
+    T: Sync + 'a,
+    G: Fn(usize) -> (&'a [T], u32) + Sync + Send,
+{
+    if rayon::current_num_threads() > 1 {
+        closest_labels_par(k, (0..len).into_par_iter().map(&get), algo, datapoint)
+    } else {
+        closest_labels(k, (0..len).map(&get), algo, datapoint)
+    }
+}
+
+/// finds the label ids of the closest `k` records to the datapoint using the
+/// distances calculated by the gpu
+fn closest_records_gpu(
+    k: usize,
+    records: &Dataset,
+    algo: AlgoType,
+    datapoint: &[f64],
+) -> anyhow::Result<Vec<u32>> {
+    let distances = gpu::rank_distances(
+        &narrow(records.matrix()),
+        records.dims(),
+        algo,
+        &narrow(datapoint),
+    )?;
+    let mut collected: Vec<(f64, usize, u32)> = distances
+        .into_iter()
+        .zip(records.labels())
+        .enumerate()
+        .map(|(index, (distance, label))| (distance as f64, index, *label))
+        .collect();
+
+    sort_closest(&mut collected, k);
+
+    Ok(collected
+        .iter()
+        .take(k)
+        .map(|(_, _, label)| *label)
+        .collect())
+}
+
+/// retrieves the labels of the records within the radius of the datapoint
+/// sorted from closest to furthest
+///
+/// only the closest records up to the largest k value of `limit` are kept
+fn radius_labels(
+    records: &Dataset,
+    radius: f64,
+    limit: Option<&[usize]>,
+    algo: AlgoType,
+    scaled: &[f64],
+) -> Vec<u32> {
+    let mut labels = labels_within(
+        radius,
+        records.iter().map(|record| (record.data, record.label)),
+        algo.as_fn(),
+        scaled,
+    );
+
+    if let Some(max_k) = limit.and_then(|ks| ks.last()) {
+        labels.truncate(*max_k);
+    }
+
+    labels
+}
+
+/// outputs the groups of every record within the radius of the datapoint or
+/// the groups of the fallback if there are none
+///
+/// `limit` are the k values when they were given. only the closest records
+/// within the radius are counted for each of them
+fn predict_radius(
+    records: &Dataset,
+    radius: f64,
+    limit: Option<&[usize]>,
+    arg: &PredictArgs,
+    datapoint: &[f64],
+    scaled: &[f64],
+) -> anyhow::Result<()> {
+    let mut labels = radius_labels(records, radius, limit, arg.algo, scaled);
+
+    println!("radius: {radius} neighbors: {}", labels.len());
+
+    let mut ks = limit.map(<[usize]>::to_vec);
+
+    if labels.is_empty() {
+        match arg.radius_fallback {
+            RadiusFallback::Nearest => {
+                println!("  no records within the radius. using the nearest record");
+
+                labels = closest_labels(
+                    1,
+                    records.iter().map(|record| (record.data, record.label)),
+                    arg.algo.as_rank_fn(),
+                    scaled,
+                );
+            }
+            RadiusFallback::Majority => {
+                println!("  no records within the radius. using every record");
+
+                labels = records.labels().to_vec();
+                // every record is counted no matter the k value
+                ks = None;
+            }
+            RadiusFallback::None => {
+                println!("  no records within the radius");
+
+                return Ok(());
+            }
+        }
+    }
+
+    print_predictions(
+        &ks.unwrap_or_else(|| vec![labels.len()]),
+        datapoint,
+        arg.task,
+        &labels,
+        records.label_names(),
+    )
+}
+
+/// outputs the groups found for each k value
+///
+/// `labels` are the label ids of the closest records for the largest k
+/// ordered from closest to furthest. the groups are extended for each k
+/// since the order of the neighbors does not change
+pub(crate) fn print_predictions(
+    ks: &[usize],
+    datapoint: &[f64],
+    task: TaskType,
+    labels: &[u32],
+    names: &[String],
+) -> anyhow::Result<()> {
+    let mut groups = Vec::new();
+    let mut counted = 0;
+
+    for k in ks {
+        let min = std::cmp::min(*k, labels.len());
+
+        count_labels(labels.iter().take(min).skip(counted).copied(), &mut groups);
+
+        counted = std::cmp::max(counted, min);
+
+        println!(
+            "{}",
+            Prediction::from_groups(*k, datapoint, task, min, &groups, names)?
+        );
+    }
+
+    Ok(())
+}
+
+/// cross validates a range of k values against the records and returns the k
+/// value with the best score
+///
+/// the range is from 1 to the square root of the number of records, limited
+/// to 25 neighbors
+pub fn auto_k(
+    records: &Dataset,
+    algo: &dyn DistanceMetric,
+    task: TaskType,
+    odd_only: bool,
+) -> anyhow::Result<usize> {
+    // the number of folds to cross validate with
+    const FOLDS: usize = 5;
+    // the largest k value that will be checked
+    const MAX_K: usize = 25;
+
+    if records.len() < FOLDS {
+        bail!("not enough records to automatically select k");
+    }
+
+    let high = ((records.len() as f64).sqrt() as usize).clamp(1, MAX_K);
+    let mut best = None::<(usize, f64)>;
+
+    for k in (1..=high).filter(|k| !odd_only || k % 2 == 1) {
+        let score = cross_validate(k, algo, task, records, FOLDS, &NoProgress)?;
+
+        println!("  k {k}: {score:.4}");
+
+        best = match best {
+            Some((best_k, best_score)) if best_score >= score => Some((best_k, best_score)),
+            _ => Some((k, score)),
+        };
+    }
+
+    let Some((k, _)) = best else {
+        bail!("no k values available to automatically select from");
+    };
+
+    Ok(k)
+}
+
+#[cfg(test)]
+mod test {
+    use clap::FromArgMatches;
+
+    use super::*;
+
+    #[test]
+    fn datapoint_file_columns() {
+        let names = ["x".to_owned(), "y".to_owned()];
+        let named = read_datapoints("id,y,x\n1,2,3\n4,5,6\n".as_bytes(), Some(&names)).unwrap();
+
+        assert_eq!(named, vec![vec![3.0, 2.0], vec![6.0, 5.0]]);
+
+        let ordered = read_datapoints("1,2\n3,4\n".as_bytes(), Some(&names)).unwrap();
+
+        assert_eq!(ordered, vec![vec![1.0, 2.0], vec![3.0, 4.0]]);
+
+        assert!(read_datapoints("x,y\n1,a\n".as_bytes(), Some(&names)).is_err());
+    }
+
+    #[test]
+    fn named_datapoints() {
+        let names = ["x".to_owned(), "y".to_owned()];
+        let parse = |given: &[&str]| {
+            let mut args = vec!["predict"];
+
+            for datapoint in given {
+                args.extend(["--datapoint", datapoint]);
+            }
+
+            let matches =
+                PredictArgs::augment_args(clap::Command::new("predict")).get_matches_from(args);
+            let arg = PredictArgs::from_arg_matches(&matches).unwrap();
+
+            load_datapoints(&arg.datapoint, arg.datapoint_file.as_deref(), Some(&names))
+                .map(|datapoints| datapoints.known().to_vec())
+        };
+
+        assert_eq!(parse(&["y=2,x=1"]).unwrap(), vec![vec![1.0, 2.0]]);
+        assert_eq!(
+            parse(&["y=2", "x=1", "x=3", "y=4", "5,6"]).unwrap(),
+            vec![vec![1.0, 2.0], vec![3.0, 4.0], vec![5.0, 6.0]]
+        );
+        assert!(parse(&["x=1"]).is_err());
+        assert!(parse(&["z=1,x=1,y=2"]).is_err());
+        assert!(parse(&["x=1,x=2,y=3"]).is_err());
+    }
+
+    #[test]
+    fn auto_k_selection() {
+        let records = |len: usize| {
+            let mut records = Dataset::new(1);
+
+            // every 8th record is a different label. k 2 scores the best on
+            // this pattern so filtering to odd values changes the selection
+            for index in 0..len {
+                records.push(&[index as f64], if index % 8 == 1 { "a" } else { "b" });
+            }
+
+            records
+        };
+        let select = |len: usize, odd_only: bool| {
+            auto_k(
+                &records(len),
+                &AlgoType::Euclidean,
+                TaskType::Classification,
+                odd_only,
+            )
+        };
+
+        assert_eq!(select(25, false).unwrap(), 2);
+        assert_eq!(select(25, true).unwrap(), 3);
+        assert!(select(4, false).is_err());
+    }
+
+    #[test]
+    fn radius_limit() {
+        let mut records = Dataset::new(1);
+
+        for (value, label) in [(0.0, "a"), (1.0, "b"), (2.0, "b"), (3.0, "a"), (10.0, "c")] {
+            records.push(&[value], label);
+        }
+
+        let within = |radius: f64, limit: Option<&[usize]>| {
+            radius_labels(&records, radius, limit, AlgoType::Euclidean, &[0.5])
+                .into_iter()
+                .map(|label| records.label_names()[label as usize].as_str())
+                .collect::<Vec<&str>>()
+        };
+
+        // the two records at 0.5 tie and are kept in the order of the
+        // records
+        assert_eq!(within(3.0, Some(&[2])), vec!["a", "b"]);
+        assert_eq!(within(3.0, Some(&[1, 3])), vec!["a", "b", "b"]);
+        assert_eq!(within(3.0, None), vec!["a", "b", "b", "a"]);
+        // records outside of the radius never vote even when there are fewer
+        // than k records inside
+        assert_eq!(within(1.0, Some(&[4])), vec!["a", "b"]);
+        assert!(within(0.1, Some(&[3])).is_empty());
+    }
+}
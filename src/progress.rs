@@ -0,0 +1,184 @@
+//! reporting the progress of long running commands
+//!
+//! search and evaluate call the hooks of a [`Progress`] as they work so that
+//! a program embedding the crate can show its own progress. every hook does
+//! nothing by default. the binary uses [`ProgressBar`] when `--progress` is
+//! specified and [`NoProgress`] otherwise.
+use std::io::Write;
+use std::sync::{Mutex, PoisonError};
+
+use crate::metrics::Evaluation;
+
+/// receives the progress of a search or evaluation
+///
+/// the hooks are called from the thread running the command but the
+/// implementation must be [`Sync`] since the command shares it with the
+/// threads evaluating the records
+pub trait Progress: Sync {
+    /// the columns are about to be searched with the k value
+    fn on_k_started(&self, k: usize) {
+        let _ = k;
+    }
+
+    /// a subset of csv columns was evaluated with the k value. evaluations
+    /// retrieved from a checkpoint are reported as well
+    fn on_candidate_evaluated(&self, k: usize, columns: &[usize], eval: &Evaluation) {
+        let _ = (k, columns, eval);
+    }
+
+    /// the fold at the 0 based index finished out of the total number of
+    /// folds
+    fn on_fold_finished(&self, fold: usize, folds: usize) {
+        let _ = (fold, folds);
+    }
+}
+
+/// ignores all progress
+#[derive(Debug, Default, Clone, Copy)]
+pub struct NoProgress;
+
+impl Progress for NoProgress {}
+
+/// draws the progress on a single line of stderr
+///
+/// the line is redrawn on every hook and ended once the bar is dropped
+#[derive(Debug, Default)]
+pub struct ProgressBar {
+    state: Mutex<BarState>,
+}
+
+#[derive(Debug, Default)]
+struct BarState {
+    k: Option<usize>,
+    candidates: usize,
+    best: Option<f64>,
+    folds: Option<(usize, usize)>,
+    drawn: bool,
+}
+
+/// the number of characters used for the fold bar
+const BAR_WIDTH: usize = 20;
+
+impl ProgressBar {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn update<F>(&self, change: F)
+    where
+        F: FnOnce(&mut BarState),
+    {
+        let mut state = self.state.lock().unwrap_or_else(PoisonError::into_inner);
+
+        change(&mut state);
+
+        let mut line = String::new();
+
+        if let Some((done, total)) = state.folds {
+            let filled = (done * BAR_WIDTH).checked_div(total).unwrap_or(BAR_WIDTH);
+
+            line.push_str(&format!(
+                "[{}{}] {done}/{total} folds",
+                "#".repeat(filled),
+                " ".repeat(BAR_WIDTH - filled)
+            ));
+        }
+
+        if let Some(k) = state.k {
+            if !line.is_empty() {
+                line.push_str(" | ");
+            }
+
+            line.push_str(&format!("k {k} candidates {}", state.candidates));
+
+            if let Some(best) = state.best {
+                line.push_str(&format!(" best {best:.4}"));
+            }
+        }
+
+        state.drawn = true;
+
+        // progress is informational so failing to draw it is ignored
+        let mut stderr = std::io::stderr().lock();
+        let _ = write!(stderr, "\r\x1b[K{line}");
+        let _ = stderr.flush();
+    }
+}
+
+impl Progress for ProgressBar {
+    fn on_k_started(&self, k: usize) {
+        self.update(|state| {
+            state.k = Some(k);
+            state.candidates = 0;
+            state.best = None;
+        });
+    }
+
+    fn on_candidate_evaluated(&self, _k: usize, _columns: &[usize], eval: &Evaluation) {
+        self.update(|state| {
+            state.candidates += 1;
+            state.best = Some(
+                state
+                    .best
+                    .map_or(eval.score(), |best| best.max(eval.score())),
+            );
+        });
+    }
+
+    fn on_fold_finished(&self, fold: usize, folds: usize) {
+        self.update(|state| state.folds = Some((fold + 1, folds)));
+    }
+}
+
+impl Drop for ProgressBar {
+    fn drop(&mut self) {
+        let state = self.state.get_mut().unwrap_or_else(PoisonError::into_inner);
+
+        if state.drawn {
+            eprintln!();
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::cli::{AlgoType, TaskType};
+    use crate::csv::Dataset;
+    use crate::evaluate::cross_validate;
+
+    #[derive(Default)]
+    struct Folds(Mutex<Vec<(usize, usize)>>);
+
+    impl Progress for Folds {
+        fn on_fold_finished(&self, fold: usize, folds: usize) {
+            self.0.lock().unwrap().push((fold, folds));
+        }
+    }
+
+    #[test]
+    fn cross_validate_reports_folds() {
+        let mut records = Dataset::new(1);
+
+        for value in 0..10 {
+            records.push(&[value as f64], if value < 5 { "a" } else { "b" });
+        }
+
+        let folds = Folds::default();
+
+        cross_validate(
+            1,
+            &AlgoType::Euclidean,
+            TaskType::Classification,
+            &records,
+            5,
+            &folds,
+        )
+        .unwrap();
+
+        assert_eq!(
+            folds.0.into_inner().unwrap(),
+            vec![(0, 5), (1, 5), (2, 5), (3, 5), (4, 5)]
+        );
+    }
+}
@@ -0,0 +1,357 @@
+//! an interactive prompt for predicting datapoints
+//!
+//! the records are loaded once and every line afterwards is either a
+//! datapoint to predict or a command that changes the arguments used for the
+//! next prediction. changing the scaling refits the scaling parameters from
+//! the loaded records without reading the csv again.
+//!
+//! - `k <value>` sets the k value or range
+//! - `algo <name>` sets the distance algorithm
+//! - `scale <name>` sets the scaling method
+//! - `task <name>` sets the type of task
+//! - `show` prints the current arguments
+//! - `help` prints the available commands
+//! - `quit` or `exit` ends the prompt
+use std::io::{BufRead, IsTerminal, Write};
+use std::str::FromStr;
+
+use anyhow::{Context, anyhow, bail};
+use clap::{Args, ValueEnum};
+
+use crate::cli::{AlgoType, ColumnList, ColumnType, Datapoint, KValue, TaskType};
+use crate::csv::{Dataset, check_numeric_labels, collect_records, get_columns_excluding};
+use crate::predict::{closest_records, print_predictions};
+use crate::scale::{ScaleType, Scaler};
+use crate::source::DataSource;
+
+const HELP: &str = "\
+datapoints are given as a comma delimitered list of numbers
+  k <value>      sets the k value or range, such as 7 or 3-9
+  algo <name>    sets the distance algorithm
+  scale <name>   sets the scaling method
+  task <name>    sets the type of task
+  show           prints the current arguments
+  help           prints this message
+  quit           ends the prompt";
+
+#[derive(Debug, Args)]
+pub struct ReplArgs {
+    /// the number of neighbors to lookup. can be changed with `k`
+    #[arg(short, long, default_value = "3")]
+    k: KValue,
+
+    /// skips even k values to avoid ties between two labels
+    #[arg(long)]
+    odd_only: bool,
+
+    /// the starting algorithm to use when calculating distances. can be
+    /// changed with `algo`
+    #[arg(long, default_value = "euclidean")]
+    algo: AlgoType,
+
+    /// the list of columns to use as datapoints
+    #[arg(short, long = "col", allow_hyphen_values = true)]
+    columns: Vec<ColumnList>,
+
+    /// columns to remove from the list of columns
+    #[arg(long = "exclude-col", allow_hyphen_values = true)]
+    exclude: Vec<ColumnList>,
+
+    /// the column to use as the label
+    #[arg(long, allow_negative_numbers = true)]
+    label: Option<ColumnType>,
+
+    /// the starting scaling method to apply to the columns. can be changed
+    /// with `scale`
+    #[arg(long, default_value = "none")]
+    scale: ScaleType,
+
+    /// the starting type of task to perform with the labels. can be changed
+    /// with `task`
+    #[arg(long, default_value = "classification")]
+    task: TaskType,
+}
+
+/// the arguments that can be changed from the prompt along with the records
+/// scaled for them
+struct Session {
+    k: KValue,
+    odd_only: bool,
+    algo: AlgoType,
+    scale: ScaleType,
+    task: TaskType,
+    /// the records as they were loaded
+    records: Dataset,
+    scaler: Scaler,
+    /// the records with the current scaling applied
+    scaled: Dataset,
+}
+
+impl Session {
+    /// starts a session with the starting arguments and scales the records
+    /// for them
+    fn new(arg: ReplArgs, records: Dataset) -> Self {
+        let scaler = Scaler::fit(
+            arg.scale,
+            records.iter().map(|record| record.data),
+            records.dims(),
+        );
+        let scaled = scaler.apply_records(&records);
+
+        Self {
+            k: arg.k,
+            odd_only: arg.odd_only,
+            algo: arg.algo,
+            scale: arg.scale,
+            task: arg.task,
+            records,
+            scaler,
+            scaled,
+        }
+    }
+
+    /// refits the scaling parameters for the current scaling method
+    fn rescale(&mut self) {
+        self.scaler = Scaler::fit(
+            self.scale,
+            self.records.iter().map(|record| record.data),
+            self.records.dims(),
+        );
+        self.scaled = self.scaler.apply_records(&self.records);
+    }
+
+    /// handles a single line of input. returns false once the prompt should
+    /// end
+    fn handle(&mut self, line: &str) -> anyhow::Result<bool> {
+        let (command, value) = match line.split_once(char::is_whitespace) {
+            Some((command, value)) => (command, value.trim()),
+            None => (line, ""),
+        };
+
+        match command {
+            "quit" | "exit" => return Ok(false),
+            "help" => println!("{HELP}"),
+            "show" => self.show(),
+            "k" => {
+                let k = KValue::from_str(value).map_err(|err| anyhow!(err))?;
+
+                k.check_odd_only(self.odd_only)?;
+
+                self.k = k;
+            }
+            "algo" => self.algo = parse_value(value)?,
+            "scale" => {
+                self.scale = parse_value(value)?;
+                self.rescale();
+            }
+            "task" => {
+                let task = parse_value(value)?;
+
+                if task == TaskType::Regression {
+                    check_numeric_labels(&self.records)?;
+                }
+
+                self.task = task;
+            }
+            _ => self.predict(line)?,
+        }
+
+        Ok(true)
+    }
+
+    fn show(&self) {
+        println!(
+            "k: {} | algo: {} | scale: {} | task: {} | records: {}",
+            self.k,
+            self.algo,
+            self.scale,
+            self.task,
+            self.records.len()
+        );
+    }
+
+    /// predicts the datapoint with the current arguments
+    fn predict(&self, given: &str) -> anyhow::Result<()> {
+        let Ok(datapoint) = Datapoint::from_str(given) else {
+            bail!("unknown command or invalid datapoint. type help for the available commands");
+        };
+        let datapoint = datapoint.into_inner();
+
+        if datapoint.len() != self.records.dims() {
+            bail!(
+                "number of datapoints does not match number of columns. expected: {}",
+                self.records.dims()
+            );
+        }
+
+        let mut scaled = datapoint.clone();
+
+        self.scaler.apply(&mut scaled);
+
+        let ks: Vec<usize> = self.k.get_range(self.scaled.len(), self.odd_only).collect();
+        let max_k = ks.last().copied().unwrap_or(0);
+        let labels = closest_records(
+            max_k,
+            self.scaled.len(),
+            |index| {
+                let record = self.scaled.get(index);
+
+                (record.data, record.label)
+            },
+            self.algo.as_rank_fn(),
+            &scaled,
+        );
+
+        print_predictions(
+            &ks,
+            &datapoint,
+            self.task,
+            &labels,
+            self.scaled.label_names(),
+        )
+    }
+}
+
+/// parses the value of a command the same way as its argument
+fn parse_value<T>(given: &str) -> anyhow::Result<T>
+where
+    T: ValueEnum,
+{
+    T::from_str(given, true).map_err(|_| {
+        let names = T::value_variants()
+            .iter()
+            .filter_map(|variant| variant.to_possible_value())
+            .map(|value| value.get_name().to_owned())
+            .collect::<Vec<_>>();
+
+        anyhow!(
+            "invalid value \"{given}\". expected one of: {}",
+            names.join(", ")
+        )
+    })
+}
+
+pub fn knn_repl<S>(mut source: S, arg: ReplArgs) -> anyhow::Result<()>
+where
+    S: DataSource,
+{
+    arg.k.check_odd_only(arg.odd_only)?;
+
+    let label = &ColumnType::label_or_last(arg.label.as_ref());
+    let (label, columns) = get_columns_excluding(
+        &mut source,
+        label,
+        &ColumnList::flatten(&arg.columns),
+        &ColumnList::flatten(&arg.exclude),
+    )?;
+    let records = collect_records(source, label, &columns)?;
+
+    if arg.task == TaskType::Regression {
+        check_numeric_labels(&records)?;
+    }
+
+    let mut session = Session::new(arg, records);
+
+    println!(
+        "loaded {} records with {} columns. type help for the available commands",
+        session.records.len(),
+        columns.len()
+    );
+
+    // the prompt is only shown to people typing so piped output stays clean
+    let prompt = std::io::stdin().is_terminal();
+    let mut lines = std::io::stdin().lock().lines();
+
+    loop {
+        if prompt {
+            print!("> ");
+            std::io::stdout()
+                .flush()
+                .context("failed to write prompt")?;
+        }
+
+        let Some(line) = lines.next() else {
+            break;
+        };
+        let line = line.context("failed to read from stdin")?;
+        let line = line.trim();
+
+        if line.is_empty() {
+            continue;
+        }
+
+        // a mistake at the prompt should not lose the loaded records
+        match session.handle(line) {
+            Ok(true) => {}
+            Ok(false) => break,
+            Err(err) => println!("error: {err:#}"),
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use clap::Parser;
+
+    #[derive(Parser)]
+    struct Cli {
+        #[command(flatten)]
+        repl: ReplArgs,
+    }
+
+    fn session() -> Session {
+        let mut records = Dataset::new(2);
+
+        for (data, label) in [
+            ([0.0, 10.0], "a"),
+            ([2.0, 20.0], "a"),
+            ([8.0, 30.0], "b"),
+            ([10.0, 40.0], "b"),
+        ] {
+            records.push(&data, label);
+        }
+
+        Session::new(Cli::parse_from(["knn"]).repl, records)
+    }
+
+    #[test]
+    fn session_commands() {
+        let mut session = session();
+
+        assert!(session.handle("k 7").unwrap());
+        assert_eq!(session.k.to_string(), "7");
+        assert!(session.handle("k none").is_err());
+        assert_eq!(session.k.to_string(), "7");
+
+        assert!(session.handle("algo manhattan").unwrap());
+        assert_eq!(session.algo, AlgoType::Manhattan);
+
+        let err = session.handle("algo nearby").unwrap_err().to_string();
+
+        assert!(err.contains("expected one of"));
+        assert_eq!(session.algo, AlgoType::Manhattan);
+
+        // the records stay as they were loaded and only the scaled copy
+        // changes
+        assert!(session.handle("scale minmax").unwrap());
+        assert_eq!(session.scale, ScaleType::MinMax);
+        assert_eq!(session.scaled.get(1).data, [0.2, 1.0 / 3.0]);
+        assert_eq!(session.records.get(1).data, [2.0, 20.0]);
+        assert!(session.handle("scale tiny").is_err());
+        assert_eq!(session.scale, ScaleType::MinMax);
+
+        // the labels are names so they cannot be used for a regression
+        assert!(session.handle("task regression").is_err());
+        assert_eq!(session.task, TaskType::Classification);
+
+        let err = session.handle("predict 1,2").unwrap_err().to_string();
+
+        assert!(err.starts_with("unknown command"));
+        assert!(session.handle("1,2,3").is_err());
+        assert!(session.handle("1,12").unwrap());
+        assert!(!session.handle("quit").unwrap());
+    }
+}
@@ -0,0 +1,323 @@
+//! the results of the commands as serializable types
+//!
+//! the same types are printed by the binary and returned to library users.
+//! when they are written out they should be wrapped in [`Versioned`] so that
+//! anything reading them can tell when the layout has changed.
+use std::collections::BTreeMap;
+use std::fmt;
+
+use anyhow::bail;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+
+use crate::classify::average_groups;
+use crate::cli::{AlgoType, TaskType};
+use crate::metrics::Evaluation;
+use crate::scale::ScaleType;
+use crate::schema::Schema;
+
+/// the version of the serialized layout of the result types. this is
+/// increased whenever a field is renamed, removed, or changes meaning
+pub const FORMAT_VERSION: u32 = 1;
+
+/// the versions of the result types that can be read. a migration is added
+/// here whenever [`FORMAT_VERSION`] is increased
+pub const RESULT_SCHEMA: Schema = Schema {
+    what: "result",
+    current: FORMAT_VERSION,
+    oldest: 1,
+    migrations: &[],
+};
+
+/// a result tagged with the version of the layout it was written with
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Versioned<T> {
+    pub version: u32,
+    #[serde(flatten)]
+    pub value: T,
+}
+
+impl<T> Versioned<T> {
+    /// tags the value with the current layout version
+    pub fn new(value: T) -> Self {
+        Self {
+            version: FORMAT_VERSION,
+            value,
+        }
+    }
+
+    /// parses a value that was written with any supported layout and
+    /// upgrades it to the current one
+    pub fn read(json: &str) -> anyhow::Result<T>
+    where
+        T: DeserializeOwned,
+    {
+        RESULT_SCHEMA
+            .from_slice::<Self>(json.as_bytes())
+            .map(|versioned| versioned.value)
+    }
+
+    /// retrieves the value if it was written with the current layout
+    pub fn into_current(self) -> anyhow::Result<T> {
+        if self.version != FORMAT_VERSION {
+            bail!(
+                "unsupported result version {}. expected {FORMAT_VERSION}",
+                self.version
+            );
+        }
+
+        Ok(self.value)
+    }
+}
+
+/// the neighbors found for a datapoint with a single k value
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Prediction {
+    pub k: usize,
+    pub datapoint: Vec<f64>,
+    pub outcome: Outcome,
+}
+
+/// the estimate made from the labels of the neighbors
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase", tag = "task")]
+pub enum Outcome {
+    /// every label found in the neighbors
+    Classification { groups: Vec<LabelGroup> },
+    /// the average of the numeric labels of the neighbors
+    Regression { value: f64 },
+}
+
+/// the number of neighbors that had a label
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LabelGroup {
+    pub label: String,
+    pub count: u32,
+    /// the count divided by the number of neighbors
+    pub fraction: f64,
+}
+
+impl Prediction {
+    /// creates the prediction from the counted groups of the neighbors
+    ///
+    /// `min` is the number of neighbors counted and `names` are the labels of
+    /// each label id in the groups
+    pub fn from_groups(
+        k: usize,
+        datapoint: &[f64],
+        task: TaskType,
+        min: usize,
+        groups: &[u32],
+        names: &[String],
+    ) -> anyhow::Result<Self> {
+        let outcome = match task {
+            TaskType::Classification => Outcome::Classification {
+                groups: names
+                    .iter()
+                    .zip(groups)
+                    .filter(|(_, count)| **count > 0)
+                    .map(|(name, count)| LabelGroup {
+                        label: name.clone(),
+                        count: *count,
+                        fraction: (*count as f64) / (min as f64),
+                    })
+                    .collect(),
+            },
+            TaskType::Regression => {
+                let Some(value) = average_groups(groups, names, min) else {
+                    bail!("regression requires numeric labels");
+                };
+
+                Outcome::Regression { value }
+            }
+        };
+
+        Ok(Self {
+            k,
+            datapoint: datapoint.to_vec(),
+            outcome,
+        })
+    }
+
+    /// the label with the most neighbors or the estimated value of a
+    /// regression. ties go to the label that was encountered first
+    pub fn label(&self) -> Option<String> {
+        match &self.outcome {
+            Outcome::Classification { groups } => groups
+                .iter()
+                .rev()
+                .max_by_key(|group| group.count)
+                .map(|group| group.label.clone()),
+            Outcome::Regression { value } => Some(value.to_string()),
+        }
+    }
+}
+
+impl fmt::Display for Prediction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "k value: {} |", self.k)?;
+
+        for v in &self.datapoint {
+            write!(f, " {v}")?;
+        }
+
+        match &self.outcome {
+            Outcome::Classification { groups } => {
+                for group in groups {
+                    write!(
+                        f,
+                        "\n  {}: {} {:.2}",
+                        group.label, group.count, group.fraction
+                    )?;
+                }
+            }
+            Outcome::Regression { value } => write!(f, "\n  value: {value}")?,
+        }
+
+        Ok(())
+    }
+}
+
+/// the closest records to a datapoint without any voting
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct NeighborList {
+    pub datapoint: Vec<f64>,
+    /// the row of the csv the datapoint was taken from
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub row: Option<usize>,
+    /// ordered from closest to furthest
+    pub neighbors: Vec<Neighbor>,
+}
+
+/// a record found close to a datapoint
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Neighbor {
+    /// the zero based row of the record in the csv
+    pub row: usize,
+    /// the distance from the datapoint after scaling
+    pub distance: f64,
+    pub label: String,
+}
+
+/// the datapoints sent to the server to predict
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PredictRequest {
+    pub datapoints: Vec<Vec<f64>>,
+}
+
+/// the predictions returned by the server in the same order as the
+/// datapoints of the request
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PredictResponse {
+    pub predictions: Vec<LabelPrediction>,
+}
+
+/// the predicted label of a datapoint along with the fraction of neighbors
+/// that had each label
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LabelPrediction {
+    pub label: String,
+    /// empty for regression
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub probabilities: BTreeMap<String, f64>,
+}
+
+impl TryFrom<Prediction> for LabelPrediction {
+    type Error = anyhow::Error;
+
+    fn try_from(prediction: Prediction) -> anyhow::Result<Self> {
+        let Some(label) = prediction.label() else {
+            bail!("no neighbors were found for the datapoint");
+        };
+
+        let probabilities = match prediction.outcome {
+            Outcome::Classification { groups } => groups
+                .into_iter()
+                .map(|group| (group.label, group.fraction))
+                .collect(),
+            Outcome::Regression { .. } => BTreeMap::new(),
+        };
+
+        Ok(Self {
+            label,
+            probabilities,
+        })
+    }
+}
+
+/// the labeled records sent to the server to add to the model
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AppendRequest {
+    pub records: Vec<LabeledDatapoint>,
+}
+
+/// a datapoint along with its label
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LabeledDatapoint {
+    pub datapoint: Vec<f64>,
+    pub label: String,
+}
+
+/// the result of adding records to the model
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AppendResponse {
+    /// the number of records added
+    pub added: usize,
+    /// the number of records in the model afterwards
+    pub records: usize,
+}
+
+/// a single configuration evaluated by a search
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SearchResult {
+    pub k: usize,
+    pub algo: AlgoType,
+    pub scale: ScaleType,
+    pub eval: Evaluation,
+    /// the csv columns used for the configuration
+    pub cols: Vec<usize>,
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn versioned_prediction() {
+        let names = vec!["a".to_owned(), "b".to_owned(), "c".to_owned()];
+        let prediction = Prediction::from_groups(
+            4,
+            &[1.0, 2.5],
+            TaskType::Classification,
+            4,
+            &[3, 0, 1],
+            &names,
+        )
+        .unwrap();
+
+        assert_eq!(
+            prediction.to_string(),
+            "k value: 4 | 1 2.5\n  a: 3 0.75\n  c: 1 0.25"
+        );
+
+        let saved = serde_json::to_string(&Versioned::new(prediction.clone())).unwrap();
+        let loaded: Versioned<Prediction> = serde_json::from_str(&saved).unwrap();
+
+        assert_eq!(loaded.into_current().unwrap(), prediction);
+        assert_eq!(Versioned::<Prediction>::read(&saved).unwrap(), prediction);
+
+        let served = LabelPrediction::try_from(prediction).unwrap();
+
+        assert_eq!(served.label, "a");
+        assert_eq!(served.probabilities["c"], 0.25);
+
+        let future = saved.replace(
+            &format!("\"version\":{FORMAT_VERSION}"),
+            &format!("\"version\":{}", FORMAT_VERSION + 1),
+        );
+        let loaded: Versioned<Prediction> = serde_json::from_str(&future).unwrap();
+
+        assert!(loaded.into_current().is_err());
+        assert!(Versioned::<Prediction>::read(&future).is_err());
+    }
+}
@@ -0,0 +1,184 @@
+//! feature scaling for the collected records
+//!
+//! distance functions treat every column equally so columns with large ranges
+//! can dominate the calculated distance. scaling will attempt to place every
+//! column on a similar range before the distances are calculated.
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+
+use crate::csv::Dataset;
+
+/// represents the scaling method to apply to the columns
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ScaleType {
+    /// leaves the columns unmodified
+    None,
+
+    /// scales the columns into the range of 0 to 1
+    #[value(name = "minmax")]
+    MinMax,
+
+    /// scales the columns to have a mean of 0 and standard deviation of 1
+    #[value(name = "zscore")]
+    ZScore,
+}
+
+impl std::fmt::Display for ScaleType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::None => f.write_str("none"),
+            Self::MinMax => f.write_str("minmax"),
+            Self::ZScore => f.write_str("zscore"),
+        }
+    }
+}
+
+/// the fitted parameters for scaling datapoints
+///
+/// each column will be transformed by `(value - offset) / factor`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Scaler {
+    offset: Vec<f64>,
+    factor: Vec<f64>,
+}
+
+impl Scaler {
+    /// calculates the scaling parameters from the provided records
+    ///
+    /// columns with no variation will be left with a factor of 1 to avoid
+    /// dividing by 0
+    pub fn fit<'a, I>(scale: ScaleType, records: I, columns: usize) -> Self
+    where
+        I: Iterator<Item = &'a [f64]>,
+    {
+        let mut offset = vec![0.0; columns];
+        let mut factor = vec![1.0; columns];
+
+        match scale {
+            ScaleType::None => {}
+            ScaleType::MinMax => {
+                let mut min = vec![f64::INFINITY; columns];
+                let mut max = vec![f64::NEG_INFINITY; columns];
+
+                for data in records {
+                    for (index, value) in data.iter().enumerate() {
+                        min[index] = min[index].min(*value);
+                        max[index] = max[index].max(*value);
+                    }
+                }
+
+                for index in 0..columns {
+                    if min[index].is_finite() {
+                        offset[index] = min[index];
+                    }
+
+                    let range = max[index] - min[index];
+
+                    if range.is_finite() && range > 0.0 {
+                        factor[index] = range;
+                    }
+                }
+            }
+            ScaleType::ZScore => {
+                let mut count = 0usize;
+                let mut sum = vec![0.0; columns];
+                let mut sum_sq = vec![0.0; columns];
+
+                for data in records {
+                    count += 1;
+
+                    for (index, value) in data.iter().enumerate() {
+                        sum[index] += value;
+                        sum_sq[index] += value * value;
+                    }
+                }
+
+                if count > 0 {
+                    let total = count as f64;
+
+                    for index in 0..columns {
+                        let mean = sum[index] / total;
+                        let variance = (sum_sq[index] / total) - (mean * mean);
+
+                        offset[index] = mean;
+
+                        if variance > 0.0 {
+                            factor[index] = variance.sqrt();
+                        }
+                    }
+                }
+            }
+        }
+
+        Self { offset, factor }
+    }
+
+    /// the offset and factor of each column
+    pub fn params(&self) -> impl Iterator<Item = (f64, f64)> + '_ {
+        self.offset.iter().copied().zip(self.factor.iter().copied())
+    }
+
+    /// scales the provided datapoint in place
+    pub fn apply(&self, data: &mut [f64]) {
+        for ((value, offset), factor) in data.iter_mut().zip(&self.offset).zip(&self.factor) {
+            *value = (*value - offset) / factor;
+        }
+    }
+
+    /// creates a scaled copy of the provided records
+    pub fn apply_records(&self, records: &Dataset) -> Dataset {
+        let mut rtn = records.clone();
+
+        if rtn.dims() > 0 {
+            for data in rtn.matrix_mut().chunks_exact_mut(records.dims()) {
+                self.apply(data);
+            }
+        }
+
+        rtn
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const DATA: [[f64; 2]; 3] = [[1.0, 10.0], [2.0, 10.0], [3.0, 10.0]];
+
+    fn fit(scale: ScaleType) -> Scaler {
+        Scaler::fit(scale, DATA.iter().map(|data| data.as_slice()), 2)
+    }
+
+    #[test]
+    fn check_minmax() {
+        let scaler = fit(ScaleType::MinMax);
+        let mut data = [2.0, 10.0];
+
+        scaler.apply(&mut data);
+
+        // the second column has no range so it is only offset
+        assert_eq!(data, [0.5, 0.0]);
+    }
+
+    #[test]
+    fn check_zscore() {
+        let scaler = fit(ScaleType::ZScore);
+        let mut data = [3.0, 10.0];
+
+        scaler.apply(&mut data);
+
+        assert!((data[0] - 1.0 / (2.0f64 / 3.0).sqrt()).abs() < 1e-12);
+        assert_eq!(data[1], 0.0);
+    }
+
+    #[test]
+    fn check_none() {
+        let scaler = fit(ScaleType::None);
+        let mut data = [3.0, 10.0];
+
+        scaler.apply(&mut data);
+
+        assert_eq!(data, [3.0, 10.0]);
+    }
+}
@@ -0,0 +1,186 @@
+//! versioning the json written by the crate
+//!
+//! model files, index files, and the serialized results each embed a
+//! `version` field. a [`Schema`] describes the current version of a format
+//! along with the migrations that upgrade older versions, so files written by
+//! an older version of the crate keep loading while newer files are rejected
+//! instead of being misread.
+//!
+//! a file at the current version is deserialized directly. older files are
+//! parsed into a [`Value`] first and then passed through each migration in
+//! order.
+use anyhow::{Context, bail};
+use serde::Deserialize;
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+
+/// upgrades a json value from one version to the next. the version field is
+/// updated after the migration returns
+pub type Migration = fn(&mut Value) -> anyhow::Result<()>;
+
+/// the versions of a json format
+#[derive(Debug, Clone, Copy)]
+pub struct Schema {
+    /// describes the format for error messages
+    pub what: &'static str,
+    /// the version that is written
+    pub current: u32,
+    /// the oldest version that can still be read. values without a version
+    /// field are version 0
+    pub oldest: u32,
+    /// the migration from each version starting at `oldest` to the next
+    pub migrations: &'static [Migration],
+}
+
+/// only the version of a value
+#[derive(Deserialize)]
+struct VersionField {
+    #[serde(default)]
+    version: u32,
+}
+
+impl Schema {
+    /// parses the json and upgrades it to the current version
+    pub fn from_slice<T>(&self, json: &[u8]) -> anyhow::Result<T>
+    where
+        T: DeserializeOwned,
+    {
+        let found: VersionField = serde_json::from_slice(json)
+            .with_context(|| format!("failed to parse {}", self.what))?;
+
+        self.check(found.version)?;
+
+        let parsed = if found.version == self.current {
+            serde_json::from_slice(json)
+        } else {
+            let mut value = serde_json::from_slice(json)
+                .with_context(|| format!("failed to parse {}", self.what))?;
+
+            self.migrate(&mut value, found.version)?;
+
+            serde_json::from_value(value)
+        };
+
+        parsed.with_context(|| format!("failed to parse {}", self.what))
+    }
+
+    /// upgrades an already parsed value to the current version
+    pub fn from_value<T>(&self, mut value: Value) -> anyhow::Result<T>
+    where
+        T: DeserializeOwned,
+    {
+        self.upgrade(&mut value)?;
+
+        serde_json::from_value(value).with_context(|| format!("failed to parse {}", self.what))
+    }
+
+    /// upgrades the value in place to the current version. used by formats
+    /// that contain another versioned format
+    pub fn upgrade(&self, value: &mut Value) -> anyhow::Result<()> {
+        let version = VersionField::deserialize(&*value)
+            .with_context(|| format!("failed to parse {}", self.what))?
+            .version;
+
+        self.check(version)?;
+        self.migrate(value, version)
+    }
+
+    /// fails if the version cannot be read
+    fn check(&self, version: u32) -> anyhow::Result<()> {
+        if version > self.current {
+            bail!(
+                "{} version {version} is newer than the supported version {}",
+                self.what,
+                self.current
+            );
+        }
+
+        if version < self.oldest {
+            bail!(
+                "unsupported {} version {version}. the oldest supported version is {}",
+                self.what,
+                self.oldest
+            );
+        }
+
+        Ok(())
+    }
+
+    /// applies the migrations from the version to the current version
+    fn migrate(&self, value: &mut Value, version: u32) -> anyhow::Result<()> {
+        for from in version..self.current {
+            let migration = self.migrations[(from - self.oldest) as usize];
+
+            migration(value).with_context(|| {
+                format!(
+                    "failed to upgrade {} from version {from} to {}",
+                    self.what,
+                    from + 1
+                )
+            })?;
+
+            let Some(object) = value.as_object_mut() else {
+                bail!("{} is not a json object", self.what);
+            };
+
+            object.insert("version".to_owned(), Value::from(from + 1));
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// version 1 renamed `n` to `count` and version 2 added `name`
+    const TEST: Schema = Schema {
+        what: "test file",
+        current: 2,
+        oldest: 0,
+        migrations: &[
+            |value| {
+                let count = value["n"].take();
+                value["count"] = count;
+
+                Ok(())
+            },
+            |value| {
+                value["name"] = Value::from("unnamed");
+
+                Ok(())
+            },
+        ],
+    };
+
+    #[derive(Debug, PartialEq, Deserialize)]
+    struct TestFile {
+        version: u32,
+        count: u32,
+        name: String,
+    }
+
+    #[test]
+    fn migrate_older_versions() {
+        let expected = TestFile {
+            version: 2,
+            count: 3,
+            name: "unnamed".to_owned(),
+        };
+
+        let legacy: TestFile = TEST.from_slice(br#"{"n":3}"#).unwrap();
+        let first: TestFile = TEST.from_slice(br#"{"version":1,"count":3}"#).unwrap();
+        let current: TestFile = TEST
+            .from_slice(br#"{"version":2,"count":3,"name":"unnamed"}"#)
+            .unwrap();
+
+        assert_eq!(legacy, expected);
+        assert_eq!(first, expected);
+        assert_eq!(current, expected);
+
+        let newer = TEST.from_slice::<TestFile>(br#"{"version":3,"count":3,"name":"a"}"#);
+
+        assert!(newer.is_err());
+    }
+}
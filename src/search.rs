@@ -1,264 +1,3087 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::path::PathBuf;
+use std::sync::mpsc::{self, Receiver};
+use std::sync::{Mutex, MutexGuard};
+use std::time::{Duration, Instant};
 
-use anyhow::bail;
-use clap::Args;
+use anyhow::{anyhow, bail};
+use clap::{Args, ValueEnum};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use rayon::prelude::*;
 
-use crate::classify::classify_datapoint;
-use crate::cli::{AlgoType, ColumnType, KValue};
-use crate::csv::{KnnRecord, Reader, collect_records, get_columns_and_label};
-use crate::distance;
+use crate::balance::{SplitCounts, balance_records, fold_counts, print_balance};
+use crate::checkpoint::{Checkpoint, CheckpointParams};
+use crate::classify::{average_groups, count_labels, largest_group, majority_label, sort_closest};
+use crate::cli::{
+    AlgoType, Balance, ColumnList, ColumnType, EvalAgainst, KValue, NestedCv, SplitStrategy,
+    TaskType, TimeLimit,
+};
+use crate::csv::{
+    Dataset, KnnRecord, check_numeric_labels, collect_records, column_names, get_columns_excluding,
+};
+use crate::distance::DistanceMetric;
+use crate::dry_run::DryRun;
+use crate::error::KnnError;
+use crate::grid::Grid;
+use crate::index::{IndexArgs, IndexType, NeighborIndex, RECALL_SAMPLE, recall};
+use crate::metrics::Evaluation;
+use crate::progress::{NoProgress, Progress};
+use crate::results::SearchResult;
+use crate::scale::{ScaleType, Scaler};
+use crate::source::DataSource;
+use crate::split::{
+    fold_dataset, partition_fold, split_dataset, split_temporal, temporal_boundary,
+};
 
-#[derive(Debug, Args)]
+#[derive(Debug, Clone, Args)]
 pub struct SearchArgs {
     /// the number of neighbors to lookup
     #[arg(short, default_value = "3-10")]
     k: KValue,
 
+    /// skips even k values to avoid ties between two labels
+    #[arg(long)]
+    odd_only: bool,
+
     /// specifies the algorithm to use when calculating distances
     #[arg(long, default_value = "euclidean")]
     algo: AlgoType,
 
     /// the list of columns to use when searching
-    #[arg(short, long = "col")]
-    columns: Vec<ColumnType>,
+    #[arg(short, long = "col", allow_hyphen_values = true)]
+    columns: Vec<ColumnList>,
+
+    /// columns to remove from the list of columns
+    #[arg(long = "exclude-col", allow_hyphen_values = true)]
+    exclude: Vec<ColumnList>,
 
     /// the percent of data to test against
     #[arg(long, default_value = "0.25")]
     test: f64,
 
+    /// how the records are divided into training and test records
+    #[arg(long, default_value = "random")]
+    split_strategy: SplitStrategy,
+
+    /// the column with the time of each record for the temporal split as a
+    /// number, such as a unix timestamp. the rows of the csv are expected to
+    /// be in order if not specified. the column is never used as a feature
+    #[arg(long, allow_negative_numbers = true)]
+    time_col: Option<ColumnType>,
+
+    /// the records that test records are classified against
+    #[arg(long, default_value = "train")]
+    eval_against: EvalAgainst,
+
+    /// balances the labels of the training records before they are
+    /// classified against. undersample randomly drops records of the larger
+    /// labels using the seed
+    #[arg(long, default_value = "none")]
+    balance: Balance,
+
     /// the colume to use as the label
+    #[arg(long, allow_negative_numbers = true)]
+    label: Option<ColumnType>,
+
+    /// the type of task to evaluate the labels with
+    #[arg(long, default_value = "classification")]
+    task: TaskType,
+
+    #[command(flatten)]
+    index: IndexArgs,
+
+    /// the direction to select columns in. only the greedy strategy can
+    /// select columns backward
+    #[arg(long, default_value = "forward")]
+    direction: Direction,
+
+    /// the strategy used to select columns
+    #[arg(long, default_value = "greedy")]
+    strategy: Strategy,
+
+    /// the number of best column subsets to keep each step when using the
+    /// greedy strategy in the forward direction
+    #[arg(long, default_value = "1")]
+    beam_width: usize,
+
+    /// evaluates every combination of columns. same as `--strategy
+    /// exhaustive`
+    #[arg(long, conflicts_with = "strategy")]
+    exhaustive: bool,
+
+    /// subtracted from the score for every selected column so that smaller
+    /// subsets of columns are preferred when ranking the results. the
+    /// forward direction keeps adding columns unless `--patience` is given
+    #[arg(long, default_value = "0")]
+    penalty: f64,
+
+    /// the maximum number of columns to select. does not apply to the
+    /// backward direction
+    #[arg(long)]
+    max_features: Option<usize>,
+
+    /// stops adding columns once the best score with the penalty applied has
+    /// not improved for the specified number of additions
+    #[arg(long)]
+    patience: Option<usize>,
+
+    /// the number of individuals in each generation of the genetic strategy
+    #[arg(long, default_value = "50")]
+    population: usize,
+
+    /// the number of generations to run for the genetic strategy
+    #[arg(long, default_value = "40")]
+    generations: usize,
+
+    /// the number of configurations to evaluate for each scaling method with
+    /// the random strategy
+    #[arg(long, default_value = "500")]
+    budget: usize,
+
+    /// the maximum amount of time to search for. accepts a number with an
+    /// optional unit of ms, s, m, or h (defaults to seconds)
+    #[arg(long)]
+    max_time: Option<TimeLimit>,
+
+    /// periodically saves the evaluated configurations to the specified file
+    #[arg(long)]
+    checkpoint: Option<PathBuf>,
+
+    /// the minimum amount of time between saving checkpoints
+    #[arg(long, default_value = "60s")]
+    checkpoint_interval: TimeLimit,
+
+    /// resumes a search from a previously saved checkpoint file. the
+    /// checkpoint will continue to be saved to this file unless
+    /// `--checkpoint` is specified
+    #[arg(long)]
+    resume: Option<PathBuf>,
+
+    /// the seed used to shuffle records when splitting the dataset and by
+    /// the genetic and random strategies. the seed of the checkpoint is used
+    /// when resuming and a random seed is used otherwise if not specified
+    #[arg(long)]
+    seed: Option<u64>,
+
+    /// a comma delimitered list of scaling methods to search over
+    #[arg(long, value_delimiter = ',', default_value = "none")]
+    scale: Vec<ScaleType>,
+
+    /// a toml file that lists the k ranges, algorithms, scaling methods, and
+    /// pools of columns to search over. any that are not listed in the file
+    /// will use the command line arguments
+    #[arg(long, conflicts_with_all = ["checkpoint", "resume"])]
+    grid: Option<PathBuf>,
+
+    /// selects the configuration with cross validation on the inner folds and
+    /// reports the score of the outer folds. specified as outer=N,inner=N
+    #[arg(long, conflicts_with_all = ["checkpoint", "resume", "grid"])]
+    nested_cv: Option<NestedCv>,
+
+    /// lists the specified number of best configurations across every k value
+    /// once the search is done
     #[arg(long)]
-    label: ColumnType,
+    top: Option<usize>,
+
+    /// prints the resolved columns, label, scaling parameters, k values, and
+    /// record counts without searching anything
+    #[arg(long, conflicts_with = "resume")]
+    dry_run: bool,
 }
 
-struct SearchResult {
-    k: usize,
-    percent: f64,
-    cols: Vec<usize>,
+impl SearchArgs {
+    /// the files other than the csv that the search reads. checkpoints are
+    /// not included since the search writes to them
+    pub fn input_files(&self) -> impl Iterator<Item = &PathBuf> {
+        self.grid.iter()
+    }
 }
 
-pub fn knn_search<R>(mut reader: Reader<R>, arg: SearchArgs) -> anyhow::Result<()>
-where
-    R: std::io::Read,
-{
-    if arg.columns.is_empty() {
-        bail!("no columns specified to pull numeric data from");
+/// represents the strategy used to select columns when searching
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Strategy {
+    /// selects a single column at a time in the specified direction
+    Greedy,
+
+    /// evaluates every combination of columns
+    Exhaustive,
+
+    /// sequential floating forward selection. similar to greedy forward but
+    /// allows previously selected columns to be removed
+    Floating,
+
+    /// evolves a population of column subsets over multiple generations
+    Genetic,
+
+    /// evaluates randomly sampled k values and column subsets
+    Random,
+}
+
+/// represents the direction that columns are selected in
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Direction {
+    /// starts with no columns and adds the most useful column each step
+    Forward,
+
+    /// starts with all columns and removes the least useful column each step
+    Backward,
+}
+
+/// the shared data and options used when evaluating a subset of columns
+struct SearchContext<'a> {
+    /// the algorithm used by the indexes
+    algo: AlgoType,
+    /// the metric used to rank the neighbors without an index
+    metric: &'a dyn DistanceMetric,
+    task: TaskType,
+    /// the method used to find the nearest neighbors
+    index: IndexArgs,
+    /// the test records and the records they are classified against. the
+    /// outcomes of every split are combined into a single evaluation
+    splits: Vec<EvalSplit<'a>>,
+    /// the k values being searched in ascending order
+    ks: Vec<usize>,
+    /// the cost of each selected column
+    penalty: f64,
+    /// the maximum number of columns to select
+    max_features: usize,
+    /// the number of subsets to keep each step of the forward direction
+    beam_width: usize,
+    /// the number of steps without improvement before stopping
+    patience: Option<usize>,
+    /// the size of each generation for the genetic strategy
+    population: usize,
+    /// the number of generations for the genetic strategy
+    generations: usize,
+    /// the number of evaluations for the random strategy
+    budget: usize,
+    /// the point in time that the search must stop
+    deadline: Option<Instant>,
+    /// the scaling method applied to the records
+    scale: ScaleType,
+    /// the previously evaluated configurations
+    checkpoint: &'a Mutex<Checkpoint>,
+    /// the names of the csv columns used for output
+    names: &'a HashMap<usize, String>,
+    /// the labels of each label id of the records
+    labels: &'a [String],
+    /// receives every evaluated subset
+    progress: &'a dyn Progress,
+    /// the seed of the rng used by the genetic and random strategies
+    seed: u64,
+}
+
+/// a set of test records and the records that they are classified against
+struct EvalSplit<'a> {
+    against: Vec<KnnRecord<'a>>,
+    test: Vec<KnnRecord<'a>>,
+    /// the precalculated distance contributions of each column
+    cache: Option<ColumnCache>,
+}
+
+impl<'a> EvalSplit<'a> {
+    /// creates the split and caches the distance contributions of the
+    /// columns if the split is small enough
+    ///
+    /// nothing is cached when using an index since the distances are only
+    /// calculated for the records that the index visits or when the metric
+    /// is not a sum of column contributions
+    fn new(
+        metric: &dyn DistanceMetric,
+        index: IndexType,
+        against: Vec<KnnRecord<'a>>,
+        test: Vec<KnnRecord<'a>>,
+        columns: usize,
+    ) -> Self {
+        // falls back to calculating the distances directly if the split is
+        // too large to cache
+        let cache = match (index, metric.term()) {
+            (IndexType::Brute, Some(term)) => ColumnCache::build(term, &test, &against, columns),
+            _ => None,
+        };
+
+        Self {
+            against,
+            test,
+            cache,
+        }
     }
+}
 
-    // store a reference to the distance algorithm
-    let algo = match arg.algo {
-        AlgoType::Euclidean => distance::euclidean,
-        AlgoType::Manhattan => distance::manhattan,
-    };
+/// the maximum number of bytes the column cache is allowed to use
+const CACHE_LIMIT: usize = 1 << 30;
 
-    // retrieve the label and datapoint columns from the csv reader
-    let (label, columns) = get_columns_and_label(&mut reader, &arg.label, &arg.columns)?;
-    let records = collect_records(reader, label, &columns)?;
+/// the distance contributions of each column between every test record and
+/// every record that is classified against
+///
+/// the distance of a subset of columns is then the sum of the contributions
+/// for each column which avoids recalculating the same differences every
+/// time a column is added to a subset
+struct ColumnCache {
+    /// the number of records classified against
+    width: usize,
+    /// indexed by the data index and then `test index * width + index`
+    columns: Vec<Vec<f64>>,
+}
 
-    let (train, test) = split_dataset(&records, arg.test);
+impl ColumnCache {
+    /// calculates the contributions for every column
+    ///
+    /// returns [`None`] if the cache would be larger than [`CACHE_LIMIT`]
+    fn build(
+        term: fn(f64, f64) -> f64,
+        test: &[KnnRecord],
+        against: &[KnnRecord],
+        columns: usize,
+    ) -> Option<Self> {
+        let width = against.len();
+        let size = test
+            .len()
+            .checked_mul(width)?
+            .checked_mul(columns)?
+            .checked_mul(std::mem::size_of::<f64>())?;
 
-    // we are going to keep this pre-allocated since it is being reused multiple
-    // times so we will just clear it when needed vs constaint memory
-    // allocations
-    let mut collected = Vec::with_capacity(train.len());
-    let mut results = Vec::new();
+        if size > CACHE_LIMIT {
+            return None;
+        }
 
-    println!("train size: {} test size: {}", train.len(), test.len());
+        let columns = (0..columns)
+            .into_par_iter()
+            .map(|index| {
+                let mut rtn = Vec::with_capacity(test.len() * width);
 
-    // we are using the train dataset and manually iterating through
-    // the test dataset for datapoints to use for testing
-    for k in arg.k.get_range(train.len()) {
-        let mut selected: Vec<(usize, usize)> = Vec::new();
-        let mut avail: Vec<(usize, usize)> = columns
-            .iter()
-            .enumerate()
-            .map(|(index, col)| (index, *col))
+                for test_record in test {
+                    for record in against {
+                        rtn.push(term(test_record.data[index], record.data[index]));
+                    }
+                }
+
+                rtn
+            })
             .collect();
-        // can also pre allocate this since it will be reused for
-        // multiple test records
-        let mut groups = HashMap::with_capacity(k);
 
-        println!("k: {k}");
+        Some(Self { width, columns })
+    }
 
-        while !avail.is_empty() {
-            let mut best = None::<(usize, f64, (usize, usize))>;
-            let mut a_buf = Vec::with_capacity(selected.len() + 1);
+    /// sums the contributions of the columns for every pair of records
+    fn sum(&self, test_len: usize, subset: &[(usize, usize)]) -> Vec<f64> {
+        let mut rtn = vec![0.0; test_len * self.width];
 
-            for (avail_index, (index, col)) in avail.iter().enumerate() {
-                let mut passed = 0;
-                let mut failed = 0;
-                let mut unknown = 0;
+        for (index, _) in subset {
+            for (total, value) in rtn.iter_mut().zip(&self.columns[*index]) {
+                *total += value;
+            }
+        }
 
-                for test_record in &test {
-                    collected.clear();
-                    groups.clear();
+        rtn
+    }
+}
 
-                    collect_data(test_record, &mut a_buf, &selected, *index);
+impl SearchContext<'_> {
+    fn lock_checkpoint(&self) -> anyhow::Result<MutexGuard<'_, Checkpoint>> {
+        self.checkpoint
+            .lock()
+            .map_err(|_| anyhow!("checkpoint lock was poisoned"))
+    }
 
-                    let iter = records.iter().map(|train_record| {
-                        // with how this is currently setup, we are going to be
-                        // allocating for every record due to the constraints of
-                        // the Iterator::map function
-                        let data = collect_data_owned(train_record, &selected, *index);
+    /// outputs the results of evaluating a subset of columns
+    fn print_subset(&self, subset: &[(usize, usize)], eval: &Evaluation) {
+        print!("       ");
 
-                        (data, train_record.label.as_str())
-                    });
+        for (_, col) in subset {
+            print!(" {}", self.names[col]);
+        }
 
-                    let min =
-                        classify_datapoint(k, iter, algo, &a_buf, &mut collected, &mut groups);
+        println!(" | {eval}");
+    }
 
-                    let mut largest = None::<(f64, &str)>;
+    /// the score of an evaluation with the penalty for the number of columns
+    /// applied
+    ///
+    /// only needed when comparing subsets of different sizes since the
+    /// penalty is the same for subsets of the same size
+    fn score(&self, eval: &Evaluation, size: usize) -> f64 {
+        eval.score() - self.penalty * size as f64
+    }
 
-                    for (key, count) in &groups {
-                        let prob = (*count as f64) / (min as f64);
+    /// checks if the time limit for the search has been reached
+    fn expired(&self) -> bool {
+        self.deadline
+            .is_some_and(|deadline| Instant::now() >= deadline)
+    }
 
-                        // find the largest percent value from the collected
-                        // labels and store that.
-                        largest = if let Some((percent, label)) = largest {
-                            if prob > percent {
-                                Some((prob, key))
-                            } else {
-                                Some((percent, label))
-                            }
-                        } else {
-                            Some((prob, key))
+    /// evaluates the test records with only the specified columns
+    ///
+    /// the columns are a list of (data index, csv column) pairs. previously
+    /// evaluated columns are retrieved from the checkpoint. when the columns
+    /// have not been evaluated they are evaluated for every k in the search
+    /// range at once since the neighbors only need to be sorted once
+    fn evaluate(&self, k: usize, subset: &[(usize, usize)]) -> anyhow::Result<Evaluation> {
+        self.evaluate_from(k, subset, None)
+    }
+
+    /// same as [`SearchContext::evaluate`] but with the summed distance
+    /// contributions of every column in the subset except the last one
+    ///
+    /// the sums are retrieved from [`SearchContext::partial_sums`]
+    fn evaluate_from(
+        &self,
+        k: usize,
+        subset: &[(usize, usize)],
+        base: Option<&[Vec<f64>]>,
+    ) -> anyhow::Result<Evaluation> {
+        let columns = subset_columns(subset);
+
+        if let Some(eval) = self.lock_checkpoint()?.get(self.scale, k, &columns) {
+            self.progress.on_candidate_evaluated(k, &columns, eval);
+
+            return Ok(eval.clone());
+        }
+
+        // the random strategy could request a k outside of the range
+        let ks = if self.ks.contains(&k) {
+            self.ks.as_slice()
+        } else {
+            std::slice::from_ref(&k)
+        };
+
+        let evals = self.calculate(ks, subset, base)?;
+        let mut rtn = None;
+        let mut checkpoint = self.lock_checkpoint()?;
+
+        for (range_k, eval) in ks.iter().zip(evals) {
+            if *range_k == k {
+                rtn = Some(eval.clone());
+            }
+
+            checkpoint.insert(self.scale, *range_k, &columns, eval)?;
+        }
+
+        let rtn = rtn.ok_or_else(|| anyhow!("k {k} was not evaluated"))?;
+
+        self.progress.on_candidate_evaluated(k, &columns, &rtn);
+
+        Ok(rtn)
+    }
+
+    /// calculates the evaluation of the test records for each of the k values
+    /// without checking the checkpoint
+    ///
+    /// the k values must be in ascending order. the evaluations are returned
+    /// in the same order as the k values
+    fn calculate(
+        &self,
+        ks: &[usize],
+        subset: &[(usize, usize)],
+        base: Option<&[Vec<f64>]>,
+    ) -> anyhow::Result<Vec<Evaluation>> {
+        let mut outcomes = Vec::new();
+
+        for (split_index, split) in self.splits.iter().enumerate() {
+            let split_base = base.map(|base| base[split_index].as_slice());
+
+            outcomes.extend(self.calculate_split(split, ks, subset, split_base)?);
+        }
+
+        let mut rtn = Vec::with_capacity(ks.len());
+
+        for index in 0..ks.len() {
+            rtn.push(self.tally(outcomes.iter().map(|record| &record[index])));
+        }
+
+        Ok(rtn)
+    }
+
+    /// calculates the outcomes of each test record in the split for each of
+    /// the k values
+    fn calculate_split(
+        &self,
+        split: &EvalSplit<'_>,
+        ks: &[usize],
+        subset: &[(usize, usize)],
+        base: Option<&[f64]>,
+    ) -> anyhow::Result<Vec<Vec<Outcome>>> {
+        let max_k = ks.last().copied().unwrap_or(0);
+        // the tree only contains the columns of the subset so it is built
+        // for every evaluation
+        let tree = match self.index.kind {
+            IndexType::Brute => None,
+            _ => Some(
+                self.index.build(
+                    subset.len(),
+                    split
+                        .against
+                        .iter()
+                        .enumerate()
+                        .map(|(index, record)| (index, collect_data_owned(record, subset))),
+                    self.algo,
+                )?,
+            ),
+        };
+
+        // the test records are classified in parallel. the buffers are
+        // allocated once per thread and cleared for every test record to
+        // avoid constant memory allocations
+        split
+            .test
+            .par_iter()
+            .enumerate()
+            .map_init(
+                || {
+                    (
+                        Vec::with_capacity(split.against.len()),
+                        Vec::new(),
+                        Vec::with_capacity(subset.len()),
+                        Vec::with_capacity(subset.len()),
+                    )
+                },
+                |(collected, groups, a_buf, b_buf), (test_index, test_record)| {
+                    collected.clear();
+                    groups.clear();
+
+                    if let Some(tree) = &tree {
+                        collect_data(test_record, a_buf, subset);
+
+                        for (distance, index) in tree.nearest(a_buf, max_k) {
+                            collected.push((distance, index, split.against[index].label));
+                        }
+                    } else if let Some(cache) = &split.cache {
+                        let offset = test_index * cache.width;
+
+                        // the base already contains every column except
+                        // for the last one so only the last one is added
+                        let remaining = match base {
+                            Some(_) => &subset[subset.len() - 1..],
+                            None => subset,
                         };
-                    }
 
-                    // check to see if the largest value found is valid.
-                    // increment values accordingly
-                    if let Some((_, label)) = largest {
-                        if label == test_record.label {
-                            passed += 1;
-                        } else {
-                            failed += 1;
+                        for (index, record) in split.against.iter().enumerate() {
+                            let mut distance = base.map_or(0.0, |base| base[offset + index]);
+
+                            for (data_index, _) in remaining {
+                                distance += cache.columns[*data_index][offset + index];
+                            }
+
+                            collected.push((distance, index, record.label));
                         }
                     } else {
-                        unknown += 1;
+                        collect_data(test_record, a_buf, subset);
+
+                        for (index, train_record) in split.against.iter().enumerate() {
+                            collect_data(train_record, b_buf, subset);
+
+                            collected.push((
+                                self.metric.rank(a_buf, b_buf),
+                                index,
+                                train_record.label,
+                            ));
+                        }
                     }
-                }
 
-                // this is not RMSE or similar and instead just calculating the
-                // percentage of records correct. the largest percentage will
-                // be included in the `selected` list. output the results for
-                // this iteration
+                    // only the neighbors of the largest k are sorted once
+                    // and the groups are extended for each k since the
+                    // order of the neighbors does not change
+                    sort_closest(collected, max_k);
 
-                let p_correct = (passed as f64) / (test.len() as f64);
+                    let mut counted = 0;
+                    let mut rtn = Vec::with_capacity(ks.len());
 
-                print!("       ");
+                    for k in ks {
+                        let min = std::cmp::min(*k, collected.len());
 
-                for (_, sel_col) in &selected {
-                    print!(" {sel_col}");
-                }
+                        count_labels(
+                            collected
+                                .iter()
+                                .take(min)
+                                .skip(counted)
+                                .map(|(_, _, label)| *label),
+                            groups,
+                        );
 
-                println!(
-                    " {col} | passed: {passed} {p_correct:.2} failed: {failed} unknown: {unknown}"
-                );
+                        counted = std::cmp::max(counted, min);
 
-                best = if let Some((best_index, best_p, (index_ref, best_col))) = best {
-                    if best_p > p_correct {
-                        Some((best_index, best_p, (index_ref, best_col)))
-                    } else {
-                        Some((avail_index, p_correct, (*index, *col)))
+                        rtn.push(self.outcome(test_record, groups, min)?);
                     }
-                } else {
-                    Some((avail_index, p_correct, (*index, *col)))
-                };
-            }
 
-            let Some((best_index, best_p, (index, col))) = best else {
-                break;
+                    Ok(rtn)
+                },
+            )
+            .collect()
+    }
+
+    /// estimates the fraction of the true nearest neighbors found by the
+    /// index for the largest k value
+    ///
+    /// returns [`None`] if there was nothing to compare
+    fn recall(&self, subset: &[(usize, usize)]) -> anyhow::Result<Option<f64>> {
+        let Some(max_k) = self.ks.last().copied() else {
+            return Ok(None);
+        };
+        let mut found = 0;
+        let mut expected = 0;
+
+        for split in &self.splits {
+            let points = || {
+                split
+                    .against
+                    .iter()
+                    .enumerate()
+                    .map(|(index, record)| (index, collect_data_owned(record, subset)))
             };
+            let queries: Vec<Vec<f64>> = split
+                .test
+                .iter()
+                .take(RECALL_SAMPLE)
+                .map(|record| collect_data_owned(record, subset))
+                .collect();
+            let index = self.index.build(subset.len(), points(), self.algo)?;
+            let (split_found, split_expected) = recall(
+                &index,
+                subset.len(),
+                points(),
+                self.algo,
+                queries.iter().map(Vec::as_slice),
+                max_k,
+            );
+
+            found += split_found;
+            expected += split_expected;
+        }
+
+        if expected == 0 {
+            Ok(None)
+        } else {
+            Ok(Some(found as f64 / expected as f64))
+        }
+    }
+
+    /// sums the distance contributions of the subset for every pair of
+    /// records
+    ///
+    /// returns [`None`] if any of the splits are not cached or the subset is
+    /// empty
+    fn partial_sums(&self, subset: &[(usize, usize)]) -> Option<Vec<Vec<f64>>> {
+        if subset.is_empty() {
+            return None;
+        }
+
+        self.splits
+            .iter()
+            .map(|split| {
+                split
+                    .cache
+                    .as_ref()
+                    .map(|cache| cache.sum(split.test.len(), subset))
+            })
+            .collect()
+    }
 
-            // updated the selected columns and remove from available so we
-            // make progress and don't repeat columns
-            selected.push((index, col));
-            avail.remove(best_index);
+    /// checks to see if the predicted value of a test record is valid
+    fn outcome(
+        &self,
+        test_record: &KnnRecord,
+        groups: &[u32],
+        min: usize,
+    ) -> anyhow::Result<Outcome> {
+        match self.task {
+            TaskType::Classification => match largest_group(groups) {
+                Some((label, _)) => Ok(Outcome::Label(label == test_record.label)),
+                None => Ok(Outcome::Unknown),
+            },
+            TaskType::Regression => match average_groups(groups, self.labels, min) {
+                // labels were checked before searching
+                Some(value) => Ok(Outcome::Value(
+                    self.labels[test_record.label as usize].parse::<f64>()?,
+                    value,
+                )),
+                None => Ok(Outcome::Unknown),
+            },
+        }
+    }
 
-            let mut cols = Vec::new();
+    /// combines the outcomes of the test records into an evaluation
+    fn tally<'b, I>(&self, outcomes: I) -> Evaluation
+    where
+        I: Iterator<Item = &'b Outcome>,
+    {
+        // used for tracking classification results
+        let mut passed = 0;
+        let mut failed = 0;
+        // used for tracking regression results
+        let mut actual = Vec::new();
+        let mut predicted = Vec::new();
 
-            for (_, col) in &selected {
-                cols.push(*col);
+        let mut unknown = 0;
+
+        // increment values accordingly
+        for outcome in outcomes {
+            match outcome {
+                Outcome::Label(true) => passed += 1,
+                Outcome::Label(false) => failed += 1,
+                Outcome::Value(a, p) => {
+                    actual.push(*a);
+                    predicted.push(*p);
+                }
+                Outcome::Unknown => unknown += 1,
             }
+        }
 
-            // store the results to be output later
-            results.push(SearchResult {
-                k,
-                percent: best_p * 100.0,
-                cols,
-            });
+        // the metrics used are determined by the task type
+        match self.task {
+            TaskType::Classification => Evaluation::Classification {
+                passed,
+                failed,
+                unknown,
+            },
+            TaskType::Regression => Evaluation::regression(&actual, &predicted, unknown),
+        }
+    }
+}
+
+/// the result of predicting a single test record
+enum Outcome {
+    /// a label was predicted and indicates if it was correct
+    Label(bool),
+    /// the actual and predicted values of a regression
+    Value(f64, f64),
+    /// nothing could be predicted
+    Unknown,
+}
+
+pub fn knn_search<S>(source: S, arg: SearchArgs, progress: &dyn Progress) -> anyhow::Result<()>
+where
+    S: DataSource,
+{
+    let search = load_search(source, arg)?;
+
+    if search.arg.dry_run {
+        search.dry_run();
+
+        return Ok(());
+    }
+
+    if let Some(nested) = search.arg.nested_cv {
+        // the evaluations of each fold are only kept in memory so the seed
+        // is only used to balance and search each fold
+        let params = CheckpointParams {
+            label: search.label,
+            columns: search.columns.clone(),
+            algo: search.arg.algo,
+            task: search.arg.task,
+            test: search.arg.test,
+            seed: search.seed,
+            eval_against: search.arg.eval_against,
+            balance: search.arg.balance,
+            split_strategy: search.arg.split_strategy,
+            time: search.time,
+        };
+
+        return nested_search(
+            &search.arg,
+            nested,
+            search.deadline,
+            &search.records,
+            params,
+            &search.names,
+            progress,
+        );
+    }
+
+    let mut results = Vec::new();
+    let baseline = search.run(progress, &mut |result| {
+        results.push(result);
+
+        Ok(())
+    })?;
+
+    // best configurations first. the sort is stable so configurations with
+    // the same score stay in the order they were evaluated
+    let penalized =
+        |record: &SearchResult| record.eval.score() - search.arg.penalty * record.cols.len() as f64;
+
+    results.sort_by(|a, b| penalized(b).total_cmp(&penalized(a)));
+
+    if let Some(top) = search.arg.top {
+        println!("best configurations:");
+
+        for record in results.iter().take(top) {
+            print_result(record, baseline, search.arg.penalty, &search.names);
         }
     }
 
-    for record in results {
-        print!("k {} % {:.2} cols:", record.k, record.percent);
+    // since the results are sorted the first result of each k is the best
+    let mut best_k: Vec<&SearchResult> = Vec::new();
 
-        for col in record.cols {
-            print!(" {col}");
+    for record in &results {
+        if !best_k.iter().any(|known| known.k == record.k) {
+            best_k.push(record);
         }
+    }
+
+    best_k.sort_by_key(|record| record.k);
+
+    println!("best per k:");
 
-        println!();
+    for record in best_k {
+        print_result(record, baseline, search.arg.penalty, &search.names);
+    }
+
+    if let Some(record) = results.first() {
+        println!("best overall:");
+
+        print_result(record, baseline, search.arg.penalty, &search.names);
     }
 
     Ok(())
 }
 
-/// split the specified list of records based on the label provided
+/// the number of results that can be found ahead of the consumer of
+/// [`SearchResults`] before the search waits
+const RESULT_BUFFER: usize = 64;
+
+/// searches on a separate thread and returns the results as they are found
+/// instead of once the whole search has finished
+///
+/// the records are loaded before this returns so any problems with the
+/// source or arguments are returned here. the search prints the same lines as
+/// [`knn_search`] while it runs. nested cross validation is not available
+/// since its folds do not produce search results.
+pub fn knn_search_iter<S>(source: S, arg: SearchArgs) -> anyhow::Result<SearchResults>
+where
+    S: DataSource,
+{
+    spawn_search(load_search(source, arg)?)
+}
+
+/// same as [`knn_search_iter`] but ranks the neighbors with a custom metric
+/// in place of the algorithm of the arguments
 ///
-/// ordering is preserved from the original list
-fn split_dataset<'a>(
-    records: &'a [KnnRecord],
-    split: f64,
-) -> (Vec<&'a KnnRecord>, Vec<&'a KnnRecord>) {
-    let mut groups: HashMap<&'a str, Vec<&KnnRecord>> = HashMap::new();
+/// the metric is only used with the brute index. the results still report
+/// the algorithm of the arguments and nothing is checkpointed since a
+/// checkpoint could not tell the metrics apart. smote finds the neighbors of
+/// the records with the algorithm so it is not available either.
+pub fn knn_search_iter_with<S, M>(
+    source: S,
+    arg: SearchArgs,
+    metric: M,
+) -> anyhow::Result<SearchResults>
+where
+    S: DataSource,
+    M: DistanceMetric + 'static,
+{
+    let mut search = load_search(source, arg)?;
 
-    for record in records {
-        groups
-            .entry(record.label.as_str())
-            // increment if the group was previously added
-            .and_modify(|list| list.push(record))
-            // insert if not already existing
-            .or_insert(vec![record]);
+    if search.arg.index.kind != IndexType::Brute {
+        bail!("a custom metric is only available with the brute index");
     }
 
-    let mut train = Vec::new();
-    let mut test = Vec::new();
+    if search.algos.len() > 1 {
+        bail!("a grid cannot search algorithms with a custom metric");
+    }
 
-    for (_, mut records) in groups {
-        // split the record groups based on the split specified.
-        let amount = (records.len() as f64 * split).floor() as usize;
+    if search.arg.checkpoint.is_some() || search.arg.resume.is_some() {
+        bail!("a search with a custom metric cannot be checkpointed");
+    }
 
-        train.extend(records.split_off(amount));
-        test.extend(records);
+    if search.arg.balance == Balance::Smote {
+        bail!("smote is not available with a custom metric");
     }
 
-    (train, test)
+    search.metric = Some(Box::new(metric));
+
+    spawn_search(search)
 }
 
-fn collect_data_owned(
-    record: &KnnRecord,
-    selected: &[(usize, usize)],
-    checking: usize,
-) -> Vec<f64> {
-    let mut rtn = Vec::with_capacity(selected.len() + 1);
+/// runs a loaded search on a separate thread for [`knn_search_iter`]
+fn spawn_search(search: LoadedSearch) -> anyhow::Result<SearchResults> {
+    if search.arg.nested_cv.is_some() {
+        bail!("nested cross validation cannot be returned as search results");
+    }
 
-    collect_data(record, &mut rtn, selected, checking);
+    let names = search.names.clone();
+    let (sender, receiver) = mpsc::sync_channel(RESULT_BUFFER);
 
-    rtn
+    std::thread::spawn(move || {
+        let result = search.run(&NoProgress, &mut |result| {
+            sender
+                .send(Ok(result))
+                .map_err(|_| anyhow!("the search results were dropped"))
+        });
+
+        // the error is only sent if the results are still being read
+        if let Err(err) = result {
+            let _ = sender.send(Err(err));
+        }
+    });
+
+    Ok(SearchResults { receiver, names })
 }
 
-fn collect_data(
-    record: &KnnRecord,
-    buf: &mut Vec<f64>,
-    selected: &[(usize, usize)],
-    checking: usize,
-) {
-    buf.clear();
+/// the results of [`knn_search_iter`] in the order they are found
+///
+/// the results are not sorted by score. dropping the iterator stops the
+/// search once it tries to return the next result. an error ends the
+/// results.
+pub struct SearchResults {
+    receiver: Receiver<anyhow::Result<SearchResult>>,
+    names: HashMap<usize, String>,
+}
 
-    // collect the datapoints from test record
-    for (index, _) in selected {
-        buf.push(record.data[*index]);
+impl SearchResults {
+    /// the name of a csv column in [`SearchResult::cols`]
+    pub fn column_name(&self, col: usize) -> Option<&str> {
+        self.names.get(&col).map(String::as_str)
+    }
+}
+
+impl Iterator for SearchResults {
+    type Item = anyhow::Result<SearchResult>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.receiver.recv().ok()
+    }
+}
+
+/// the records and arguments of a search once they have been validated and
+/// loaded from the source
+struct LoadedSearch {
+    /// the arguments with the strategy of the exhaustive flag applied
+    arg: SearchArgs,
+    /// a grid file replaced the arguments below
+    grid: bool,
+    algos: Vec<AlgoType>,
+    scales: Vec<ScaleType>,
+    k_values: Vec<KValue>,
+    /// the csv columns of each pool
+    pool_columns: Vec<Vec<usize>>,
+    deadline: Option<Instant>,
+    /// the records with every column of each pool
+    records: Dataset,
+    label: usize,
+    /// the csv column of each data index of the records
+    columns: Vec<usize>,
+    names: HashMap<usize, String>,
+    /// the csv column with the time of each record
+    time: Option<usize>,
+    /// the time of each record when a time column is given. the records are
+    /// ordered by it
+    times: Option<Vec<f64>>,
+    /// ranks the neighbors in place of the algorithm
+    metric: Option<Box<dyn DistanceMetric>>,
+    /// the given seed or a random one. the seed of a resumed checkpoint is
+    /// used instead if one was not given
+    seed: u64,
+}
+
+/// validates the arguments and loads the records to search
+fn load_search<S>(mut source: S, mut arg: SearchArgs) -> anyhow::Result<LoadedSearch>
+where
+    S: DataSource,
+{
+    let seed = arg.seed.unwrap_or_else(rand::random);
+    let grid = match &arg.grid {
+        Some(path) => Some(Grid::load(path)?),
+        None => None,
+    };
+
+    // the grid will replace any of the arguments that it specifies
+    let pools = match &grid {
+        Some(grid) if !grid.columns.is_empty() => grid.columns.clone(),
+        _ => vec![ColumnList::flatten(&arg.columns)],
+    };
+    let algos = match &grid {
+        Some(grid) if !grid.algo.is_empty() => grid.algo.clone(),
+        _ => vec![arg.algo],
+    };
+    let scales = match &grid {
+        Some(grid) if !grid.scale.is_empty() => grid.scale.clone(),
+        _ => arg.scale.clone(),
+    };
+    let k_values = match &grid {
+        Some(grid) if !grid.k.is_empty() => grid.k.clone(),
+        _ => vec![arg.k.clone()],
+    };
+
+    for k in &k_values {
+        k.check_odd_only(arg.odd_only)?;
+    }
+
+    if arg.max_features == Some(0) {
+        bail!("max features must be larger than 0");
+    }
+
+    if arg.patience == Some(0) {
+        bail!("patience must be larger than 0");
+    }
+
+    if !arg.penalty.is_finite() || arg.penalty < 0.0 {
+        bail!("penalty must be a positive number or 0");
+    }
+
+    if arg.beam_width == 0 {
+        bail!("beam width must be larger than 0");
+    }
+
+    if arg.population == 0 {
+        bail!("population must be larger than 0");
     }
 
-    buf.push(record.data[checking]);
+    if arg.generations == 0 {
+        bail!("generations must be larger than 0");
+    }
+
+    if arg.balance != Balance::None {
+        if arg.task != TaskType::Classification {
+            bail!("balancing is only available for classification");
+        }
+
+        if arg.eval_against == EvalAgainst::All {
+            bail!("balancing is only available when evaluating against the training records");
+        }
+    }
+
+    if arg.split_strategy == SplitStrategy::Temporal && arg.nested_cv.is_some() {
+        bail!("the temporal split strategy cannot be used with nested cross validation");
+    }
+
+    if arg.time_col.is_some() && arg.split_strategy != SplitStrategy::Temporal {
+        bail!("a time column is only used by the temporal split strategy");
+    }
+
+    let deadline = arg
+        .max_time
+        .map(|limit| Instant::now() + limit.into_inner());
+
+    // the exhaustive flag is a shorthand for the strategy
+    if arg.exhaustive {
+        arg.strategy = Strategy::Exhaustive;
+    }
+
+    if arg.direction == Direction::Backward && arg.strategy != Strategy::Greedy {
+        bail!("the backward direction is only available with the greedy strategy");
+    }
+
+    if arg.beam_width != 1
+        && (arg.strategy != Strategy::Greedy || arg.direction != Direction::Forward)
+    {
+        bail!("a beam width is only available with the greedy strategy in the forward direction");
+    }
+
+    // retrieve the label and datapoint columns from the source. the
+    // records contain every column from each of the pools
+    let label_column = ColumnType::label_or_last(arg.label.as_ref());
+    let mut label = 0;
+    let mut columns = Vec::new();
+    let mut pool_columns = Vec::with_capacity(pools.len());
+    let time = match &arg.time_col {
+        Some(time_col) => {
+            let (_, found) = get_columns_excluding(
+                &mut source,
+                &label_column,
+                std::slice::from_ref(time_col),
+                &[],
+            )?;
+
+            if found.len() != 1 {
+                bail!("the time column must be a single column");
+            }
+
+            Some(found[0])
+        }
+        None => None,
+    };
+    // the time column is never a feature
+    let exclude = [
+        ColumnList::flatten(&arg.exclude).as_slice(),
+        arg.time_col.as_slice(),
+    ]
+    .concat();
+
+    for pool in &pools {
+        let (found, pool_cols) = get_columns_excluding(&mut source, &label_column, pool, &exclude)?;
+
+        for col in &pool_cols {
+            if !columns.contains(col) {
+                columns.push(*col);
+            }
+        }
+
+        label = found;
+        pool_columns.push(pool_cols);
+    }
+
+    let names = column_names(&mut source, &[columns.as_slice(), &[label]].concat())?;
+    let (records, times) = match time {
+        Some(time) => {
+            let loaded =
+                match collect_records(source, label, &[columns.as_slice(), &[time]].concat()) {
+                    // the time is not a feature so the hint of the error would
+                    // not apply to it
+                    Err(
+                        KnnError::Parse { row, column, .. }
+                        | KnnError::MissingValue { row, column, .. },
+                    ) if column == time => bail!(
+                        "the time column must be a number, such as a unix timestamp. row: {}",
+                        row + 1
+                    ),
+                    result => result?,
+                };
+            let (records, times) = order_by_time(&loaded);
+
+            // records that share the time of the first test record are
+            // tested so every record could end up tested
+            if temporal_boundary(times.len(), arg.test, Some(&times)) == 0
+                && temporal_boundary(times.len(), arg.test, None) > 0
+            {
+                bail!(
+                    "every training record has the same time as the first test record so none \
+                     are left to train on. time: {}",
+                    times[0]
+                );
+            }
+
+            (records, Some(times))
+        }
+        None => (collect_records(source, label, &columns)?, None),
+    };
+
+    if arg.task == TaskType::Regression {
+        check_numeric_labels(&records)?;
+    }
+
+    Ok(LoadedSearch {
+        arg,
+        grid: grid.is_some(),
+        algos,
+        scales,
+        k_values,
+        pool_columns,
+        deadline,
+        records,
+        label,
+        columns,
+        names,
+        time,
+        times,
+        metric: None,
+        seed,
+    })
+}
+
+/// removes the time from the end of every record and orders the records by
+/// their time. records with the same time stay in the order of the csv
+fn order_by_time(loaded: &Dataset) -> (Dataset, Vec<f64>) {
+    let dims = loaded.dims() - 1;
+    let time = |index: usize| loaded.get(index).data[dims];
+    let mut order: Vec<usize> = (0..loaded.len()).collect();
+
+    order.sort_by(|a, b| time(*a).total_cmp(&time(*b)));
+
+    let mut records = Dataset::with_labels_of(loaded, dims, loaded.len());
+    let mut times = Vec::with_capacity(loaded.len());
+
+    for index in order {
+        let record = loaded.get(index);
+
+        records.push_id(&record.data[..dims], record.label);
+        times.push(record.data[dims]);
+    }
+
+    (records, times)
+}
+
+impl LoadedSearch {
+    /// divides the records into the training and test records with the split
+    /// strategy. the records must be in the same order as the loaded records
+    fn split<'a>(
+        &self,
+        records: &'a Dataset,
+        seed: u64,
+    ) -> (Vec<KnnRecord<'a>>, Vec<KnnRecord<'a>>) {
+        match self.arg.split_strategy {
+            SplitStrategy::Random => {
+                // regression labels are continuous so stratifying by label
+                // would place almost every record into its own group
+                let stratify = self.arg.task == TaskType::Classification;
+
+                split_dataset(records, self.arg.test, stratify, seed)
+            }
+            SplitStrategy::Temporal => {
+                split_temporal(records, self.arg.test, self.times.as_deref())
+            }
+        }
+    }
+
+    /// prints the resolved configuration of the search. the scaling is
+    /// fitted on the training records the same as the search unless nested
+    /// cross validation is used
+    fn dry_run(&self) {
+        let fitted: Vec<KnnRecord> = if self.arg.nested_cv.is_some() {
+            self.records.iter().collect()
+        } else {
+            let seed = self.seed;
+            let (train, test) = self.split(&self.records, seed);
+
+            println!(
+                "train size: {} test size: {} seed: {seed}",
+                train.len(),
+                test.len()
+            );
+
+            train
+        };
+
+        println!(
+            "algo: {}",
+            self.algos
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>()
+                .join(",")
+        );
+        print!(
+            "{}",
+            DryRun {
+                label: self.label,
+                columns: &self.columns,
+                names: &self.names,
+                records: &self.records,
+                fitted: &fitted,
+                scales: &self.scales,
+                k_values: &self.k_values,
+                odd_only: self.arg.odd_only,
+            }
+        );
+    }
+
+    /// searches every configuration and passes each result to `sink` as
+    /// soon as it is found. stops with the error of `sink` if it fails
+    ///
+    /// returns the majority baseline of the test records for classification
+    fn run(
+        &self,
+        progress: &dyn Progress,
+        sink: &mut dyn FnMut(SearchResult) -> anyhow::Result<()>,
+    ) -> anyhow::Result<Option<(u32, f64)>> {
+        let LoadedSearch {
+            arg,
+            grid,
+            algos,
+            scales,
+            k_values,
+            pool_columns,
+            deadline,
+            records,
+            label,
+            columns,
+            names,
+            time,
+            times: _,
+            metric,
+            seed,
+        } = self;
+        let deadline = *deadline;
+        let label = *label;
+
+        // resuming will continue to save to the same file unless another file is
+        // specified
+        let checkpoint_path = arg.checkpoint.clone().or_else(|| arg.resume.clone());
+        let interval = arg.checkpoint_interval.into_inner();
+        let mut loaded = if let Some(resume) = &arg.resume {
+            Some(Checkpoint::load(resume, checkpoint_path.clone(), interval)?)
+        } else {
+            None
+        };
+
+        // when resuming the seed of the checkpoint is used if one is not given so
+        // that the records are split the same way
+        let seed = arg
+            .seed
+            .or_else(|| loaded.as_ref().map(|checkpoint| checkpoint.params().seed))
+            .unwrap_or(*seed);
+
+        let classification = arg.task == TaskType::Classification;
+        let (train, test) = self.split(records, seed);
+
+        println!(
+            "train size: {} test size: {} seed: {seed}",
+            train.len(),
+            test.len()
+        );
+
+        if classification {
+            let counts = SplitCounts::new(
+                records.label_names().len(),
+                train.iter().copied(),
+                test.iter().copied(),
+            );
+            let k = k_values
+                .iter()
+                .filter_map(|k| k.get_range(train.len(), arg.odd_only).max())
+                .max()
+                .unwrap_or(1);
+
+            print_balance(records, &[counts], k);
+        }
+
+        // the accuracy of always guessing the most frequent training label. used
+        // to show how much better the search results are than a trivial guess
+        let baseline = if classification {
+            majority_baseline(&train, &test)
+        } else {
+            None
+        };
+
+        if let Some((label, percent)) = baseline {
+            println!(
+                "baseline: {} % {:.2}",
+                records.label_name(label),
+                percent * 100.0
+            );
+        }
+
+        for (pool_cols, algo) in pool_columns
+            .iter()
+            .flat_map(|pool_cols| algos.iter().map(move |algo| (pool_cols, *algo)))
+        {
+            if deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+                break;
+            }
+
+            // a grid may not use every column so the records only keep the
+            // columns of the current pool
+            let indexes: Vec<usize> = pool_cols
+                .iter()
+                .map(|col| columns.iter().position(|known| known == col).unwrap())
+                .collect();
+            let mut pool_records = Dataset::with_labels_of(records, indexes.len(), records.len());
+            let mut data = Vec::with_capacity(indexes.len());
+
+            for record in records.iter() {
+                data.clear();
+                data.extend(indexes.iter().map(|index| record.data[*index]));
+
+                pool_records.push_id(&data, record.label);
+            }
+
+            let (train, _) = self.split(&pool_records, seed);
+            let max_features = arg.max_features.unwrap_or(pool_cols.len());
+
+            let params = CheckpointParams {
+                label,
+                columns: pool_cols.clone(),
+                algo,
+                task: arg.task,
+                test: arg.test,
+                seed,
+                eval_against: arg.eval_against,
+                balance: arg.balance,
+                split_strategy: arg.split_strategy,
+                time: *time,
+            };
+
+            // a grid cannot be checkpointed so only a single pool and algorithm
+            // will be searched when resuming
+            let checkpoint = Mutex::new(if let Some(loaded) = loaded.take() {
+                loaded.check_params(&params)?;
+
+                println!("resuming with {} evaluations", loaded.len());
+
+                loaded
+            } else {
+                Checkpoint::new(params, checkpoint_path.clone(), interval)
+            });
+
+            if *grid {
+                print!("algo: {algo} cols:");
+
+                for col in pool_cols {
+                    print!(" {}", names[col]);
+                }
+
+                println!();
+            }
+
+            for scale in scales {
+                if deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+                    break;
+                }
+
+                // the scaling parameters are only fitted on the training records
+                // so that the test records do not influence them
+                let scaler = Scaler::fit(
+                    *scale,
+                    train.iter().map(|record| record.data),
+                    pool_cols.len(),
+                );
+                let scaled = scaler.apply_records(&pool_records);
+                // the split uses the same seed so the scaled records will be
+                // divided the same as the original records
+                let (scaled_train, scaled_test) = self.split(&scaled, seed);
+                // only the training records are balanced so the test records
+                // keep the distribution of the csv
+                let mut synthetic = Dataset::new(pool_cols.len());
+                let scaled_train =
+                    balance_records(scaled_train, arg.balance, algo, seed, &mut synthetic);
+
+                let mut ks: Vec<usize> = k_values
+                    .iter()
+                    .flat_map(|k| k.get_range(scaled_train.len(), arg.odd_only))
+                    .collect();
+                ks.sort_unstable();
+                ks.dedup();
+
+                let against = match arg.eval_against {
+                    EvalAgainst::Train => scaled_train,
+                    EvalAgainst::All => scaled.iter().collect(),
+                };
+                let metric: &dyn DistanceMetric = match metric {
+                    Some(metric) => metric.as_ref(),
+                    None => &algo,
+                };
+                let ctx = SearchContext {
+                    algo,
+                    metric,
+                    index: arg.index,
+                    task: arg.task,
+                    splits: vec![EvalSplit::new(
+                        metric,
+                        arg.index.kind,
+                        against,
+                        scaled_test,
+                        pool_cols.len(),
+                    )],
+                    ks,
+                    max_features,
+                    penalty: arg.penalty,
+                    beam_width: arg.beam_width,
+                    patience: arg.patience,
+                    population: arg.population,
+                    generations: arg.generations,
+                    budget: arg.budget,
+                    deadline,
+                    scale: *scale,
+                    checkpoint: &checkpoint,
+                    names,
+                    labels: records.label_names(),
+                    progress,
+                    seed,
+                };
+
+                println!("scale: {scale}");
+
+                // approximate indexes are compared to brute force using every
+                // column of the pool
+                if !arg.index.kind.is_exact() {
+                    let subset: Vec<(usize, usize)> =
+                        pool_cols.iter().copied().enumerate().collect();
+
+                    if let Some(found) = ctx.recall(&subset)? {
+                        println!("recall: {found:.4}");
+                    }
+                }
+
+                search_columns(&ctx, arg.strategy, arg.direction, algo, pool_cols, sink)?;
+            }
+
+            checkpoint
+                .into_inner()
+                .map_err(|_| anyhow!("checkpoint lock was poisoned"))?
+                .save()?;
+        }
+
+        if deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+            println!("time limit reached. reporting the results found so far");
+        }
+
+        Ok(baseline)
+    }
+}
+
+/// outputs the summary of a single search result
+fn print_result(
+    record: &SearchResult,
+    baseline: Option<(u32, f64)>,
+    penalty: f64,
+    names: &HashMap<usize, String>,
+) {
+    let penalized = record.eval.score() - penalty * record.cols.len() as f64;
+
+    match record.eval {
+        Evaluation::Classification { .. } => {
+            let percent = record.eval.score() * 100.0;
+
+            print!(
+                "k {} {} {} % {percent:.2}",
+                record.k, record.algo, record.scale
+            );
+
+            if let Some((_, baseline_p)) = baseline {
+                print!(" lift {:+.2}", percent - baseline_p * 100.0);
+            }
+
+            if penalty > 0.0 {
+                print!(" penalized % {:.2}", penalized * 100.0);
+            }
+        }
+        Evaluation::Regression { rmse, mae, r2, .. } => {
+            print!(
+                "k {} {} {} rmse {rmse:.4} mae {mae:.4} r2 {r2:.4}",
+                record.k, record.algo, record.scale
+            );
+
+            if penalty > 0.0 {
+                print!(" penalized {penalized:.4}");
+            }
+        }
+    }
+
+    print!(" cols:");
+
+    for col in &record.cols {
+        print!(" {}", names[col]);
+    }
+
+    println!();
+}
+
+/// searches the columns with the specified strategy for each k value of the
+/// context and passes the results to `sink`
+fn search_columns(
+    ctx: &SearchContext<'_>,
+    strategy: Strategy,
+    direction: Direction,
+    algo: AlgoType,
+    columns: &[usize],
+    sink: &mut dyn FnMut(SearchResult) -> anyhow::Result<()>,
+) -> anyhow::Result<()> {
+    // the random strategy samples the k values along with the columns so it
+    // does not iterate through the k range
+    if strategy == Strategy::Random {
+        for (k, eval, cols) in random_search(ctx, &ctx.ks, columns)? {
+            sink(SearchResult {
+                k,
+                algo,
+                scale: ctx.scale,
+                eval,
+                cols,
+            })?;
+        }
+
+        return Ok(());
+    }
+
+    // we are using the train dataset and manually iterating through the test
+    // dataset for datapoints to use for testing
+    for &k in &ctx.ks {
+        if ctx.expired() {
+            break;
+        }
+
+        println!("k: {k}");
+
+        ctx.progress.on_k_started(k);
+
+        let steps = match (strategy, direction) {
+            (Strategy::Greedy, Direction::Forward) => greedy_forward(ctx, k, columns)?,
+            (Strategy::Greedy, Direction::Backward) => greedy_backward(ctx, k, columns)?,
+            (Strategy::Exhaustive, _) => exhaustive(ctx, k, columns)?,
+            (Strategy::Floating, _) => floating(ctx, k, columns)?,
+            (Strategy::Random, _) => unreachable!("random strategy is handled separately"),
+            (Strategy::Genetic, _) => genetic(ctx, k, columns)?,
+        };
+
+        for (eval, cols) in steps {
+            sink(SearchResult {
+                k,
+                algo,
+                scale: ctx.scale,
+                eval,
+                cols,
+            })?;
+        }
+    }
+
+    Ok(())
+}
+
+/// searches with nested cross validation
+///
+/// for each outer fold the configuration is selected by searching with the
+/// inner folds of the outer training records. the selected configuration is
+/// then evaluated against the outer test records which were never seen
+/// during the search
+///
+/// the columns searched are the columns of the checkpoint params
+fn nested_search(
+    arg: &SearchArgs,
+    nested: NestedCv,
+    deadline: Option<Instant>,
+    records: &Dataset,
+    params: CheckpointParams,
+    names: &HashMap<usize, String>,
+    progress: &dyn Progress,
+) -> anyhow::Result<()> {
+    let columns = params.columns.as_slice();
+    let max_features = arg.max_features.unwrap_or(columns.len());
+    let mut outer_evals = Vec::with_capacity(nested.outer);
+    let outer_folds = fold_dataset(records.iter(), nested.outer);
+    let seed = params.seed;
+
+    if arg.balance != Balance::None {
+        println!("balance seed: {seed}");
+    }
+
+    if arg.task == TaskType::Classification {
+        let k = arg
+            .k
+            .get_range(records.len(), arg.odd_only)
+            .max()
+            .unwrap_or(1);
+
+        print_balance(records, &fold_counts(records, &outer_folds), k);
+    }
+
+    for (fold_index, fold) in outer_folds.iter().enumerate() {
+        if deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+            break;
+        }
+
+        let (outer_train, outer_test) = partition_fold(records.iter(), fold);
+
+        println!(
+            "outer fold: {fold_index} train size: {} test size: {}",
+            outer_train.len(),
+            outer_test.len()
+        );
+
+        let mut candidates = Vec::new();
+
+        for scale in &arg.scale {
+            // the scaling parameters are only fitted on the outer training
+            // records so the outer test records do not influence them
+            let scaler = Scaler::fit(
+                *scale,
+                outer_train.iter().map(|record| record.data),
+                columns.len(),
+            );
+            let scaled = scaler.apply_records(records);
+            let (scaled_train, _) = partition_fold(scaled.iter(), fold);
+
+            let mut splits = Vec::with_capacity(nested.inner);
+            let mut synthetic = vec![Dataset::new(columns.len()); nested.inner];
+
+            for (inner_fold, synthetic) in fold_dataset(scaled_train.iter().copied(), nested.inner)
+                .into_iter()
+                .zip(&mut synthetic)
+            {
+                let (inner_train, inner_test) =
+                    partition_fold(scaled_train.iter().copied(), &inner_fold);
+                let against = match arg.eval_against {
+                    EvalAgainst::Train => {
+                        balance_records(inner_train, arg.balance, arg.algo, seed, synthetic)
+                    }
+                    EvalAgainst::All => scaled_train.clone(),
+                };
+
+                splits.push(EvalSplit::new(
+                    &arg.algo,
+                    arg.index.kind,
+                    against,
+                    inner_test,
+                    columns.len(),
+                ));
+            }
+
+            // the evaluations of each fold are only kept in memory
+            let checkpoint = Mutex::new(Checkpoint::new(params.clone(), None, Duration::MAX));
+            let ctx = SearchContext {
+                algo: arg.algo,
+                metric: &arg.algo,
+                index: arg.index,
+                task: arg.task,
+                splits,
+                ks: arg.k.get_range(scaled_train.len(), arg.odd_only).collect(),
+                max_features,
+                penalty: arg.penalty,
+                beam_width: arg.beam_width,
+                patience: arg.patience,
+                population: arg.population,
+                generations: arg.generations,
+                budget: arg.budget,
+                deadline,
+                scale: *scale,
+                checkpoint: &checkpoint,
+                names,
+                labels: records.label_names(),
+                progress,
+                seed,
+            };
+
+            println!("scale: {scale}");
+
+            search_columns(
+                &ctx,
+                arg.strategy,
+                arg.direction,
+                arg.algo,
+                columns,
+                &mut |candidate| {
+                    candidates.push(candidate);
+
+                    Ok(())
+                },
+            )?;
+        }
+
+        let penalized =
+            |record: &SearchResult| record.eval.score() - arg.penalty * record.cols.len() as f64;
+
+        // the first best configuration is kept on ties
+        let Some(best) = candidates.into_iter().reduce(|best, next| {
+            if penalized(&next) > penalized(&best) {
+                next
+            } else {
+                best
+            }
+        }) else {
+            continue;
+        };
+
+        // evaluate the selected configuration on the outer fold
+        let scaler = Scaler::fit(
+            best.scale,
+            outer_train.iter().map(|record| record.data),
+            columns.len(),
+        );
+        let scaled = scaler.apply_records(records);
+        let (scaled_train, scaled_test) = partition_fold(scaled.iter(), fold);
+        let mut synthetic = Dataset::new(columns.len());
+        let against = match arg.eval_against {
+            EvalAgainst::Train => {
+                balance_records(scaled_train, arg.balance, arg.algo, seed, &mut synthetic)
+            }
+            EvalAgainst::All => scaled.iter().collect(),
+        };
+        let subset: Vec<(usize, usize)> = best
+            .cols
+            .iter()
+            .map(|col| (columns.iter().position(|known| known == col).unwrap(), *col))
+            .collect();
+        let checkpoint = Mutex::new(Checkpoint::new(params.clone(), None, Duration::MAX));
+        let ctx = SearchContext {
+            algo: arg.algo,
+            metric: &arg.algo,
+            index: arg.index,
+            task: arg.task,
+            splits: vec![EvalSplit::new(
+                &arg.algo,
+                arg.index.kind,
+                against,
+                scaled_test,
+                columns.len(),
+            )],
+            ks: vec![best.k],
+            max_features,
+            penalty: arg.penalty,
+            beam_width: arg.beam_width,
+            patience: arg.patience,
+            population: arg.population,
+            generations: arg.generations,
+            budget: arg.budget,
+            deadline: None,
+            scale: best.scale,
+            checkpoint: &checkpoint,
+            names,
+            labels: records.label_names(),
+            progress,
+            seed,
+        };
+        let eval = ctx.evaluate(best.k, &subset)?;
+
+        print!(
+            "outer fold: {fold_index} k {} {} {} cols:",
+            best.k, best.algo, best.scale
+        );
+
+        for col in &best.cols {
+            print!(" {}", names[col]);
+        }
+
+        println!(" | {eval}");
+
+        outer_evals.push(eval);
+
+        progress.on_fold_finished(fold_index, outer_folds.len());
+    }
+
+    if deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+        println!("time limit reached. reporting the folds completed so far");
+    }
+
+    if outer_evals.is_empty() {
+        println!("no outer folds were evaluated");
+
+        return Ok(());
+    }
+
+    let count = outer_evals.len() as f64;
+
+    match arg.task {
+        TaskType::Classification => {
+            let mean = outer_evals.iter().map(|eval| eval.score()).sum::<f64>() / count;
+
+            println!(
+                "nested cv: % {:.2} folds: {}",
+                mean * 100.0,
+                outer_evals.len()
+            );
+        }
+        TaskType::Regression => {
+            // the score of a regression is the negative rmse
+            let mean = outer_evals.iter().map(|eval| -eval.score()).sum::<f64>() / count;
+
+            println!("nested cv: rmse {mean:.4} folds: {}", outer_evals.len());
+        }
+    }
+
+    Ok(())
+}
+
+/// adds columns to the best subsets found until all columns have been
+/// selected or the maximum number of columns is reached
+///
+/// this is a beam search where the best `beam_width` subsets from each round
+/// are kept for the next. a width of 1 will greedily add the single column
+/// that produces the best evaluation.
+///
+/// returns the best evaluation and the selected csv columns for each step
+fn greedy_forward(
+    ctx: &SearchContext<'_>,
+    k: usize,
+    columns: &[usize],
+) -> anyhow::Result<Vec<(Evaluation, Vec<usize>)>> {
+    let mut rtn = Vec::new();
+    let all: Vec<(usize, usize)> = columns
+        .iter()
+        .enumerate()
+        .map(|(index, col)| (index, *col))
+        .collect();
+    let max_features = std::cmp::min(ctx.max_features, all.len());
+    // every beam will contain the same number of columns
+    let mut beams: Vec<Vec<(usize, usize)>> = vec![Vec::new()];
+    // the same set of columns can be reached from different beams so only
+    // evaluate it once
+    let mut seen: HashSet<Vec<usize>> = HashSet::new();
+    let mut tracker = PatienceTracker::new(ctx.patience);
+
+    while beams[0].len() < max_features {
+        let mut candidates: Vec<ScoredSubset> = Vec::new();
+
+        for beam in &beams {
+            for column in &all {
+                // incomplete steps are discarded
+                if ctx.expired() {
+                    return Ok(rtn);
+                }
+
+                if beam.contains(column) {
+                    continue;
+                }
+
+                let mut subset = beam.clone();
+                subset.push(*column);
+
+                let mut key: Vec<usize> = subset.iter().map(|(index, _)| *index).collect();
+                key.sort_unstable();
+
+                if !seen.insert(key) {
+                    continue;
+                }
+
+                let eval = ctx.evaluate(k, &subset)?;
+
+                // output the results for this iteration
+                ctx.print_subset(&subset, &eval);
+
+                candidates.push((eval, subset));
+            }
+        }
+
+        if candidates.is_empty() {
+            break;
+        }
+
+        // the sort is stable so reversing first will allow later candidates
+        // to win ties, same as selecting a single best column
+        candidates.reverse();
+        candidates.sort_by(|(a, _), (b, _)| b.score().total_cmp(&a.score()));
+        candidates.truncate(ctx.beam_width);
+
+        let (best_eval, best_subset) = &candidates[0];
+        let exhausted = tracker.update(ctx.score(best_eval, best_subset.len()));
+
+        rtn.push((best_eval.clone(), subset_columns(best_subset)));
+
+        if exhausted {
+            println!("stopping: no improvement for {} additions", tracker.steps);
+
+            break;
+        }
+
+        beams = candidates.into_iter().map(|(_, subset)| subset).collect();
+    }
+
+    Ok(rtn)
+}
+
+/// starts with all columns selected and greedily removes the column that
+/// produces the best evaluation when left out until a single column remains
+///
+/// returns the evaluation and the selected csv columns for each step
+fn greedy_backward(
+    ctx: &SearchContext<'_>,
+    k: usize,
+    columns: &[usize],
+) -> anyhow::Result<Vec<(Evaluation, Vec<usize>)>> {
+    let mut rtn = Vec::new();
+    let mut selected: Vec<(usize, usize)> = columns
+        .iter()
+        .enumerate()
+        .map(|(index, col)| (index, *col))
+        .collect();
+
+    if selected.is_empty() || ctx.expired() {
+        return Ok(rtn);
+    }
+
+    let eval = ctx.evaluate(k, &selected)?;
+
+    ctx.print_subset(&selected, &eval);
+
+    rtn.push((eval, subset_columns(&selected)));
+
+    while selected.len() > 1 {
+        let Some((best_index, best_eval)) = best_removal(ctx, k, &mut selected)? else {
+            break;
+        };
+
+        selected.remove(best_index);
+
+        rtn.push((best_eval, subset_columns(&selected)));
+    }
+
+    Ok(rtn)
+}
+
+/// tracks how many column additions have happened since the best score last
+/// improved
+struct PatienceTracker {
+    patience: Option<usize>,
+    best: Option<f64>,
+    steps: usize,
+}
+
+impl PatienceTracker {
+    fn new(patience: Option<usize>) -> Self {
+        Self {
+            patience,
+            best: None,
+            steps: 0,
+        }
+    }
+
+    /// records the best score of the latest addition
+    ///
+    /// returns true if the search should stop adding columns
+    fn update(&mut self, score: f64) -> bool {
+        match self.best {
+            Some(best) if score <= best => self.steps += 1,
+            _ => {
+                self.best = Some(score);
+                self.steps = 0;
+            }
+        }
+
+        self.patience.is_some_and(|patience| self.steps >= patience)
+    }
+}
+
+/// an evaluation and the (data index, csv column) pairs that produced it
+type ScoredSubset = (Evaluation, Vec<(usize, usize)>);
+
+/// sequential floating forward selection (SFFS)
+///
+/// similar to [`greedy_forward`] but after each addition it will attempt to
+/// remove previously selected columns as long as doing so produces a better
+/// evaluation than the best subset found of that size.
+///
+/// returns the best evaluation and csv columns for each subset size
+fn floating(
+    ctx: &SearchContext<'_>,
+    k: usize,
+    columns: &[usize],
+) -> anyhow::Result<Vec<(Evaluation, Vec<usize>)>> {
+    // the best subset found for each size. index 0 is a subset of 1 column
+    let mut best_sizes: Vec<Option<ScoredSubset>> =
+        vec![None; std::cmp::min(ctx.max_features, columns.len())];
+    let mut selected: Vec<(usize, usize)> = Vec::new();
+    let mut avail: Vec<(usize, usize)> = columns
+        .iter()
+        .enumerate()
+        .map(|(index, col)| (index, *col))
+        .collect();
+    let mut tracker = PatienceTracker::new(ctx.patience);
+
+    while selected.len() < best_sizes.len() {
+        let Some((best_index, best_eval)) = best_addition(ctx, k, &mut selected, &avail)? else {
+            break;
+        };
+
+        let added = avail.remove(best_index);
+
+        selected.push(added);
+
+        let exhausted = tracker.update(ctx.score(&best_eval, selected.len()));
+
+        update_best_size(&mut best_sizes, &selected, best_eval);
+
+        if exhausted {
+            println!("stopping: no improvement for {} additions", tracker.steps);
+
+            break;
+        }
+
+        // conditionally remove columns while it improves on the best subset
+        // of the smaller size. the column that was just added is never
+        // removed so the search always makes progress
+        while selected.len() > 2 {
+            let Some((remove_index, remove_eval)) = best_removal(ctx, k, &mut selected)? else {
+                break;
+            };
+
+            if selected[remove_index] == added {
+                break;
+            }
+
+            let improves = match &best_sizes[selected.len() - 2] {
+                Some((eval, _)) => remove_eval.score() > eval.score(),
+                None => true,
+            };
+
+            if !improves {
+                break;
+            }
+
+            avail.push(selected.remove(remove_index));
+
+            update_best_size(&mut best_sizes, &selected, remove_eval);
+        }
+    }
+
+    Ok(best_sizes
+        .into_iter()
+        .flatten()
+        .map(|(eval, subset)| (eval, subset_columns(&subset)))
+        .collect())
+}
+
+/// searches for the best subset of columns with a genetic algorithm
+///
+/// each individual in the population is a set of selected columns. new
+/// generations are created by tournament selection, uniform crossover, and
+/// random mutation while the best individuals are always carried over.
+///
+/// returns the best evaluation and csv columns every time the best individual
+/// improves
+fn genetic(
+    ctx: &SearchContext<'_>,
+    k: usize,
+    columns: &[usize],
+) -> anyhow::Result<Vec<(Evaluation, Vec<usize>)>> {
+    // the number of best individuals that are carried over to the next
+    // generation unchanged
+    const ELITE: usize = 2;
+    // the number of individuals that compete for each parent
+    const TOURNAMENT: usize = 3;
+
+    let mut rng = StdRng::seed_from_u64(ctx.seed);
+    let mut rtn = Vec::new();
+    // individuals are evaluated once and then remembered since the same
+    // subsets will appear many times between generations
+    let mut known: HashMap<Vec<bool>, Evaluation> = HashMap::new();
+    let mutation = 1.0 / columns.len() as f64;
+
+    let mut current: Vec<Vec<bool>> = (0..ctx.population)
+        .map(|_| {
+            let mut genes: Vec<bool> = (0..columns.len()).map(|_| rng.random_bool(0.5)).collect();
+
+            repair_genes(&mut genes, ctx.max_features, &mut rng);
+
+            genes
+        })
+        .collect();
+
+    let mut best = None::<(f64, Vec<bool>)>;
+
+    for generation in 0..ctx.generations {
+        let mut scored = Vec::with_capacity(current.len());
+
+        for genes in current {
+            let size = genes.iter().filter(|gene| **gene).count();
+            let score = match known.get(&genes) {
+                Some(eval) => ctx.score(eval, size),
+                None => {
+                    if ctx.expired() {
+                        return Ok(rtn);
+                    }
+
+                    let subset = genes_subset(&genes, columns);
+                    let eval = ctx.evaluate(k, &subset)?;
+
+                    ctx.print_subset(&subset, &eval);
+
+                    let score = ctx.score(&eval, size);
+
+                    known.insert(genes.clone(), eval);
+
+                    score
+                }
+            };
+
+            scored.push((score, genes));
+        }
+
+        // sort descending so the best individuals are first
+        scored.sort_by(|(a, _), (b, _)| b.total_cmp(a));
+
+        let improved = match &best {
+            Some((best_score, _)) => scored[0].0 > *best_score,
+            None => true,
+        };
+
+        if improved {
+            best = Some(scored[0].clone());
+
+            let genes = &scored[0].1;
+
+            println!("generation: {generation} improved");
+
+            rtn.push((
+                known[genes].clone(),
+                subset_columns(&genes_subset(genes, columns)),
+            ));
+        }
+
+        let mut next: Vec<Vec<bool>> = scored
+            .iter()
+            .take(ELITE)
+            .map(|(_, genes)| genes.clone())
+            .collect();
+
+        while next.len() < ctx.population {
+            let a = tournament(&scored, TOURNAMENT, &mut rng);
+            let b = tournament(&scored, TOURNAMENT, &mut rng);
+
+            let mut child: Vec<bool> = a
+                .iter()
+                .zip(b)
+                .map(|(a_gene, b_gene)| {
+                    if rng.random_bool(0.5) {
+                        *a_gene
+                    } else {
+                        *b_gene
+                    }
+                })
+                .collect();
+
+            for gene in child.iter_mut() {
+                if rng.random_bool(mutation) {
+                    *gene = !*gene;
+                }
+            }
+
+            repair_genes(&mut child, ctx.max_features, &mut rng);
+
+            next.push(child);
+        }
+
+        current = next;
+    }
+
+    Ok(rtn)
+}
+
+/// evaluates randomly sampled combinations of k values and columns until the
+/// budget of evaluations is exhausted
+///
+/// returns the k value, evaluation, and csv columns every time the best
+/// configuration improves
+fn random_search(
+    ctx: &SearchContext<'_>,
+    ks: &[usize],
+    columns: &[usize],
+) -> anyhow::Result<Vec<(usize, Evaluation, Vec<usize>)>> {
+    // stop sampling if this many duplicates are drawn in a row since the
+    // budget is likely larger than the number of possible configurations
+    const MAX_DUPLICATES: usize = 1000;
+
+    let mut rng = StdRng::seed_from_u64(ctx.seed);
+    let mut rtn = Vec::new();
+    let mut seen: HashSet<(usize, Vec<bool>)> = HashSet::new();
+    let mut best = None::<f64>;
+    let mut duplicates = 0;
+
+    if ks.is_empty() || columns.is_empty() {
+        return Ok(rtn);
+    }
+
+    while seen.len() < ctx.budget && duplicates < MAX_DUPLICATES && !ctx.expired() {
+        let k = ks[rng.random_range(0..ks.len())];
+        let mut genes: Vec<bool> = (0..columns.len()).map(|_| rng.random_bool(0.5)).collect();
+
+        repair_genes(&mut genes, ctx.max_features, &mut rng);
+
+        if !seen.insert((k, genes.clone())) {
+            duplicates += 1;
+
+            continue;
+        }
+
+        duplicates = 0;
+
+        let subset = genes_subset(&genes, columns);
+        let eval = ctx.evaluate(k, &subset)?;
+
+        print!("  k {k:>3}");
+
+        ctx.print_subset(&subset, &eval);
+
+        let score = ctx.score(&eval, subset.len());
+        let improved = match best {
+            Some(best_score) => score > best_score,
+            None => true,
+        };
+
+        if improved {
+            best = Some(score);
+
+            rtn.push((k, eval, subset_columns(&subset)));
+        }
+    }
+
+    Ok(rtn)
+}
+
+/// picks the best individual from a random sample of the population
+fn tournament<'a, R>(scored: &'a [(f64, Vec<bool>)], size: usize, rng: &mut R) -> &'a [bool]
+where
+    R: Rng,
+{
+    let mut best = &scored[rng.random_range(0..scored.len())];
+
+    for _ in 1..size {
+        let pick = &scored[rng.random_range(0..scored.len())];
+
+        if pick.0 > best.0 {
+            best = pick;
+        }
+    }
+
+    &best.1
+}
+
+/// ensures that at least one column and no more than the maximum number of
+/// columns are selected
+fn repair_genes<R>(genes: &mut [bool], max_features: usize, rng: &mut R)
+where
+    R: Rng,
+{
+    if !genes.iter().any(|gene| *gene) {
+        genes[rng.random_range(0..genes.len())] = true;
+    }
+
+    let mut selected: Vec<usize> = (0..genes.len()).filter(|index| genes[*index]).collect();
+
+    while selected.len() > max_features {
+        let index = selected.swap_remove(rng.random_range(0..selected.len()));
+
+        genes[index] = false;
+    }
+}
+
+/// converts the genes of an individual into (data index, csv column) pairs
+fn genes_subset(genes: &[bool], columns: &[usize]) -> Vec<(usize, usize)> {
+    genes
+        .iter()
+        .zip(columns)
+        .enumerate()
+        .filter(|(_, (gene, _))| **gene)
+        .map(|(index, (_, col))| (index, *col))
+        .collect()
+}
+
+/// stores the evaluation for the subset if it is better than the currently
+/// known best subset of the same size
+fn update_best_size(
+    best_sizes: &mut [Option<ScoredSubset>],
+    subset: &[(usize, usize)],
+    eval: Evaluation,
+) {
+    let Some(slot) = best_sizes.get_mut(subset.len() - 1) else {
+        return;
+    };
+
+    let replace = match slot {
+        Some((known, _)) => eval.score() > known.score(),
+        None => true,
+    };
+
+    if replace {
+        *slot = Some((eval, subset.to_vec()));
+    }
+}
+
+/// finds the available column that produces the best evaluation when added
+/// to the selected columns
+///
+/// returns the index in `avail` and the evaluation. the selected columns will
+/// be unchanged when this returns
+fn best_addition(
+    ctx: &SearchContext<'_>,
+    k: usize,
+    selected: &mut Vec<(usize, usize)>,
+    avail: &[(usize, usize)],
+) -> anyhow::Result<Option<(usize, Evaluation)>> {
+    let mut best = None::<(usize, Evaluation)>;
+    // the distances of the selected columns are the same for every candidate
+    // so they are only summed once
+    let base = ctx.partial_sums(selected);
+
+    for (avail_index, column) in avail.iter().enumerate() {
+        if ctx.expired() {
+            return Ok(None);
+        }
+
+        selected.push(*column);
+
+        let eval = ctx.evaluate_from(k, selected, base.as_deref());
+
+        // output the results for this iteration
+        if let Ok(eval) = &eval {
+            ctx.print_subset(selected, eval);
+        }
+
+        selected.pop();
+
+        let eval = eval?;
+
+        best = if let Some((best_index, best_eval)) = best {
+            if best_eval.score() > eval.score() {
+                Some((best_index, best_eval))
+            } else {
+                Some((avail_index, eval))
+            }
+        } else {
+            Some((avail_index, eval))
+        };
+    }
+
+    Ok(best)
+}
+
+/// finds the selected column that produces the best evaluation when removed
+///
+/// returns the index in `selected` and the evaluation. the selected columns
+/// will be unchanged when this returns
+fn best_removal(
+    ctx: &SearchContext<'_>,
+    k: usize,
+    selected: &mut Vec<(usize, usize)>,
+) -> anyhow::Result<Option<(usize, Evaluation)>> {
+    let mut best = None::<(usize, Evaluation)>;
+
+    for remove_index in 0..selected.len() {
+        if ctx.expired() {
+            return Ok(None);
+        }
+
+        let removed = selected.remove(remove_index);
+
+        let eval = ctx.evaluate(k, selected);
+
+        // print before restoring so that only the evaluated columns are
+        // shown
+        if let Ok(eval) = &eval {
+            ctx.print_subset(selected, eval);
+        }
+
+        selected.insert(remove_index, removed);
+
+        let eval = eval?;
+
+        best = if let Some((best_index, best_eval)) = best {
+            if best_eval.score() > eval.score() {
+                Some((best_index, best_eval))
+            } else {
+                Some((remove_index, eval))
+            }
+        } else {
+            Some((remove_index, eval))
+        };
+    }
+
+    Ok(best)
+}
+
+/// retrieves the csv columns from a list of (data index, csv column) pairs
+fn subset_columns(subset: &[(usize, usize)]) -> Vec<usize> {
+    subset.iter().map(|(_, col)| *col).collect()
+}
+
+/// evaluates every combination of columns up to the maximum number of
+/// columns
+///
+/// returns the best evaluation and csv columns for each subset size
+fn exhaustive(
+    ctx: &SearchContext<'_>,
+    k: usize,
+    columns: &[usize],
+) -> anyhow::Result<Vec<(Evaluation, Vec<usize>)>> {
+    let mut rtn = Vec::new();
+    let max_features = std::cmp::min(ctx.max_features, columns.len());
+
+    for size in 1..=max_features {
+        let mut best = None::<(Evaluation, Vec<(usize, usize)>)>;
+        // the indexes of the columns in the current combination. starts with
+        // the first `size` columns
+        let mut indexes: Vec<usize> = (0..size).collect();
+        let mut subset = Vec::with_capacity(size);
+
+        loop {
+            // the best subset of an incomplete size is discarded
+            if ctx.expired() {
+                return Ok(rtn);
+            }
+
+            subset.clear();
+            subset.extend(indexes.iter().map(|index| (*index, columns[*index])));
+
+            let eval = ctx.evaluate(k, &subset)?;
+
+            ctx.print_subset(&subset, &eval);
+
+            best = match best {
+                Some((best_eval, best_subset)) if best_eval.score() > eval.score() => {
+                    Some((best_eval, best_subset))
+                }
+                _ => Some((eval, subset.clone())),
+            };
+
+            if !next_combination(&mut indexes, columns.len()) {
+                break;
+            }
+        }
+
+        if let Some((eval, subset)) = best {
+            rtn.push((eval, subset_columns(&subset)));
+        }
+    }
+
+    Ok(rtn)
+}
+
+/// advances the list of indexes to the next combination in lexicographic
+/// order
+///
+/// returns false if there are no combinations remaining
+fn next_combination(indexes: &mut [usize], total: usize) -> bool {
+    let size = indexes.len();
+
+    // find the right most index that can still be incremented
+    let mut pos = size;
+
+    while pos > 0 {
+        pos -= 1;
+
+        if indexes[pos] < total - size + pos {
+            indexes[pos] += 1;
+
+            for next in (pos + 1)..size {
+                indexes[next] = indexes[next - 1] + 1;
+            }
+
+            return true;
+        }
+    }
+
+    false
+}
+
+/// calculates the accuracy of predicting the most frequent label from the
+/// training records for every test record
+///
+/// returns [`None`] if either of the lists are empty
+fn majority_baseline(train: &[KnnRecord], test: &[KnnRecord]) -> Option<(u32, f64)> {
+    if test.is_empty() {
+        return None;
+    }
+
+    let label = majority_label(train.iter().map(|record| record.label))?;
+
+    let passed = test.iter().filter(|record| record.label == label).count();
+
+    Some((label, (passed as f64) / (test.len() as f64)))
+}
+
+fn collect_data_owned(record: &KnnRecord, subset: &[(usize, usize)]) -> Vec<f64> {
+    let mut rtn = Vec::with_capacity(subset.len());
+
+    collect_data(record, &mut rtn, subset);
+
+    rtn
+}
+
+fn collect_data(record: &KnnRecord, buf: &mut Vec<f64>, subset: &[(usize, usize)]) {
+    buf.clear();
+
+    // collect the datapoints from the record
+    for (index, _) in subset {
+        buf.push(record.data[*index]);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::source::MemorySource;
+    use clap::Parser;
+
+    #[derive(Parser)]
+    struct Cli {
+        #[command(flatten)]
+        search: SearchArgs,
+    }
+
+    #[test]
+    fn search_iter_results() {
+        let rows = (0..20)
+            .map(|index| {
+                vec![
+                    index.to_string(),
+                    (index % 3).to_string(),
+                    if index < 10 { "a" } else { "b" }.to_owned(),
+                ]
+            })
+            .collect();
+        let source = MemorySource::new(
+            Some(vec!["x".to_owned(), "y".to_owned(), "class".to_owned()]),
+            rows,
+        );
+        let cli = Cli::parse_from([
+            "knn", "-c", "x", "-c", "y", "--label", "class", "-k", "1-3", "--seed", "4",
+        ]);
+
+        let mut results = knn_search_iter(source, cli.search).unwrap();
+        let first = results.next().unwrap().unwrap();
+
+        assert_eq!(first.k, 1);
+        assert_eq!(results.column_name(first.cols[0]), Some("x"));
+
+        let rest: Vec<SearchResult> = results.map(Result::unwrap).collect();
+
+        assert!(rest.iter().any(|result| result.k == 3));
+    }
+
+    #[test]
+    fn search_custom_metric() {
+        let source = || {
+            let rows = (0..20)
+                .map(|index| {
+                    vec![
+                        index.to_string(),
+                        (index % 3).to_string(),
+                        if index < 10 { "a" } else { "b" }.to_owned(),
+                    ]
+                })
+                .collect();
+
+            MemorySource::new(
+                Some(vec!["x".to_owned(), "y".to_owned(), "class".to_owned()]),
+                rows,
+            )
+        };
+        let args = || {
+            Cli::parse_from([
+                "knn", "-c", "x", "-c", "y", "--label", "class", "-k", "1-3", "--seed", "4",
+            ])
+            .search
+        };
+
+        // the closure is not cached by column so it ranks every subset
+        // directly and has to match the cached euclidean search
+        let cached: Vec<SearchResult> = knn_search_iter(source(), args())
+            .unwrap()
+            .map(Result::unwrap)
+            .collect();
+        let custom: Vec<SearchResult> =
+            knn_search_iter_with(source(), args(), |a: &[f64], b: &[f64]| {
+                crate::distance::squared_euclidean(a, b)
+            })
+            .unwrap()
+            .map(Result::unwrap)
+            .collect();
+
+        assert_eq!(cached.len(), custom.len());
+
+        for (cached, custom) in cached.iter().zip(&custom) {
+            assert_eq!(cached.k, custom.k);
+            assert_eq!(cached.cols, custom.cols);
+            assert_eq!(cached.eval.score(), custom.eval.score());
+        }
+
+        let mut kd = args();
+        kd.index.kind = IndexType::KdTree;
+
+        assert!(knn_search_iter_with(source(), kd, |_: &[f64], _: &[f64]| 0.0).is_err());
+    }
+
+    /// records every evaluated subset so that searches can be compared
+    #[derive(Default)]
+    struct Recorder(Mutex<Vec<(usize, Vec<usize>, String)>>);
+
+    impl Progress for Recorder {
+        fn on_candidate_evaluated(&self, k: usize, columns: &[usize], eval: &Evaluation) {
+            self.0
+                .lock()
+                .unwrap()
+                .push((k, columns.to_vec(), format!("{eval:?}")));
+        }
+    }
+
+    /// 40 records with 4 columns of uneven values where the class depends
+    /// on the first two columns
+    fn features_source() -> MemorySource {
+        let rows = (0..40)
+            .map(|index: usize| {
+                let a = (index * 7 % 11) as f64 / 3.0;
+                let b = (index * 5 % 13) as f64 / 7.0;
+                let c = (index * 3 % 17) as f64 / 5.0;
+                let d = (index * 11 % 19) as f64 / 2.0;
+
+                vec![
+                    a.to_string(),
+                    b.to_string(),
+                    c.to_string(),
+                    d.to_string(),
+                    if a + b > 3.0 { "a" } else { "b" }.to_owned(),
+                ]
+            })
+            .collect();
+
+        MemorySource::new(
+            Some(
+                ["a", "b", "c", "d", "class"]
+                    .into_iter()
+                    .map(str::to_owned)
+                    .collect(),
+            ),
+            rows,
+        )
+    }
+
+    #[test]
+    fn cached_matches_metric() {
+        let record = |args: &[&str], metric: Option<Box<dyn DistanceMetric>>| {
+            let cli = Cli::parse_from([&["knn", "--label", "class", "--seed", "4"], args].concat());
+            let mut search = load_search(features_source(), cli.search).unwrap();
+            let recorder = Recorder::default();
+
+            search.metric = metric;
+            search.run(&recorder, &mut |_| Ok(())).unwrap();
+
+            let mut evals = recorder.0.into_inner().unwrap();
+            evals.sort();
+            evals
+        };
+
+        for strategy in ["greedy", "floating"] {
+            // the closures are not cached by column so every subset is ranked
+            // directly from the collected data
+            let euclidean = record(&["--strategy", strategy], None);
+            let custom = record(
+                &["--strategy", strategy],
+                Some(Box::new(|a: &[f64], b: &[f64]| {
+                    crate::distance::squared_euclidean(a, b)
+                })),
+            );
+
+            assert!(!euclidean.is_empty());
+            assert_eq!(euclidean, custom);
+
+            let manhattan = record(&["--strategy", strategy, "--algo", "manhattan"], None);
+            let custom = record(
+                &["--strategy", strategy, "--algo", "manhattan"],
+                Some(Box::new(|a: &[f64], b: &[f64]| {
+                    crate::distance::manhattan(a, b)
+                })),
+            );
+
+            assert!(!manhattan.is_empty());
+            assert_eq!(manhattan, custom);
+        }
+    }
+
+    /// the class is the xor of the first two columns so neither is useful
+    /// alone while the third column matches the class for most records
+    fn xor_source() -> MemorySource {
+        let rows = (0..48)
+            .map(|index: usize| {
+                let a = [0.1, 0.2, 0.8, 0.9][index % 4] + (index % 3) as f64 / 100.0;
+                let b = [0.15, 0.25, 0.75, 0.85][index / 4 % 4] + (index % 5) as f64 / 100.0;
+                let class = (a > 0.5) != (b > 0.5);
+                let noisy = class != index.is_multiple_of(5);
+
+                vec![
+                    a.to_string(),
+                    b.to_string(),
+                    if noisy { "1" } else { "0" }.to_owned(),
+                    if class { "yes" } else { "no" }.to_owned(),
+                ]
+            })
+            .collect();
+
+        MemorySource::new(
+            Some(
+                ["a", "b", "c", "class"]
+                    .into_iter()
+                    .map(str::to_owned)
+                    .collect(),
+            ),
+            rows,
+        )
+    }
+
+    #[test]
+    fn floating_drops_column() {
+        let search = |strategy: &str| -> Vec<SearchResult> {
+            let cli = Cli::parse_from([
+                "knn",
+                "--label",
+                "class",
+                "-k",
+                "1",
+                "--seed",
+                "4",
+                "--strategy",
+                strategy,
+            ]);
+
+            knn_search_iter(xor_source(), cli.search)
+                .unwrap()
+                .map(Result::unwrap)
+                .collect()
+        };
+
+        let greedy = search("greedy");
+        let floating = search("floating");
+
+        // both start with the third column but only floating is able to
+        // remove it once the first two columns are selected
+        assert_eq!(greedy[0].cols, vec![2]);
+        assert_eq!(floating[0].cols, vec![2]);
+        assert_eq!(greedy[1].cols.len(), 2);
+        assert!(greedy[1].cols.contains(&2));
+
+        let mut pair = floating[1].cols.clone();
+        pair.sort_unstable();
+
+        assert_eq!(pair, vec![0, 1]);
+        assert!(floating[1].eval.score() > greedy[1].eval.score());
+    }
+
+    #[test]
+    fn genetic_seeded() {
+        let search = |seed: &str| -> Vec<(Vec<usize>, f64)> {
+            let cli = Cli::parse_from([
+                "knn",
+                "--label",
+                "class",
+                "-k",
+                "3",
+                "--strategy",
+                "genetic",
+                "--population",
+                "8",
+                "--generations",
+                "5",
+                "--seed",
+                seed,
+            ]);
+
+            knn_search_iter(features_source(), cli.search)
+                .unwrap()
+                .map(|result| {
+                    let result = result.unwrap();
+
+                    (result.cols, result.eval.score())
+                })
+                .collect()
+        };
+
+        let first = search("4");
+
+        assert!(!first.is_empty());
+        assert_eq!(first, search("4"));
+
+        let load = |args: &[&str]| {
+            let cli = Cli::parse_from(
+                [&["knn", "--label", "class", "--strategy", "genetic"], args].concat(),
+            );
+
+            load_search(features_source(), cli.search).map(|_| ())
+        };
+
+        assert!(load(&["--population", "0"]).is_err());
+        assert!(load(&["--generations", "0"]).is_err());
+        assert!(load(&["--population", "1", "--generations", "1"]).is_ok());
+    }
+
+    #[test]
+    fn random_selects_best() {
+        // the penalty makes the first two columns better than all three
+        let cli = Cli::parse_from([
+            "knn",
+            "--label",
+            "class",
+            "-k",
+            "1",
+            "--strategy",
+            "random",
+            "--penalty",
+            "0.01",
+            "--budget",
+            "20",
+            "--seed",
+            "4",
+        ]);
+        let results: Vec<SearchResult> = knn_search_iter(xor_source(), cli.search)
+            .unwrap()
+            .map(Result::unwrap)
+            .collect();
+        let best = results.last().unwrap();
+
+        assert_eq!(best.k, 1);
+        assert_eq!(best.cols, vec![0, 1]);
+        assert_eq!(best.eval.score(), 1.0);
+    }
+
+    #[test]
+    fn beam_keeps_pairs() {
+        let search = |width: &str| -> Vec<SearchResult> {
+            let cli = Cli::parse_from([
+                "knn",
+                "--label",
+                "class",
+                "-k",
+                "1",
+                "--beam-width",
+                width,
+                "--seed",
+                "4",
+            ]);
+
+            knn_search_iter(xor_source(), cli.search)
+                .unwrap()
+                .map(Result::unwrap)
+                .collect()
+        };
+
+        let single = search("1");
+        let beam = search("2");
+
+        // a single beam is stuck with the third column while the second beam
+        // is able to pair the first two columns
+        assert!(single[1].cols.contains(&2));
+
+        let mut pair = beam[1].cols.clone();
+        pair.sort_unstable();
+
+        assert_eq!(pair, vec![0, 1]);
+        assert!(beam[1].eval.score() > single[1].eval.score());
+        assert_eq!(beam.len(), 3);
+    }
+
+    #[test]
+    fn backward_removes_noise() {
+        let cli = Cli::parse_from([
+            "knn",
+            "--label",
+            "class",
+            "-k",
+            "1",
+            "--direction",
+            "backward",
+            "--seed",
+            "4",
+        ]);
+        let results: Vec<SearchResult> = knn_search_iter(xor_source(), cli.search)
+            .unwrap()
+            .map(Result::unwrap)
+            .collect();
+        let steps: Vec<Vec<usize>> = results.iter().map(|result| result.cols.clone()).collect();
+
+        // the third column is removed first since the class only depends on
+        // the first two
+        assert_eq!(steps[0], vec![0, 1, 2]);
+        assert_eq!(steps[1], vec![0, 1]);
+        assert_eq!(steps.len(), 3);
+        assert_eq!(results[1].eval.score(), 1.0);
+    }
+
+    #[test]
+    fn penalty_needs_patience() {
+        let search = |args: &[&str]| -> Vec<SearchResult> {
+            let cli = Cli::parse_from(
+                [
+                    &[
+                        "knn",
+                        "--label",
+                        "class",
+                        "-k",
+                        "3",
+                        "--penalty",
+                        "0.05",
+                        "--seed",
+                        "4",
+                    ],
+                    args,
+                ]
+                .concat(),
+            );
+
+            knn_search_iter(features_source(), cli.search)
+                .unwrap()
+                .map(Result::unwrap)
+                .collect()
+        };
+        let penalized =
+            |result: &SearchResult| result.eval.score() - 0.05 * result.cols.len() as f64;
+
+        // the penalty only ranks the results so every column is still added
+        let all = search(&[]);
+
+        assert_eq!(all.len(), 4);
+        assert!(penalized(&all[1]) < penalized(&all[0]));
+
+        // patience stops once the penalized score of a larger subset is worse
+        let patient = search(&["--patience", "1"]);
+
+        assert_eq!(patient.len(), 2);
+        assert_eq!(patient[0].cols, vec![0]);
+    }
+
+    #[test]
+    fn temporal_time_errors() {
+        let load = |times: &[&str]| {
+            let rows = times
+                .iter()
+                .enumerate()
+                .map(|(index, time)| {
+                    vec![
+                        index.to_string(),
+                        time.to_string(),
+                        if index % 2 == 0 { "a" } else { "b" }.to_owned(),
+                    ]
+                })
+                .collect();
+            let source = MemorySource::new(
+                Some(vec!["x".to_owned(), "t".to_owned(), "class".to_owned()]),
+                rows,
+            );
+            let cli = Cli::parse_from([
+                "knn",
+                "-c",
+                "x",
+                "--label",
+                "class",
+                "--split-strategy",
+                "temporal",
+                "--time-col",
+                "t",
+            ]);
+
+            load_search(source, cli.search).map(|_| ())
+        };
+
+        let dates = load(&["2024-01-01", "2024-01-02", "2024-01-03", "2024-01-04"]);
+
+        assert!(
+            dates
+                .unwrap_err()
+                .to_string()
+                .starts_with("the time column")
+        );
+
+        let tied = load(&["5", "5", "5", "5", "5", "5", "5", "5"]);
+
+        assert!(tied.unwrap_err().to_string().ends_with("time: 5"));
+        assert!(load(&["1", "2", "3", "4", "5", "5", "5", "5"]).is_ok());
+    }
+
+    #[test]
+    fn ignored_strategy_options() {
+        let source = || {
+            MemorySource::new(
+                Some(vec!["x".to_owned(), "class".to_owned()]),
+                vec![vec!["1".to_owned(), "a".to_owned()]],
+            )
+        };
+        let load = |args: &[&str]| {
+            let cli = Cli::parse_from([&["knn", "--label", "class"], args].concat());
+
+            load_search(source(), cli.search).map(|_| ())
+        };
+
+        assert!(load(&["--strategy", "floating", "--direction", "backward"]).is_err());
+        assert!(load(&["--exhaustive", "--direction", "backward"]).is_err());
+        assert!(load(&["--strategy", "genetic", "--beam-width", "3"]).is_err());
+        assert!(load(&["--direction", "backward", "--beam-width", "3"]).is_err());
+        assert!(load(&["--direction", "backward"]).is_ok());
+        assert!(load(&["--beam-width", "3"]).is_ok());
+    }
+
+    #[test]
+    fn next_combination_all() {
+        let mut indexes = vec![0, 1];
+        let mut found = vec![indexes.clone()];
+
+        while next_combination(&mut indexes, 4) {
+            found.push(indexes.clone());
+        }
+
+        assert_eq!(
+            found,
+            vec![
+                vec![0, 1],
+                vec![0, 2],
+                vec![0, 3],
+                vec![1, 2],
+                vec![1, 3],
+                vec![2, 3]
+            ]
+        );
+    }
+
+    #[test]
+    fn patience_tracker_stops() {
+        let mut tracker = PatienceTracker::new(Some(2));
+
+        assert!(!tracker.update(0.5));
+        assert!(!tracker.update(0.4));
+        assert!(!tracker.update(0.6));
+        assert!(!tracker.update(0.6));
+        assert!(tracker.update(0.1));
+    }
 }
@@ -0,0 +1,299 @@
+//! serving predictions over http
+//!
+//! the model is loaded or fitted once when the server starts and every
+//! request is answered from memory. requests are handled by as many workers
+//! as there are threads.
+//!
+//! - `GET /health` responds with `{"status":"ok"}`
+//! - `POST /predict` accepts a [`PredictRequest`] and responds with a
+//!   versioned [`PredictResponse`]
+//! - `POST /records` accepts an [`AppendRequest`], adds the records to the
+//!   model in memory, and responds with a versioned [`AppendResponse`]
+//!
+//! the same model can also be served over grpc. refer to [`crate::grpc`]
+use std::io::Read;
+use std::path::PathBuf;
+use std::sync::{Arc, PoisonError, RwLock};
+
+use anyhow::{Context, anyhow, bail};
+use clap::Args;
+use serde::Serialize;
+use tiny_http::{Header, Method, Request, Response, Server};
+
+use crate::grpc;
+use crate::memory;
+use crate::model::{Model, ModelArgs, fit_model};
+use crate::results::{
+    AppendRequest, AppendResponse, LabelPrediction, PredictRequest, PredictResponse, Versioned,
+};
+use crate::source::DataSource;
+
+/// the largest request body that will be read
+const MAX_BODY: usize = 16 * 1024 * 1024;
+
+#[derive(Debug, Args)]
+pub struct ServeArgs {
+    #[command(flatten)]
+    fit: ModelArgs,
+
+    /// loads a model file created by `fit` instead of the csv
+    #[arg(long, conflicts_with_all = ["columns", "label"])]
+    model: Option<PathBuf>,
+
+    /// the address to listen on
+    #[arg(long, default_value = "127.0.0.1:8080")]
+    addr: String,
+
+    /// also serves the grpc api of `proto/knn.proto` on this address.
+    /// requires the grpc feature
+    #[arg(long)]
+    grpc_addr: Option<String>,
+
+    /// only serves grpc
+    #[arg(long, requires = "grpc_addr")]
+    no_http: bool,
+}
+
+pub fn knn_serve<S>(source: Option<S>, arg: ServeArgs) -> anyhow::Result<()>
+where
+    S: DataSource,
+{
+    let model = match (&arg.model, source) {
+        (Some(_), Some(_)) => bail!("a csv file cannot be used with a model file"),
+        (Some(path), None) => Model::load(path)?,
+        (None, Some(source)) => fit_model(source, &arg.fit)?,
+        (None, None) => bail!("no csv or model file specified"),
+    };
+
+    let model = Arc::new(RwLock::new(model));
+
+    if arg.no_http {
+        let Some(grpc_addr) = &arg.grpc_addr else {
+            bail!("no address specified to serve on");
+        };
+
+        print_serving(&model, grpc_addr);
+
+        return grpc::serve(model, grpc_addr);
+    }
+
+    let server = Server::http(&arg.addr)
+        .map_err(|err| anyhow!(err))
+        .with_context(|| format!("failed to listen on {}", arg.addr))?;
+    let workers = rayon::current_num_threads();
+
+    print_serving(&model, &arg.addr);
+
+    std::thread::scope(|scope| {
+        let grpc = arg.grpc_addr.as_ref().map(|grpc_addr| {
+            print_serving(&model, grpc_addr);
+
+            let model = model.clone();
+            let server = &server;
+
+            scope.spawn(move || {
+                let result = grpc::serve(model, grpc_addr);
+
+                // the http workers are stopped so the error is reported
+                // instead of only serving half of the requested apis
+                for _ in 0..workers {
+                    server.unblock();
+                }
+
+                result
+            })
+        });
+
+        for _ in 0..workers {
+            scope.spawn(|| {
+                for request in server.incoming_requests() {
+                    respond(&model, request);
+                }
+            });
+        }
+
+        match grpc {
+            Some(handle) => handle.join().expect("grpc server panicked"),
+            None => Ok(()),
+        }
+    })
+}
+
+fn print_serving(model: &RwLock<Model>, addr: &str) {
+    let model = model.read().unwrap_or_else(PoisonError::into_inner);
+
+    println!(
+        "serving {} records with k {} on {addr}",
+        model.stored.labels.len(),
+        model.k
+    );
+}
+
+/// answers a single request. failing to send the response only affects the
+/// client that made the request so it is ignored
+fn respond(model: &RwLock<Model>, mut request: Request) {
+    let (status, body) = handle(model, &mut request);
+
+    let header = Header::from_bytes("Content-Type", "application/json")
+        .expect("content type header is valid");
+    let response = Response::from_string(body)
+        .with_status_code(status)
+        .with_header(header);
+
+    let _ = request.respond(response);
+}
+
+/// routes the request and returns the status code and json body to respond
+/// with
+fn handle(model: &RwLock<Model>, request: &mut Request) -> (u16, String) {
+    match (request.method(), request.url()) {
+        (Method::Get, "/health") => (200, json(&serde_json::json!({ "status": "ok" }))),
+        (Method::Post, "/predict") => match predict(model, request) {
+            Ok(response) => (200, json(&Versioned::new(response))),
+            Err(err) => (400, error(&format!("{err:#}"))),
+        },
+        (Method::Post, "/records") => match append(model, request) {
+            Ok(response) => (200, json(&Versioned::new(response))),
+            Err(err) => (400, error(&format!("{err:#}"))),
+        },
+        (_, "/health" | "/predict" | "/records") => (405, error("method not allowed")),
+        _ => (404, error("not found")),
+    }
+}
+
+/// predicts every datapoint of the request body
+fn predict(model: &RwLock<Model>, request: &mut Request) -> anyhow::Result<PredictResponse> {
+    let parsed: PredictRequest =
+        serde_json::from_str(&read_body(request)?).context("failed to parse the request body")?;
+    let model = model.read().unwrap_or_else(PoisonError::into_inner);
+
+    let predictions = model
+        .predict_batch(&parsed.datapoints)
+        .map(|prediction| LabelPrediction::try_from(prediction?))
+        .collect::<anyhow::Result<_>>()?;
+
+    Ok(PredictResponse { predictions })
+}
+
+/// adds every record of the request body to the model. none of the records
+/// are added if any of them are invalid
+fn append(model: &RwLock<Model>, request: &mut Request) -> anyhow::Result<AppendResponse> {
+    let parsed: AppendRequest =
+        serde_json::from_str(&read_body(request)?).context("failed to parse the request body")?;
+    let mut model = model.write().unwrap_or_else(PoisonError::into_inner);
+
+    model.append(
+        parsed
+            .records
+            .iter()
+            .map(|record| (record.datapoint.as_slice(), record.label.as_str())),
+    )?;
+
+    Ok(AppendResponse {
+        added: parsed.records.len(),
+        records: model.stored.labels.len(),
+    })
+}
+
+/// reads the json body of the request
+fn read_body(request: &mut Request) -> anyhow::Result<String> {
+    let length = request.body_length().unwrap_or(0);
+
+    if length > MAX_BODY {
+        bail!("the request body is larger than {MAX_BODY} bytes");
+    }
+
+    memory::reserve(length, "reading the request")?;
+
+    let mut body = String::with_capacity(length);
+
+    request
+        .as_reader()
+        .take(MAX_BODY as u64 + 1)
+        .read_to_string(&mut body)
+        .context("failed to read the request body")?;
+
+    if body.len() > MAX_BODY {
+        bail!("the request body is larger than {MAX_BODY} bytes");
+    }
+
+    Ok(body)
+}
+
+fn json<T>(value: &T) -> String
+where
+    T: Serialize,
+{
+    serde_json::to_string(value).expect("responses serialize to json")
+}
+
+fn error(message: &str) -> String {
+    json(&serde_json::json!({ "error": message }))
+}
+
+#[cfg(test)]
+mod test {
+    use tiny_http::TestRequest;
+
+    use super::*;
+    use crate::cli::{AlgoType, TaskType};
+    use crate::csv::Dataset;
+    use crate::index::IndexType;
+    use crate::model::MODEL_VERSION;
+    use crate::scale::{ScaleType, Scaler};
+    use crate::store::{ScaledRecords, StoredIndex};
+
+    #[test]
+    fn predict_requests() {
+        let mut records = Dataset::new(2);
+        records.push(&[1.0, 2.0], "a");
+        records.push(&[3.0, 4.0], "b");
+
+        let loaded = ScaledRecords {
+            columns: vec!["x".to_owned(), "y".to_owned()],
+            label: "class".to_owned(),
+            scale: ScaleType::None,
+            scaler: Scaler::fit(ScaleType::None, records.iter().map(|r| r.data), 2),
+            records,
+        };
+        let model = RwLock::new(Model {
+            version: MODEL_VERSION,
+            k: 1,
+            task: TaskType::Classification,
+            stored: StoredIndex::build(loaded, AlgoType::Euclidean, &IndexType::Brute.into())
+                .unwrap(),
+        });
+        let post = |body: &'static str| {
+            let mut request: Request = TestRequest::new()
+                .with_method(Method::Post)
+                .with_path("/predict")
+                .with_body(body)
+                .into();
+            let (status, body) = handle(&model, &mut request);
+
+            (
+                status,
+                serde_json::from_str::<serde_json::Value>(&body).unwrap(),
+            )
+        };
+
+        let (status, body) = post(r#"{"datapoints":[[2.9,4.0]]}"#);
+
+        assert_eq!(status, 200);
+        assert_eq!(
+            body,
+            serde_json::json!({
+                "version": crate::results::FORMAT_VERSION,
+                "predictions": [{ "label": "b", "probabilities": { "b": 1.0 } }],
+            })
+        );
+
+        let (status, body) = post(r#"{"datapoints":[[2.9,4.0,1.0]]}"#);
+
+        assert_eq!(status, 400);
+        assert_eq!(
+            body["error"],
+            "number of datapoints does not match number of columns in the model. columns: x y"
+        );
+    }
+}
@@ -0,0 +1,567 @@
+//! the sources that records can be loaded from
+//!
+//! every command reads its records through [`DataSource`] so the same code
+//! works for any source. [`CsvSource`] reads the rows of a csv and
+//! [`MemorySource`] reads rows that are already in memory. other backends,
+//! such as a database query, only need to implement [`DataSource`] to be
+//! loaded with [`collect_records`](crate::csv::collect_records).
+use std::collections::{HashMap, HashSet};
+use std::str::FromStr;
+
+use crate::cli::{HeaderMatch, OnDuplicate};
+use crate::csv::{Reader, StringRecord};
+use crate::error::{KnnError, KnnResult};
+
+/// the values, ignoring case, that are treated as missing along with empty
+/// values
+pub const MISSING: [&str; 4] = ["na", "n/a", "nan", "null"];
+
+/// checks if a value is empty or one of [`MISSING`]
+pub fn is_missing(value: &str) -> bool {
+    let value = value.trim();
+
+    value.is_empty()
+        || MISSING
+            .iter()
+            .any(|missing| value.eq_ignore_ascii_case(missing))
+}
+
+/// a source of rows that are split into features and a label
+///
+/// rows are read one at a time so the source never needs to be loaded into
+/// memory at once. errors from the backend of the source can be returned as
+/// [`KnnError::Source`]
+pub trait DataSource {
+    /// the name of every column or [`None`] if the source does not name its
+    /// columns
+    fn headers(&mut self) -> KnnResult<Option<Vec<String>>>;
+
+    /// the number of columns in each row or [`None`] if it is not known.
+    /// defaults to the number of headers
+    fn width(&mut self) -> KnnResult<Option<usize>> {
+        Ok(self.headers()?.map(|headers| headers.len()))
+    }
+
+    /// how a name shared by more than one header is resolved. sources that
+    /// use [`OnDuplicate::Suffix`] rename the headers they return
+    fn on_duplicate(&self) -> OnDuplicate {
+        OnDuplicate::Error
+    }
+
+    /// how the names of columns are compared against the headers. defaults
+    /// to an exact match
+    fn header_match(&self) -> HeaderMatch {
+        HeaderMatch::Exact
+    }
+
+    /// the labels of the rows that [`next_row`](DataSource::next_row) keeps.
+    /// [`next_text`](DataSource::next_text) returns every row so the caller
+    /// checks the label itself. defaults to keeping every row
+    fn label_filter(&self) -> LabelFilter {
+        LabelFilter::default()
+    }
+
+    /// reads the next row with the values of `columns` as the features and
+    /// the value of `label` as the label
+    ///
+    /// returns [`None`] once every row has been read
+    fn next_row(&mut self, label: usize, columns: &[usize]) -> Option<KnnResult<Row<'_>>>;
+
+    /// reads the next row as the text of every column without parsing any
+    /// of them
+    ///
+    /// returns [`None`] once every row has been read
+    fn next_text(&mut self) -> Option<KnnResult<Vec<String>>>;
+}
+
+impl<S> DataSource for &mut S
+where
+    S: DataSource + ?Sized,
+{
+    fn headers(&mut self) -> KnnResult<Option<Vec<String>>> {
+        (**self).headers()
+    }
+
+    fn width(&mut self) -> KnnResult<Option<usize>> {
+        (**self).width()
+    }
+
+    fn on_duplicate(&self) -> OnDuplicate {
+        (**self).on_duplicate()
+    }
+
+    fn header_match(&self) -> HeaderMatch {
+        (**self).header_match()
+    }
+
+    fn label_filter(&self) -> LabelFilter {
+        (**self).label_filter()
+    }
+
+    fn next_row(&mut self, label: usize, columns: &[usize]) -> Option<KnnResult<Row<'_>>> {
+        (**self).next_row(label, columns)
+    }
+
+    fn next_text(&mut self) -> Option<KnnResult<Vec<String>>> {
+        (**self).next_text()
+    }
+}
+
+/// a single row read from a [`DataSource`]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Row<'a> {
+    pub features: &'a [f64],
+    pub label: &'a str,
+}
+
+/// the labels of the rows that are kept when reading a source
+///
+/// a row is kept if its label is one of the included labels, or there are
+/// no included labels, and it is not one of the excluded labels. the filter
+/// remembers which of its labels it has been given so labels that are not in
+/// the source can be reported
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct LabelFilter {
+    include: Vec<String>,
+    exclude: Vec<String>,
+    /// the included and then excluded labels that a row has had
+    seen: Vec<bool>,
+}
+
+impl LabelFilter {
+    pub fn new(include: Vec<String>, exclude: Vec<String>) -> Self {
+        let seen = vec![false; include.len() + exclude.len()];
+
+        Self {
+            include,
+            exclude,
+            seen,
+        }
+    }
+
+    /// true if a row with the label is kept
+    pub fn keeps(&mut self, label: &str) -> bool {
+        let included = self.include.iter().position(|name| name == label);
+        let excluded = self.exclude.iter().position(|name| name == label);
+
+        if let Some(index) = included {
+            self.seen[index] = true;
+        }
+
+        if let Some(index) = excluded {
+            self.seen[self.include.len() + index] = true;
+        }
+
+        (self.include.is_empty() || included.is_some()) && excluded.is_none()
+    }
+
+    /// true if only rows with the included labels are kept
+    pub fn is_including(&self) -> bool {
+        !self.include.is_empty()
+    }
+
+    /// the included and excluded labels that no row given to
+    /// [`LabelFilter::keeps`] has had
+    pub fn unseen(&self) -> impl Iterator<Item = &str> {
+        self.include
+            .iter()
+            .chain(&self.exclude)
+            .zip(&self.seen)
+            .filter(|(_, seen)| !**seen)
+            .map(|(name, _)| name.as_str())
+    }
+}
+
+/// reads the rows of a csv
+pub struct CsvSource<R> {
+    reader: Reader<R>,
+    record: StringRecord,
+    features: Vec<f64>,
+    /// the position of the next row
+    index: usize,
+    on_duplicate: OnDuplicate,
+    header_match: HeaderMatch,
+    labels: LabelFilter,
+}
+
+impl<R> CsvSource<R>
+where
+    R: std::io::Read,
+{
+    pub fn new(reader: Reader<R>) -> Self {
+        Self {
+            reader,
+            record: StringRecord::new(),
+            features: Vec::new(),
+            index: 0,
+            on_duplicate: OnDuplicate::default(),
+            header_match: HeaderMatch::default(),
+            labels: LabelFilter::default(),
+        }
+    }
+
+    /// sets how a name shared by more than one header is resolved
+    pub fn with_on_duplicate(mut self, on_duplicate: OnDuplicate) -> Self {
+        self.on_duplicate = on_duplicate;
+        self
+    }
+
+    /// sets how the names of columns are compared against the headers
+    pub fn with_header_match(mut self, header_match: HeaderMatch) -> Self {
+        self.header_match = header_match;
+        self
+    }
+
+    /// sets the labels of the rows that are read. rows with any other label
+    /// are skipped
+    pub fn with_label_filter(mut self, labels: LabelFilter) -> Self {
+        self.labels = labels;
+        self
+    }
+}
+
+impl<R> DataSource for CsvSource<R>
+where
+    R: std::io::Read,
+{
+    fn headers(&mut self) -> KnnResult<Option<Vec<String>>> {
+        if !self.reader.has_headers() {
+            return Ok(None);
+        }
+
+        let headers = self.reader.headers().map_err(KnnError::Headers)?;
+        let mut headers: Vec<String> = headers.iter().map(str::to_owned).collect();
+
+        if self.on_duplicate == OnDuplicate::Suffix {
+            suffix_duplicates(&mut headers);
+        }
+
+        Ok(Some(headers))
+    }
+
+    fn on_duplicate(&self) -> OnDuplicate {
+        self.on_duplicate
+    }
+
+    fn header_match(&self) -> HeaderMatch {
+        self.header_match
+    }
+
+    fn label_filter(&self) -> LabelFilter {
+        self.labels.clone()
+    }
+
+    fn width(&mut self) -> KnnResult<Option<usize>> {
+        // without headers the csv reader returns the first row, which is
+        // still read as a record afterwards
+        let first = self.reader.byte_headers().map_err(KnnError::Headers)?;
+
+        Ok(Some(first.len()))
+    }
+
+    fn next_row(&mut self, label: usize, columns: &[usize]) -> Option<KnnResult<Row<'_>>> {
+        let index = loop {
+            let index = self.index;
+
+            match self.reader.read_record(&mut self.record) {
+                Ok(true) => {}
+                Ok(false) => return None,
+                Err(source) => return Some(Err(KnnError::Csv { row: index, source })),
+            }
+
+            self.index += 1;
+
+            // a row without a label is still parsed so the error is reported
+            match self.record.get(label) {
+                Some(value) if !self.labels.keeps(value) => {}
+                _ => break index,
+            }
+        };
+
+        let record = &self.record;
+        let result = parse_row(
+            index,
+            label,
+            columns,
+            |col| record.get(col),
+            &mut self.features,
+        );
+
+        match result {
+            Ok(label) => Some(Ok(Row {
+                features: &self.features,
+                label,
+            })),
+            Err(err) => {
+                // the headers are read from the reader since the row is
+                // still borrowed
+                let headers: Option<Vec<String>> = if self.reader.has_headers() {
+                    self.reader
+                        .headers()
+                        .ok()
+                        .map(|headers| headers.iter().map(str::to_owned).collect())
+                } else {
+                    None
+                };
+                let line = self.record.position().map(|position| position.line());
+
+                Some(Err(err.with_row(
+                    self.record.iter(),
+                    headers.as_deref(),
+                    label,
+                    line,
+                )))
+            }
+        }
+    }
+
+    fn next_text(&mut self) -> Option<KnnResult<Vec<String>>> {
+        let index = self.index;
+
+        match self.reader.read_record(&mut self.record) {
+            Ok(true) => {}
+            Ok(false) => return None,
+            Err(source) => return Some(Err(KnnError::Csv { row: index, source })),
+        }
+
+        self.index += 1;
+
+        Some(Ok(self.record.iter().map(str::to_owned).collect()))
+    }
+}
+
+/// reads rows of text values that are already in memory
+///
+/// the values are parsed the same as the values of a csv
+#[derive(Debug, Clone)]
+pub struct MemorySource {
+    headers: Option<Vec<String>>,
+    rows: Vec<Vec<String>>,
+    features: Vec<f64>,
+    /// the position of the next row
+    index: usize,
+}
+
+impl MemorySource {
+    /// creates a source from the rows. every row is a list of the values of
+    /// each column
+    pub fn new(headers: Option<Vec<String>>, rows: Vec<Vec<String>>) -> Self {
+        Self {
+            headers,
+            rows,
+            features: Vec::new(),
+            index: 0,
+        }
+    }
+}
+
+impl DataSource for MemorySource {
+    fn headers(&mut self) -> KnnResult<Option<Vec<String>>> {
+        Ok(self.headers.clone())
+    }
+
+    fn width(&mut self) -> KnnResult<Option<usize>> {
+        let width = match &self.headers {
+            Some(headers) => Some(headers.len()),
+            None => self.rows.first().map(Vec::len),
+        };
+
+        Ok(width)
+    }
+
+    fn next_row(&mut self, label: usize, columns: &[usize]) -> Option<KnnResult<Row<'_>>> {
+        let index = self.index;
+        let row = self.rows.get(index)?;
+
+        self.index += 1;
+
+        Some(
+            parse_row(
+                index,
+                label,
+                columns,
+                |col| row.get(col).map(String::as_str),
+                &mut self.features,
+            )
+            .map(|label| Row {
+                features: &self.features,
+                label,
+            })
+            .map_err(|err| {
+                err.with_row(
+                    row.iter().map(String::as_str),
+                    self.headers.as_deref(),
+                    label,
+                    None,
+                )
+            }),
+        )
+    }
+
+    fn next_text(&mut self) -> Option<KnnResult<Vec<String>>> {
+        let row = self.rows.get(self.index)?;
+
+        self.index += 1;
+
+        Some(Ok(row.clone()))
+    }
+}
+
+/// renames every header after the first with the same name by adding the
+/// count of the name, such as `x_2`. a suffix is skipped if another header
+/// already has the name
+pub fn suffix_duplicates(headers: &mut [String]) {
+    let mut taken: HashSet<String> = headers.iter().cloned().collect();
+    let mut seen: HashMap<String, usize> = HashMap::new();
+
+    for header in headers.iter_mut() {
+        let count = seen.entry(header.clone()).or_insert(0);
+
+        *count += 1;
+
+        if *count == 1 {
+            continue;
+        }
+
+        let mut renamed = format!("{header}_{count}");
+
+        while taken.contains(&renamed) {
+            *count += 1;
+            renamed = format!("{header}_{count}");
+        }
+
+        taken.insert(renamed.clone());
+        *header = renamed;
+    }
+}
+
+/// parses the values of `columns` into `features` and retrieves the label
+///
+/// `get` retrieves the value of a column from the row and `index` is the
+/// position of the row for error messages
+pub fn parse_row<'a, G>(
+    index: usize,
+    label: usize,
+    columns: &[usize],
+    get: G,
+    features: &mut Vec<f64>,
+) -> KnnResult<&'a str>
+where
+    G: Fn(usize) -> Option<&'a str>,
+{
+    features.clear();
+
+    for col in columns {
+        let Some(value) = get(*col) else {
+            return Err(KnnError::MissingValue {
+                row: index,
+                column: *col,
+                context: None,
+            });
+        };
+
+        let Ok(v) = f64::from_str(value) else {
+            return Err(KnnError::Parse {
+                row: index,
+                column: *col,
+                context: None,
+            });
+        };
+
+        features.push(v);
+    }
+
+    let Some(found) = get(label) else {
+        return Err(KnnError::MissingLabel {
+            row: index,
+            context: None,
+        });
+    };
+
+    Ok(found)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::csv::ReaderBuilder;
+
+    #[test]
+    fn sources_match() {
+        let text = "x,y,class\n1,2,a\n3,4,b\n";
+        let mut csv = CsvSource::new(ReaderBuilder::new().from_reader(text.as_bytes()));
+        let mut memory = MemorySource::new(
+            Some(vec!["x".to_owned(), "y".to_owned(), "class".to_owned()]),
+            vec![
+                vec!["1".to_owned(), "2".to_owned(), "a".to_owned()],
+                vec!["3".to_owned(), "4".to_owned(), "b".to_owned()],
+            ],
+        );
+
+        assert_eq!(csv.headers().unwrap(), memory.headers().unwrap());
+
+        for _ in 0..2 {
+            let from_csv = csv.next_row(2, &[1, 0]).unwrap().unwrap();
+            let from_csv = (from_csv.features.to_vec(), from_csv.label.to_owned());
+            let from_memory = memory.next_row(2, &[1, 0]).unwrap().unwrap();
+
+            assert_eq!(from_csv.0, from_memory.features);
+            assert_eq!(from_csv.1, from_memory.label);
+        }
+
+        assert!(csv.next_row(2, &[1, 0]).is_none());
+        assert!(memory.next_row(2, &[1, 0]).is_none());
+
+        let mut bad = MemorySource::new(None, vec![vec!["x".to_owned(), "a".to_owned()]]);
+
+        assert!(bad.next_row(1, &[0]).unwrap().is_err());
+    }
+
+    #[test]
+    fn filtered_labels() {
+        let text = "x,class\n1,a\n2,b\n3,c\n4,a\n";
+        let read = |filter: LabelFilter| {
+            let mut source = CsvSource::new(ReaderBuilder::new().from_reader(text.as_bytes()))
+                .with_label_filter(filter);
+            let mut rows = Vec::new();
+
+            while let Some(row) = source.next_row(1, &[0]) {
+                let row = row.unwrap();
+
+                rows.push((row.features[0], row.label.to_owned()));
+            }
+
+            rows
+        };
+
+        let include = read(LabelFilter::new(vec!["a".into(), "c".into()], vec![]));
+
+        assert_eq!(
+            include,
+            vec![(1.0, "a".into()), (3.0, "c".into()), (4.0, "a".into())]
+        );
+
+        let both = read(LabelFilter::new(
+            vec!["a".into(), "c".into()],
+            vec!["a".into()],
+        ));
+
+        assert_eq!(both, vec![(3.0, "c".into())]);
+
+        let mut filter = LabelFilter::new(vec!["a".into(), "d".into()], vec!["e".into()]);
+
+        for label in ["a", "b", "c"] {
+            filter.keeps(label);
+        }
+
+        assert_eq!(filter.unseen().collect::<Vec<_>>(), vec!["d", "e"]);
+
+        let source = CsvSource::new(ReaderBuilder::new().from_reader(text.as_bytes()))
+            .with_label_filter(LabelFilter::new(vec!["d".into()], vec![]));
+
+        assert!(matches!(
+            crate::csv::collect_records(source, 1, &[0]),
+            Err(KnnError::NoIncludedLabels)
+        ));
+    }
+}
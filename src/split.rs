@@ -0,0 +1,277 @@
+//! utilities for dividing records into training and testing sets
+use std::collections::HashMap;
+
+use rand::SeedableRng;
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+
+use crate::csv::{Dataset, KnnRecord};
+
+/// splits the specified list of records into a list of folds
+///
+/// records are grouped by their label and then assigned to folds in a round
+/// robin fashion so that each fold has a similar distribution of labels. the
+/// indexes in each fold are in the same order as the original list.
+pub fn fold_dataset<'a, I>(records: I, folds: usize) -> Vec<Vec<usize>>
+where
+    I: IntoIterator<Item = KnnRecord<'a>>,
+{
+    let mut order: Vec<u32> = Vec::new();
+    let mut groups: HashMap<u32, Vec<usize>> = HashMap::new();
+
+    for (index, record) in records.into_iter().enumerate() {
+        groups
+            .entry(record.label)
+            .and_modify(|list| list.push(index))
+            .or_insert_with(|| {
+                order.push(record.label);
+
+                vec![index]
+            });
+    }
+
+    let mut rtn = vec![Vec::new(); folds];
+
+    // continue the round robin across groups so the folds stay balanced
+    for (position, index) in order.iter().flat_map(|label| &groups[label]).enumerate() {
+        rtn[position % folds].push(*index);
+    }
+
+    for fold in &mut rtn {
+        fold.sort_unstable();
+    }
+
+    rtn
+}
+
+/// divides the records into the records outside of the fold and the records
+/// inside of the fold
+///
+/// the indexes of the fold must be sorted which is how they are returned from
+/// [`fold_dataset`]
+pub fn partition_fold<'a, I>(records: I, fold: &[usize]) -> (Vec<KnnRecord<'a>>, Vec<KnnRecord<'a>>)
+where
+    I: IntoIterator<Item = KnnRecord<'a>>,
+{
+    let mut outside = Vec::new();
+    let mut inside = Vec::with_capacity(fold.len());
+    let mut fold_iter = fold.iter().peekable();
+
+    for (index, record) in records.into_iter().enumerate() {
+        if fold_iter.next_if_eq(&&index).is_some() {
+            inside.push(record);
+        } else {
+            outside.push(record);
+        }
+    }
+
+    (outside, inside)
+}
+
+/// split the specified list of records based on the label provided
+///
+/// the records of each group are shuffled with an rng created from the
+/// provided seed so the same seed will always produce the same split. if
+/// `stratify` is false then all records are treated as a single group
+pub fn split_dataset<'a>(
+    records: &'a Dataset,
+    split: f64,
+    stratify: bool,
+    seed: u64,
+) -> (Vec<KnnRecord<'a>>, Vec<KnnRecord<'a>>) {
+    let mut groups: HashMap<Option<u32>, Vec<KnnRecord<'a>>> = HashMap::new();
+
+    for record in records.iter() {
+        let key = stratify.then_some(record.label);
+
+        groups
+            .entry(key)
+            // increment if the group was previously added
+            .and_modify(|list| list.push(record))
+            // insert if not already existing
+            .or_insert(vec![record]);
+    }
+
+    // the groups are sorted by their label so that the rng is consumed in
+    // the same order every time
+    let mut groups: Vec<(Option<u32>, Vec<KnnRecord<'a>>)> = groups.into_iter().collect();
+    groups.sort_unstable_by_key(|(key, _)| key.map(|label| records.label_name(label)));
+
+    let mut rng = StdRng::seed_from_u64(seed);
+    let mut train = Vec::new();
+    let mut test = Vec::new();
+
+    for (_, mut records) in groups {
+        records.shuffle(&mut rng);
+
+        // split the record groups based on the split specified.
+        let amount = (records.len() as f64 * split).floor() as usize;
+
+        train.extend(records.split_off(amount));
+        test.extend(records);
+    }
+
+    (train, test)
+}
+
+/// splits the records in order so that every test record comes after every
+/// training record
+///
+/// the last `split` of the records are used for testing. `times` is the time
+/// of each record in the same order, which must be sorted. records with the
+/// same time as the first test record are also tested so no training record
+/// shares a time with a test record
+pub fn split_temporal<'a>(
+    records: &'a Dataset,
+    split: f64,
+    times: Option<&[f64]>,
+) -> (Vec<KnnRecord<'a>>, Vec<KnnRecord<'a>>) {
+    let mut train: Vec<KnnRecord<'a>> = records.iter().collect();
+    let test = train.split_off(temporal_boundary(records.len(), split, times));
+
+    (train, test)
+}
+
+/// the position of the first test record of [`split_temporal`] for `len`
+/// records
+///
+/// the position is 0 when every training record shares the time of the
+/// first test record
+pub fn temporal_boundary(len: usize, split: f64, times: Option<&[f64]>) -> usize {
+    let amount = (len as f64 * split).floor() as usize;
+    let mut boundary = len - amount;
+
+    if let Some(times) = times {
+        while boundary > 0 && boundary < times.len() && times[boundary - 1] == times[boundary] {
+            boundary -= 1;
+        }
+    }
+
+    boundary
+}
+
+/// takes a fraction of each label group from the specified list of records
+///
+/// each group will keep at least a single record as long as the fraction is
+/// larger than 0. ordering is preserved from the original list
+pub fn subsample_dataset<'a>(records: &[KnnRecord<'a>], fraction: f64) -> Vec<KnnRecord<'a>> {
+    let mut totals: HashMap<u32, usize> = HashMap::new();
+
+    for record in records {
+        *totals.entry(record.label).or_insert(0) += 1;
+    }
+
+    let mut taken: HashMap<u32, usize> = HashMap::new();
+    let mut rtn = Vec::new();
+
+    for record in records {
+        let total = totals[&record.label];
+        let amount = std::cmp::max((total as f64 * fraction).floor() as usize, 1);
+        let counter = taken.entry(record.label).or_insert(0);
+
+        if *counter < amount {
+            rtn.push(*record);
+
+            *counter += 1;
+        }
+    }
+
+    rtn
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn records(labels: &[&str]) -> Dataset {
+        let mut rtn = Dataset::new(1);
+
+        // the data is the position of the record so they can be told apart
+        for (index, label) in labels.iter().enumerate() {
+            rtn.push(&[index as f64], label);
+        }
+
+        rtn
+    }
+
+    #[test]
+    fn fold_dataset_stratified() {
+        let records = records(&["a", "a", "b", "b", "a", "b"]);
+
+        let folds = fold_dataset(records.iter(), 2);
+
+        assert_eq!(folds, vec![vec![0, 3, 4], vec![1, 2, 5]]);
+    }
+
+    #[test]
+    fn partition_fold_sorted() {
+        let records = records(&["a", "b", "c", "d"]);
+
+        let (outside, inside) = partition_fold(records.iter(), &[1, 3]);
+
+        let outside: Vec<u32> = outside.iter().map(|r| r.label).collect();
+        let inside: Vec<u32> = inside.iter().map(|r| r.label).collect();
+
+        assert_eq!(outside, vec![0, 2]);
+        assert_eq!(inside, vec![1, 3]);
+    }
+
+    #[test]
+    fn split_dataset_stratified() {
+        let records = records(&["a", "a", "a", "a", "b", "b", "b", "b"]);
+
+        let (train, test) = split_dataset(&records, 0.25, true, 7);
+
+        assert_eq!(train.len(), 6);
+        assert_eq!(test.len(), 2);
+        assert_eq!(test.iter().filter(|r| r.label == 0).count(), 1);
+    }
+
+    #[test]
+    fn split_dataset_reproducible() {
+        let labels = ["a"; 20];
+        let records = records(&labels);
+
+        let (_, first) = split_dataset(&records, 0.5, true, 42);
+        let (_, second) = split_dataset(&records, 0.5, true, 42);
+
+        let first: Vec<f64> = first.into_iter().map(|r| r.data[0]).collect();
+        let second: Vec<f64> = second.into_iter().map(|r| r.data[0]).collect();
+
+        assert_eq!(first, second);
+    }
+
+    #[test]
+    fn subsample_dataset_stratified() {
+        let records = records(&["a", "a", "a", "a", "b", "b"]);
+        let refs = records.iter().collect::<Vec<_>>();
+
+        let sample = subsample_dataset(&refs, 0.5);
+
+        assert_eq!(sample.len(), 3);
+        assert_eq!(sample.iter().filter(|r| r.label == 0).count(), 2);
+    }
+
+    #[test]
+    fn split_temporal_after() {
+        let records = records(&["a", "b", "a", "b", "a", "b", "a", "b"]);
+
+        let (train, test) = split_temporal(&records, 0.25, None);
+
+        assert_eq!(train.len(), 6);
+        assert_eq!(test[0].data, records.get(6).data);
+
+        // the record that shares a time with the first test record is tested
+        let times = [1.0, 2.0, 3.0, 4.0, 5.0, 6.0, 6.0, 7.0];
+        let (train, test) = split_temporal(&records, 0.25, Some(&times));
+
+        assert_eq!(train.len(), 5);
+        assert_eq!(test.len(), 3);
+
+        // every training record shares the time of the first test record
+        let times = [1.0; 8];
+
+        assert_eq!(temporal_boundary(8, 0.25, Some(&times)), 0);
+        assert_eq!(temporal_boundary(8, 0.25, None), 6);
+    }
+}
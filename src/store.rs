@@ -0,0 +1,305 @@
+//! saving a parsed, scaled, and indexed dataset
+//!
+//! building an index requires parsing the csv, fitting the scaler, and then
+//! arranging the records. an index file stores the result so that predictions
+//! can be made without repeating any of it.
+use std::fs::OpenOptions;
+use std::io::{BufWriter, Read};
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, bail};
+use clap::{Args, Subcommand};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+
+use crate::cli::{AlgoType, ColumnList, ColumnType};
+use crate::csv::{Dataset, collect_records, column_names, get_columns_excluding};
+use crate::index::{Index, IndexArgs};
+use crate::memory;
+use crate::scale::{ScaleType, Scaler};
+use crate::schema::Schema;
+use crate::source::DataSource;
+
+#[derive(Debug, Subcommand)]
+pub enum IndexCmd {
+    /// parses, scales, and indexes the csv file and saves it for later use
+    Build(IndexBuildArgs),
+}
+
+#[derive(Debug, Args)]
+pub struct IndexBuildArgs {
+    /// specifies the algorithm to use when calculating distances
+    #[arg(long, default_value = "euclidean")]
+    algo: AlgoType,
+
+    /// the list of columns to use as datapoints
+    #[arg(short, long = "col", allow_hyphen_values = true)]
+    columns: Vec<ColumnList>,
+
+    /// columns to remove from the list of columns
+    #[arg(long = "exclude-col", allow_hyphen_values = true)]
+    exclude: Vec<ColumnList>,
+
+    /// the column to use as the label
+    #[arg(long, allow_negative_numbers = true)]
+    label: Option<ColumnType>,
+
+    /// the scaling method to apply to the columns
+    #[arg(long, default_value = "none")]
+    scale: ScaleType,
+
+    #[command(flatten)]
+    index: IndexArgs,
+
+    /// the file to save the index to
+    #[arg(long)]
+    out: PathBuf,
+}
+
+/// the version of the index file layout
+pub const INDEX_VERSION: u32 = 1;
+
+/// the versions of the index file that can be loaded
+pub const INDEX_SCHEMA: Schema = Schema {
+    what: "index file",
+    current: INDEX_VERSION,
+    oldest: 0,
+    // index files written before they were versioned only lack the version
+    migrations: &[|_| Ok(())],
+};
+
+/// the format of the index file
+#[derive(Serialize, Deserialize)]
+pub struct StoredIndex {
+    pub version: u32,
+    /// the names of the columns used for the datapoints
+    pub columns: Vec<String>,
+    /// the name of the label column
+    pub label: String,
+    pub algo: AlgoType,
+    pub scale: ScaleType,
+    /// the scaling parameters fitted on every record
+    pub scaler: Scaler,
+    /// the label id of each record. the id of a record in the index is its
+    /// position in the list
+    pub labels: Vec<u32>,
+    /// the label of each label id
+    pub label_names: Vec<String>,
+    pub index: Index,
+}
+
+impl StoredIndex {
+    /// indexes the scaled records
+    pub fn build(loaded: ScaledRecords, algo: AlgoType, index: &IndexArgs) -> anyhow::Result<Self> {
+        let built = index.build(
+            loaded.columns.len(),
+            loaded
+                .records
+                .iter()
+                .enumerate()
+                .map(|(index, record)| (index, record.data)),
+            algo,
+        )?;
+        let (labels, label_names) = loaded.records.into_labels();
+
+        Ok(Self {
+            version: INDEX_VERSION,
+            columns: loaded.columns,
+            label: loaded.label,
+            algo,
+            scale: loaded.scale,
+            scaler: loaded.scaler,
+            labels,
+            label_names,
+            index: built,
+        })
+    }
+
+    /// scales the (datapoint, label) pairs and adds them to the index
+    /// without loading the csv again
+    ///
+    /// the scaler keeps the parameters fitted on the original records.
+    /// nothing is added if any of the datapoints have the wrong number of
+    /// values
+    pub fn append<'a, I>(&mut self, records: I) -> anyhow::Result<()>
+    where
+        I: IntoIterator<Item = (&'a [f64], &'a str)>,
+    {
+        let mut scaled = Vec::new();
+
+        for (datapoint, label) in records {
+            if datapoint.len() != self.columns.len() {
+                bail!(
+                    "number of datapoints does not match number of columns in the index. columns: {}",
+                    self.columns.join(" ")
+                );
+            }
+
+            let mut datapoint = datapoint.to_vec();
+
+            self.scaler.apply(&mut datapoint);
+            scaled.push((datapoint, label));
+        }
+
+        let start = self.labels.len();
+
+        self.index.extend(
+            scaled
+                .iter()
+                .enumerate()
+                .map(|(offset, (datapoint, _))| (start + offset, datapoint)),
+        )?;
+
+        for (_, label) in scaled {
+            let id = match self.label_names.iter().position(|name| name == label) {
+                Some(id) => id,
+                None => {
+                    self.label_names.push(label.to_owned());
+                    self.label_names.len() - 1
+                }
+            };
+
+            self.labels.push(id as u32);
+        }
+
+        Ok(())
+    }
+
+    /// loads a previously saved index file. older versions are upgraded
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        load_json(path, &INDEX_SCHEMA)
+    }
+
+    /// writes the index to the specified file
+    pub fn save(&self, path: &Path) -> anyhow::Result<()> {
+        save_json(path, INDEX_SCHEMA.what, self)
+    }
+}
+
+/// the records of the csv after the scaler was fitted and applied
+pub struct ScaledRecords {
+    /// the names of the columns used for the datapoints
+    pub columns: Vec<String>,
+    /// the name of the label column
+    pub label: String,
+    pub scale: ScaleType,
+    pub scaler: Scaler,
+    pub records: Dataset,
+}
+
+/// parses the records of the csv and scales them
+pub fn load_scaled<S>(
+    mut source: S,
+    columns: &[ColumnType],
+    exclude: &[ColumnType],
+    label: &ColumnType,
+    scale: ScaleType,
+) -> anyhow::Result<ScaledRecords>
+where
+    S: DataSource,
+{
+    let (label, columns) = get_columns_excluding(&mut source, label, columns, exclude)?;
+    let mut names = column_names(&mut source, &columns)?;
+    let label_name = column_names(&mut source, &[label])?
+        .remove(&label)
+        .unwrap_or_else(|| label.to_string());
+    let records = collect_records(source, label, &columns)?;
+
+    let scaler = Scaler::fit(
+        scale,
+        records.iter().map(|record| record.data),
+        columns.len(),
+    );
+    let records = scaler.apply_records(&records);
+
+    Ok(ScaledRecords {
+        columns: columns
+            .iter()
+            .map(|col| names.remove(col).unwrap_or_else(|| col.to_string()))
+            .collect(),
+        label: label_name,
+        scale,
+        scaler,
+        records,
+    })
+}
+
+/// loads a json file that was saved with [`save_json`] and upgrades it to
+/// the current version of the schema
+pub fn load_json<T>(path: &Path, schema: &Schema) -> anyhow::Result<T>
+where
+    T: DeserializeOwned,
+{
+    let what = schema.what;
+    let mut file = OpenOptions::new()
+        .read(true)
+        .open(path)
+        .with_context(|| format!("failed to open {what}"))?;
+
+    // the json is kept in memory while it is parsed and the parsed data is
+    // smaller than the json
+    let size = file
+        .metadata()
+        .with_context(|| format!("failed to read {what}"))?
+        .len() as usize;
+
+    memory::reserve(size.saturating_mul(2), &format!("loading the {what}"))?;
+
+    let mut json = Vec::with_capacity(size);
+
+    file.read_to_end(&mut json)
+        .with_context(|| format!("failed to read {what}"))?;
+
+    schema.from_slice(&json)
+}
+
+/// writes the value to the specified file as json
+pub fn save_json<T>(path: &Path, what: &str, value: &T) -> anyhow::Result<()>
+where
+    T: Serialize,
+{
+    let file = OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .open(path)
+        .with_context(|| format!("failed to create {what}"))?;
+
+    serde_json::to_writer(BufWriter::new(file), value)
+        .with_context(|| format!("failed to write {what}"))
+}
+
+pub fn knn_index<S>(source: S, cmd: IndexCmd) -> anyhow::Result<()>
+where
+    S: DataSource,
+{
+    match cmd {
+        IndexCmd::Build(arg) => knn_index_build(source, arg),
+    }
+}
+
+fn knn_index_build<S>(source: S, arg: IndexBuildArgs) -> anyhow::Result<()>
+where
+    S: DataSource,
+{
+    let label = ColumnType::label_or_last(arg.label.as_ref());
+    let loaded = load_scaled(
+        source,
+        &ColumnList::flatten(&arg.columns),
+        &ColumnList::flatten(&arg.exclude),
+        &label,
+        arg.scale,
+    )?;
+    let stored = StoredIndex::build(loaded, arg.algo, &arg.index)?;
+
+    stored.save(&arg.out)?;
+
+    println!(
+        "records: {} columns: {} index: {}",
+        stored.labels.len(),
+        stored.columns.join(" "),
+        arg.index.kind
+    );
+
+    Ok(())
+}
@@ -0,0 +1,147 @@
+//! checking every row of a csv before a long run
+//!
+//! the other commands stop at the first row that cannot be loaded. validate
+//! reads every row with the same columns and label and reports each row that
+//! would fail, such as values that are not numbers, missing values, and rows
+//! with the wrong number of fields.
+use anyhow::bail;
+use clap::Args;
+
+use crate::cli::{ColumnList, ColumnType, TaskType};
+use crate::csv::get_columns_excluding;
+use crate::error::KnnError;
+use crate::source::DataSource;
+
+#[derive(Debug, Args)]
+pub struct ValidateArgs {
+    /// the list of columns to check
+    #[arg(short, long = "col", allow_hyphen_values = true)]
+    columns: Vec<ColumnList>,
+
+    /// columns to remove from the list of columns
+    #[arg(long = "exclude-col", allow_hyphen_values = true)]
+    exclude: Vec<ColumnList>,
+
+    /// the column to use as the label
+    #[arg(long, allow_negative_numbers = true)]
+    label: Option<ColumnType>,
+
+    /// the type of task the labels will be used for. regression also checks
+    /// that every label is a number
+    #[arg(long, default_value = "classification")]
+    task: TaskType,
+
+    /// the maximum number of invalid rows to print. every row is still
+    /// checked and counted
+    #[arg(long)]
+    max_errors: Option<usize>,
+}
+
+/// a row that could not be loaded
+#[derive(Debug)]
+pub struct InvalidRow {
+    /// the zero based position of the row
+    pub row: usize,
+    pub error: KnnError,
+}
+
+impl std::fmt::Display for InvalidRow {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.error)?;
+
+        // the csv error describes what was wrong with the row
+        if let KnnError::Csv { source, .. } = &self.error {
+            write!(f, ": {source}")?;
+        }
+
+        Ok(())
+    }
+}
+
+/// reads every row of the source and calls `invalid` with each row that
+/// cannot be loaded. returns the number of rows read
+pub fn validate_rows<S, F>(
+    mut source: S,
+    label: usize,
+    columns: &[usize],
+    task: TaskType,
+    mut invalid: F,
+) -> usize
+where
+    S: DataSource,
+    F: FnMut(InvalidRow),
+{
+    let mut rows = 0;
+
+    while let Some(result) = source.next_row(label, columns) {
+        let error = match result {
+            Ok(row) if task == TaskType::Regression && row.label.parse::<f64>().is_err() => {
+                Some(KnnError::NonNumericLabel {
+                    row: rows,
+                    label: row.label.to_owned(),
+                })
+            }
+            Ok(_) => None,
+            Err(error) => Some(error),
+        };
+
+        if let Some(error) = error {
+            invalid(InvalidRow { row: rows, error });
+        }
+
+        rows += 1;
+    }
+
+    rows
+}
+
+pub fn knn_validate<S>(mut source: S, arg: ValidateArgs) -> anyhow::Result<()>
+where
+    S: DataSource,
+{
+    let label = &ColumnType::label_or_last(arg.label.as_ref());
+    let (label, columns) = get_columns_excluding(
+        &mut source,
+        label,
+        &ColumnList::flatten(&arg.columns),
+        &ColumnList::flatten(&arg.exclude),
+    )?;
+    let mut invalid = 0;
+
+    let rows = validate_rows(source, label, &columns, arg.task, |row| {
+        if arg.max_errors.is_none_or(|max| invalid < max) {
+            println!("{row}");
+        }
+
+        invalid += 1;
+    });
+
+    println!("rows: {rows} invalid: {invalid}");
+
+    if invalid > 0 {
+        bail!("{invalid} of {rows} rows cannot be loaded");
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::csv::ReaderBuilder;
+    use crate::source::CsvSource;
+
+    #[test]
+    fn every_invalid_row() {
+        let text = "x,y,class\n1,2,a\n1,b,a\n3,4\n5,6,b,7\n7,,c\n8,9,d\n";
+        let source = CsvSource::new(ReaderBuilder::new().from_reader(text.as_bytes()));
+        let mut found = Vec::new();
+
+        let rows = validate_rows(source, 2, &[0, 1], TaskType::Classification, |row| {
+            found.push(row.row)
+        });
+
+        assert_eq!(rows, 6);
+        assert_eq!(found, vec![1, 2, 3, 4]);
+    }
+}
@@ -0,0 +1,79 @@
+//! a javascript api for running the classifier in the browser
+//!
+//! only built for wasm32 targets. the records are passed in directly since
+//! there is no file system to load the csv from.
+use clap::ValueEnum;
+use wasm_bindgen::prelude::*;
+
+use crate::classifier::KnnClassifier;
+use crate::cli::AlgoType;
+use crate::csv::Dataset;
+use crate::scale::ScaleType;
+
+/// a knn classifier fitted to a set of records
+#[wasm_bindgen(js_name = KnnClassifier)]
+pub struct WasmClassifier {
+    inner: KnnClassifier,
+}
+
+#[wasm_bindgen(js_class = KnnClassifier)]
+impl WasmClassifier {
+    /// fits a classifier to the records
+    ///
+    /// `data` is a row major matrix with `dims` values for each label.
+    /// `metric` is euclidean or manhattan and `scale` is none, minmax, or
+    /// zscore
+    pub fn fit(
+        data: &[f64],
+        labels: Vec<String>,
+        dims: usize,
+        k: usize,
+        metric: &str,
+        scale: &str,
+    ) -> Result<WasmClassifier, JsError> {
+        if dims == 0 || data.len() != labels.len() * dims {
+            return Err(JsError::new(
+                "the data must have dims values for every label",
+            ));
+        }
+
+        let algo = AlgoType::from_str(metric, true).map_err(|err| JsError::new(&err))?;
+        let scale = ScaleType::from_str(scale, true).map_err(|err| JsError::new(&err))?;
+
+        let mut records = Dataset::with_capacity(dims, labels.len());
+
+        for (data, label) in data.chunks_exact(dims).zip(&labels) {
+            records.push(data, label);
+        }
+
+        let inner = KnnClassifier::builder()
+            .k(k)
+            .metric(algo)
+            .scale(scale)
+            .fit(records)
+            .map_err(|err| JsError::new(&format!("{err:#}")))?;
+
+        Ok(Self { inner })
+    }
+
+    /// the labels of the records in the order used by `predictProba`
+    pub fn labels(&self) -> Vec<String> {
+        self.inner.labels().to_vec()
+    }
+
+    /// the label with the most neighbors
+    pub fn predict(&self, datapoint: &[f64]) -> Result<String, JsError> {
+        self.inner
+            .predict(datapoint)
+            .map(str::to_owned)
+            .map_err(|err| JsError::new(&format!("{err:#}")))
+    }
+
+    /// the fraction of neighbors with each label
+    #[wasm_bindgen(js_name = predictProba)]
+    pub fn predict_proba(&self, datapoint: &[f64]) -> Result<Vec<f64>, JsError> {
+        self.inner
+            .predict_proba(datapoint)
+            .map_err(|err| JsError::new(&format!("{err:#}")))
+    }
+}
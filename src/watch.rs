@@ -0,0 +1,71 @@
+//! re-running a command whenever the files it reads change
+//!
+//! the modified times of the files are polled instead of subscribing to
+//! events from the system so the same code works on every platform. the
+//! files are compared against their state from before the command started
+//! so changes made while the command is running are not missed.
+use std::path::PathBuf;
+use std::time::{Duration, SystemTime};
+
+use anyhow::bail;
+
+/// the amount of time between checking the files for changes
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// runs the command and then runs it again every time one of the files
+/// changes. errors from the command are printed instead of ending the watch
+/// since the files are likely being edited to fix them
+///
+/// only returns if there are no files to watch
+pub fn watch<F>(paths: &[PathBuf], mut run: F) -> anyhow::Result<()>
+where
+    F: FnMut() -> anyhow::Result<()>,
+{
+    if paths.is_empty() {
+        bail!("no files to watch");
+    }
+
+    loop {
+        let seen = modified(paths);
+
+        if let Err(err) = run() {
+            eprintln!("Error: {err:#}");
+        }
+
+        eprintln!("watching {} file(s) for changes", paths.len());
+
+        wait_for_change(paths, &seen);
+    }
+}
+
+/// blocks until the files no longer match `seen` and have stopped changing.
+/// editors and exports commonly write a file in several steps so the files
+/// have to stay the same for a full interval
+fn wait_for_change(paths: &[PathBuf], seen: &[Option<SystemTime>]) {
+    let mut last = seen.to_vec();
+
+    loop {
+        std::thread::sleep(POLL_INTERVAL);
+
+        let current = modified(paths);
+
+        if current != seen && current == last {
+            return;
+        }
+
+        last = current;
+    }
+}
+
+/// the last modified time of each file. files that cannot be read, such as
+/// while they are being replaced, are [`None`]
+fn modified(paths: &[PathBuf]) -> Vec<Option<SystemTime>> {
+    paths
+        .iter()
+        .map(|path| {
+            std::fs::metadata(path)
+                .and_then(|meta| meta.modified())
+                .ok()
+        })
+        .collect()
+}